@@ -8,6 +8,9 @@ const formatrix = @import("formatrix");
 pub fn main() !void {
     const allocator = std.heap.page_allocator;
 
+    // Refuse to run against an incompatible library
+    try formatrix.checkAbi();
+
     // Print library version
     std.debug.print("Formatrix version: {s}\n", .{formatrix.version()});
 
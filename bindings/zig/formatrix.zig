@@ -53,6 +53,9 @@ pub const Result = enum(c_int) {
     unsupported_format = 4,
     null_pointer = 5,
     utf8_error = 6,
+    invalid_handle = 7,
+    abi_mismatch = 8,
+    aborted = 9,
 
     pub fn isSuccess(self: Result) bool {
         return self == .success;
@@ -67,6 +70,9 @@ pub const Result = enum(c_int) {
             .unsupported_format => Error.UnsupportedFormat,
             .null_pointer => Error.NullPointer,
             .utf8_error => Error.Utf8Error,
+            .invalid_handle => Error.InvalidHandle,
+            .abi_mismatch => Error.AbiMismatch,
+            .aborted => Error.Aborted,
         };
     }
 };
@@ -79,20 +85,129 @@ pub const Error = error{
     UnsupportedFormat,
     NullPointer,
     Utf8Error,
+    InvalidHandle,
+    AbiMismatch,
+    Aborted,
 };
 
-/// Opaque document handle
-pub const DocumentHandle = opaque {};
+/// Kinds of top-level block
+pub const BlockKind = enum(c_int) {
+    paragraph = 0,
+    heading = 1,
+    code_block = 2,
+    diagram = 3,
+    block_quote = 4,
+    list = 5,
+    thematic_break = 6,
+    table = 7,
+    raw = 8,
+    definition_list = 9,
+    admonition = 10,
+    footnote_definition = 11,
+    line_block = 12,
+    aside = 13,
+    details = 14,
+    language = 15,
+    include = 16,
+    figure = 17,
+    _,
+};
+
+/// C interface version these bindings were written against
+pub const abi_version: u32 = 1;
+
+/// Capability bits reported by the library
+pub const Capability = struct {
+    pub const config_handles: u64 = 1 << 0;
+    pub const files: u64 = 1 << 1;
+    pub const json: u64 = 1 << 2;
+    pub const block_accessors: u64 = 1 << 3;
+    pub const stats: u64 = 1 << 4;
+    pub const feature_queries: u64 = 1 << 5;
+    pub const streaming_render: u64 = 1 << 6;
+    pub const metadata: u64 = 1 << 7;
+};
+
+/// Library details, as filled in by `libraryInfo`
+pub const LibraryInfo = extern struct {
+    struct_size: u32,
+    abi_version: u32,
+    capabilities: u64,
+    version: [*:0]const u8,
+    format_count: u32,
+    block_kind_count: u32,
+};
+
+/// Registry id of a parsed document; freed or stale ids are rejected
+/// with Error.InvalidHandle
+pub const DocumentHandle = u64;
+
+/// Registry id of a parse configuration (0 means defaults)
+pub const ParseConfigHandle = u64;
+
+/// Registry id of a render configuration (0 means defaults)
+pub const RenderConfigHandle = u64;
+
+/// Unicode normalization applied to parser input
+pub const Normalization = enum(c_int) {
+    none = 0,
+    nfc = 1,
+    nfd = 2,
+};
 
 // External C functions from libformatrix_core
 extern "c" fn formatrix_parse(
     content: [*:0]const u8,
     format: Format,
-    out_handle: *?*DocumentHandle,
+    out_handle: *DocumentHandle,
+) Result;
+
+extern "c" fn formatrix_parse_with_config(
+    content: [*:0]const u8,
+    format: Format,
+    config: ParseConfigHandle,
+    out_handle: *DocumentHandle,
 ) Result;
 
+extern "c" fn formatrix_render_with_config(
+    handle: DocumentHandle,
+    format: Format,
+    config: RenderConfigHandle,
+    out_content: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+/// Receives one UTF-8 chunk of streamed output, valid only during the
+/// call; return false to stop rendering with Error.Aborted
+pub const ChunkCallback = *const fn (user_data: ?*anyopaque, chunk: [*]const u8, length: usize) callconv(.C) bool;
+
+extern "c" fn formatrix_render_streaming(
+    handle: DocumentHandle,
+    format: Format,
+    config: RenderConfigHandle,
+    chunk_size: usize,
+    callback: ChunkCallback,
+    user_data: ?*anyopaque,
+) Result;
+
+extern "c" fn formatrix_parse_config_new() ParseConfigHandle;
+extern "c" fn formatrix_parse_config_free(config: ParseConfigHandle) Result;
+extern "c" fn formatrix_parse_config_set_preserve_spans(config: ParseConfigHandle, value: bool) Result;
+extern "c" fn formatrix_parse_config_set_preserve_raw_source(config: ParseConfigHandle, value: bool) Result;
+extern "c" fn formatrix_parse_config_set_strip_zero_width(config: ParseConfigHandle, value: bool) Result;
+extern "c" fn formatrix_parse_config_set_normalization(config: ParseConfigHandle, form: Normalization) Result;
+extern "c" fn formatrix_parse_config_set_front_matter_delimiter(config: ParseConfigHandle, delimiter: ?[*:0]const u8) Result;
+extern "c" fn formatrix_parse_config_set_option(config: ParseConfigHandle, key: [*:0]const u8, value: [*:0]const u8) Result;
+
+extern "c" fn formatrix_render_config_new() RenderConfigHandle;
+extern "c" fn formatrix_render_config_free(config: RenderConfigHandle) Result;
+extern "c" fn formatrix_render_config_set_line_width(config: RenderConfigHandle, width: usize) Result;
+extern "c" fn formatrix_render_config_set_indent(config: RenderConfigHandle, indent: [*:0]const u8) Result;
+extern "c" fn formatrix_render_config_set_hard_breaks(config: RenderConfigHandle, value: bool) Result;
+extern "c" fn formatrix_render_config_set_option(config: RenderConfigHandle, key: [*:0]const u8, value: [*:0]const u8) Result;
+
 extern "c" fn formatrix_render(
-    handle: *const DocumentHandle,
+    handle: DocumentHandle,
     format: Format,
     out_content: *?[*:0]u8,
     out_length: *usize,
@@ -100,30 +215,105 @@ extern "c" fn formatrix_render(
 
 extern "c" fn formatrix_open_file(
     path: [*:0]const u8,
-    out_handle: *?*DocumentHandle,
+    out_handle: *DocumentHandle,
     out_format: *Format,
 ) Result;
 
 extern "c" fn formatrix_save_file(
-    handle: *const DocumentHandle,
+    handle: DocumentHandle,
     path: [*:0]const u8,
 ) Result;
 
 extern "c" fn formatrix_save_file_as(
-    handle: *const DocumentHandle,
+    handle: DocumentHandle,
     path: [*:0]const u8,
     format: Format,
 ) Result;
 
 extern "c" fn formatrix_get_title(
-    handle: *const DocumentHandle,
+    handle: DocumentHandle,
     out_title: *?[*:0]u8,
     out_length: *usize,
 ) Result;
 
-extern "c" fn formatrix_block_count(handle: *const DocumentHandle) usize;
+extern "c" fn formatrix_set_title(handle: DocumentHandle, title: ?[*:0]const u8) Result;
+
+extern "c" fn formatrix_get_authors(
+    handle: DocumentHandle,
+    out_authors: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_set_authors(handle: DocumentHandle, authors: ?[*:0]const u8) Result;
+
+extern "c" fn formatrix_get_date(
+    handle: DocumentHandle,
+    out_date: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_set_date(handle: DocumentHandle, date: ?[*:0]const u8) Result;
+
+extern "c" fn formatrix_get_language(
+    handle: DocumentHandle,
+    out_language: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_set_language(handle: DocumentHandle, language: ?[*:0]const u8) Result;
+
+extern "c" fn formatrix_get_meta_keys(
+    handle: DocumentHandle,
+    out_keys: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_get_meta(
+    handle: DocumentHandle,
+    key: [*:0]const u8,
+    out_value: *?[*:0]u8,
+    out_length: *usize,
+) Result;
 
-extern "c" fn formatrix_get_format(handle: *const DocumentHandle) Format;
+extern "c" fn formatrix_set_meta(
+    handle: DocumentHandle,
+    key: [*:0]const u8,
+    value: ?[*:0]const u8,
+) Result;
+
+extern "c" fn formatrix_block_count(handle: DocumentHandle) usize;
+
+extern "c" fn formatrix_block_kind(
+    handle: DocumentHandle,
+    index: usize,
+    out_kind: *BlockKind,
+) Result;
+
+extern "c" fn formatrix_block_heading_level(
+    handle: DocumentHandle,
+    index: usize,
+    out_level: *u8,
+) Result;
+
+extern "c" fn formatrix_block_text(
+    handle: DocumentHandle,
+    index: usize,
+    out_text: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_block_code_language(
+    handle: DocumentHandle,
+    index: usize,
+    out_language: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_word_count(handle: DocumentHandle) usize;
+
+extern "c" fn formatrix_char_count(handle: DocumentHandle) usize;
+
+extern "c" fn formatrix_get_format(handle: DocumentHandle) Format;
 
 extern "c" fn formatrix_detect_format(content: [*:0]const u8) Format;
 
@@ -137,33 +327,175 @@ extern "c" fn formatrix_convert(
     out_length: *usize,
 ) Result;
 
-extern "c" fn formatrix_free_document(handle: ?*DocumentHandle) void;
+extern "c" fn formatrix_document_to_json(
+    handle: DocumentHandle,
+    out_json: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_document_from_json(
+    json: [*:0]const u8,
+    out_handle: *DocumentHandle,
+) Result;
+
+extern "c" fn formatrix_supports_feature(format: Format, feature: [*:0]const u8) bool;
+
+extern "c" fn formatrix_supported_features(
+    format: Format,
+    out_features: *?[*:0]u8,
+    out_length: *usize,
+) Result;
+
+extern "c" fn formatrix_free_document(handle: DocumentHandle) Result;
 
 extern "c" fn formatrix_free_string(s: ?[*:0]u8) void;
 
 extern "c" fn formatrix_version() [*:0]const u8;
 
+extern "c" fn formatrix_abi_version() u32;
+
+extern "c" fn formatrix_check_abi(expected: u32) Result;
+
+extern "c" fn formatrix_capabilities() u64;
+
+extern "c" fn formatrix_has_capabilities(capabilities: u64) bool;
+
+extern "c" fn formatrix_library_info(out_info: *LibraryInfo) Result;
+
+fn check(result: Result) Error!void {
+    if (result.toError()) |err| {
+        return err;
+    }
+}
+
+/// Copy a library-owned string (null for unset) and free the original
+fn takeString(value: ?[*:0]u8, length: usize, allocator: std.mem.Allocator) std.mem.Allocator.Error!?[]u8 {
+    const ptr = value orelse return null;
+    defer formatrix_free_string(ptr);
+
+    const owned = try allocator.alloc(u8, length);
+    @memcpy(owned, ptr[0..length]);
+    return owned;
+}
+
+fn optionalPtr(value: ?[:0]const u8) ?[*:0]const u8 {
+    return if (value) |v| v.ptr else null;
+}
+
+/// Parse settings, passed to `Document.parseWithConfig`
+pub const ParseConfig = struct {
+    handle: ParseConfigHandle,
+
+    const Self = @This();
+
+    /// Create a configuration with default settings
+    pub fn init() Self {
+        return Self{ .handle = formatrix_parse_config_new() };
+    }
+
+    pub fn deinit(self: *Self) void {
+        _ = formatrix_parse_config_free(self.handle);
+        self.handle = 0;
+    }
+
+    pub fn setPreserveSpans(self: Self, value: bool) Error!void {
+        try check(formatrix_parse_config_set_preserve_spans(self.handle, value));
+    }
+
+    pub fn setPreserveRawSource(self: Self, value: bool) Error!void {
+        try check(formatrix_parse_config_set_preserve_raw_source(self.handle, value));
+    }
+
+    pub fn setStripZeroWidth(self: Self, value: bool) Error!void {
+        try check(formatrix_parse_config_set_strip_zero_width(self.handle, value));
+    }
+
+    pub fn setNormalization(self: Self, form: Normalization) Error!void {
+        try check(formatrix_parse_config_set_normalization(self.handle, form));
+    }
+
+    /// Front matter delimiter; null restores the default
+    pub fn setFrontMatterDelimiter(self: Self, delimiter: ?[:0]const u8) Error!void {
+        const ptr = if (delimiter) |d| d.ptr else null;
+        try check(formatrix_parse_config_set_front_matter_delimiter(self.handle, ptr));
+    }
+
+    pub fn setOption(self: Self, key: [:0]const u8, value: [:0]const u8) Error!void {
+        try check(formatrix_parse_config_set_option(self.handle, key.ptr, value.ptr));
+    }
+};
+
+/// Render settings, passed to `Document.renderWithConfig`
+pub const RenderConfig = struct {
+    handle: RenderConfigHandle,
+
+    const Self = @This();
+
+    /// Create a configuration with default settings
+    pub fn init() Self {
+        return Self{ .handle = formatrix_render_config_new() };
+    }
+
+    pub fn deinit(self: *Self) void {
+        _ = formatrix_render_config_free(self.handle);
+        self.handle = 0;
+    }
+
+    /// Target line width; 0 disables wrapping
+    pub fn setLineWidth(self: Self, width: usize) Error!void {
+        try check(formatrix_render_config_set_line_width(self.handle, width));
+    }
+
+    pub fn setIndent(self: Self, indent: [:0]const u8) Error!void {
+        try check(formatrix_render_config_set_indent(self.handle, indent.ptr));
+    }
+
+    pub fn setHardBreaks(self: Self, value: bool) Error!void {
+        try check(formatrix_render_config_set_hard_breaks(self.handle, value));
+    }
+
+    /// Set a flavor option such as "md.bullet"; invalid keys or values
+    /// return Error.InvalidInput
+    pub fn setOption(self: Self, key: [:0]const u8, value: [:0]const u8) Error!void {
+        try check(formatrix_render_config_set_option(self.handle, key.ptr, value.ptr));
+    }
+};
+
 /// A parsed document with automatic resource management
 pub const Document = struct {
-    handle: *DocumentHandle,
+    handle: DocumentHandle,
 
     const Self = @This();
 
     /// Parse content in the specified format
     pub fn parse(content: [:0]const u8, format: Format) Error!Self {
-        var handle: ?*DocumentHandle = null;
+        var handle: DocumentHandle = 0;
         const result = formatrix_parse(content.ptr, format, &handle);
 
         if (result.toError()) |err| {
             return err;
         }
 
-        return Self{ .handle = handle.? };
+        return Self{ .handle = handle };
+    }
+
+    /// Parse content with custom settings
+    pub fn parseWithConfig(content: [:0]const u8, format: Format, config: ParseConfig) Error!Self {
+        var handle: DocumentHandle = 0;
+        try check(formatrix_parse_with_config(content.ptr, format, config.handle, &handle));
+        return Self{ .handle = handle };
+    }
+
+    /// Build a document from its JSON AST (see docs/schema/document.schema.json)
+    pub fn fromJson(json: [:0]const u8) Error!Self {
+        var handle: DocumentHandle = 0;
+        try check(formatrix_document_from_json(json.ptr, &handle));
+        return Self{ .handle = handle };
     }
 
     /// Open a file and parse it
     pub fn openFile(path: [:0]const u8) Error!struct { doc: Self, format: Format } {
-        var handle: ?*DocumentHandle = null;
+        var handle: DocumentHandle = 0;
         var format: Format = .plain_text;
         const result = formatrix_open_file(path.ptr, &handle, &format);
 
@@ -172,15 +504,15 @@ pub const Document = struct {
         }
 
         return .{
-            .doc = Self{ .handle = handle.? },
+            .doc = Self{ .handle = handle },
             .format = format,
         };
     }
 
     /// Free the document resources
     pub fn deinit(self: *Self) void {
-        formatrix_free_document(self.handle);
-        self.handle = undefined;
+        _ = formatrix_free_document(self.handle);
+        self.handle = 0;
     }
 
     /// Render the document to the specified format
@@ -202,6 +534,52 @@ pub const Document = struct {
         return owned;
     }
 
+    /// Render progressively, passing chunks of about `chunk_size` bytes
+    /// (0 for the library default) to `callback`; the callback must not
+    /// modify or free this document
+    pub fn renderStreaming(
+        self: Self,
+        format: Format,
+        config: ?RenderConfig,
+        chunk_size: usize,
+        callback: ChunkCallback,
+        user_data: ?*anyopaque,
+    ) Error!void {
+        const config_handle: RenderConfigHandle = if (config) |c| c.handle else 0;
+        try check(formatrix_render_streaming(self.handle, format, config_handle, chunk_size, callback, user_data));
+    }
+
+    /// Render the document with custom settings
+    pub fn renderWithConfig(
+        self: Self,
+        format: Format,
+        config: RenderConfig,
+        allocator: std.mem.Allocator,
+    ) (Error || std.mem.Allocator.Error)![]u8 {
+        var content: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_render_with_config(self.handle, format, config.handle, &content, &length));
+        defer formatrix_free_string(content);
+
+        const owned = try allocator.alloc(u8, length);
+        @memcpy(owned, content.?[0..length]);
+        return owned;
+    }
+
+    /// The document's AST as JSON
+    pub fn toJson(self: Self, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)![]u8 {
+        var json: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_document_to_json(self.handle, &json, &length));
+        defer formatrix_free_string(json);
+
+        const owned = try allocator.alloc(u8, length);
+        @memcpy(owned, json.?[0..length]);
+        return owned;
+    }
+
     /// Save the document to a file (format detected from extension)
     pub fn saveFile(self: Self, path: [:0]const u8) Error!void {
         const result = formatrix_save_file(self.handle, path.ptr);
@@ -245,6 +623,120 @@ pub const Document = struct {
         return formatrix_block_count(self.handle);
     }
 
+    /// Words of prose (code excluded)
+    pub fn wordCount(self: Self) usize {
+        return formatrix_word_count(self.handle);
+    }
+
+    /// Characters of prose and code
+    pub fn charCount(self: Self) usize {
+        return formatrix_char_count(self.handle);
+    }
+
+    /// Kind of the top-level block at `index`
+    pub fn blockKind(self: Self, index: usize) Error!BlockKind {
+        var kind: BlockKind = .paragraph;
+        try check(formatrix_block_kind(self.handle, index, &kind));
+        return kind;
+    }
+
+    /// Level of the heading at `index`
+    pub fn headingLevel(self: Self, index: usize) Error!u8 {
+        var level: u8 = 0;
+        try check(formatrix_block_heading_level(self.handle, index, &level));
+        return level;
+    }
+
+    /// Plain text of the block at `index`
+    pub fn blockText(self: Self, index: usize, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)![]u8 {
+        var text: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_block_text(self.handle, index, &text, &length));
+        defer formatrix_free_string(text);
+
+        const owned = try allocator.alloc(u8, length);
+        @memcpy(owned, text.?[0..length]);
+        return owned;
+    }
+
+    /// Language of the code block at `index`, if it names one
+    pub fn codeLanguage(self: Self, index: usize, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)!?[]u8 {
+        var value: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_block_code_language(self.handle, index, &value, &length));
+        return takeString(value, length, allocator);
+    }
+
+    /// Set or (with null) clear the title
+    pub fn setTitle(self: Self, title: ?[:0]const u8) Error!void {
+        try check(formatrix_set_title(self.handle, optionalPtr(title)));
+    }
+
+    /// Authors, one per line
+    pub fn authors(self: Self, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)![]u8 {
+        var value: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_get_authors(self.handle, &value, &length));
+        return (try takeString(value, length, allocator)).?;
+    }
+
+    /// Replace the authors with the non-blank lines of `authors`
+    pub fn setAuthors(self: Self, names: ?[:0]const u8) Error!void {
+        try check(formatrix_set_authors(self.handle, optionalPtr(names)));
+    }
+
+    pub fn date(self: Self, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)!?[]u8 {
+        var value: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_get_date(self.handle, &value, &length));
+        return takeString(value, length, allocator);
+    }
+
+    pub fn setDate(self: Self, value: ?[:0]const u8) Error!void {
+        try check(formatrix_set_date(self.handle, optionalPtr(value)));
+    }
+
+    /// Document language as a BCP 47 tag
+    pub fn language(self: Self, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)!?[]u8 {
+        var value: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_get_language(self.handle, &value, &length));
+        return takeString(value, length, allocator);
+    }
+
+    pub fn setLanguage(self: Self, value: ?[:0]const u8) Error!void {
+        try check(formatrix_set_language(self.handle, optionalPtr(value)));
+    }
+
+    /// Custom front matter keys, sorted, one per line
+    pub fn metaKeys(self: Self, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)![]u8 {
+        var value: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_get_meta_keys(self.handle, &value, &length));
+        return (try takeString(value, length, allocator)).?;
+    }
+
+    /// A custom front matter value as plain text
+    pub fn meta(self: Self, key: [:0]const u8, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)!?[]u8 {
+        var value: ?[*:0]u8 = null;
+        var length: usize = 0;
+
+        try check(formatrix_get_meta(self.handle, key.ptr, &value, &length));
+        return takeString(value, length, allocator);
+    }
+
+    /// Set a custom front matter value (typed like an unquoted YAML
+    /// scalar), or remove the key with null
+    pub fn setMeta(self: Self, key: [:0]const u8, value: ?[:0]const u8) Error!void {
+        try check(formatrix_set_meta(self.handle, key.ptr, optionalPtr(value)));
+    }
+
     /// Get the source format of the document
     pub fn sourceFormat(self: Self) Format {
         return formatrix_get_format(self.handle);
@@ -290,11 +782,54 @@ pub fn detectFileFormat(path: [:0]const u8) Format {
     return formatrix_detect_file_format(path.ptr);
 }
 
+/// Whether `format` supports `feature` (e.g. "tables")
+pub fn supportsFeature(format: Format, feature: [:0]const u8) bool {
+    return formatrix_supports_feature(format, feature.ptr);
+}
+
+/// Features supported by `format`, one per line
+pub fn supportedFeatures(format: Format, allocator: std.mem.Allocator) (Error || std.mem.Allocator.Error)![]u8 {
+    var features: ?[*:0]u8 = null;
+    var length: usize = 0;
+
+    try check(formatrix_supported_features(format, &features, &length));
+    defer formatrix_free_string(features);
+
+    const owned = try allocator.alloc(u8, length);
+    @memcpy(owned, features.?[0..length]);
+    return owned;
+}
+
 /// Get the library version
 pub fn version() [:0]const u8 {
     return std.mem.span(formatrix_version());
 }
 
+/// Fail with Error.AbiMismatch unless the loaded library speaks the ABI
+/// these bindings were written for; call once at startup
+pub fn checkAbi() Error!void {
+    try check(formatrix_check_abi(abi_version));
+}
+
+/// Whether the loaded library supports every `Capability` bit in `bits`
+pub fn hasCapabilities(bits: u64) bool {
+    return formatrix_has_capabilities(bits);
+}
+
+/// Details of the loaded library
+pub fn libraryInfo() Error!LibraryInfo {
+    var info = LibraryInfo{
+        .struct_size = @sizeOf(LibraryInfo),
+        .abi_version = 0,
+        .capabilities = 0,
+        .version = "",
+        .format_count = 0,
+        .block_kind_count = 0,
+    };
+    try check(formatrix_library_info(&info));
+    return info;
+}
+
 // Tests
 test "format extension" {
     try std.testing.expectEqualStrings("md", Format.markdown.extension());
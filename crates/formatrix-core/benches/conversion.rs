@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Parse, render and parse→render throughput
+//!
+//! Every text run, code block and URL in the owned AST is a `String`, so
+//! parse cost scales with the number of inline nodes rather than just the
+//! input size. The `borrowed` group runs plain text through
+//! [`formatrix_core::borrowed`] instead, which borrows them from the input.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use formatrix_core::borrowed::{BorrowingParser, BorrowingRenderer};
+use formatrix_core::formats::{MarkdownHandler, PlainTextHandler};
+use formatrix_core::{FormatHandler, ParseConfig, RenderConfig};
+
+/// A Markdown-ish document of roughly `paragraphs * 300` bytes
+fn sample(paragraphs: usize) -> String {
+    let mut out = String::new();
+    for i in 0..paragraphs {
+        if i % 10 == 0 {
+            out.push_str(&format!("## Section {}\n\n", i / 10));
+        }
+        out.push_str(
+            "Lorem ipsum dolor sit amet, *consectetur* adipiscing elit, sed do \
+             eiusmod tempor incididunt ut labore et dolore magna aliqua. See \
+             [the docs](https://example.org/docs) and `inline code` for more. \
+             Ut enim ad minim veniam, quis nostrud exercitation ullamco.\n\n",
+        );
+        if i % 25 == 0 {
+            out.push_str("```rust\nfn main() {\n    println!(\"hello\");\n}\n```\n\n");
+        }
+    }
+    out
+}
+
+fn bench_handler(c: &mut Criterion, name: &str, handler: &dyn FormatHandler) {
+    let parse_config = ParseConfig::default();
+    let render_config = RenderConfig::default();
+    let mut group = c.benchmark_group(name);
+
+    for paragraphs in [100, 1_000, 10_000] {
+        let input = sample(paragraphs);
+        let doc = handler.parse(&input, &parse_config).unwrap();
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("parse", paragraphs), &input, |b, input| {
+            b.iter(|| handler.parse(black_box(input), &parse_config).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("render", paragraphs), &doc, |b, doc| {
+            b.iter(|| handler.render(black_box(doc), &render_config).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("round_trip", paragraphs),
+            &input,
+            |b, input| {
+                b.iter(|| {
+                    let doc = handler.parse(black_box(input), &parse_config).unwrap();
+                    handler.render(&doc, &render_config).unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// The plain text handler through the borrowed AST, to compare with the
+/// `plaintext` group
+fn bench_borrowed(c: &mut Criterion) {
+    let handler = PlainTextHandler::new();
+    let parse_config = ParseConfig::default();
+    let render_config = RenderConfig::default();
+    let mut group = c.benchmark_group("plaintext_borrowed");
+
+    for paragraphs in [100, 1_000, 10_000] {
+        let input = sample(paragraphs);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("parse", paragraphs), &input, |b, input| {
+            b.iter(|| {
+                handler
+                    .parse_borrowed(black_box(input), &parse_config)
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("round_trip", paragraphs),
+            &input,
+            |b, input| {
+                b.iter(|| {
+                    let doc = handler
+                        .parse_borrowed(black_box(input), &parse_config)
+                        .unwrap();
+                    handler.render_borrowed(&doc, &render_config).unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn conversion(c: &mut Criterion) {
+    bench_handler(c, "plaintext", &PlainTextHandler::new());
+    bench_borrowed(c);
+    bench_handler(c, "markdown", &MarkdownHandler::new());
+}
+
+criterion_group!(benches, conversion);
+criterion_main!(benches);
@@ -7,10 +7,15 @@
 //! The AST is format-agnostic — it represents the semantic structure
 //! of a document, not its syntactic surface form.
 
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 
 /// Source format of a document
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum SourceFormat {
     PlainText,
     Markdown,
@@ -19,9 +24,105 @@ pub enum SourceFormat {
     OrgMode,
     ReStructuredText,
     Typst,
+    /// A format provided by a third-party handler, identified by its
+    /// canonical extension; construct with [`SourceFormat::custom`]
+    Custom(&'static str),
+}
+
+/// Owned mirror of [`SourceFormat`] for deserialisation, which cannot
+/// borrow a `&'static str` from the input
+#[derive(Deserialize)]
+#[serde(rename = "SourceFormat")]
+enum SourceFormatRepr {
+    PlainText,
+    Markdown,
+    AsciiDoc,
+    Djot,
+    OrgMode,
+    ReStructuredText,
+    Typst,
+    Custom(String),
+}
+
+impl TryFrom<SourceFormatRepr> for SourceFormat {
+    type Error = String;
+
+    fn try_from(repr: SourceFormatRepr) -> std::result::Result<Self, String> {
+        Ok(match repr {
+            SourceFormatRepr::PlainText => SourceFormat::PlainText,
+            SourceFormatRepr::Markdown => SourceFormat::Markdown,
+            SourceFormatRepr::AsciiDoc => SourceFormat::AsciiDoc,
+            SourceFormatRepr::Djot => SourceFormat::Djot,
+            SourceFormatRepr::OrgMode => SourceFormat::OrgMode,
+            SourceFormatRepr::ReStructuredText => SourceFormat::ReStructuredText,
+            SourceFormatRepr::Typst => SourceFormat::Typst,
+            SourceFormatRepr::Custom(id) => SourceFormat::try_custom(&id).ok_or_else(|| {
+                format!(
+                    "unknown format {:?}: already {} custom formats",
+                    id, MAX_CUSTOM_FORMATS
+                )
+            })?,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        SourceFormatRepr::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Most distinct custom format ids a process will intern
+pub const MAX_CUSTOM_FORMATS: usize = 256;
+
+/// Interned custom format ids, so `SourceFormat` stays `Copy`
+static CUSTOM_IDS: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// `id` from `ids`, adding it if there is room
+fn intern_custom_id(ids: &mut BTreeSet<&'static str>, id: &str) -> Option<&'static str> {
+    if let Some(interned) = ids.get(id) {
+        return Some(interned);
+    }
+    if ids.len() >= MAX_CUSTOM_FORMATS {
+        return None;
+    }
+    let interned: &'static str = Box::leak(id.to_string().into_boxed_str());
+    ids.insert(interned);
+    Some(interned)
 }
 
 impl SourceFormat {
+    /// A user-defined format, identified by its canonical extension
+    /// (`"textile"`, `"wiki"`)
+    ///
+    /// Ids are interned: each distinct id is allocated once for the life of
+    /// the process.
+    ///
+    /// # Panics
+    ///
+    /// If [`MAX_CUSTOM_FORMATS`] other ids are already interned; see
+    /// [`SourceFormat::try_custom`].
+    pub fn custom(id: &str) -> Self {
+        Self::try_custom(id).expect("too many custom formats")
+    }
+
+    /// [`SourceFormat::custom`], or `None` if `id` is new and
+    /// [`MAX_CUSTOM_FORMATS`] ids are already interned
+    ///
+    /// Deserialisation goes through this, so documents naming arbitrary
+    /// formats can't grow the interner without bound.
+    pub fn try_custom(id: &str) -> Option<Self> {
+        let mut ids = CUSTOM_IDS.lock().unwrap_or_else(|e| e.into_inner());
+        intern_custom_id(&mut ids, id).map(SourceFormat::Custom)
+    }
+
+    /// Whether this is a user-defined format
+    pub fn is_custom(&self) -> bool {
+        matches!(self, SourceFormat::Custom(_))
+    }
+
     /// Get the canonical file extension for this format
     pub fn extension(&self) -> &'static str {
         match self {
@@ -32,6 +133,7 @@ pub fn extension(&self) -> &'static str {
             SourceFormat::OrgMode => "org",
             SourceFormat::ReStructuredText => "rst",
             SourceFormat::Typst => "typ",
+            SourceFormat::Custom(id) => id,
         }
     }
 
@@ -45,12 +147,14 @@ pub fn mime_type(&self) -> &'static str {
             SourceFormat::OrgMode => "text/org",
             SourceFormat::ReStructuredText => "text/x-rst",
             SourceFormat::Typst => "text/typst",
+            SourceFormat::Custom(_) => "text/plain",
         }
     }
 }
 
 /// Document metadata
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DocumentMeta {
     /// Document title (extracted from first heading or frontmatter)
     pub title: Option<String>,
@@ -61,15 +165,335 @@ pub struct DocumentMeta {
     /// Date from frontmatter
     pub date: Option<String>,
 
-    /// Arbitrary key-value metadata from frontmatter
-    pub frontmatter: std::collections::HashMap<String, String>,
+    /// Arbitrary key-value metadata from frontmatter, in key order
+    pub frontmatter: std::collections::BTreeMap<String, MetaValue>,
 
     /// Tags / keywords
     pub tags: Vec<String>,
+
+    /// Document language as a BCP 47 tag (front matter `lang`, Org
+    /// `#+LANGUAGE`, HTML `<html lang>`)
+    pub language: Option<String>,
+
+    /// Base text direction (HTML `dir`); inferred from `language` if unset
+    pub direction: Option<TextDirection>,
+}
+
+/// A typed front matter value
+///
+/// Serialised untagged, so JSON and YAML see plain scalars and arrays.
+/// A timestamp is written as its RFC 3339 string and, like every string,
+/// reads back as a [`MetaValue::String`]; [`MetaValue::as_datetime`]
+/// parses it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum MetaValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    /// Date or timestamp; a bare date is midnight UTC
+    #[serde(skip_deserializing)]
+    DateTime(DateTime<FixedOffset>),
+    String(String),
+    List(Vec<MetaValue>),
+}
+
+impl MetaValue {
+    /// Type an unquoted scalar the way YAML would: `true`/`false`, numbers
+    /// and ISO 8601 dates, falling back to a string
+    pub fn infer(value: &str) -> Self {
+        let value = value.trim();
+        match value {
+            "true" | "True" | "TRUE" => return MetaValue::Bool(true),
+            "false" | "False" | "FALSE" => return MetaValue::Bool(false),
+            _ => {}
+        }
+        if let Ok(n) = value.parse::<i64>() {
+            return MetaValue::Integer(n);
+        }
+        if value.contains('.') {
+            if let Ok(n) = value.parse::<f64>() {
+                if n.is_finite() {
+                    return MetaValue::Float(n);
+                }
+            }
+        }
+        match parse_datetime(value) {
+            Some(dt) => MetaValue::DateTime(dt),
+            None => MetaValue::String(value.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MetaValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Boolean value; also accepts the strings `true`/`false`/`yes`/`no`
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            MetaValue::Bool(b) => Some(*b),
+            MetaValue::String(s) => match s.to_lowercase().as_str() {
+                "true" | "yes" | "on" => Some(true),
+                "false" | "no" | "off" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Timestamp value; also parses ISO 8601 strings
+    pub fn as_datetime(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            MetaValue::DateTime(dt) => Some(*dt),
+            MetaValue::String(s) => parse_datetime(s),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MetaValue {
+    /// Plain text form: dates as `YYYY-MM-DD` when they have no time of
+    /// day, other timestamps as RFC 3339, lists comma-separated
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetaValue::Bool(b) => write!(f, "{}", b),
+            MetaValue::Integer(n) => write!(f, "{}", n),
+            // Debug keeps the `.0` on whole numbers so they read back as floats
+            MetaValue::Float(n) => write!(f, "{:?}", n),
+            MetaValue::DateTime(dt) => {
+                if dt.offset().local_minus_utc() == 0 && dt.time() == chrono::NaiveTime::MIN {
+                    write!(f, "{}", dt.format("%Y-%m-%d"))
+                } else {
+                    write!(f, "{}", dt.to_rfc3339())
+                }
+            }
+            MetaValue::String(s) => f.write_str(s),
+            MetaValue::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<&str> for MetaValue {
+    fn from(value: &str) -> Self {
+        MetaValue::String(value.to_string())
+    }
+}
+
+impl From<String> for MetaValue {
+    fn from(value: String) -> Self {
+        MetaValue::String(value)
+    }
+}
+
+impl From<bool> for MetaValue {
+    fn from(value: bool) -> Self {
+        MetaValue::Bool(value)
+    }
+}
+
+/// Parse an RFC 3339 timestamp, `YYYY-MM-DD HH:MM[:SS]` (taken as UTC) or
+/// a bare `YYYY-MM-DD` date (midnight UTC)
+pub fn parse_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt);
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_time(chrono::NaiveTime::MIN))
+        })?;
+    Some(naive.and_utc().fixed_offset())
+}
+
+/// Text direction for bidirectional text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+    /// Determined from the first strong character (HTML `dir="auto"`)
+    Auto,
+}
+
+impl TextDirection {
+    /// Conventional direction of a language ("ar", "he-IL", "fa" are RTL)
+    pub fn for_language(language: &str) -> Self {
+        let primary = language.split(['-', '_']).next().unwrap_or_default();
+        match primary.to_lowercase().as_str() {
+            "ar" | "he" | "iw" | "fa" | "ur" | "yi" | "ps" | "sd" | "dv" | "ug" | "ckb" | "syr" => {
+                TextDirection::Rtl
+            }
+            _ => TextDirection::Ltr,
+        }
+    }
+}
+
+impl DocumentMeta {
+    /// Effective base direction: explicit `direction`, else the language's
+    /// conventional direction, else left-to-right
+    pub fn effective_direction(&self) -> TextDirection {
+        self.direction.unwrap_or_else(|| {
+            self.language
+                .as_deref()
+                .map_or(TextDirection::Ltr, TextDirection::for_language)
+        })
+    }
+
+    /// Custom front matter field
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.frontmatter.get(key)
+    }
+
+    /// Custom field, if it is a string
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(MetaValue::as_str)
+    }
+
+    /// Custom field as a boolean (see [`MetaValue::as_bool`])
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(MetaValue::as_bool)
+    }
+
+    /// Custom field as a timestamp (see [`MetaValue::as_datetime`])
+    pub fn get_datetime(&self, key: &str) -> Option<DateTime<FixedOffset>> {
+        self.get(key).and_then(MetaValue::as_datetime)
+    }
+
+    /// Creation date: the document `date`, else a `created` field
+    pub fn created(&self) -> Option<DateTime<FixedOffset>> {
+        self.date
+            .as_deref()
+            .and_then(parse_datetime)
+            .or_else(|| self.get_datetime("created"))
+    }
+}
+
+/// A short, frequently repeated token: a code language, diagram engine,
+/// raw format or admonition kind
+///
+/// Clones share one allocation, and an [`Interner`](crate::intern::Interner)
+/// makes equal names across documents share it too. Serialised as a plain
+/// string.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name(Arc<str>);
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Name(Arc::from(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether both names share one allocation
+    pub fn ptr_eq(a: &Name, b: &Name) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl std::ops::Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::borrow::Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Name(Arc::from(name))
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Serialize for Name {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Name::from)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Name {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
 }
 
 /// Source span for error reporting and lossless round-trip
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Span {
     /// Start byte offset
     pub start: usize,
@@ -83,6 +507,7 @@ pub struct Span {
 
 /// A complete document
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Document {
     /// The format this document was parsed from
     pub source_format: SourceFormat,
@@ -100,6 +525,7 @@ pub struct Document {
 
 /// A list item
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ListItem {
     /// Content blocks within the list item
     pub content: Vec<Block>,
@@ -110,6 +536,7 @@ pub struct ListItem {
 
 /// Block-level content elements
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Block {
     /// A paragraph of inline content
     Paragraph {
@@ -127,11 +554,21 @@ pub enum Block {
 
     /// A fenced or indented code block
     CodeBlock {
-        language: Option<String>,
+        language: Option<Name>,
         content: String,
         span: Option<Span>,
     },
 
+    /// Diagram source for a rendering engine (Mermaid, PlantUML, Graphviz)
+    ///
+    /// Text formats write it back as a source fence; exports can swap it for
+    /// rendered SVG via [`crate::diagram::DiagramRenderer`].
+    Diagram {
+        engine: Name,
+        source: String,
+        span: Option<Span>,
+    },
+
     /// A block quote
     BlockQuote {
         content: Vec<Block>,
@@ -147,9 +584,7 @@ pub enum Block {
     },
 
     /// A thematic break / horizontal rule
-    ThematicBreak {
-        span: Option<Span>,
-    },
+    ThematicBreak { span: Option<Span> },
 
     /// A table
     Table {
@@ -161,7 +596,7 @@ pub enum Block {
 
     /// Raw content in a specific format (passthrough)
     Raw {
-        format: Option<String>,
+        format: Option<Name>,
         content: String,
         span: Option<Span>,
     },
@@ -174,7 +609,7 @@ pub enum Block {
 
     /// An admonition / callout (note, warning, tip, etc.)
     Admonition {
-        kind: String,
+        kind: Name,
         title: Option<Vec<Inline>>,
         content: Vec<Block>,
         span: Option<Span>,
@@ -186,10 +621,127 @@ pub enum Block {
         content: Vec<Block>,
         span: Option<Span>,
     },
+
+    /// Verse / line block: each line is kept exactly as written
+    ///
+    /// RST `| ` line blocks, Org `#+BEGIN_VERSE`, AsciiDoc `[verse]`.
+    /// Leading whitespace of each line is preserved in its first text
+    /// inline, for indented verse and address blocks.
+    LineBlock {
+        lines: Vec<Vec<Inline>>,
+        span: Option<Span>,
+    },
+
+    /// Sidebar / aside: content set apart from the main flow
+    ///
+    /// AsciiDoc `****` sidebars and HTML `<aside>`; formats without a native
+    /// construct render it like an admonition titled with `title`.
+    Aside {
+        title: Option<Vec<Inline>>,
+        content: Vec<Block>,
+        span: Option<Span>,
+    },
+
+    /// A collapsible block with an always-visible summary
+    ///
+    /// HTML/Markdown `<details>`, Org `#+BEGIN_DETAILS`, AsciiDoc
+    /// `[%collapsible]`; formats without a native construct fall back to
+    /// the summary as a heading-like line followed by the content.
+    Details {
+        summary: Vec<Inline>,
+        content: Vec<Block>,
+        /// Expanded by default
+        open: bool,
+        span: Option<Span>,
+    },
+
+    /// Content in a different language or direction from its surroundings
+    ///
+    /// HTML `<div lang dir>`, Djot/Pandoc `::: {lang=...}` divs, AsciiDoc
+    /// `[lang=...]` roles.
+    Language {
+        lang: Option<Name>,
+        dir: Option<TextDirection>,
+        content: Vec<Block>,
+        span: Option<Span>,
+    },
+
+    /// An unresolved include/embed directive (see [`crate::include`])
+    Include { target: String, span: Option<Span> },
+
+    /// A numbered, captioned float (figure, table, equation or listing)
+    Figure {
+        kind: FigureKind,
+        id: Option<String>,
+        caption: Option<Vec<Inline>>,
+        content: Vec<Block>,
+        span: Option<Span>,
+    },
+}
+
+impl Block {
+    /// Source span of this block, if the parser recorded one
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Block::Paragraph { span, .. }
+            | Block::Heading { span, .. }
+            | Block::CodeBlock { span, .. }
+            | Block::Diagram { span, .. }
+            | Block::BlockQuote { span, .. }
+            | Block::List { span, .. }
+            | Block::ThematicBreak { span, .. }
+            | Block::Table { span, .. }
+            | Block::Raw { span, .. }
+            | Block::DefinitionList { span, .. }
+            | Block::Admonition { span, .. }
+            | Block::FootnoteDefinition { span, .. }
+            | Block::LineBlock { span, .. }
+            | Block::Aside { span, .. }
+            | Block::Details { span, .. }
+            | Block::Language { span, .. }
+            | Block::Include { span, .. }
+            | Block::Figure { span, .. } => span.as_ref(),
+        }
+    }
+
+    /// Mutable access to the block's source span
+    pub fn span_mut(&mut self) -> Option<&mut Span> {
+        match self {
+            Block::Paragraph { span, .. }
+            | Block::Heading { span, .. }
+            | Block::CodeBlock { span, .. }
+            | Block::Diagram { span, .. }
+            | Block::BlockQuote { span, .. }
+            | Block::List { span, .. }
+            | Block::ThematicBreak { span, .. }
+            | Block::Table { span, .. }
+            | Block::Raw { span, .. }
+            | Block::DefinitionList { span, .. }
+            | Block::Admonition { span, .. }
+            | Block::FootnoteDefinition { span, .. }
+            | Block::LineBlock { span, .. }
+            | Block::Aside { span, .. }
+            | Block::Details { span, .. }
+            | Block::Language { span, .. }
+            | Block::Include { span, .. }
+            | Block::Figure { span, .. } => span.as_mut(),
+        }
+    }
+}
+
+/// The kind of float a [`Block::Figure`] wraps; each kind is numbered separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FigureKind {
+    Figure,
+    Table,
+    Equation,
+    Listing,
 }
 
 /// Table column alignment
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Alignment {
     Left,
     Center,
@@ -199,6 +751,7 @@ pub enum Alignment {
 
 /// Inline content elements
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Inline {
     /// Plain text
     Text { content: String },
@@ -212,7 +765,7 @@ pub enum Inline {
     /// Inline code
     Code {
         content: String,
-        language: Option<String>,
+        language: Option<Name>,
     },
 
     /// A hyperlink
@@ -227,6 +780,25 @@ pub enum Inline {
         url: String,
         alt: String,
         title: Option<String>,
+        /// Intrinsic size in pixels, when known (see
+        /// [`InlineImages`](crate::transform::InlineImages))
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<u32>,
+    },
+
+    /// An embedded audio clip (HTML5 `<audio>`; a link in text formats)
+    Audio { url: String, title: Option<String> },
+
+    /// An embedded video (HTML5 `<video>`; a link in text formats)
+    Video {
+        url: String,
+        title: Option<String>,
+        /// Poster image shown before playback
+        poster: Option<String>,
+        width: Option<u32>,
+        height: Option<u32>,
     },
 
     /// A hard line break
@@ -244,12 +816,20 @@ pub enum Inline {
     /// Subscript
     Subscript { content: Vec<Inline> },
 
+    /// A run of text in a different language or direction
+    /// (HTML `<span lang dir>`, `<bdi>`, Djot `[text]{lang=he}`)
+    Language {
+        lang: Option<Name>,
+        dir: Option<TextDirection>,
+        content: Vec<Inline>,
+    },
+
     /// A footnote reference
     FootnoteReference { label: String },
 
     /// Raw inline content (e.g. HTML)
     RawInline {
-        format: Option<String>,
+        format: Option<Name>,
         content: String,
     },
 
@@ -258,4 +838,50 @@ pub enum Inline {
 
     /// Math (display/block)
     DisplayMath { content: String },
+
+    /// A standalone link target (RST `.. _target:`, AsciiDoc `[[anchor]]`,
+    /// HTML `<a id>`); renders as nothing visible
+    Anchor { id: String },
+
+    /// Ruby annotation: a pronunciation/gloss shown above `base`
+    ///
+    /// HTML `<ruby>base<rt>annotation</rt></ruby>`, Typst/AsciiDoc via raw
+    /// HTML or macros; plain formats fall back to `base(annotation)`.
+    Ruby { base: String, annotation: String },
+
+    /// An invisible index entry marking this spot for the back-of-book
+    /// index (AsciiDoc `(((term)))`, `indexterm:[]`, LaTeX `\index{}`)
+    IndexTerm {
+        term: String,
+        subterm: Option<String>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_like_strings_stay_strings() {
+        let value = MetaValue::String("2024-05-02T10:30:00+02:00".to_string());
+        let json = serde_json::to_string(&value).unwrap();
+        let parsed: MetaValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, value);
+
+        let timestamp = MetaValue::infer("2024-05-02T10:30:00+02:00");
+        let json = serde_json::to_string(&timestamp).unwrap();
+        let parsed: MetaValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.as_datetime(), timestamp.as_datetime());
+    }
+
+    #[test]
+    fn test_custom_ids_are_capped() {
+        let mut ids = BTreeSet::new();
+        for i in 0..MAX_CUSTOM_FORMATS {
+            assert!(intern_custom_id(&mut ids, &format!("f{}", i)).is_some());
+        }
+        assert_eq!(intern_custom_id(&mut ids, "f0"), Some("f0"));
+        assert_eq!(intern_custom_id(&mut ids, "one-too-many"), None);
+        assert_eq!(ids.len(), MAX_CUSTOM_FORMATS);
+    }
 }
@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Copy-on-write AST for parse→render conversions
+//!
+//! [`Document`] owns every string, so a parse allocates once per text run,
+//! code block and heading. A [`DocumentRef`] borrows those from the source
+//! instead: its common nodes hold `Cow<'src, str>`, and every other node is
+//! an ordinary owned [`Block`] or [`Inline`], so a handler can move over
+//! one node type at a time. [`BorrowingParser::parse_borrowed`] builds one
+//! and [`BorrowingRenderer::render_borrowed`] renders it; both default to
+//! going through the owned AST, and
+//! [`crate::formats::PlainTextHandler`] overrides them.
+//! [`DocumentRef::into_owned`] gives the ordinary AST when a transform or
+//! the FFI needs it.
+
+use crate::ast::{Block, Document, DocumentMeta, Inline, Name, SourceFormat, Span};
+use crate::traits::{ParseConfig, Parser, RenderConfig, Renderer, Result};
+use std::borrow::Cow;
+
+/// An inline that may borrow its text from the source
+#[derive(Debug, Clone)]
+pub enum InlineRef<'src> {
+    Text(Cow<'src, str>),
+    Code {
+        content: Cow<'src, str>,
+        language: Option<Name>,
+    },
+    Owned(Inline),
+}
+
+/// A block that may borrow its text from the source
+#[derive(Debug, Clone)]
+pub enum BlockRef<'src> {
+    Paragraph {
+        content: Vec<InlineRef<'src>>,
+        span: Option<Span>,
+    },
+    Heading {
+        level: u8,
+        content: Vec<InlineRef<'src>>,
+        id: Option<String>,
+        span: Option<Span>,
+    },
+    CodeBlock {
+        language: Option<Name>,
+        content: Cow<'src, str>,
+        span: Option<Span>,
+    },
+    Owned(Block),
+}
+
+/// A [`Document`] whose common nodes may borrow from the source
+#[derive(Debug, Clone)]
+pub struct DocumentRef<'src> {
+    pub source_format: SourceFormat,
+    pub meta: DocumentMeta,
+    pub content: Vec<BlockRef<'src>>,
+    pub raw_source: Option<Cow<'src, str>>,
+}
+
+impl InlineRef<'_> {
+    pub fn into_owned(self) -> Inline {
+        match self {
+            InlineRef::Text(content) => Inline::Text {
+                content: content.into_owned(),
+            },
+            InlineRef::Code { content, language } => Inline::Code {
+                content: content.into_owned(),
+                language,
+            },
+            InlineRef::Owned(inline) => inline,
+        }
+    }
+}
+
+impl BlockRef<'_> {
+    pub fn into_owned(self) -> Block {
+        fn inlines(content: Vec<InlineRef>) -> Vec<Inline> {
+            content.into_iter().map(InlineRef::into_owned).collect()
+        }
+        match self {
+            BlockRef::Paragraph { content, span } => Block::Paragraph {
+                content: inlines(content),
+                span,
+            },
+            BlockRef::Heading {
+                level,
+                content,
+                id,
+                span,
+            } => Block::Heading {
+                level,
+                content: inlines(content),
+                id,
+                span,
+            },
+            BlockRef::CodeBlock {
+                language,
+                content,
+                span,
+            } => Block::CodeBlock {
+                language,
+                content: content.into_owned(),
+                span,
+            },
+            BlockRef::Owned(block) => block,
+        }
+    }
+}
+
+impl DocumentRef<'_> {
+    pub fn into_owned(self) -> Document {
+        Document {
+            source_format: self.source_format,
+            meta: self.meta,
+            content: self.content.into_iter().map(BlockRef::into_owned).collect(),
+            raw_source: self.raw_source.map(Cow::into_owned),
+        }
+    }
+}
+
+impl From<Document> for DocumentRef<'_> {
+    fn from(doc: Document) -> Self {
+        Self {
+            source_format: doc.source_format,
+            meta: doc.meta,
+            content: doc.content.into_iter().map(BlockRef::Owned).collect(),
+            raw_source: doc.raw_source.map(Cow::Owned),
+        }
+    }
+}
+
+/// A parser that can borrow text from its input
+pub trait BorrowingParser: Parser {
+    /// Parse `input` into a [`DocumentRef`] borrowing from it
+    fn parse_borrowed<'src>(
+        &self,
+        input: &'src str,
+        config: &ParseConfig,
+    ) -> Result<DocumentRef<'src>> {
+        Ok(self.parse(input, config)?.into())
+    }
+}
+
+/// A renderer that can render a [`DocumentRef`] without owning it
+pub trait BorrowingRenderer: Renderer {
+    fn render_borrowed(&self, doc: &DocumentRef<'_>, config: &RenderConfig) -> Result<String> {
+        self.render(&doc.clone().into_owned(), config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::PlainTextHandler;
+
+    #[test]
+    fn test_plaintext_borrows_from_source() {
+        let handler = PlainTextHandler::new();
+        let config = ParseConfig {
+            preserve_spans: true,
+            ..Default::default()
+        };
+        let source = "First  paragraph\nwraps.\n\n  Second one.\n\n\n\nThird";
+        let borrowed = handler.parse_borrowed(source, &config).unwrap();
+        assert!(borrowed.content.iter().all(|block| matches!(
+            block,
+            BlockRef::Paragraph { content, .. }
+                if matches!(content[..], [InlineRef::Text(Cow::Borrowed(_))])
+        )));
+
+        let owned = handler.parse(source, &config).unwrap();
+        let render = RenderConfig::default();
+        assert_eq!(
+            handler.render_borrowed(&borrowed, &render).unwrap(),
+            handler.render(&owned, &render).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(borrowed.into_owned()).unwrap(),
+            serde_json::to_value(owned).unwrap()
+        );
+    }
+}
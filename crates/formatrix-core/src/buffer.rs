@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Rope-backed editing buffer
+//!
+//! [`DocumentBuffer`] holds the text an editor is working on in a rope,
+//! so inserts and deletes anywhere are cheap regardless of document size,
+//! and keeps the parsed [`Document`] alongside it. Edits are queued and
+//! applied to the AST with [`incremental::reparse`] the next time the
+//! document is requested.
+//!
+//! Offsets are byte offsets, like [`Span`](crate::ast::Span); positions
+//! are 1-based lines and 1-based columns counted in characters. Spans
+//! always index the buffer's own text, so it is parsed without input
+//! normalization; normalize text before loading it instead.
+
+use crate::ast::{Document, SourceFormat};
+use crate::incremental::{self, TextEdit};
+use crate::traits::{ConversionError, FormatRegistry, ParseConfig, Result};
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// A line/column position in the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    /// Line number (1-based)
+    pub line: usize,
+    /// Column in characters (1-based)
+    pub column: usize,
+}
+
+/// Editable text plus its parsed document
+pub struct DocumentBuffer {
+    rope: Rope,
+    format: SourceFormat,
+    config: ParseConfig,
+    /// Last parsed text and document; `pending` edits apply on top of it
+    parsed: Option<(String, Document)>,
+    pending: Vec<TextEdit>,
+    /// Blocks re-parsed by the latest [`DocumentBuffer::document`] call
+    changed: Range<usize>,
+}
+
+impl DocumentBuffer {
+    pub fn new(text: &str, format: SourceFormat) -> Self {
+        Self {
+            rope: Rope::from_str(text),
+            format,
+            config: ParseConfig {
+                preserve_spans: true,
+                ..Default::default()
+            },
+            parsed: None,
+            pending: Vec::new(),
+            changed: 0..0,
+        }
+    }
+
+    /// Parse with this configuration (spans are always kept, and the
+    /// normalization options are ignored)
+    pub fn with_config(mut self, config: ParseConfig) -> Self {
+        self.config = ParseConfig {
+            preserve_spans: true,
+            unicode_normalization: None,
+            strip_zero_width: false,
+            ..config
+        };
+        self.parsed = None;
+        self
+    }
+
+    pub fn format(&self) -> SourceFormat {
+        self.format
+    }
+
+    pub fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
+    pub fn text(&self) -> String {
+        self.rope.to_string()
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    /// Text of a 1-based line, including its line break
+    pub fn line(&self, line: usize) -> Option<String> {
+        let index = line.checked_sub(1)?;
+        (index < self.rope.len_lines()).then(|| self.rope.line(index).to_string())
+    }
+
+    /// Replace the bytes in `range` with `text`
+    pub fn replace(&mut self, range: Range<usize>, text: &str) -> Result<()> {
+        let start = self.char_index(range.start)?;
+        let end = self.char_index(range.end)?;
+        if start > end {
+            return Err(offset_error(range.start, self.len_bytes()));
+        }
+        self.rope.remove(start..end);
+        self.rope.insert(start, text);
+        self.pending.push(TextEdit::new(range, text));
+        Ok(())
+    }
+
+    pub fn insert(&mut self, offset: usize, text: &str) -> Result<()> {
+        self.replace(offset..offset, text)
+    }
+
+    pub fn delete(&mut self, range: Range<usize>) -> Result<()> {
+        self.replace(range, "")
+    }
+
+    /// Replace the whole text
+    pub fn set_text(&mut self, text: &str) {
+        self.rope = Rope::from_str(text);
+        self.parsed = None;
+        self.pending.clear();
+    }
+
+    /// Line and column of a byte offset
+    pub fn position(&self, offset: usize) -> Result<Position> {
+        let char_index = self.char_index(offset)?;
+        let line = self.rope.char_to_line(char_index);
+        Ok(Position {
+            line: line + 1,
+            column: char_index - self.rope.line_to_char(line) + 1,
+        })
+    }
+
+    /// Byte offset of a position; columns past the end of the line clamp
+    /// to its end (before the line break)
+    pub fn offset(&self, position: Position) -> Option<usize> {
+        let line = position.line.checked_sub(1)?;
+        if line >= self.rope.len_lines() {
+            return None;
+        }
+        let line_start = self.rope.line_to_char(line);
+        let content = self.rope.line(line);
+        let mut line_len = content.len_chars();
+        for c in ['\n', '\r'] {
+            if line_len > 0 && content.char(line_len - 1) == c {
+                line_len -= 1;
+            }
+        }
+        let column = position.column.saturating_sub(1).min(line_len);
+        Some(self.rope.char_to_byte(line_start + column))
+    }
+
+    /// The parsed document, bringing it up to date with any edits
+    pub fn document(&mut self) -> Result<&Document> {
+        let handler = FormatRegistry::global().get(self.format).ok_or(
+            ConversionError::UnsupportedFeature {
+                format: self.format,
+                feature: "parsing".to_string(),
+            },
+        )?;
+
+        match self.parsed.take() {
+            Some((mut text, mut document)) => {
+                let mut changed: Option<Range<usize>> = None;
+                for edit in self.pending.drain(..) {
+                    let reparsed =
+                        incremental::reparse(handler, &document, &text, &edit, &self.config)?;
+                    text = edit.apply(&text)?;
+                    document = reparsed.document;
+                    changed = Some(match changed {
+                        Some(c) => {
+                            c.start.min(reparsed.changed.start)..c.end.max(reparsed.changed.end)
+                        }
+                        None => reparsed.changed,
+                    });
+                }
+                if let Some(changed) = changed {
+                    // Later edits may have removed blocks from an earlier range
+                    let len = document.content.len();
+                    self.changed = changed.start.min(len)..changed.end.min(len);
+                }
+                self.parsed = Some((text, document));
+            }
+            None => {
+                let text = self.text();
+                let document = handler.parse(&text, &self.config)?;
+                self.changed = 0..document.content.len();
+                self.pending.clear();
+                self.parsed = Some((text, document));
+            }
+        }
+        Ok(&self.parsed.as_ref().expect("parsed above").1)
+    }
+
+    /// Indices of the blocks re-parsed by the latest
+    /// [`document`](DocumentBuffer::document) call
+    pub fn changed_blocks(&self) -> Range<usize> {
+        self.changed.clone()
+    }
+
+    fn char_index(&self, offset: usize) -> Result<usize> {
+        let len = self.rope.len_bytes();
+        if offset > len {
+            return Err(offset_error(offset, len));
+        }
+        let index = self.rope.byte_to_char(offset);
+        if self.rope.char_to_byte(index) != offset {
+            return Err(offset_error(offset, len));
+        }
+        Ok(index)
+    }
+}
+
+fn offset_error(offset: usize, len: usize) -> ConversionError {
+    ConversionError::ParseError {
+        line: 0,
+        column: 0,
+        message: format!(
+            "offset {} is not a character boundary in the {}-byte buffer",
+            offset, len
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Block;
+    use crate::visit;
+
+    fn paragraphs(doc: &Document) -> Vec<String> {
+        doc.content
+            .iter()
+            .map(|b| match b {
+                Block::Paragraph { content, .. } => visit::inlines_to_text(content),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edits_update_document() {
+        let mut buffer = DocumentBuffer::new("One\n\nTwo\n\nThree", SourceFormat::PlainText);
+        assert_eq!(
+            paragraphs(buffer.document().unwrap()),
+            ["One", "Two", "Three"]
+        );
+
+        buffer.insert(8, " and a half").unwrap();
+        buffer.delete(0..5).unwrap();
+        assert_eq!(buffer.text(), "Two and a half\n\nThree");
+        assert_eq!(
+            paragraphs(buffer.document().unwrap()),
+            ["Two and a half", "Three"]
+        );
+        assert_eq!(buffer.changed_blocks(), 0..2);
+    }
+
+    #[test]
+    fn test_spans_index_unnormalized_text() {
+        let config = ParseConfig {
+            unicode_normalization: Some(crate::UnicodeNormalization::Nfc),
+            strip_zero_width: true,
+            ..Default::default()
+        };
+        let text = "cafe\u{301}\u{200b}\n\nTwo\n\nThree";
+        let mut buffer = DocumentBuffer::new(text, SourceFormat::PlainText).with_config(config);
+        let doc = buffer.document().unwrap();
+        assert_eq!(paragraphs(doc)[0], "cafe\u{301}\u{200b}");
+        match &doc.content[1] {
+            Block::Paragraph { span, .. } => {
+                assert_eq!(span.as_ref().map(|s| s.start), text.find("Two"));
+            }
+            _ => panic!("expected a paragraph"),
+        }
+
+        buffer.insert(text.len(), "!").unwrap();
+        assert_eq!(paragraphs(buffer.document().unwrap())[2], "Three!");
+        assert_eq!(buffer.changed_blocks(), 1..3);
+    }
+
+    #[test]
+    fn test_positions() {
+        let buffer = DocumentBuffer::new("héllo\nwörld\n", SourceFormat::PlainText);
+
+        let offset = "héllo\nwö".len();
+        let position = buffer.position(offset).unwrap();
+        assert_eq!(position, Position { line: 2, column: 3 });
+        assert_eq!(buffer.offset(position), Some(offset));
+        assert_eq!(
+            buffer.offset(Position {
+                line: 1,
+                column: 99
+            }),
+            Some("héllo".len())
+        );
+        assert_eq!(buffer.offset(Position { line: 9, column: 1 }), None);
+
+        // Inside the two-byte 'é'
+        assert!(buffer.position(2).is_err());
+        assert_eq!(buffer.line(2).as_deref(), Some("wörld\n"));
+    }
+}
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Binary serialization and JSON Schema for the AST
+//!
+//! Documents serialize to CBOR with the same serde shape as their JSON
+//! form, typically at well under half the size, which is what the database
+//! stores. The JSON form is described by a JSON Schema published at
+//! `docs/schema/document.schema.json` for consumers in other languages;
+//! [`json_schema`] (feature `schema`) regenerates it.
+
+use crate::ast::Document;
+use crate::traits::{ConversionError, Result};
+use std::io::{Read, Write};
+
+/// Encode a document as CBOR
+pub fn to_cbor(doc: &Document) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    write_cbor(doc, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode a document from CBOR
+pub fn from_cbor(bytes: &[u8]) -> Result<Document> {
+    read_cbor(bytes)
+}
+
+/// Encode a document as CBOR into `writer`
+pub fn write_cbor<W: Write>(doc: &Document, writer: W) -> Result<()> {
+    ciborium::into_writer(doc, writer).map_err(|e| match e {
+        ciborium::ser::Error::Io(e) => ConversionError::IoError(e),
+        e => ConversionError::SerializationError(e.to_string()),
+    })
+}
+
+/// Decode a document from CBOR read from `reader`
+pub fn read_cbor<R: Read>(reader: R) -> Result<Document> {
+    ciborium::from_reader(reader).map_err(|e| match e {
+        ciborium::de::Error::Io(e) => ConversionError::IoError(e),
+        e => ConversionError::SerializationError(e.to_string()),
+    })
+}
+
+/// JSON Schema for the JSON form of [`Document`]
+#[cfg(feature = "schema")]
+pub fn json_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Document)).expect("schema is valid JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Block, Inline, MetaValue, SourceFormat};
+    use crate::formats::PlainTextHandler;
+    use crate::traits::{ParseConfig, Parser};
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let mut doc = PlainTextHandler::new()
+            .parse("Hello world\n\nSecond paragraph", &ParseConfig::default())
+            .unwrap();
+        doc.source_format = SourceFormat::custom("cbor-test");
+        doc.meta.title = Some("Title".to_string());
+        doc.meta
+            .frontmatter
+            .insert("draft".to_string(), MetaValue::Bool(true));
+        doc.content.push(Block::CodeBlock {
+            language: Some("rust".into()),
+            content: "fn main() {}".to_string(),
+            span: None,
+        });
+
+        let bytes = to_cbor(&doc).unwrap();
+        let json = serde_json::to_vec(&doc).unwrap();
+        assert!(bytes.len() < json.len());
+
+        let decoded = from_cbor(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&doc).unwrap()
+        );
+        assert!(matches!(
+            &decoded.content[0],
+            Block::Paragraph { content, .. }
+                if matches!(&content[0], Inline::Text { content } if content == "Hello world")
+        ));
+    }
+
+    #[test]
+    fn test_invalid_cbor() {
+        assert!(matches!(
+            from_cbor(&[0xff, 0x00]),
+            Err(ConversionError::SerializationError(_))
+        ));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_published_schema_is_current() {
+        let published: serde_json::Value =
+            serde_json::from_str(include_str!("../../../docs/schema/document.schema.json"))
+                .unwrap();
+        assert_eq!(
+            published,
+            json_schema(),
+            "regenerate docs/schema/document.schema.json"
+        );
+    }
+}
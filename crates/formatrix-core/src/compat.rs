@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Feature compatibility between formats
+//!
+//! Compares the handlers' [`supported_features`] so tools can warn before
+//! a conversion which constructs (tables, math, footnotes) the target
+//! format cannot represent.
+//!
+//! [`supported_features`]: crate::traits::FormatHandler::supported_features
+
+use crate::ast::SourceFormat;
+use crate::traits::FormatRegistry;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A feature of the source format that the target format lacks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureLoss {
+    pub feature: String,
+    pub from: SourceFormat,
+    pub to: SourceFormat,
+}
+
+impl fmt::Display for FeatureLoss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not supported in .{} and will be degraded",
+            self.feature,
+            self.to.extension()
+        )
+    }
+}
+
+impl FormatRegistry {
+    /// Features supported by `from` but not by `to`
+    ///
+    /// A format with no registered handler counts as supporting nothing.
+    pub fn compatibility(&self, from: SourceFormat, to: SourceFormat) -> Vec<FeatureLoss> {
+        let Some(source) = self.get(from) else {
+            return Vec::new();
+        };
+        let target = self.get(to);
+
+        source
+            .supported_features()
+            .iter()
+            .filter(|feature| !target.is_some_and(|t| t.supports_feature(feature)))
+            .map(|feature| FeatureLoss {
+                feature: feature.to_string(),
+                from,
+                to,
+            })
+            .collect()
+    }
+}
+
+/// [`FormatRegistry::compatibility`] for the built-in handlers
+pub fn compatibility(from: SourceFormat, to: SourceFormat) -> Vec<FeatureLoss> {
+    FormatRegistry::global().compatibility(from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Document;
+    use crate::traits::{FormatHandler, ParseConfig, Parser, RenderConfig, Renderer, Result};
+
+    struct Handler(SourceFormat, &'static [&'static str]);
+
+    impl Parser for Handler {
+        fn format(&self) -> SourceFormat {
+            self.0
+        }
+
+        fn parse(&self, input: &str, config: &ParseConfig) -> Result<Document> {
+            crate::formats::PlainTextHandler::new().parse(input, config)
+        }
+    }
+
+    impl Renderer for Handler {
+        fn format(&self) -> SourceFormat {
+            self.0
+        }
+
+        fn render(&self, doc: &Document, config: &RenderConfig) -> Result<String> {
+            crate::formats::PlainTextHandler::new().render(doc, config)
+        }
+    }
+
+    impl FormatHandler for Handler {
+        fn supports_feature(&self, feature: &str) -> bool {
+            self.1.contains(&feature)
+        }
+
+        fn supported_features(&self) -> &[&str] {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_compatibility() {
+        let rich = SourceFormat::custom("rich");
+        let lean = SourceFormat::custom("lean");
+        let mut registry = FormatRegistry::new();
+        registry.register(Box::new(Handler(rich, &["tables", "math", "footnotes"])));
+        registry.register(Box::new(Handler(lean, &["tables"])));
+
+        let losses = registry.compatibility(rich, lean);
+        let features: Vec<_> = losses.iter().map(|l| l.feature.as_str()).collect();
+        assert_eq!(features, ["math", "footnotes"]);
+        assert_eq!(
+            losses[0].to_string(),
+            "math is not supported in .lean and will be degraded"
+        );
+
+        assert!(registry.compatibility(lean, rich).is_empty());
+        assert_eq!(
+            registry
+                .compatibility(rich, SourceFormat::custom("unregistered"))
+                .len(),
+            3
+        );
+    }
+}
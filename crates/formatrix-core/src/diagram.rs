@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Diagram rendering hooks
+//!
+//! Parsers keep Mermaid/PlantUML/Graphviz sources as [`Block::Diagram`];
+//! text renderers write them back as source fences. Exports that want
+//! pictures run the [`RenderDiagrams`] transform with one or more
+//! [`DiagramRenderer`]s to swap the source for embedded SVG.
+//!
+//! [`Block::Diagram`]: crate::ast::Block::Diagram
+//! [`RenderDiagrams`]: crate::transform::RenderDiagrams
+
+use crate::traits::{ConversionError, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Fence/code-block languages recognised as diagram sources
+pub const DIAGRAM_ENGINES: &[&str] = &["mermaid", "plantuml", "graphviz", "dot", "d2", "ditaa"];
+
+/// Map a code block language to a diagram engine name, if it is one
+pub fn diagram_engine(language: &str) -> Option<&'static str> {
+    let language = language.to_lowercase();
+    match language.as_str() {
+        "dot" | "graphviz" => Some("graphviz"),
+        "puml" | "plantuml" => Some("plantuml"),
+        other => DIAGRAM_ENGINES.iter().copied().find(|e| *e == other),
+    }
+}
+
+/// Render diagram source to SVG
+pub trait DiagramRenderer: Send + Sync {
+    /// Engine this renderer handles (e.g. "mermaid", "graphviz")
+    fn engine(&self) -> &str;
+
+    /// Render diagram source to an SVG document
+    fn render_svg(&self, source: &str) -> Result<String>;
+}
+
+/// Render diagrams by piping the source through an external command
+/// (e.g. `dot -Tsvg`, `mmdc -i - -o - -e svg`, `plantuml -tsvg -pipe`)
+#[derive(Debug, Clone)]
+pub struct CommandDiagramRenderer {
+    engine: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandDiagramRenderer {
+    pub fn new(engine: &str, program: &str, args: &[&str]) -> Self {
+        Self {
+            engine: engine.to_string(),
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    /// Graphviz via `dot -Tsvg`
+    pub fn graphviz() -> Self {
+        Self::new("graphviz", "dot", &["-Tsvg"])
+    }
+
+    /// PlantUML via `plantuml -tsvg -pipe`
+    pub fn plantuml() -> Self {
+        Self::new("plantuml", "plantuml", &["-tsvg", "-pipe"])
+    }
+
+    /// Mermaid via the mermaid-cli `mmdc`
+    pub fn mermaid() -> Self {
+        Self::new("mermaid", "mmdc", &["-i", "-", "-o", "-", "-e", "svg"])
+    }
+
+    fn error(&self, message: impl Into<String>) -> ConversionError {
+        ConversionError::DiagramError {
+            engine: self.engine.clone(),
+            message: message.into(),
+        }
+    }
+}
+
+impl DiagramRenderer for CommandDiagramRenderer {
+    fn engine(&self) -> &str {
+        &self.engine
+    }
+
+    fn render_svg(&self, source: &str) -> Result<String> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.error(format!("failed to run {}: {}", self.program, e)))?;
+
+        // Feed stdin from another thread while this one drains the output,
+        // or a program that writes before it has read everything blocks on
+        // a full pipe while we block writing to it
+        let stdin = child.stdin.take();
+        let (output, written) = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || match stdin {
+                Some(mut stdin) => stdin.write_all(source.as_bytes()),
+                None => Ok(()),
+            });
+            let output = child.wait_with_output();
+            (output, writer.join().expect("stdin writer panicked"))
+        });
+
+        let output = output?;
+        if !output.status.success() {
+            return Err(self.error(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        written?;
+
+        String::from_utf8(output.stdout).map_err(|e| self.error(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagram_engine() {
+        assert_eq!(diagram_engine("Mermaid"), Some("mermaid"));
+        assert_eq!(diagram_engine("dot"), Some("graphviz"));
+        assert_eq!(diagram_engine("puml"), Some("plantuml"));
+        assert_eq!(diagram_engine("rust"), None);
+    }
+
+    #[test]
+    fn test_missing_command_is_an_error() {
+        let renderer = CommandDiagramRenderer::new("test", "formatrix-no-such-binary", &[]);
+        assert!(renderer.render_svg("graph {}").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_large_source_does_not_deadlock() {
+        // More than a pipe buffer each way
+        let source = "<svg/>\n".repeat(100_000);
+        let renderer = CommandDiagramRenderer::new("test", "cat", &[]);
+        assert_eq!(renderer.render_svg(&source).unwrap(), source);
+    }
+}
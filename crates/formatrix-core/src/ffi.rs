@@ -0,0 +1,1670 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! C FFI for the Ada TUI and other native front-ends
+//!
+//! Documents and configurations stay on the Rust side and are referred to
+//! by `u64` handles from an internal registry, so a freed, stale or
+//! made-up handle yields [`FfiResult::InvalidHandle`] instead of UB.
+//! Strings are NUL-terminated UTF-8; those returned through out-parameters
+//! belong to the caller and are released with [`formatrix_free_string`].
+//! Formats are passed as the integer codes of [`FfiFormat`], and an
+//! unknown code yields [`FfiResult::UnsupportedFormat`].
+//!
+//! Null pointer arguments are reported as [`FfiResult::NullPointer`]; any
+//! other string pointer must be a valid C string. Out-parameters are only
+//! written on success. The Zig bindings in `bindings/zig` mirror these
+//! declarations.
+//!
+//! Front-ends should call [`formatrix_check_abi`] at startup: the ABI
+//! version changes whenever an existing declaration does, while additions
+//! are announced through [`formatrix_capabilities`]. Structs that cross the
+//! boundary start with their size, so older callers keep working when
+//! fields are appended.
+
+#![allow(clippy::missing_safety_doc)]
+
+use crate::ast::{Block, Document, MetaValue, SourceFormat};
+use crate::file_ops::{self, FileError};
+use crate::options;
+use crate::traits::{
+    ConversionError, FormatRegistry, ParseConfig, RenderConfig, UnicodeNormalization,
+};
+use crate::visit;
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+
+/// Document formats, as integer codes
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiFormat {
+    PlainText = 0,
+    Markdown = 1,
+    AsciiDoc = 2,
+    Djot = 3,
+    OrgMode = 4,
+    ReStructuredText = 5,
+    Typst = 6,
+}
+
+impl FfiFormat {
+    /// The format for a code received from C
+    pub fn from_raw(code: c_int) -> Option<Self> {
+        Some(match code {
+            0 => FfiFormat::PlainText,
+            1 => FfiFormat::Markdown,
+            2 => FfiFormat::AsciiDoc,
+            3 => FfiFormat::Djot,
+            4 => FfiFormat::OrgMode,
+            5 => FfiFormat::ReStructuredText,
+            6 => FfiFormat::Typst,
+            _ => return None,
+        })
+    }
+}
+
+impl From<FfiFormat> for SourceFormat {
+    fn from(format: FfiFormat) -> Self {
+        match format {
+            FfiFormat::PlainText => SourceFormat::PlainText,
+            FfiFormat::Markdown => SourceFormat::Markdown,
+            FfiFormat::AsciiDoc => SourceFormat::AsciiDoc,
+            FfiFormat::Djot => SourceFormat::Djot,
+            FfiFormat::OrgMode => SourceFormat::OrgMode,
+            FfiFormat::ReStructuredText => SourceFormat::ReStructuredText,
+            FfiFormat::Typst => SourceFormat::Typst,
+        }
+    }
+}
+
+impl From<SourceFormat> for FfiFormat {
+    /// Third-party formats have no code and are reported as plain text
+    fn from(format: SourceFormat) -> Self {
+        match format {
+            SourceFormat::PlainText | SourceFormat::Custom(_) => FfiFormat::PlainText,
+            SourceFormat::Markdown => FfiFormat::Markdown,
+            SourceFormat::AsciiDoc => FfiFormat::AsciiDoc,
+            SourceFormat::Djot => FfiFormat::Djot,
+            SourceFormat::OrgMode => FfiFormat::OrgMode,
+            SourceFormat::ReStructuredText => FfiFormat::ReStructuredText,
+            SourceFormat::Typst => FfiFormat::Typst,
+        }
+    }
+}
+
+/// Status codes returned by fallible calls
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiResult {
+    Success = 0,
+    InvalidInput = 1,
+    ParseError = 2,
+    RenderError = 3,
+    UnsupportedFormat = 4,
+    NullPointer = 5,
+    Utf8Error = 6,
+    /// The handle was freed, belongs to another kind of object or was
+    /// never issued
+    InvalidHandle = 7,
+    /// The library speaks a different ABI version than the caller
+    AbiMismatch = 8,
+    /// A streaming callback asked to stop
+    Aborted = 9,
+}
+
+/// Kinds of [`Block`], as integer codes
+///
+/// New kinds are only ever appended, so existing codes stay stable.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiBlockKind {
+    Paragraph = 0,
+    Heading = 1,
+    CodeBlock = 2,
+    Diagram = 3,
+    BlockQuote = 4,
+    List = 5,
+    ThematicBreak = 6,
+    Table = 7,
+    Raw = 8,
+    DefinitionList = 9,
+    Admonition = 10,
+    FootnoteDefinition = 11,
+    LineBlock = 12,
+    Aside = 13,
+    Details = 14,
+    Language = 15,
+    Include = 16,
+    Figure = 17,
+}
+
+impl From<&Block> for FfiBlockKind {
+    fn from(block: &Block) -> Self {
+        match block {
+            Block::Paragraph { .. } => FfiBlockKind::Paragraph,
+            Block::Heading { .. } => FfiBlockKind::Heading,
+            Block::CodeBlock { .. } => FfiBlockKind::CodeBlock,
+            Block::Diagram { .. } => FfiBlockKind::Diagram,
+            Block::BlockQuote { .. } => FfiBlockKind::BlockQuote,
+            Block::List { .. } => FfiBlockKind::List,
+            Block::ThematicBreak { .. } => FfiBlockKind::ThematicBreak,
+            Block::Table { .. } => FfiBlockKind::Table,
+            Block::Raw { .. } => FfiBlockKind::Raw,
+            Block::DefinitionList { .. } => FfiBlockKind::DefinitionList,
+            Block::Admonition { .. } => FfiBlockKind::Admonition,
+            Block::FootnoteDefinition { .. } => FfiBlockKind::FootnoteDefinition,
+            Block::LineBlock { .. } => FfiBlockKind::LineBlock,
+            Block::Aside { .. } => FfiBlockKind::Aside,
+            Block::Details { .. } => FfiBlockKind::Details,
+            Block::Language { .. } => FfiBlockKind::Language,
+            Block::Include { .. } => FfiBlockKind::Include,
+            Block::Figure { .. } => FfiBlockKind::Figure,
+        }
+    }
+}
+
+impl From<ConversionError> for FfiResult {
+    fn from(err: ConversionError) -> Self {
+        match err {
+            ConversionError::ParseError { .. } => FfiResult::ParseError,
+            ConversionError::UnsupportedFeature { .. } => FfiResult::UnsupportedFormat,
+            ConversionError::InvalidOption { .. } => FfiResult::InvalidInput,
+            _ => FfiResult::RenderError,
+        }
+    }
+}
+
+impl From<FileError> for FfiResult {
+    fn from(err: FileError) -> Self {
+        match err {
+            FileError::Io(_) => FfiResult::InvalidInput,
+            FileError::UnknownFormat { .. } | FileError::UnsupportedFormat { .. } => {
+                FfiResult::UnsupportedFormat
+            }
+            FileError::Parse(_) => FfiResult::ParseError,
+            FileError::Render(_) => FfiResult::RenderError,
+        }
+    }
+}
+
+/// Id of a parsed document in the handle registry
+pub type DocumentHandle = u64;
+/// Id of a parse configuration in the handle registry
+pub type ParseConfigHandle = u64;
+/// Id of a render configuration in the handle registry
+pub type RenderConfigHandle = u64;
+
+/// Source of handle ids, shared by all registries so an id is never valid
+/// for two kinds of object; 0 is never issued
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+static DOCUMENTS: Registry<Document> = Registry::new();
+static PARSE_CONFIGS: Registry<ParseConfig> = Registry::new();
+static RENDER_CONFIGS: Registry<RenderConfig> = Registry::new();
+
+/// Objects owned by the library on behalf of C callers, keyed by handle
+///
+/// Ids are never reused, so a freed, stale or made-up handle is always
+/// detected. Each object has its own lock, so the registry itself is only
+/// locked while looking a handle up.
+struct Registry<T> {
+    objects: Mutex<BTreeMap<u64, Arc<RwLock<T>>>>,
+}
+
+impl<T> Registry<T> {
+    const fn new() -> Self {
+        Self {
+            objects: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn objects(&self) -> MutexGuard<'_, BTreeMap<u64, Arc<RwLock<T>>>> {
+        self.objects.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&self, value: T) -> u64 {
+        let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+        self.objects().insert(handle, Arc::new(RwLock::new(value)));
+        handle
+    }
+
+    fn get(&self, handle: u64) -> Result<Arc<RwLock<T>>, FfiResult> {
+        self.objects()
+            .get(&handle)
+            .cloned()
+            .ok_or(FfiResult::InvalidHandle)
+    }
+
+    fn read<R>(&self, handle: u64, f: impl FnOnce(&T) -> R) -> Result<R, FfiResult> {
+        let object = self.get(handle)?;
+        let object = object.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(f(&object))
+    }
+
+    fn write<R>(&self, handle: u64, f: impl FnOnce(&mut T) -> R) -> Result<R, FfiResult> {
+        let object = self.get(handle)?;
+        let mut object = object.write().unwrap_or_else(PoisonError::into_inner);
+        Ok(f(&mut object))
+    }
+
+    fn remove(&self, handle: u64) -> FfiStatus {
+        match self.objects().remove(&handle) {
+            Some(_) => Ok(()),
+            None => Err(FfiResult::InvalidHandle),
+        }
+    }
+}
+
+type FfiStatus = Result<(), FfiResult>;
+
+/// Run a call body, turning its error (or a panic, which must not unwind
+/// into C) into a status code
+fn run(body: impl FnOnce() -> FfiStatus) -> FfiResult {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => FfiResult::Success,
+        Ok(Err(code)) => code,
+        Err(_) => FfiResult::InvalidInput,
+    }
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Result<&'a str, FfiResult> {
+    if ptr.is_null() {
+        return Err(FfiResult::NullPointer);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| FfiResult::Utf8Error)
+}
+
+/// Like [`str_arg`], with a null pointer read as `None`
+unsafe fn optional_str_arg<'a>(ptr: *const c_char) -> Result<Option<&'a str>, FfiResult> {
+    if ptr.is_null() {
+        Ok(None)
+    } else {
+        str_arg(ptr).map(Some)
+    }
+}
+
+unsafe fn write_out<T>(out: *mut T, value: T) -> FfiStatus {
+    *out.as_mut().ok_or(FfiResult::NullPointer)? = value;
+    Ok(())
+}
+
+fn format_arg(code: c_int) -> Result<SourceFormat, FfiResult> {
+    FfiFormat::from_raw(code)
+        .map(SourceFormat::from)
+        .ok_or(FfiResult::UnsupportedFormat)
+}
+
+/// The configuration behind `handle`, or the defaults for handle 0
+fn config_arg<T: Clone + Default>(registry: &Registry<T>, handle: u64) -> Result<T, FfiResult> {
+    match handle {
+        0 => Ok(T::default()),
+        handle => registry.read(handle, T::clone),
+    }
+}
+
+/// Hand `value` to the caller as a C string and its length in bytes
+unsafe fn write_string(
+    value: String,
+    out_content: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiStatus {
+    if out_content.is_null() || out_length.is_null() {
+        return Err(FfiResult::NullPointer);
+    }
+    let length = value.len();
+    // Interior NULs would silently truncate the text on the C side
+    let value = CString::new(value).map_err(|_| FfiResult::RenderError)?;
+    *out_content = value.into_raw();
+    *out_length = length;
+    Ok(())
+}
+
+/// Like [`write_string`], with `None` written as a null string of length 0
+unsafe fn write_optional_string(
+    value: Option<&str>,
+    out_content: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiStatus {
+    match value {
+        Some(value) => write_string(value.to_string(), out_content, out_length),
+        None if out_content.is_null() || out_length.is_null() => Err(FfiResult::NullPointer),
+        None => {
+            *out_content = ptr::null_mut();
+            *out_length = 0;
+            Ok(())
+        }
+    }
+}
+
+unsafe fn write_document(document: Document, out_handle: *mut DocumentHandle) -> FfiStatus {
+    if out_handle.is_null() {
+        return Err(FfiResult::NullPointer);
+    }
+    *out_handle = DOCUMENTS.insert(document);
+    Ok(())
+}
+
+fn render_document(
+    doc: &Document,
+    format: SourceFormat,
+    config: &RenderConfig,
+) -> Result<String, FfiResult> {
+    let handler = FormatRegistry::global()
+        .get(format)
+        .ok_or(FfiResult::UnsupportedFormat)?;
+    Ok(handler.render(doc, config)?)
+}
+
+// ----------------------------------------------------------------------
+// Configuration
+// ----------------------------------------------------------------------
+
+/// A parse configuration with default settings; free it with
+/// [`formatrix_parse_config_free`]
+#[no_mangle]
+pub extern "C" fn formatrix_parse_config_new() -> ParseConfigHandle {
+    PARSE_CONFIGS.insert(ParseConfig::default())
+}
+
+#[no_mangle]
+pub extern "C" fn formatrix_parse_config_free(config: ParseConfigHandle) -> FfiResult {
+    run(|| PARSE_CONFIGS.remove(config))
+}
+
+#[no_mangle]
+pub extern "C" fn formatrix_parse_config_set_preserve_spans(
+    config: ParseConfigHandle,
+    value: bool,
+) -> FfiResult {
+    run(|| PARSE_CONFIGS.write(config, |c| c.preserve_spans = value))
+}
+
+#[no_mangle]
+pub extern "C" fn formatrix_parse_config_set_preserve_raw_source(
+    config: ParseConfigHandle,
+    value: bool,
+) -> FfiResult {
+    run(|| PARSE_CONFIGS.write(config, |c| c.preserve_raw_source = value))
+}
+
+#[no_mangle]
+pub extern "C" fn formatrix_parse_config_set_strip_zero_width(
+    config: ParseConfigHandle,
+    value: bool,
+) -> FfiResult {
+    run(|| PARSE_CONFIGS.write(config, |c| c.strip_zero_width = value))
+}
+
+/// Unicode normalization of the input: 0 none, 1 NFC, 2 NFD
+#[no_mangle]
+pub extern "C" fn formatrix_parse_config_set_normalization(
+    config: ParseConfigHandle,
+    form: c_int,
+) -> FfiResult {
+    run(|| {
+        let form = match form {
+            0 => None,
+            1 => Some(UnicodeNormalization::Nfc),
+            2 => Some(UnicodeNormalization::Nfd),
+            _ => return Err(FfiResult::InvalidInput),
+        };
+        PARSE_CONFIGS.write(config, |c| c.unicode_normalization = form)
+    })
+}
+
+/// Front matter delimiter; null restores the default (`---`)
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_parse_config_set_front_matter_delimiter(
+    config: ParseConfigHandle,
+    delimiter: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let delimiter = optional_str_arg(delimiter)?.map(str::to_string);
+        PARSE_CONFIGS.write(config, |c| c.front_matter_delimiter = delimiter)
+    })
+}
+
+/// Set a format-specific parse option
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_parse_config_set_option(
+    config: ParseConfigHandle,
+    key: *const c_char,
+    value: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let (key, value) = (str_arg(key)?, str_arg(value)?);
+        PARSE_CONFIGS.write(config, |c| {
+            c.format_options.insert(key.to_string(), value.to_string());
+        })
+    })
+}
+
+/// A render configuration with default settings; free it with
+/// [`formatrix_render_config_free`]
+#[no_mangle]
+pub extern "C" fn formatrix_render_config_new() -> RenderConfigHandle {
+    RENDER_CONFIGS.insert(RenderConfig::default())
+}
+
+#[no_mangle]
+pub extern "C" fn formatrix_render_config_free(config: RenderConfigHandle) -> FfiResult {
+    run(|| RENDER_CONFIGS.remove(config))
+}
+
+/// Target line width for wrapping; 0 disables wrapping
+#[no_mangle]
+pub extern "C" fn formatrix_render_config_set_line_width(
+    config: RenderConfigHandle,
+    width: usize,
+) -> FfiResult {
+    run(|| RENDER_CONFIGS.write(config, |c| c.line_width = width))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_render_config_set_indent(
+    config: RenderConfigHandle,
+    indent: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let indent = str_arg(indent)?;
+        RENDER_CONFIGS.write(config, |c| c.indent = indent.to_string())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn formatrix_render_config_set_hard_breaks(
+    config: RenderConfigHandle,
+    value: bool,
+) -> FfiResult {
+    run(|| RENDER_CONFIGS.write(config, |c| c.hard_breaks = value))
+}
+
+/// Set a flavor option such as `md.bullet` (see [`crate::options`])
+///
+/// Unknown built-in keys and invalid values are rejected with
+/// [`FfiResult::InvalidInput`] and leave the configuration unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_render_config_set_option(
+    config: RenderConfigHandle,
+    key: *const c_char,
+    value: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let (key, value) = (str_arg(key)?, str_arg(value)?);
+        RENDER_CONFIGS.write(config, |config| -> FfiStatus {
+            let candidate = config.clone().with_option(key, value);
+            options::validate(&candidate)?;
+            *config = candidate;
+            Ok(())
+        })?
+    })
+}
+
+// ----------------------------------------------------------------------
+// Parsing and rendering
+// ----------------------------------------------------------------------
+
+/// Parse `content` with default settings
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_parse(
+    content: *const c_char,
+    format: c_int,
+    out_handle: *mut DocumentHandle,
+) -> FfiResult {
+    formatrix_parse_with_config(content, format, 0, out_handle)
+}
+
+/// Parse `content`; `config` 0 means default settings
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_parse_with_config(
+    content: *const c_char,
+    format: c_int,
+    config: ParseConfigHandle,
+    out_handle: *mut DocumentHandle,
+) -> FfiResult {
+    run(|| {
+        let content = str_arg(content)?;
+        let format = format_arg(format)?;
+        let config = config_arg(&PARSE_CONFIGS, config)?;
+        let document = file_ops::parse_content(content, format, &config)?;
+        write_document(document, out_handle)
+    })
+}
+
+/// Render a document with default settings
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_render(
+    handle: DocumentHandle,
+    format: c_int,
+    out_content: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    formatrix_render_with_config(handle, format, 0, out_content, out_length)
+}
+
+/// Render a document; `config` 0 means default settings
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_render_with_config(
+    handle: DocumentHandle,
+    format: c_int,
+    config: RenderConfigHandle,
+    out_content: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let format = format_arg(format)?;
+        let config = config_arg(&RENDER_CONFIGS, config)?;
+        let output = DOCUMENTS.read(handle, |doc| render_document(doc, format, &config))??;
+        write_string(output, out_content, out_length)
+    })
+}
+
+/// Receives one chunk of streamed output
+///
+/// `chunk` is UTF-8, not NUL-terminated, and only valid during the call;
+/// multi-byte characters are never split across chunks. Returning `false`
+/// stops the render with [`FfiResult::Aborted`].
+pub type FfiChunkCallback = Option<
+    unsafe extern "C" fn(user_data: *mut c_void, chunk: *const c_char, length: usize) -> bool,
+>;
+
+/// Chunk size used when the caller passes 0
+const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Buffers renderer output and hands it to a [`FfiChunkCallback`]
+struct ChunkWriter {
+    callback: unsafe extern "C" fn(*mut c_void, *const c_char, usize) -> bool,
+    user_data: *mut c_void,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    aborted: bool,
+}
+
+impl ChunkWriter {
+    /// Pass on the complete characters in the buffer, keeping any
+    /// trailing partial one for the next chunk
+    fn emit(&mut self) -> io::Result<()> {
+        let complete = match std::str::from_utf8(&self.buffer) {
+            Ok(_) => self.buffer.len(),
+            Err(err) => err.valid_up_to(),
+        };
+        if complete == 0 {
+            return Ok(());
+        }
+        let ptr = self.buffer.as_ptr().cast();
+        // SAFETY: the caller of `formatrix_render_streaming` vouches for
+        // the callback and its user data
+        if !unsafe { (self.callback)(self.user_data, ptr, complete) } {
+            self.aborted = true;
+            return Err(io::Error::other("aborted by callback"));
+        }
+        self.buffer.drain(..complete);
+        Ok(())
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        if self.buffer.len() >= self.chunk_size {
+            self.emit()?;
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.emit()
+    }
+}
+
+/// Render a document progressively, passing the output to `callback` in
+/// chunks of about `chunk_size` bytes (0 for the default) instead of
+/// returning one string
+///
+/// `config` 0 means default settings. The callback must not free or
+/// modify the document it is rendering.
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_render_streaming(
+    handle: DocumentHandle,
+    format: c_int,
+    config: RenderConfigHandle,
+    chunk_size: usize,
+    callback: FfiChunkCallback,
+    user_data: *mut c_void,
+) -> FfiResult {
+    run(|| {
+        let callback = callback.ok_or(FfiResult::NullPointer)?;
+        let format = format_arg(format)?;
+        let config = config_arg(&RENDER_CONFIGS, config)?;
+        let handler = FormatRegistry::global()
+            .get(format)
+            .ok_or(FfiResult::UnsupportedFormat)?;
+
+        let mut writer = ChunkWriter {
+            callback,
+            user_data,
+            chunk_size: if chunk_size == 0 {
+                DEFAULT_CHUNK_SIZE
+            } else {
+                chunk_size
+            },
+            buffer: Vec::new(),
+            aborted: false,
+        };
+        let result = DOCUMENTS.read(handle, |doc| {
+            handler.render_to(doc, &mut writer, &config)?;
+            writer.flush()?;
+            Ok(())
+        })?;
+        match result {
+            Err(_) if writer.aborted => Err(FfiResult::Aborted),
+            result => result.map_err(|err: ConversionError| err.into()),
+        }
+    })
+}
+
+/// Convert `content` between formats with default settings
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_convert(
+    content: *const c_char,
+    from_format: c_int,
+    to_format: c_int,
+    out_content: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let content = str_arg(content)?;
+        let (from, to) = (format_arg(from_format)?, format_arg(to_format)?);
+        let doc = file_ops::parse_content(content, from, &ParseConfig::default())?;
+        let output = render_document(&doc, to, &RenderConfig::default())?;
+        write_string(output, out_content, out_length)
+    })
+}
+
+// ----------------------------------------------------------------------
+// Files
+// ----------------------------------------------------------------------
+
+/// Open and parse a file, detecting its format
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_open_file(
+    path: *const c_char,
+    out_handle: *mut DocumentHandle,
+    out_format: *mut FfiFormat,
+) -> FfiResult {
+    run(|| {
+        let path = str_arg(path)?;
+        if out_handle.is_null() || out_format.is_null() {
+            return Err(FfiResult::NullPointer);
+        }
+        let opened = file_ops::open_file(path)?;
+        *out_format = opened.file_info.format.into();
+        write_document(opened.document, out_handle)
+    })
+}
+
+/// Save a document in the format given by the path's extension
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_save_file(
+    handle: DocumentHandle,
+    path: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let path = str_arg(path)?;
+        Ok(DOCUMENTS.read(handle, |doc| file_ops::save_file(doc, path))??)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_save_file_as(
+    handle: DocumentHandle,
+    path: *const c_char,
+    format: c_int,
+) -> FfiResult {
+    run(|| {
+        let path = str_arg(path)?;
+        let format = format_arg(format)?;
+        let config = RenderConfig::default();
+        Ok(DOCUMENTS.read(handle, |doc| {
+            file_ops::save_file_as(doc, path, format, &config)
+        })??)
+    })
+}
+
+// ----------------------------------------------------------------------
+// Document queries
+// ----------------------------------------------------------------------
+
+/// The document title; a document without one yields a null string and
+/// length 0
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_get_title(
+    handle: DocumentHandle,
+    out_title: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let title = DOCUMENTS.read(handle, |doc| doc.meta.title.clone())?;
+        write_optional_string(title.as_deref(), out_title, out_length)
+    })
+}
+
+/// Number of top-level blocks (0 for an invalid handle)
+#[no_mangle]
+pub extern "C" fn formatrix_block_count(handle: DocumentHandle) -> usize {
+    DOCUMENTS.read(handle, |doc| doc.content.len()).unwrap_or(0)
+}
+
+/// Words of prose, as in [`crate::DocumentStats::words`] (0 for an
+/// invalid handle)
+#[no_mangle]
+pub extern "C" fn formatrix_word_count(handle: DocumentHandle) -> usize {
+    DOCUMENTS.read(handle, |doc| doc.stats().words).unwrap_or(0)
+}
+
+/// Characters of prose and code together (0 for an invalid handle)
+#[no_mangle]
+pub extern "C" fn formatrix_char_count(handle: DocumentHandle) -> usize {
+    DOCUMENTS
+        .read(handle, |doc| {
+            let stats = doc.stats();
+            stats.text_characters + stats.code_characters
+        })
+        .unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn formatrix_get_format(handle: DocumentHandle) -> FfiFormat {
+    DOCUMENTS
+        .read(handle, |doc| doc.source_format.into())
+        .unwrap_or(FfiFormat::PlainText)
+}
+
+/// Guess the format of `content` (plain text when unsure or null)
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_detect_format(content: *const c_char) -> FfiFormat {
+    str_arg(content).map_or(FfiFormat::PlainText, |content| {
+        file_ops::format_from_content(content).into()
+    })
+}
+
+/// The format implied by a path's extension (plain text when unknown)
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_detect_file_format(path: *const c_char) -> FfiFormat {
+    str_arg(path)
+        .ok()
+        .and_then(|path| file_ops::format_from_extension(path.as_ref()))
+        .map_or(FfiFormat::PlainText, FfiFormat::from)
+}
+
+// ----------------------------------------------------------------------
+// Metadata
+// ----------------------------------------------------------------------
+//
+// Getters write a null string of length 0 for an unset field; setters
+// take a null pointer to clear one.
+
+/// Set or (with null) clear the document title
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_set_title(
+    handle: DocumentHandle,
+    title: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let title = optional_str_arg(title)?.map(str::to_string);
+        DOCUMENTS.write(handle, |doc| doc.meta.title = title)
+    })
+}
+
+/// The authors, one per line
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_get_authors(
+    handle: DocumentHandle,
+    out_authors: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let authors = DOCUMENTS.read(handle, |doc| doc.meta.authors.join("\n"))?;
+        write_string(authors, out_authors, out_length)
+    })
+}
+
+/// Replace the authors with the non-blank lines of `authors`; null clears
+/// them
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_set_authors(
+    handle: DocumentHandle,
+    authors: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let authors = optional_str_arg(authors)?
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|author| !author.is_empty())
+            .map(str::to_string)
+            .collect();
+        DOCUMENTS.write(handle, |doc| doc.meta.authors = authors)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_get_date(
+    handle: DocumentHandle,
+    out_date: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let date = DOCUMENTS.read(handle, |doc| doc.meta.date.clone())?;
+        write_optional_string(date.as_deref(), out_date, out_length)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_set_date(
+    handle: DocumentHandle,
+    date: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let date = optional_str_arg(date)?.map(str::to_string);
+        DOCUMENTS.write(handle, |doc| doc.meta.date = date)
+    })
+}
+
+/// The document language as a BCP 47 tag
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_get_language(
+    handle: DocumentHandle,
+    out_language: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let language = DOCUMENTS.read(handle, |doc| doc.meta.language.clone())?;
+        write_optional_string(language.as_deref(), out_language, out_length)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_set_language(
+    handle: DocumentHandle,
+    language: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let language = optional_str_arg(language)?.map(str::to_string);
+        DOCUMENTS.write(handle, |doc| doc.meta.language = language)
+    })
+}
+
+/// Names of the custom front matter keys, sorted, one per line
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_get_meta_keys(
+    handle: DocumentHandle,
+    out_keys: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let keys = DOCUMENTS.read(handle, |doc| {
+            let keys: Vec<_> = doc.meta.frontmatter.keys().map(String::as_str).collect();
+            keys.join("\n")
+        })?;
+        write_string(keys, out_keys, out_length)
+    })
+}
+
+/// A custom front matter value as plain text (lists comma-separated,
+/// dates as `YYYY-MM-DD` or RFC 3339)
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_get_meta(
+    handle: DocumentHandle,
+    key: *const c_char,
+    out_value: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let key = str_arg(key)?;
+        let value = DOCUMENTS.read(handle, |doc| {
+            doc.meta.frontmatter.get(key).map(ToString::to_string)
+        })?;
+        write_optional_string(value.as_deref(), out_value, out_length)
+    })
+}
+
+/// Set a custom front matter value, typed the way an unquoted YAML scalar
+/// would be (`true`, `42`, `2024-05-01`, ...); null removes the key
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_set_meta(
+    handle: DocumentHandle,
+    key: *const c_char,
+    value: *const c_char,
+) -> FfiResult {
+    run(|| {
+        let key = str_arg(key)?.to_string();
+        let value = optional_str_arg(value)?.map(MetaValue::infer);
+        DOCUMENTS.write(handle, |doc| match value {
+            Some(value) => {
+                doc.meta.frontmatter.insert(key, value);
+            }
+            None => {
+                doc.meta.frontmatter.remove(&key);
+            }
+        })
+    })
+}
+
+// ----------------------------------------------------------------------
+// Format features
+// ----------------------------------------------------------------------
+
+/// Whether `format` supports `feature` (see
+/// [`crate::FormatHandler::supports_feature`]); false for invalid
+/// arguments
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_supports_feature(format: c_int, feature: *const c_char) -> bool {
+    let (Ok(format), Ok(feature)) = (format_arg(format), str_arg(feature)) else {
+        return false;
+    };
+    FormatRegistry::global()
+        .get(format)
+        .is_some_and(|handler| handler.supports_feature(feature))
+}
+
+/// Features supported by `format`, one per line
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_supported_features(
+    format: c_int,
+    out_features: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let handler = FormatRegistry::global()
+            .get(format_arg(format)?)
+            .ok_or(FfiResult::UnsupportedFormat)?;
+        let features = handler.supported_features().join("\n");
+        write_string(features, out_features, out_length)
+    })
+}
+
+// ----------------------------------------------------------------------
+// Blocks
+// ----------------------------------------------------------------------
+
+/// Run `f` on the top-level block at `index`; out of range is
+/// [`FfiResult::InvalidInput`]
+fn with_block<R>(
+    handle: DocumentHandle,
+    index: usize,
+    f: impl FnOnce(&Block) -> Result<R, FfiResult>,
+) -> Result<R, FfiResult> {
+    DOCUMENTS.read(handle, |doc| {
+        doc.content
+            .get(index)
+            .ok_or(FfiResult::InvalidInput)
+            .and_then(f)
+    })?
+}
+
+/// Visible text of a block and everything nested in it, one line per
+/// block; code and raw blocks contribute their source
+fn block_text(block: &Block) -> String {
+    let mut lines = Vec::new();
+    visit::walk_blocks(std::slice::from_ref(block), &mut |block| match block {
+        Block::CodeBlock { content, .. } | Block::Raw { content, .. } => {
+            lines.push(content.clone())
+        }
+        Block::Diagram { source, .. } => lines.push(source.clone()),
+        _ => lines.extend(
+            visit::block_inlines(block)
+                .into_iter()
+                .map(visit::inlines_to_text),
+        ),
+    });
+    lines.join("\n")
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_block_kind(
+    handle: DocumentHandle,
+    index: usize,
+    out_kind: *mut FfiBlockKind,
+) -> FfiResult {
+    run(|| {
+        let kind = with_block(handle, index, |block| Ok(block.into()))?;
+        write_out(out_kind, kind)
+    })
+}
+
+/// Level (1-6) of the heading at `index`; other blocks are
+/// [`FfiResult::InvalidInput`]
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_block_heading_level(
+    handle: DocumentHandle,
+    index: usize,
+    out_level: *mut u8,
+) -> FfiResult {
+    run(|| {
+        let level = with_block(handle, index, |block| match block {
+            Block::Heading { level, .. } => Ok(*level),
+            _ => Err(FfiResult::InvalidInput),
+        })?;
+        write_out(out_level, level)
+    })
+}
+
+/// Plain text of the block at `index`, formatting removed
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_block_text(
+    handle: DocumentHandle,
+    index: usize,
+    out_text: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let text = with_block(handle, index, |block| Ok(block_text(block)))?;
+        write_string(text, out_text, out_length)
+    })
+}
+
+/// Language of the code block at `index`; a block without one yields a
+/// null string and length 0, other blocks [`FfiResult::InvalidInput`]
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_block_code_language(
+    handle: DocumentHandle,
+    index: usize,
+    out_language: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let language = with_block(handle, index, |block| match block {
+            Block::CodeBlock { language, .. } => Ok(language.clone()),
+            _ => Err(FfiResult::InvalidInput),
+        })?;
+        write_optional_string(language.as_deref(), out_language, out_length)
+    })
+}
+
+// ----------------------------------------------------------------------
+// AST interchange
+// ----------------------------------------------------------------------
+
+/// The document's AST as JSON, in the shape described by
+/// `docs/schema/document.schema.json`
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_document_to_json(
+    handle: DocumentHandle,
+    out_json: *mut *mut c_char,
+    out_length: *mut usize,
+) -> FfiResult {
+    run(|| {
+        let json = DOCUMENTS
+            .read(handle, serde_json::to_string)?
+            .map_err(|_| FfiResult::RenderError)?;
+        write_string(json, out_json, out_length)
+    })
+}
+
+/// Build a document from its JSON AST; JSON that does not describe a
+/// document yields [`FfiResult::ParseError`]
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_document_from_json(
+    json: *const c_char,
+    out_handle: *mut DocumentHandle,
+) -> FfiResult {
+    run(|| {
+        let json = str_arg(json)?;
+        let document = serde_json::from_str(json).map_err(|_| FfiResult::ParseError)?;
+        write_document(document, out_handle)
+    })
+}
+
+// ----------------------------------------------------------------------
+// Memory and version
+// ----------------------------------------------------------------------
+
+/// Release a document; freeing it twice yields
+/// [`FfiResult::InvalidHandle`]
+#[no_mangle]
+pub extern "C" fn formatrix_free_document(handle: DocumentHandle) -> FfiResult {
+    run(|| DOCUMENTS.remove(handle))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Library version as a static string; do not free it
+#[no_mangle]
+pub extern "C" fn formatrix_version() -> *const c_char {
+    VERSION.as_ptr().cast()
+}
+
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+// ----------------------------------------------------------------------
+// ABI version and capabilities
+// ----------------------------------------------------------------------
+
+/// Version of the C interface
+///
+/// Bumped when an existing function, enum code or struct field changes;
+/// new functions, codes, capabilities and trailing struct fields leave it
+/// as it is.
+pub const FORMATRIX_ABI_VERSION: u32 = 1;
+
+/// Parse and render configuration handles
+pub const FFI_CAP_CONFIG_HANDLES: u64 = 1 << 0;
+/// `formatrix_open_file` and the save functions
+pub const FFI_CAP_FILES: u64 = 1 << 1;
+/// JSON import and export of documents
+pub const FFI_CAP_JSON: u64 = 1 << 2;
+/// Per-block kind, text, heading level and code language
+pub const FFI_CAP_BLOCK_ACCESSORS: u64 = 1 << 3;
+/// Word and character counts
+pub const FFI_CAP_STATS: u64 = 1 << 4;
+/// Format feature queries
+pub const FFI_CAP_FEATURE_QUERIES: u64 = 1 << 5;
+/// `formatrix_render_streaming`
+pub const FFI_CAP_STREAMING_RENDER: u64 = 1 << 6;
+/// Metadata getters and setters beyond the title
+pub const FFI_CAP_METADATA: u64 = 1 << 7;
+
+const CAPABILITIES: u64 = FFI_CAP_CONFIG_HANDLES
+    | FFI_CAP_FILES
+    | FFI_CAP_JSON
+    | FFI_CAP_BLOCK_ACCESSORS
+    | FFI_CAP_STATS
+    | FFI_CAP_FEATURE_QUERIES
+    | FFI_CAP_STREAMING_RENDER
+    | FFI_CAP_METADATA;
+
+/// Library details, filled in by [`formatrix_library_info`]
+///
+/// The caller sets `struct_size` to the size of its own declaration and
+/// the library writes only the fields that fit, then stores the number of
+/// bytes written back in `struct_size`. Fields are only ever appended.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FfiLibraryInfo {
+    pub struct_size: u32,
+    pub abi_version: u32,
+    /// `FFI_CAP_*` bits
+    pub capabilities: u64,
+    /// Static library version string; do not free it
+    pub version: *const c_char,
+    /// Number of [`FfiFormat`] codes
+    pub format_count: u32,
+    /// Number of [`FfiBlockKind`] codes
+    pub block_kind_count: u32,
+}
+
+/// The smallest layout accepted: `struct_size` and `abi_version`
+const MIN_INFO_SIZE: usize = 8;
+
+#[no_mangle]
+pub extern "C" fn formatrix_abi_version() -> u32 {
+    FORMATRIX_ABI_VERSION
+}
+
+/// [`FfiResult::AbiMismatch`] unless the library speaks ABI `expected`
+#[no_mangle]
+pub extern "C" fn formatrix_check_abi(expected: u32) -> FfiResult {
+    if expected == FORMATRIX_ABI_VERSION {
+        FfiResult::Success
+    } else {
+        FfiResult::AbiMismatch
+    }
+}
+
+/// The `FFI_CAP_*` bits this library supports
+#[no_mangle]
+pub extern "C" fn formatrix_capabilities() -> u64 {
+    CAPABILITIES
+}
+
+/// Whether every bit of `capabilities` is supported
+#[no_mangle]
+pub extern "C" fn formatrix_has_capabilities(capabilities: u64) -> bool {
+    CAPABILITIES & capabilities == capabilities
+}
+
+/// Fill the caller's [`FfiLibraryInfo`], up to its `struct_size`
+#[no_mangle]
+pub unsafe extern "C" fn formatrix_library_info(out_info: *mut FfiLibraryInfo) -> FfiResult {
+    run(|| {
+        if out_info.is_null() {
+            return Err(FfiResult::NullPointer);
+        }
+        // The caller's struct may be shorter than ours, so it is only
+        // touched through raw pointers
+        let requested = out_info.cast::<u32>().read() as usize;
+        if requested < MIN_INFO_SIZE {
+            return Err(FfiResult::InvalidInput);
+        }
+        let written = requested.min(std::mem::size_of::<FfiLibraryInfo>());
+        let info = FfiLibraryInfo {
+            struct_size: written as u32,
+            abi_version: FORMATRIX_ABI_VERSION,
+            capabilities: CAPABILITIES,
+            version: VERSION.as_ptr().cast(),
+            format_count: FfiFormat::Typst as u32 + 1,
+            block_kind_count: FfiBlockKind::Figure as u32 + 1,
+        };
+        ptr::copy_nonoverlapping(
+            ptr::addr_of!(info).cast::<u8>(),
+            out_info.cast::<u8>(),
+            written,
+        );
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str, config: ParseConfigHandle) -> DocumentHandle {
+        let content = CString::new(content).unwrap();
+        let mut handle = 0;
+        let result = unsafe {
+            formatrix_parse_with_config(
+                content.as_ptr(),
+                FfiFormat::PlainText as c_int,
+                config,
+                &mut handle,
+            )
+        };
+        assert_eq!(result, FfiResult::Success);
+        handle
+    }
+
+    fn render(handle: DocumentHandle, config: RenderConfigHandle) -> String {
+        let (mut out, mut length) = (ptr::null_mut(), 0);
+        let result = unsafe {
+            formatrix_render_with_config(
+                handle,
+                FfiFormat::PlainText as c_int,
+                config,
+                &mut out,
+                &mut length,
+            )
+        };
+        assert_eq!(result, FfiResult::Success);
+        let text = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        assert_eq!(text.len(), length);
+        unsafe { formatrix_free_string(out) };
+        text
+    }
+
+    #[test]
+    fn test_parse_and_render_with_config() {
+        let parse_config = formatrix_parse_config_new();
+        formatrix_parse_config_set_strip_zero_width(parse_config, true);
+        let doc = parse("one two\u{200B} three four", parse_config);
+        formatrix_parse_config_free(parse_config);
+
+        assert!(!render(doc, 0).contains('\u{200B}'));
+
+        let render_config = formatrix_render_config_new();
+        formatrix_render_config_set_line_width(render_config, 8);
+        assert_eq!(
+            render(doc, render_config).trim_end(),
+            "one two\nthree\nfour"
+        );
+
+        formatrix_render_config_free(render_config);
+        formatrix_free_document(doc);
+    }
+
+    #[test]
+    fn test_render_config_options() {
+        let config = formatrix_render_config_new();
+        let set = |key: &str, value: &str| {
+            let (key, value) = (CString::new(key).unwrap(), CString::new(value).unwrap());
+            unsafe { formatrix_render_config_set_option(config, key.as_ptr(), value.as_ptr()) }
+        };
+        assert_eq!(set("md.bullet", "*"), FfiResult::Success);
+        assert_eq!(set("md.bullet", "x"), FfiResult::InvalidInput);
+        assert_eq!(set("md.no-such-option", "1"), FfiResult::InvalidInput);
+        let bullet = RENDER_CONFIGS.read(config, |c| c.format_options["md.bullet"].clone());
+        assert_eq!(bullet.unwrap(), "*");
+        formatrix_render_config_free(config);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let doc = parse("First paragraph\n\nSecond", 0);
+        let (mut json, mut length) = (ptr::null_mut(), 0);
+        let mut copy = 0;
+        unsafe {
+            assert_eq!(
+                formatrix_document_to_json(doc, &mut json, &mut length),
+                FfiResult::Success
+            );
+            assert!(CStr::from_ptr(json).to_str().unwrap().contains("Second"));
+            assert_eq!(
+                formatrix_document_from_json(json, &mut copy),
+                FfiResult::Success
+            );
+            assert_eq!(formatrix_block_count(copy), formatrix_block_count(doc));
+
+            let bad = CString::new(r#"{"content": 1}"#).unwrap();
+            assert_eq!(
+                formatrix_document_from_json(bad.as_ptr(), &mut copy),
+                FfiResult::ParseError
+            );
+            formatrix_free_string(json);
+        }
+        formatrix_free_document(copy);
+        formatrix_free_document(doc);
+    }
+
+    #[test]
+    fn test_block_accessors() {
+        use crate::ast::{DocumentMeta, Inline, Name};
+
+        let text = |s: &str| Inline::Text {
+            content: s.to_string(),
+        };
+        let handle = DOCUMENTS.insert(Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![
+                Block::Heading {
+                    level: 2,
+                    content: vec![
+                        text("Intro "),
+                        Inline::Emphasis {
+                            content: vec![text("here")],
+                        },
+                    ],
+                    id: None,
+                    span: None,
+                },
+                Block::CodeBlock {
+                    language: Some(Name::new("rust")),
+                    content: "fn main() {}".to_string(),
+                    span: None,
+                },
+            ],
+            raw_source: None,
+        });
+
+        let (mut kind, mut level) = (FfiBlockKind::Paragraph, 0);
+        let (mut out, mut length) = (ptr::null_mut(), 0);
+        assert_eq!(formatrix_block_count(handle), 2);
+        assert_eq!(formatrix_word_count(handle), 2);
+        assert_eq!(formatrix_char_count(handle), "Intro here".len() + 12);
+        unsafe {
+            assert_eq!(
+                formatrix_block_kind(handle, 1, &mut kind),
+                FfiResult::Success
+            );
+            assert_eq!(kind, FfiBlockKind::CodeBlock);
+            assert_eq!(
+                formatrix_block_kind(handle, 2, &mut kind),
+                FfiResult::InvalidInput
+            );
+
+            assert_eq!(
+                formatrix_block_heading_level(handle, 0, &mut level),
+                FfiResult::Success
+            );
+            assert_eq!(level, 2);
+            assert_eq!(
+                formatrix_block_heading_level(handle, 1, &mut level),
+                FfiResult::InvalidInput
+            );
+
+            formatrix_block_text(handle, 0, &mut out, &mut length);
+            assert_eq!(CStr::from_ptr(out).to_str().unwrap(), "Intro here");
+            formatrix_free_string(out);
+
+            formatrix_block_code_language(handle, 1, &mut out, &mut length);
+            assert_eq!(CStr::from_ptr(out).to_str().unwrap(), "rust");
+            formatrix_free_string(out);
+        }
+        formatrix_free_document(handle);
+    }
+
+    #[test]
+    fn test_feature_queries() {
+        let feature = CString::new("tables").unwrap();
+        let (mut out, mut length) = (ptr::null_mut(), 0);
+        unsafe {
+            assert!(!formatrix_supports_feature(0, feature.as_ptr()));
+            assert!(!formatrix_supports_feature(99, feature.as_ptr()));
+            assert!(!formatrix_supports_feature(1, ptr::null()));
+
+            assert_eq!(
+                formatrix_supported_features(0, &mut out, &mut length),
+                FfiResult::Success
+            );
+            let handler = FormatRegistry::global()
+                .get(SourceFormat::PlainText)
+                .unwrap();
+            let expected = handler.supported_features().join("\n");
+            assert_eq!(CStr::from_ptr(out).to_str().unwrap(), expected);
+            formatrix_free_string(out);
+            assert_eq!(
+                formatrix_supported_features(-1, &mut out, &mut length),
+                FfiResult::UnsupportedFormat
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_streaming() {
+        unsafe extern "C" fn collect(data: *mut c_void, chunk: *const c_char, len: usize) -> bool {
+            let chunks = &mut *data.cast::<Vec<String>>();
+            let bytes = std::slice::from_raw_parts(chunk.cast::<u8>(), len);
+            chunks.push(std::str::from_utf8(bytes).unwrap().to_string());
+            chunks.len() < 3
+        }
+
+        let text = "Première ligne.\n\nSecond paragraph.\n\nThird one.";
+        let doc = parse(text, 0);
+        let mut chunks: Vec<String> = Vec::new();
+        let data = ptr::addr_of_mut!(chunks).cast();
+        unsafe {
+            let result = formatrix_render_streaming(doc, 0, 0, 4, Some(collect), data);
+            assert_eq!(result, FfiResult::Aborted);
+            assert_eq!(chunks.len(), 3);
+
+            chunks.clear();
+            let result = formatrix_render_streaming(doc, 0, 0, 0, Some(collect), data);
+            assert_eq!(result, FfiResult::Success);
+            assert_eq!(chunks.concat().trim_end(), text);
+
+            let result = formatrix_render_streaming(doc, 0, 0, 0, None, data);
+            assert_eq!(result, FfiResult::NullPointer);
+        }
+        formatrix_free_document(doc);
+    }
+
+    /// Read a string getter into an owned value
+    unsafe fn get(
+        getter: impl FnOnce(*mut *mut c_char, *mut usize) -> FfiResult,
+    ) -> Option<String> {
+        let (mut out, mut length) = (ptr::null_mut(), 0);
+        assert_eq!(getter(&mut out, &mut length), FfiResult::Success);
+        if out.is_null() {
+            return None;
+        }
+        let value = CStr::from_ptr(out).to_str().unwrap().to_string();
+        formatrix_free_string(out);
+        Some(value)
+    }
+
+    #[test]
+    fn test_metadata() {
+        let doc = parse("text", 0);
+        let (authors, key) = (
+            CString::new("Ada\n\n Grace \n").unwrap(),
+            CString::new("draft").unwrap(),
+        );
+        let (date, value) = (
+            CString::new("2024-05-01").unwrap(),
+            CString::new("true").unwrap(),
+        );
+        unsafe {
+            assert_eq!(get(|o, l| formatrix_get_date(doc, o, l)), None);
+            assert_eq!(
+                formatrix_set_authors(doc, authors.as_ptr()),
+                FfiResult::Success
+            );
+            assert_eq!(formatrix_set_date(doc, date.as_ptr()), FfiResult::Success);
+            assert_eq!(
+                formatrix_set_meta(doc, key.as_ptr(), value.as_ptr()),
+                FfiResult::Success
+            );
+            assert_eq!(
+                get(|o, l| formatrix_get_authors(doc, o, l)).unwrap(),
+                "Ada\nGrace"
+            );
+            assert_eq!(
+                get(|o, l| formatrix_get_date(doc, o, l)).unwrap(),
+                "2024-05-01"
+            );
+            assert_eq!(
+                get(|o, l| formatrix_get_meta_keys(doc, o, l)).unwrap(),
+                "draft"
+            );
+            assert_eq!(
+                get(|o, l| formatrix_get_meta(doc, key.as_ptr(), o, l)).unwrap(),
+                "true"
+            );
+        }
+        let meta = DOCUMENTS.read(doc, |d| d.meta.clone()).unwrap();
+        assert_eq!(meta.frontmatter["draft"], MetaValue::Bool(true));
+
+        unsafe {
+            assert_eq!(
+                formatrix_set_meta(doc, key.as_ptr(), ptr::null()),
+                FfiResult::Success
+            );
+            assert_eq!(
+                get(|o, l| formatrix_get_meta(doc, key.as_ptr(), o, l)),
+                None
+            );
+            assert_eq!(formatrix_set_date(doc, ptr::null()), FfiResult::Success);
+            assert_eq!(get(|o, l| formatrix_get_date(doc, o, l)), None);
+            assert_eq!(
+                formatrix_set_meta(doc, ptr::null(), value.as_ptr()),
+                FfiResult::NullPointer
+            );
+        }
+        formatrix_free_document(doc);
+    }
+
+    #[test]
+    fn test_stale_handles() {
+        let doc = parse("text", 0);
+        let config = formatrix_render_config_new();
+        assert_eq!(formatrix_free_document(doc), FfiResult::Success);
+        assert_eq!(formatrix_free_document(doc), FfiResult::InvalidHandle);
+        assert_eq!(formatrix_free_document(0), FfiResult::InvalidHandle);
+        assert_eq!(formatrix_block_count(doc), 0);
+
+        let (mut out, mut length) = (ptr::null_mut(), 0);
+        let result = unsafe { formatrix_render(doc, 0, &mut out, &mut length) };
+        assert_eq!(result, FfiResult::InvalidHandle);
+        assert!(out.is_null());
+
+        // A configuration id is not a document id
+        assert_eq!(formatrix_free_document(config), FfiResult::InvalidHandle);
+        assert_eq!(formatrix_render_config_free(config), FfiResult::Success);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let content = CString::new("text").unwrap();
+        let mut handle = 0;
+        unsafe {
+            assert_eq!(
+                formatrix_parse(content.as_ptr(), 42, &mut handle),
+                FfiResult::UnsupportedFormat
+            );
+            assert_eq!(
+                formatrix_parse(ptr::null(), 0, &mut handle),
+                FfiResult::NullPointer
+            );
+            assert_eq!(
+                formatrix_parse(content.as_ptr(), 0, ptr::null_mut()),
+                FfiResult::NullPointer
+            );
+        }
+        assert_eq!(
+            formatrix_parse_config_set_normalization(formatrix_parse_config_new(), 3),
+            FfiResult::InvalidInput
+        );
+        assert_eq!(handle, 0);
+    }
+
+    #[test]
+    fn test_abi_version_and_capabilities() {
+        assert_eq!(formatrix_abi_version(), FORMATRIX_ABI_VERSION);
+        assert_eq!(
+            formatrix_check_abi(FORMATRIX_ABI_VERSION),
+            FfiResult::Success
+        );
+        assert_eq!(
+            formatrix_check_abi(FORMATRIX_ABI_VERSION + 1),
+            FfiResult::AbiMismatch
+        );
+        assert!(formatrix_has_capabilities(FFI_CAP_JSON | FFI_CAP_FILES));
+        assert!(!formatrix_has_capabilities(1 << 63));
+
+        let size = std::mem::size_of::<FfiLibraryInfo>();
+        let mut info = FfiLibraryInfo {
+            struct_size: size as u32,
+            abi_version: 0,
+            capabilities: 0,
+            version: ptr::null(),
+            format_count: 0,
+            block_kind_count: 0,
+        };
+        unsafe {
+            assert_eq!(formatrix_library_info(&mut info), FfiResult::Success);
+            assert_eq!(
+                CStr::from_ptr(info.version).to_bytes(),
+                env!("CARGO_PKG_VERSION").as_bytes()
+            );
+        }
+        assert_eq!(info.struct_size as usize, size);
+        assert_eq!(info.capabilities, formatrix_capabilities());
+        assert_eq!((info.format_count, info.block_kind_count), (7, 18));
+    }
+
+    #[test]
+    fn test_library_info_older_layout() {
+        // A caller that only knows the first three fields
+        let mut buffer = [u64::MAX; 8];
+        let info = buffer.as_mut_ptr().cast::<FfiLibraryInfo>();
+        unsafe {
+            info.cast::<u32>().write(16);
+            assert_eq!(formatrix_library_info(info), FfiResult::Success);
+            let info = info.read();
+            assert_eq!(info.struct_size, 16);
+            assert_eq!(info.abi_version, FORMATRIX_ABI_VERSION);
+            assert_eq!(info.capabilities, CAPABILITIES);
+        }
+        assert!(buffer[2..].iter().all(|&word| word == u64::MAX));
+
+        unsafe {
+            info.cast::<u32>().write(4);
+            assert_eq!(formatrix_library_info(info), FfiResult::InvalidInput);
+            assert_eq!(
+                formatrix_library_info(ptr::null_mut()),
+                FfiResult::NullPointer
+            );
+        }
+    }
+}
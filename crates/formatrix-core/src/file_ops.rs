@@ -9,12 +9,9 @@
 //! - Content-based format detection heuristics
 
 use crate::ast::{Document, SourceFormat};
-use crate::formats::{
-    AsciidocHandler, DjotHandler, MarkdownHandler, OrgModeHandler, PlainTextHandler, RstHandler,
-    TypstHandler,
-};
-use crate::traits::{ParseConfig, Parser, RenderConfig, Renderer};
+use crate::traits::{FormatHandler, FormatRegistry, ParseConfig, RenderConfig};
 use std::fs;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 use thiserror::Error;
 
@@ -51,6 +48,19 @@ fn from(err: crate::traits::ConversionError) -> Self {
                 FileError::UnsupportedFormat { format }
             }
             crate::traits::ConversionError::SerializationError(msg) => FileError::Render(msg),
+            err @ crate::traits::ConversionError::IncludeError { .. } => {
+                FileError::Parse(err.to_string())
+            }
+            err @ crate::traits::ConversionError::DiagramError { .. } => {
+                FileError::Render(err.to_string())
+            }
+            err @ crate::traits::ConversionError::SpellCheckError(_) => {
+                FileError::Parse(err.to_string())
+            }
+            err @ (crate::traits::ConversionError::UndefinedVariable(_)
+            | crate::traits::ConversionError::InvalidOption { .. }) => {
+                FileError::Render(err.to_string())
+            }
         }
     }
 }
@@ -222,17 +232,16 @@ pub fn open_file_as(
     })
 }
 
+fn builtin_handler(format: SourceFormat) -> FileResult<&'static dyn FormatHandler> {
+    FormatRegistry::global()
+        .get(format)
+        .ok_or(FileError::UnsupportedFormat { format })
+}
+
 /// Parse content string to Document
-fn parse_content(content: &str, format: SourceFormat, config: &ParseConfig) -> FileResult<Document> {
-    let doc = match format {
-        SourceFormat::PlainText => PlainTextHandler::new().parse(content, config)?,
-        SourceFormat::Markdown => MarkdownHandler::new().parse(content, config)?,
-        SourceFormat::AsciiDoc => AsciidocHandler::new().parse(content, config)?,
-        SourceFormat::Djot => DjotHandler::new().parse(content, config)?,
-        SourceFormat::OrgMode => OrgModeHandler::new().parse(content, config)?,
-        SourceFormat::ReStructuredText => RstHandler::new().parse(content, config)?,
-        SourceFormat::Typst => TypstHandler::new().parse(content, config)?,
-    };
+pub(crate) fn parse_content(content: &str, format: SourceFormat, config: &ParseConfig) -> FileResult<Document> {
+    let content = &*config.normalize_input(content);
+    let doc = builtin_handler(format)?.parse(content, config)?;
     Ok(doc)
 }
 
@@ -266,28 +275,27 @@ pub fn save_file_as(
     config: &RenderConfig,
 ) -> FileResult<()> {
     let path = path.as_ref();
-
-    // Render content
-    let content = render_content(doc, format, config)?;
-
-    // Write to file
-    fs::write(path, content)?;
-
-    Ok(())
-}
-
-/// Render document to string
-fn render_content(doc: &Document, format: SourceFormat, config: &RenderConfig) -> FileResult<String> {
-    let output = match format {
-        SourceFormat::PlainText => PlainTextHandler::new().render(doc, config)?,
-        SourceFormat::Markdown => MarkdownHandler::new().render(doc, config)?,
-        SourceFormat::AsciiDoc => AsciidocHandler::new().render(doc, config)?,
-        SourceFormat::Djot => DjotHandler::new().render(doc, config)?,
-        SourceFormat::OrgMode => OrgModeHandler::new().render(doc, config)?,
-        SourceFormat::ReStructuredText => RstHandler::new().render(doc, config)?,
-        SourceFormat::Typst => TypstHandler::new().render(doc, config)?,
-    };
-    Ok(output)
+    let handler = builtin_handler(format)?;
+
+    // Stream into a sibling temporary file so a failed render never leaves
+    // a truncated document behind
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".formatrix-tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = fs::File::create(&tmp_path)
+        .map_err(FileError::from)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            handler.render_to(doc, &mut writer, config)?;
+            writer.flush()?;
+            Ok(())
+        })
+        .and_then(|()| Ok(fs::rename(&tmp_path, path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
 }
 
 /// Convert a file from one format to another
@@ -445,4 +453,20 @@ fn test_supported_extensions() {
         assert!(!is_supported_extension("docx"));
         assert!(!is_supported_extension("pdf"));
     }
+
+    #[test]
+    fn test_parse_normalizes_unicode() {
+        let config = ParseConfig {
+            unicode_normalization: Some(crate::traits::UnicodeNormalization::Nfc),
+            strip_zero_width: true,
+            ..Default::default()
+        };
+        // "café" with a combining acute accent and a zero-width space
+        let doc = parse_content("cafe\u{301}\u{200B}", SourceFormat::PlainText, &config).unwrap();
+
+        let crate::ast::Block::Paragraph { content, .. } = &doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(&content[0], crate::ast::Inline::Text { content } if content == "caf\u{e9}"));
+    }
 }
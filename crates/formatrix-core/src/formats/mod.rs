@@ -6,6 +6,7 @@
 pub mod markdown;
 pub mod djot;
 pub mod orgmode;
+pub mod table;
 
 // FD-S01, FD-S02, FD-S03: SHOULD requirement implementations
 pub mod asciidoc;
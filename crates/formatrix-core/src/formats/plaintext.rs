@@ -2,8 +2,13 @@
 // Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
 //! Plain text format handler
 
-use crate::ast::{Block, Document, DocumentMeta, Inline, SourceFormat};
+use crate::ast::{Block, Document, DocumentMeta, Inline, SourceFormat, Span, TextDirection};
+use crate::borrowed::{BlockRef, BorrowingParser, BorrowingRenderer, DocumentRef, InlineRef};
+use crate::formats::table::{self, TableStyle};
 use crate::traits::{FormatHandler, ParseConfig, Parser, RenderConfig, Renderer, Result};
+use crate::{options, wrap};
+use std::borrow::Cow;
+use std::io::Write;
 
 /// Plain text format handler
 pub struct PlainTextHandler;
@@ -26,22 +31,19 @@ fn format(&self) -> SourceFormat {
     }
 
     fn parse(&self, input: &str, config: &ParseConfig) -> Result<Document> {
-        // Split into paragraphs on blank lines
-        let paragraphs: Vec<Block> = input
-            .split("\n\n")
-            .filter(|p| !p.trim().is_empty())
-            .map(|p| Block::Paragraph {
+        let content = paragraphs(input, config)
+            .map(|(text, span)| Block::Paragraph {
                 content: vec![Inline::Text {
-                    content: p.trim().to_string(),
+                    content: text.to_string(),
                 }],
-                span: None,
+                span,
             })
             .collect();
 
         Ok(Document {
             source_format: SourceFormat::PlainText,
             meta: DocumentMeta::default(),
-            content: paragraphs,
+            content,
             raw_source: if config.preserve_raw_source {
                 Some(input.to_string())
             } else {
@@ -51,31 +53,193 @@ fn parse(&self, input: &str, config: &ParseConfig) -> Result<Document> {
     }
 }
 
+impl BorrowingParser for PlainTextHandler {
+    fn parse_borrowed<'src>(
+        &self,
+        input: &'src str,
+        config: &ParseConfig,
+    ) -> Result<DocumentRef<'src>> {
+        let content = paragraphs(input, config)
+            .map(|(text, span)| BlockRef::Paragraph {
+                content: vec![InlineRef::Text(Cow::Borrowed(text))],
+                span,
+            })
+            .collect();
+
+        Ok(DocumentRef {
+            source_format: SourceFormat::PlainText,
+            meta: DocumentMeta::default(),
+            content,
+            raw_source: config.preserve_raw_source.then_some(Cow::Borrowed(input)),
+        })
+    }
+}
+
+/// The paragraphs of `input`, split on blank lines and trimmed, with their
+/// spans if `config` keeps them
+fn paragraphs<'src>(
+    input: &'src str,
+    config: &ParseConfig,
+) -> impl Iterator<Item = (&'src str, Option<Span>)> {
+    let preserve_spans = config.preserve_spans;
+    let mut offset = 0;
+    input.split("\n\n").filter_map(move |part| {
+        let start = offset + (part.len() - part.trim_start().len());
+        offset += part.len() + 2;
+        let trimmed = part.trim();
+        (!trimmed.is_empty()).then(|| {
+            let span = preserve_spans.then(|| span_at(input, start, start + trimmed.len()));
+            (trimmed, span)
+        })
+    })
+}
+
+/// Span of `input[start..end]` with 1-based line and column
+fn span_at(input: &str, start: usize, end: usize) -> Span {
+    let before = &input[..start];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Span {
+        start,
+        end,
+        line: before.matches('\n').count() as u32 + 1,
+        column: before[line_start..].chars().count() as u32 + 1,
+    }
+}
+
 impl Renderer for PlainTextHandler {
     fn format(&self) -> SourceFormat {
         SourceFormat::PlainText
     }
 
-    fn render(&self, doc: &Document, _config: &RenderConfig) -> Result<String> {
+    fn render(&self, doc: &Document, config: &RenderConfig) -> Result<String> {
         let mut output = String::new();
+        let style = Style::new(config)?;
 
         for (i, block) in doc.content.iter().enumerate() {
             if i > 0 {
                 output.push_str("\n\n");
             }
-            render_block(&mut output, block);
+            render_block(&mut output, block, &style);
         }
 
         Ok(output)
     }
+
+    fn render_to(
+        &self,
+        doc: &Document,
+        writer: &mut dyn Write,
+        config: &RenderConfig,
+    ) -> Result<()> {
+        // One block at a time, reusing the buffer
+        let mut output = String::new();
+        let style = Style::new(config)?;
+
+        for (i, block) in doc.content.iter().enumerate() {
+            output.clear();
+            if i > 0 {
+                output.push_str("\n\n");
+            }
+            render_block(&mut output, block, &style);
+            writer.write_all(output.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Render options resolved once per document
+struct Style {
+    width: usize,
+    bullet: Option<String>,
 }
 
-fn render_block(output: &mut String, block: &Block) {
+impl Style {
+    fn new(config: &RenderConfig) -> Result<Self> {
+        Ok(Self {
+            width: config.line_width,
+            bullet: options::TXT_BULLET.get(config)?,
+        })
+    }
+}
+
+impl BorrowingRenderer for PlainTextHandler {
+    fn render_borrowed(&self, doc: &DocumentRef<'_>, config: &RenderConfig) -> Result<String> {
+        let mut output = String::new();
+        let style = Style::new(config)?;
+
+        for (i, block) in doc.content.iter().enumerate() {
+            if i > 0 {
+                output.push_str("\n\n");
+            }
+            match block {
+                // Plain text paragraphs are one run, filled straight from the source
+                BlockRef::Paragraph { content, .. } => match &content[..] {
+                    [InlineRef::Text(text)] => {
+                        output.push_str(&wrap::fill(text, style.width, &[], ""))
+                    }
+                    _ => render_paragraph(&mut output, content, &style, render_inline_ref),
+                },
+                BlockRef::Heading { content, .. } => {
+                    for inline in content {
+                        render_inline_ref(&mut output, inline);
+                    }
+                }
+                BlockRef::CodeBlock { content, .. } => output.push_str(content),
+                BlockRef::Owned(block) => render_block(&mut output, block, &style),
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Fill a paragraph to the line width; `render` writes one inline and
+/// says whether it must stay on one line
+fn render_paragraph<T>(
+    output: &mut String,
+    content: &[T],
+    style: &Style,
+    render: fn(&mut String, &T) -> bool,
+) {
+    let mut text = String::new();
+    let mut protected = Vec::new();
+    for inline in content {
+        let start = text.len();
+        if render(&mut text, inline) {
+            protected.push(start..text.len());
+        }
+    }
+    output.push_str(&wrap::fill(&text, style.width, &protected, ""));
+}
+
+/// [`render_inline`], saying whether the inline must stay on one line
+fn render_unbroken(output: &mut String, inline: &Inline) -> bool {
+    render_inline(output, inline);
+    matches!(
+        inline,
+        Inline::Code { .. } | Inline::Link { .. } | Inline::Audio { .. } | Inline::Video { .. }
+    )
+}
+
+fn render_inline_ref(output: &mut String, inline: &InlineRef<'_>) -> bool {
+    match inline {
+        InlineRef::Text(content) => {
+            output.push_str(content);
+            false
+        }
+        InlineRef::Code { content, .. } => {
+            output.push_str(content);
+            true
+        }
+        InlineRef::Owned(inline) => render_unbroken(output, inline),
+    }
+}
+
+fn render_block(output: &mut String, block: &Block, style: &Style) {
     match block {
         Block::Paragraph { content, .. } => {
-            for inline in content {
-                render_inline(output, inline);
-            }
+            render_paragraph(output, content, style, render_unbroken);
         }
         Block::Heading { content, .. } => {
             for inline in content {
@@ -85,21 +249,113 @@ fn render_block(output: &mut String, block: &Block) {
         Block::CodeBlock { content, .. } => {
             output.push_str(content);
         }
+        Block::Diagram { source, .. } => {
+            output.push_str(source);
+        }
         Block::BlockQuote { content, .. } => {
             for block in content {
-                render_block(output, block);
+                render_block(output, block, style);
             }
         }
         Block::List { items, .. } => {
-            for item in items {
+            for (i, item) in items.iter().enumerate() {
+                if let Some(bullet) = &style.bullet {
+                    if i > 0 {
+                        output.push('\n');
+                    }
+                    output.push_str(bullet);
+                    output.push(' ');
+                }
                 for block in &item.content {
-                    render_block(output, block);
+                    render_block(output, block, style);
                 }
             }
         }
+        Block::Table {
+            headers,
+            rows,
+            alignments,
+            ..
+        } => {
+            let cells = |cells: &[Vec<Inline>]| -> Vec<String> {
+                cells
+                    .iter()
+                    .map(|cell| {
+                        let mut text = String::new();
+                        for inline in cell {
+                            render_inline(&mut text, inline);
+                        }
+                        text.replace('\n', " ")
+                    })
+                    .collect()
+            };
+            let rows: Vec<Vec<String>> = rows.iter().map(|row| cells(row)).collect();
+            output.push_str(&table::render_table(
+                &cells(headers),
+                &rows,
+                alignments,
+                TableStyle::Plain,
+            ));
+        }
         Block::Raw { content, .. } => {
             output.push_str(content);
         }
+        Block::LineBlock { lines, .. } => {
+            for (i, line) in lines.iter().enumerate() {
+                if i > 0 {
+                    output.push('\n');
+                }
+                for inline in line {
+                    render_inline(output, inline);
+                }
+            }
+        }
+        Block::Aside { title, content, .. } => {
+            if let Some(title) = title {
+                for inline in title {
+                    render_inline(output, inline);
+                }
+                output.push_str("\n\n");
+            }
+            for (i, block) in content.iter().enumerate() {
+                if i > 0 {
+                    output.push_str("\n\n");
+                }
+                render_block(output, block, style);
+            }
+        }
+        Block::Language { content, .. } => {
+            for (i, block) in content.iter().enumerate() {
+                if i > 0 {
+                    output.push_str("\n\n");
+                }
+                render_block(output, block, style);
+            }
+        }
+        Block::Details {
+            summary, content, ..
+        } => {
+            for inline in summary {
+                render_inline(output, inline);
+            }
+            for block in content {
+                output.push_str("\n\n");
+                render_block(output, block, style);
+            }
+        }
+        Block::Figure {
+            content, caption, ..
+        } => {
+            for block in content {
+                render_block(output, block, style);
+            }
+            if let Some(caption) = caption {
+                output.push('\n');
+                for inline in caption {
+                    render_inline(output, inline);
+                }
+            }
+        }
         _ => {}
     }
 }
@@ -123,6 +379,38 @@ fn render_inline(output: &mut String, inline: &Inline) {
                 render_inline(output, i);
             }
         }
+        Inline::Audio { url, title } | Inline::Video { url, title, .. } => match title {
+            Some(title) => {
+                output.push_str(title);
+                output.push_str(" <");
+                output.push_str(url);
+                output.push('>');
+            }
+            None => output.push_str(url),
+        },
+        Inline::Language { dir, content, .. } => {
+            // Unicode directional isolates keep embedded runs from
+            // reordering the surrounding text
+            let isolate = match dir {
+                Some(TextDirection::Ltr) => Some('\u{2066}'),
+                Some(TextDirection::Rtl) => Some('\u{2067}'),
+                Some(TextDirection::Auto) => Some('\u{2068}'),
+                None => None,
+            };
+            output.extend(isolate);
+            for i in content {
+                render_inline(output, i);
+            }
+            if isolate.is_some() {
+                output.push('\u{2069}');
+            }
+        }
+        Inline::Ruby { base, annotation } => {
+            output.push_str(base);
+            output.push('(');
+            output.push_str(annotation);
+            output.push(')');
+        }
         Inline::LineBreak => output.push('\n'),
         Inline::SoftBreak => output.push(' '),
         _ => {}
@@ -153,6 +441,21 @@ fn test_parse_simple() {
         assert_eq!(doc.content.len(), 2);
     }
 
+    #[test]
+    fn test_parse_spans() {
+        let handler = PlainTextHandler::new();
+        let config = ParseConfig {
+            preserve_spans: true,
+            ..Default::default()
+        };
+        let input = "First\n\n\n  Second\nline";
+        let doc = handler.parse(input, &config).unwrap();
+
+        let span = doc.content[1].span().unwrap();
+        assert_eq!(&input[span.start..span.end], "Second\nline");
+        assert_eq!((span.line, span.column), (4, 3));
+    }
+
     #[test]
     fn test_roundtrip() {
         let handler = PlainTextHandler::new();
@@ -162,4 +465,217 @@ fn test_roundtrip() {
 
         assert_eq!(output, input);
     }
+
+    #[test]
+    fn test_render_to_writer() {
+        use crate::traits::RendererExt;
+
+        let handler = PlainTextHandler::new();
+        let input = "Hello world\n\nSecond paragraph\n\nThird";
+        let doc = handler.parse(input, &ParseConfig::default()).unwrap();
+
+        let mut output = Vec::new();
+        handler
+            .render_writer(&doc, &mut output, &RenderConfig::default())
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), input);
+    }
+
+    #[test]
+    fn test_render_wraps_paragraphs() {
+        let handler = PlainTextHandler::new();
+        let doc = Document {
+            source_format: SourceFormat::PlainText,
+            meta: DocumentMeta::default(),
+            content: vec![
+                Block::Paragraph {
+                    content: vec![
+                        Inline::Text {
+                            content: "Run the command ".to_string(),
+                        },
+                        Inline::Code {
+                            content: "cargo test --workspace".to_string(),
+                            language: None,
+                        },
+                        Inline::Text {
+                            content: " before every commit".to_string(),
+                        },
+                    ],
+                    span: None,
+                },
+                Block::CodeBlock {
+                    language: None,
+                    content: "a long line of code that must never be wrapped".to_string(),
+                    span: None,
+                },
+            ],
+            raw_source: None,
+        };
+        let config = RenderConfig {
+            line_width: 20,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            handler.render(&doc, &config).unwrap(),
+            "Run the command\ncargo test --workspace\nbefore every commit\n\n\
+             a long line of code that must never be wrapped"
+        );
+    }
+
+    #[test]
+    fn test_render_bullet_option() {
+        let handler = PlainTextHandler::new();
+        let item = |s: &str| crate::ast::ListItem {
+            content: vec![Block::Paragraph {
+                content: vec![Inline::Text {
+                    content: s.to_string(),
+                }],
+                span: None,
+            }],
+            checked: None,
+        };
+        let doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::List {
+                ordered: false,
+                start: None,
+                items: vec![item("One"), item("Two")],
+                span: None,
+            }],
+            raw_source: None,
+        };
+
+        let config = RenderConfig::default().with_option("txt.bullet", "*");
+        assert_eq!(handler.render(&doc, &config).unwrap(), "* One\n* Two");
+    }
+
+    #[test]
+    fn test_render_line_block() {
+        let handler = PlainTextHandler::new();
+        let line = |s: &str| {
+            vec![Inline::Text {
+                content: s.to_string(),
+            }]
+        };
+        let doc = Document {
+            source_format: SourceFormat::ReStructuredText,
+            meta: DocumentMeta::default(),
+            content: vec![Block::LineBlock {
+                lines: vec![line("Roses are red,"), line("  violets are blue")],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        let output = handler.render(&doc, &RenderConfig::default()).unwrap();
+
+        assert_eq!(output, "Roses are red,\n  violets are blue");
+    }
+
+    #[test]
+    fn test_render_ruby_fallback() {
+        let handler = PlainTextHandler::new();
+        let doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![Inline::Ruby {
+                    base: "漢字".to_string(),
+                    annotation: "かんじ".to_string(),
+                }],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        let output = handler.render(&doc, &RenderConfig::default()).unwrap();
+
+        assert_eq!(output, "漢字(かんじ)");
+    }
+
+    #[test]
+    fn test_render_rtl_span_isolated() {
+        let handler = PlainTextHandler::new();
+        let doc = Document {
+            source_format: SourceFormat::Djot,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![
+                    Inline::Text {
+                        content: "Hello ".to_string(),
+                    },
+                    Inline::Language {
+                        lang: Some("he".into()),
+                        dir: Some(TextDirection::Rtl),
+                        content: vec![Inline::Text {
+                            content: "שלום".to_string(),
+                        }],
+                    },
+                ],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        let output = handler.render(&doc, &RenderConfig::default()).unwrap();
+
+        assert_eq!(output, "Hello \u{2067}שלום\u{2069}");
+        assert_eq!(TextDirection::for_language("he-IL"), TextDirection::Rtl);
+        assert_eq!(TextDirection::for_language("en"), TextDirection::Ltr);
+    }
+
+    #[test]
+    fn test_render_details() {
+        let handler = PlainTextHandler::new();
+        let doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Details {
+                summary: vec![Inline::Text {
+                    content: "Why?".to_string(),
+                }],
+                content: vec![Block::Paragraph {
+                    content: vec![Inline::Text {
+                        content: "Because.".to_string(),
+                    }],
+                    span: None,
+                }],
+                open: false,
+                span: None,
+            }],
+            raw_source: None,
+        };
+        let output = handler.render(&doc, &RenderConfig::default()).unwrap();
+
+        assert_eq!(output, "Why?\n\nBecause.");
+    }
+
+    #[test]
+    fn test_render_audio_video() {
+        let handler = PlainTextHandler::new();
+        let doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![
+                    Inline::Audio {
+                        url: "talk.ogg".to_string(),
+                        title: Some("The talk".to_string()),
+                    },
+                    Inline::SoftBreak,
+                    Inline::Video {
+                        url: "demo.webm".to_string(),
+                        title: None,
+                        poster: Some("demo.png".to_string()),
+                        width: Some(640),
+                        height: Some(360),
+                    },
+                ],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        let output = handler.render(&doc, &RenderConfig::default()).unwrap();
+
+        assert_eq!(output, "The talk <talk.ogg> demo.webm");
+    }
 }
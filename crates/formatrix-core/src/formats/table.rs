@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Column-aligned table layout shared by the renderers
+//!
+//! [`render_table`] pads already-rendered cells to their column's width so
+//! the pipes line up, the way org-mode and prettier format tables. Widths
+//! count grapheme clusters, so accented text lines up; wide (CJK) glyphs
+//! still take one column each.
+
+use crate::ast::Alignment;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Table syntax to produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableStyle {
+    /// Pipe table with `:---:` alignment markers (GFM)
+    Markdown,
+    /// Same pipe syntax as Markdown; header row optional
+    Djot,
+    /// Org table with a `|---+---|` rule under the header
+    Org,
+    /// Columns separated by two spaces, header underlined with dashes
+    Plain,
+}
+
+/// Lay out a table from rendered (and escaped) cell text
+///
+/// Rows shorter than the widest row are padded with empty cells. The
+/// result has no trailing newline.
+pub fn render_table(
+    headers: &[String],
+    rows: &[Vec<String>],
+    alignments: &[Alignment],
+    style: TableStyle,
+) -> String {
+    let columns = rows
+        .iter()
+        .map(Vec::len)
+        .chain([headers.len()])
+        .max()
+        .unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    // GFM needs a header row, so headerless Markdown tables get an empty one
+    let header = match (headers.is_empty(), style) {
+        (false, _) => Some(headers),
+        (true, TableStyle::Markdown) => Some(&[][..]),
+        (true, _) => None,
+    };
+
+    let min_width = match style {
+        TableStyle::Markdown | TableStyle::Djot => 3,
+        TableStyle::Org | TableStyle::Plain => 1,
+    };
+    let mut widths = vec![min_width; columns];
+    for row in header.into_iter().chain(rows.iter().map(Vec::as_slice)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.graphemes(true).count());
+        }
+    }
+    let alignment = |i: usize| alignments.get(i).copied().unwrap_or(Alignment::Default);
+
+    let mut lines = Vec::with_capacity(rows.len() + 2);
+    if let Some(header) = header {
+        lines.push(format_row(header, &widths, &alignment, style));
+        lines.push(rule(&widths, &alignment, style));
+    }
+    for row in rows {
+        lines.push(format_row(row, &widths, &alignment, style));
+    }
+    lines.join("\n")
+}
+
+fn format_row(
+    cells: &[String],
+    widths: &[usize],
+    alignment: &impl Fn(usize) -> Alignment,
+    style: TableStyle,
+) -> String {
+    let padded: Vec<String> = widths
+        .iter()
+        .enumerate()
+        .map(|(i, width)| {
+            let cell = cells.get(i).map_or("", String::as_str);
+            pad(cell, *width, alignment(i))
+        })
+        .collect();
+    match style {
+        TableStyle::Plain => padded.join("  ").trim_end().to_string(),
+        _ => format!("| {} |", padded.join(" | ")),
+    }
+}
+
+/// The line between the header and the body
+fn rule(widths: &[usize], alignment: &impl Fn(usize) -> Alignment, style: TableStyle) -> String {
+    let dashes = |n: usize| "-".repeat(n);
+    match style {
+        TableStyle::Markdown | TableStyle::Djot => {
+            let cells: Vec<String> = widths
+                .iter()
+                .enumerate()
+                .map(|(i, width)| match alignment(i) {
+                    Alignment::Left => format!(":{}", dashes(width - 1)),
+                    Alignment::Right => format!("{}:", dashes(width - 1)),
+                    Alignment::Center => format!(":{}:", dashes(width - 2)),
+                    Alignment::Default => dashes(*width),
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        }
+        TableStyle::Org => {
+            let cells: Vec<String> = widths.iter().map(|w| dashes(w + 2)).collect();
+            format!("|{}|", cells.join("+"))
+        }
+        TableStyle::Plain => {
+            let cells: Vec<String> = widths.iter().map(|w| dashes(*w)).collect();
+            cells.join("  ")
+        }
+    }
+}
+
+fn pad(cell: &str, width: usize, alignment: Alignment) -> String {
+    let fill = width.saturating_sub(cell.graphemes(true).count());
+    let (left, right) = match alignment {
+        Alignment::Right => (fill, 0),
+        Alignment::Center => (fill / 2, fill - fill / 2),
+        Alignment::Left | Alignment::Default => (0, fill),
+    };
+    format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let table = render_table(
+            &strings(&["Name", "Qty", "Note"]),
+            &[strings(&["Café", "12", "ok"]), strings(&["Tea", "3"])],
+            &[Alignment::Left, Alignment::Right, Alignment::Center],
+            TableStyle::Markdown,
+        );
+        assert_eq!(
+            table,
+            "| Name | Qty | Note |\n\
+             | :--- | --: | :--: |\n\
+             | Café |  12 |  ok  |\n\
+             | Tea  |   3 |      |"
+        );
+    }
+
+    #[test]
+    fn test_org_and_plain_tables() {
+        let headers = strings(&["a", "long header"]);
+        let rows = [strings(&["wide cell", "x"])];
+
+        assert_eq!(
+            render_table(&headers, &rows, &[], TableStyle::Org),
+            "| a         | long header |\n\
+             |-----------+-------------|\n\
+             | wide cell | x           |"
+        );
+        assert_eq!(
+            render_table(&headers, &rows, &[], TableStyle::Plain),
+            "a          long header\n\
+             ---------  -----------\n\
+             wide cell  x"
+        );
+        assert_eq!(
+            render_table(&[], &rows, &[], TableStyle::Markdown),
+            "|           |     |\n\
+             | --------- | --- |\n\
+             | wide cell | x   |"
+        );
+    }
+}
@@ -0,0 +1,657 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Document metadata in each format's native syntax
+//!
+//! Renderers call [`render`] to re-emit [`DocumentMeta`] (custom fields
+//! included) at the top of their output, so metadata survives a round
+//! trip through any format:
+//!
+//! | Format            | Syntax                                        |
+//! |-------------------|-----------------------------------------------|
+//! | Markdown, Djot    | YAML front matter                             |
+//! | AsciiDoc          | document header and attributes                |
+//! | Org               | `#+TITLE:`, `#+KEYWORDS:`, ... keywords       |
+//! | reStructuredText  | title plus bibliographic field list           |
+//! | Typst             | `#set document(...)`, `#metadata(...)`        |
+//! | Plain text        | nothing                                       |
+//!
+//! [`split_yaml`] is the matching reader for YAML front matter.
+
+use crate::ast::{parse_datetime, DocumentMeta, MetaValue, SourceFormat, TextDirection};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, Timelike};
+
+/// Metadata block for `format`, ending in a blank line; empty if there is
+/// no metadata or the format has no metadata syntax
+pub fn render(meta: &DocumentMeta, format: SourceFormat) -> String {
+    if is_empty(meta) {
+        return String::new();
+    }
+    match format {
+        SourceFormat::Markdown | SourceFormat::Djot => render_yaml(meta),
+        SourceFormat::AsciiDoc => render_asciidoc(meta),
+        SourceFormat::OrgMode => render_org(meta),
+        SourceFormat::ReStructuredText => render_rst(meta),
+        SourceFormat::Typst => render_typst(meta),
+        SourceFormat::PlainText | SourceFormat::Custom(_) => String::new(),
+    }
+}
+
+fn is_empty(meta: &DocumentMeta) -> bool {
+    meta.title.is_none()
+        && meta.authors.is_empty()
+        && meta.date.is_none()
+        && meta.tags.is_empty()
+        && meta.language.is_none()
+        && meta.direction.is_none()
+        && meta.frontmatter.is_empty()
+}
+
+/// Custom fields, in key order
+fn custom_fields(meta: &DocumentMeta) -> Vec<(&String, &MetaValue)> {
+    meta.frontmatter.iter().collect()
+}
+
+fn direction_name(direction: TextDirection) -> &'static str {
+    match direction {
+        TextDirection::Ltr => "ltr",
+        TextDirection::Rtl => "rtl",
+        TextDirection::Auto => "auto",
+    }
+}
+
+/// Metadata values are single-line in every target syntax
+fn one_line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `value` as a double-quoted YAML scalar, on one line
+fn quoted(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn render_yaml(meta: &DocumentMeta) -> String {
+    let list = |items: &[String]| {
+        let items: Vec<String> = items.iter().map(|i| quoted(i)).collect();
+        format!("[{}]", items.join(", "))
+    };
+
+    let mut out = String::from("---\n");
+    if let Some(title) = &meta.title {
+        out.push_str(&format!("title: {}\n", quoted(title)));
+    }
+    match meta.authors.as_slice() {
+        [] => {}
+        // A single author is split at commas when read back
+        [author] if !author.contains(',') => out.push_str(&format!("author: {}\n", quoted(author))),
+        authors => out.push_str(&format!("author: {}\n", list(authors))),
+    }
+    if let Some(date) = &meta.date {
+        out.push_str(&format!("date: {}\n", quoted(date)));
+    }
+    if !meta.tags.is_empty() {
+        out.push_str(&format!("tags: {}\n", list(&meta.tags)));
+    }
+    if let Some(language) = &meta.language {
+        out.push_str(&format!("lang: {}\n", quoted(language)));
+    }
+    if let Some(direction) = meta.direction {
+        out.push_str(&format!("dir: {}\n", direction_name(direction)));
+    }
+    for (key, value) in custom_fields(meta) {
+        out.push_str(&format!("{}: {}\n", yaml_plain(key), yaml_value(value)));
+    }
+    out.push_str("---\n\n");
+    out
+}
+
+/// Strings are always quoted so they read back as strings, not as
+/// whatever [`MetaValue::infer`] would make of them
+fn yaml_value(value: &MetaValue) -> String {
+    match value {
+        MetaValue::String(s) => quoted(s),
+        MetaValue::List(items) => {
+            let items: Vec<String> = items.iter().map(yaml_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        scalar => yaml_plain(&scalar.to_string()),
+    }
+}
+
+/// `value` as a plain YAML scalar, or quoted where it would not read back
+/// as one: empty, starting with an indicator such as `[` or `#`, or
+/// containing `: `, ` #` or a line break
+fn yaml_plain(value: &str) -> String {
+    let indicator = value.starts_with([
+        '[', ']', '{', '}', ',', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@', '`',
+    ]) || value.starts_with("- ")
+        || value.starts_with("? ");
+    if value.is_empty()
+        || indicator
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.ends_with(':')
+        || value.contains(char::is_control)
+        || value != value.trim()
+    {
+        quoted(value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_asciidoc(meta: &DocumentMeta) -> String {
+    let mut out = String::new();
+    let authors = one_line(&meta.authors.join("; "));
+    // The author line only counts directly after a document title
+    match &meta.title {
+        Some(title) => {
+            out.push_str(&format!("= {}\n", one_line(title)));
+            if !authors.is_empty() {
+                out.push_str(&format!("{}\n", authors));
+            }
+        }
+        None if !authors.is_empty() => out.push_str(&format!(":authors: {}\n", authors)),
+        None => {}
+    }
+    if let Some(date) = &meta.date {
+        out.push_str(&format!(":revdate: {}\n", one_line(date)));
+    }
+    if !meta.tags.is_empty() {
+        out.push_str(&format!(":keywords: {}\n", one_line(&meta.tags.join(", "))));
+    }
+    if let Some(language) = &meta.language {
+        out.push_str(&format!(":lang: {}\n", language));
+    }
+    if let Some(direction) = meta.direction {
+        out.push_str(&format!(":dir: {}\n", direction_name(direction)));
+    }
+    for (key, value) in custom_fields(meta) {
+        out.push_str(&format!(":{}: {}\n", key, one_line(&value.to_string())));
+    }
+    out.push('\n');
+    out
+}
+
+fn render_org(meta: &DocumentMeta) -> String {
+    let mut out = String::new();
+    if let Some(title) = &meta.title {
+        out.push_str(&format!("#+TITLE: {}\n", one_line(title)));
+    }
+    if !meta.authors.is_empty() {
+        out.push_str(&format!(
+            "#+AUTHOR: {}\n",
+            one_line(&meta.authors.join(", "))
+        ));
+    }
+    if let Some(date) = &meta.date {
+        out.push_str(&format!("#+DATE: {}\n", one_line(date)));
+    }
+    if !meta.tags.is_empty() {
+        out.push_str(&format!(
+            "#+KEYWORDS: {}\n",
+            one_line(&meta.tags.join(", "))
+        ));
+    }
+    if let Some(language) = &meta.language {
+        out.push_str(&format!("#+LANGUAGE: {}\n", language));
+    }
+    for (key, value) in custom_fields(meta) {
+        out.push_str(&format!("#+{}: {}\n", key, one_line(&value.to_string())));
+    }
+    out.push('\n');
+    out
+}
+
+fn render_rst(meta: &DocumentMeta) -> String {
+    let mut out = String::new();
+    if let Some(title) = &meta.title {
+        let title = one_line(title);
+        let rule = "=".repeat(title.chars().count());
+        out.push_str(&format!("{}\n{}\n{}\n\n", rule, title, rule));
+    }
+
+    let mut fields = Vec::new();
+    match meta.authors.as_slice() {
+        [] => {}
+        [author] => fields.push(("Author".to_string(), author.clone())),
+        authors => fields.push(("Authors".to_string(), authors.join("; "))),
+    }
+    if let Some(date) = &meta.date {
+        fields.push(("Date".to_string(), date.clone()));
+    }
+    if !meta.tags.is_empty() {
+        fields.push(("Keywords".to_string(), meta.tags.join(", ")));
+    }
+    if let Some(language) = &meta.language {
+        fields.push(("Language".to_string(), language.clone()));
+    }
+    for (key, value) in custom_fields(meta) {
+        fields.push((key.clone(), value.to_string()));
+    }
+
+    for (key, value) in &fields {
+        out.push_str(&format!(":{}: {}\n", key, one_line(&value.to_string())));
+    }
+    if !fields.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+fn render_typst(meta: &DocumentMeta) -> String {
+    let list = |items: &[String]| typst_array(items.iter().map(|i| quoted(i)).collect());
+
+    let mut args = Vec::new();
+    if let Some(title) = &meta.title {
+        args.push(format!("title: {}", quoted(title)));
+    }
+    if !meta.authors.is_empty() {
+        args.push(format!("author: {}", list(&meta.authors)));
+    }
+    if !meta.tags.is_empty() {
+        args.push(format!("keywords: {}", list(&meta.tags)));
+    }
+    let date = meta.date.as_deref().map(|d| (d, parse_datetime(d)));
+    if let Some((_, Some(dt))) = &date {
+        args.push(format!("date: {}", typst_datetime(dt)));
+    }
+
+    let mut out = String::new();
+    if !args.is_empty() {
+        out.push_str(&format!("#set document({})\n", args.join(", ")));
+    }
+    let mut text_args = Vec::new();
+    if let Some(language) = &meta.language {
+        text_args.push(format!("lang: {}", quoted(language)));
+    }
+    if let Some(direction) = meta.direction {
+        text_args.push(format!("dir: {}", direction_name(direction)));
+    }
+    if !text_args.is_empty() {
+        out.push_str(&format!("#set text({})\n", text_args.join(", ")));
+    }
+
+    // Everything `document` has no field for goes into a labelled metadata
+    // element, which Typst keeps queryable but does not display
+    let mut fields: Vec<String> = custom_fields(meta)
+        .into_iter()
+        .map(|(k, v)| format!("{}: {}", quoted(k), typst_value(v)))
+        .collect();
+    if let Some((date, None)) = date {
+        fields.insert(0, format!("\"date\": {}", quoted(date)));
+    }
+    if !fields.is_empty() {
+        out.push_str(&format!(
+            "#metadata(({})) <frontmatter>\n",
+            fields.join(", ")
+        ));
+    }
+
+    out.push('\n');
+    out
+}
+
+/// Typst `datetime(...)`; Typst datetimes carry no time zone, so the
+/// local time is kept as written
+fn typst_datetime(dt: &DateTime<FixedOffset>) -> String {
+    if dt.time() == NaiveTime::MIN {
+        format!(
+            "datetime(year: {}, month: {}, day: {})",
+            dt.year(),
+            dt.month(),
+            dt.day()
+        )
+    } else {
+        format!(
+            "datetime(year: {}, month: {}, day: {}, hour: {}, minute: {}, second: {})",
+            dt.year(),
+            dt.month(),
+            dt.day(),
+            dt.hour(),
+            dt.minute(),
+            dt.second()
+        )
+    }
+}
+
+fn typst_value(value: &MetaValue) -> String {
+    match value {
+        MetaValue::Bool(_) | MetaValue::Integer(_) | MetaValue::Float(_) => value.to_string(),
+        MetaValue::DateTime(dt) => typst_datetime(dt),
+        MetaValue::String(s) => quoted(s),
+        MetaValue::List(items) => typst_array(items.iter().map(typst_value).collect()),
+    }
+}
+
+fn typst_array(items: Vec<String>) -> String {
+    // A one-element Typst array needs a trailing comma
+    if items.len() == 1 {
+        format!("({},)", items[0])
+    } else {
+        format!("({})", items.join(", "))
+    }
+}
+
+/// Split YAML front matter off the start of `input`.
+///
+/// `delimiter` defaults to `---` (see `ParseConfig::front_matter_delimiter`);
+/// the block may also be closed with `...`. Returns the metadata and the
+/// remaining body, or `None` if the input has no front matter. Understands
+/// the subset of YAML that metadata uses: scalars (plain or quoted),
+/// flow lists (`[a, b]`) and block lists (`- a`).
+pub fn split_yaml<'a>(input: &'a str, delimiter: Option<&str>) -> Option<(DocumentMeta, &'a str)> {
+    let delimiter = delimiter.unwrap_or("---");
+    let first_line_end = input.find('\n')?;
+    if input[..first_line_end].trim_end() != delimiter {
+        return None;
+    }
+
+    let mut offset = first_line_end + 1;
+    let mut lines = Vec::new();
+    loop {
+        let rest = &input[offset..];
+        let (line, next) = match rest.find('\n') {
+            Some(end) => (&rest[..end], offset + end + 1),
+            None => (rest, input.len()),
+        };
+        let trimmed = line.trim_end();
+        if trimmed == delimiter || trimmed == "..." {
+            let body = input[next..].trim_start_matches(['\r', '\n']);
+            return Some((parse_yaml(&lines), body));
+        }
+        if next >= input.len() {
+            return None;
+        }
+        lines.push(line);
+        offset = next;
+    }
+}
+
+fn parse_yaml(lines: &[&str]) -> DocumentMeta {
+    let mut entries: Vec<(String, MetaValue)> = Vec::new();
+
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix("- ") {
+            match entries.last_mut() {
+                Some((_, MetaValue::List(items))) => items.push(yaml_scalar(item)),
+                // `key:` with nothing after it opens a block list
+                Some((_, value)) if value.as_str() == Some("") => {
+                    *value = MetaValue::List(vec![yaml_scalar(item)]);
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let (key, value) = match quoted_len(trimmed) {
+            Some(len) => match trimmed[len..].trim_start().strip_prefix(':') {
+                Some(value) => (yaml_scalar(&trimmed[..len]).to_string(), value),
+                None => continue,
+            },
+            None => match trimmed.split_once(':') {
+                Some((key, value)) => (key.trim().to_string(), value),
+                None => continue,
+            },
+        };
+        let value = value.trim();
+        let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(items) => {
+                MetaValue::List(flow_items(items).into_iter().map(yaml_scalar).collect())
+            }
+            None => yaml_scalar(value),
+        };
+        entries.push((key, value));
+    }
+
+    let mut meta = DocumentMeta::default();
+    for (key, value) in entries {
+        let items = |value: MetaValue| match value {
+            MetaValue::List(items) => items.iter().map(ToString::to_string).collect(),
+            scalar => scalar
+                .to_string()
+                .split(',')
+                .map(|i| i.trim().to_string())
+                .filter(|i| !i.is_empty())
+                .collect(),
+        };
+        match key.as_str() {
+            "title" => meta.title = Some(value.to_string()),
+            "author" | "authors" => meta.authors = items(value),
+            "date" => meta.date = Some(value.to_string()),
+            "tags" | "keywords" => meta.tags = items(value),
+            "lang" | "language" => meta.language = Some(value.to_string()),
+            "dir" => {
+                meta.direction = match value.to_string().as_str() {
+                    "rtl" => Some(TextDirection::Rtl),
+                    "ltr" => Some(TextDirection::Ltr),
+                    "auto" => Some(TextDirection::Auto),
+                    _ => None,
+                }
+            }
+            _ => {
+                meta.frontmatter.insert(key, value);
+            }
+        }
+    }
+    meta
+}
+
+/// Length of the quoted scalar `text` starts with, quotes included
+fn quoted_len(text: &str) -> Option<usize> {
+    let quote = text.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            // `''` is a quote inside a single-quoted scalar
+            if quote == '\'' && chars.next_if(|&(_, c)| c == '\'').is_some() {
+                continue;
+            }
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// The items of a flow list's contents, split at commas outside quotes
+fn flow_items(items: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = items;
+    loop {
+        let start = rest.len() - rest.trim_start().len();
+        let after_quote = quoted_len(&rest[start..]).map_or(0, |len| start + len);
+        let end = rest[after_quote..]
+            .find(',')
+            .map_or(rest.len(), |i| after_quote + i);
+        let item = rest[..end].trim();
+        if !item.is_empty() {
+            out.push(item);
+        }
+        if end == rest.len() {
+            return out;
+        }
+        rest = &rest[end + 1..];
+    }
+}
+
+/// A YAML scalar: quoted scalars are strings, plain ones are typed with
+/// [`MetaValue::infer`]
+fn yaml_scalar(value: &str) -> MetaValue {
+    let value = value.trim();
+    if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    escaped => out.extend(escaped),
+                },
+                c => out.push(c),
+            }
+        }
+        MetaValue::String(out)
+    } else if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        MetaValue::String(inner.replace("''", "'"))
+    } else {
+        MetaValue::infer(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta() -> DocumentMeta {
+        let mut meta = DocumentMeta {
+            title: Some("On \"Quotes\": a study".to_string()),
+            authors: vec!["Ada".to_string(), "Grace".to_string()],
+            date: Some("2024-03-01".to_string()),
+            tags: vec!["draft".to_string()],
+            language: Some("en-GB".to_string()),
+            ..Default::default()
+        };
+        meta.frontmatter
+            .insert("status".to_string(), "review".into());
+        meta
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let rendered = render(&meta(), SourceFormat::Markdown);
+        let input = format!("{}Body text\n", rendered);
+        let (parsed, body) = split_yaml(&input, None).unwrap();
+
+        assert_eq!(body, "Body text\n");
+        assert_eq!(parsed.title, meta().title);
+        assert_eq!(parsed.authors, meta().authors);
+        assert_eq!(parsed.date, meta().date);
+        assert_eq!(parsed.tags, meta().tags);
+        assert_eq!(parsed.language, meta().language);
+        assert_eq!(parsed.frontmatter, meta().frontmatter);
+    }
+
+    #[test]
+    fn test_yaml_quoting() {
+        let mut meta = DocumentMeta {
+            authors: vec!["Doe, John".to_string()],
+            ..Default::default()
+        };
+        meta.frontmatter.insert("see: also".to_string(), "x".into());
+        meta.frontmatter.insert("#tag".to_string(), "y".into());
+        let rendered = render(&meta, SourceFormat::Markdown);
+        assert!(rendered.contains("\"see: also\": \"x\"\n"));
+        let (parsed, _) = split_yaml(&rendered, None).unwrap();
+        assert_eq!(parsed.authors, meta.authors);
+        assert_eq!(parsed.frontmatter, meta.frontmatter);
+
+        let input = "---\nauthors: [\"Doe, John\", 'O''Brien, Pat', Lee]\n---\n";
+        let (parsed, _) = split_yaml(input, None).unwrap();
+        assert_eq!(parsed.authors, ["Doe, John", "O'Brien, Pat", "Lee"]);
+    }
+
+    #[test]
+    fn test_yaml_multi_line_values() {
+        let mut meta = DocumentMeta {
+            title: Some("Line one\nLine two".to_string()),
+            ..Default::default()
+        };
+        meta.frontmatter
+            .insert("notes".to_string(), "a\r\n---\n\tb".into());
+        let input = format!("{}Body\n", render(&meta, SourceFormat::Markdown));
+        let (parsed, body) = split_yaml(&input, None).unwrap();
+
+        assert_eq!(body, "Body\n");
+        assert_eq!(parsed.title, meta.title);
+        assert_eq!(parsed.frontmatter, meta.frontmatter);
+    }
+
+    #[test]
+    fn test_split_yaml_block_lists() {
+        let input = "---\ntitle: Plain\nauthors:\n  - 'O''Brien'\n  - Lee\n...\n\n# Heading";
+        let (meta, body) = split_yaml(input, None).unwrap();
+
+        assert_eq!(meta.title.as_deref(), Some("Plain"));
+        assert_eq!(meta.authors, vec!["O'Brien", "Lee"]);
+        assert_eq!(body, "# Heading");
+        assert!(split_yaml("no front matter", None).is_none());
+        assert!(split_yaml("---\nunterminated: yes\n", None).is_none());
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let input = "---\ndate: 2024-05-01\ndraft: true\ncount: 3\nweight: 1.0\nid: \"42\"\nupdated: 2024-05-02T10:30:00+02:00\n---\n";
+        let (meta, _) = split_yaml(input, None).unwrap();
+
+        assert_eq!(meta.get_bool("draft"), Some(true));
+        assert_eq!(meta.get("count"), Some(&MetaValue::Integer(3)));
+        assert_eq!(meta.get_str("id"), Some("42"));
+        assert_eq!(
+            meta.created().unwrap().to_rfc3339(),
+            "2024-05-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            meta.get_datetime("updated").unwrap().to_rfc3339(),
+            "2024-05-02T10:30:00+02:00"
+        );
+
+        let rendered = render(&meta, SourceFormat::Markdown);
+        assert!(rendered.contains("draft: true\n"));
+        assert!(rendered.contains("weight: 1.0\n"));
+        let (reparsed, _) = split_yaml(&rendered, None).unwrap();
+        assert_eq!(reparsed.frontmatter, meta.frontmatter);
+
+        let typst = render(&meta, SourceFormat::Typst);
+        assert!(typst.contains(
+            "\"updated\": datetime(year: 2024, month: 5, day: 2, hour: 10, minute: 30, second: 0)"
+        ));
+    }
+
+    #[test]
+    fn test_native_syntaxes() {
+        let meta = meta();
+
+        let org = render(&meta, SourceFormat::OrgMode);
+        assert!(org.contains("#+AUTHOR: Ada, Grace\n"));
+        assert!(org.contains("#+KEYWORDS: draft\n#+LANGUAGE: en-GB\n#+status: review\n"));
+
+        let rst = render(&meta, SourceFormat::ReStructuredText);
+        assert!(rst.contains(":Authors: Ada; Grace\n:Date: 2024-03-01\n"));
+
+        let typst = render(&meta, SourceFormat::Typst);
+        assert!(typst.contains("keywords: (\"draft\",)"));
+        assert!(typst.contains("date: datetime(year: 2024, month: 3, day: 1)"));
+        assert!(typst.contains("#metadata((\"status\": \"review\")) <frontmatter>"));
+
+        let adoc = render(&meta, SourceFormat::AsciiDoc);
+        assert!(adoc.starts_with("= On \"Quotes\": a study\nAda; Grace\n:revdate: 2024-03-01\n"));
+        let untitled = DocumentMeta {
+            title: None,
+            ..meta.clone()
+        };
+        let adoc = render(&untitled, SourceFormat::AsciiDoc);
+        assert!(adoc.starts_with(":authors: Ada; Grace\n:revdate: 2024-03-01\n"));
+
+        assert_eq!(render(&meta, SourceFormat::PlainText), "");
+        assert_eq!(render(&DocumentMeta::default(), SourceFormat::Markdown), "");
+    }
+}
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Deterministic content hashing
+//!
+//! [`Document::content_hash`] hashes a canonical form of the AST: the
+//! metadata and content serialized to JSON with object keys sorted, with
+//! source spans removed. Documents that differ only in where their blocks
+//! sit in the source, whether raw source was kept or which format they
+//! were parsed from hash the same, so the hash can deduplicate documents
+//! in the database and detect real changes in the editor.
+
+use crate::ast::Document;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+impl Document {
+    /// SHA-256 of the canonical AST, as lowercase hex
+    pub fn content_hash(&self) -> String {
+        let mut content = serde_json::to_value(&self.content).expect("AST serializes to JSON");
+        strip_spans(&mut content);
+        let meta = serde_json::to_value(&self.meta).expect("AST serializes to JSON");
+
+        // serde_json sorts object keys, so field order does not leak in
+        let canonical = serde_json::to_vec(&[meta, content]).expect("JSON values serialize");
+        Sha256::digest(&canonical)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+fn strip_spans(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.remove("span");
+            map.values_mut().for_each(strip_spans);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_spans),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{MetaValue, SourceFormat};
+    use crate::formats::PlainTextHandler;
+    use crate::traits::{ParseConfig, Parser};
+
+    #[test]
+    fn test_hash_ignores_spans_and_source() {
+        let handler = PlainTextHandler::new();
+        let plain = handler
+            .parse("One\n\nTwo", &ParseConfig::default())
+            .unwrap();
+        let mut with_spans = handler
+            .parse(
+                "\n\nOne\n\n\n\nTwo\n",
+                &ParseConfig {
+                    preserve_spans: true,
+                    preserve_raw_source: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        with_spans.source_format = SourceFormat::Markdown;
+
+        assert_eq!(plain.content_hash(), with_spans.content_hash());
+        assert_eq!(plain.content_hash().len(), 64);
+    }
+
+    #[test]
+    fn test_hash_tracks_content_and_meta() {
+        let handler = PlainTextHandler::new();
+        let doc = handler
+            .parse("One\n\nTwo", &ParseConfig::default())
+            .unwrap();
+        let edited = handler
+            .parse("One\n\nTwo!", &ParseConfig::default())
+            .unwrap();
+        assert_ne!(doc.content_hash(), edited.content_hash());
+
+        let mut tagged = doc.clone();
+        for (key, value) in [("a", "1"), ("b", "2"), ("c", "3"), ("d", "4")] {
+            tagged
+                .meta
+                .frontmatter
+                .insert(key.to_string(), MetaValue::from(value));
+        }
+        assert_ne!(doc.content_hash(), tagged.content_hash());
+        assert_eq!(tagged.content_hash(), tagged.clone().content_hash());
+    }
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Localized labels
+//!
+//! Words that renderers and transforms write into the output themselves
+//! ("Note", "Figure 1", "Index"), translated for the document language
+//! ([`DocumentMeta::language`]). Unknown languages fall back to English.
+//!
+//! [`DocumentMeta::language`]: crate::ast::DocumentMeta::language
+
+/// A generated label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Label {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+    Figure,
+    Table,
+    Equation,
+    Listing,
+    Contents,
+    Index,
+}
+
+impl Label {
+    /// The label for an admonition kind ("note", "WARNING", ...)
+    pub fn from_admonition(kind: &str) -> Option<Self> {
+        match kind.to_lowercase().as_str() {
+            "note" | "info" => Some(Label::Note),
+            "tip" | "hint" => Some(Label::Tip),
+            "important" => Some(Label::Important),
+            "warning" => Some(Label::Warning),
+            "caution" | "danger" => Some(Label::Caution),
+            _ => None,
+        }
+    }
+
+    /// This label in the given language (BCP 47 tag), falling back to English
+    pub fn localized(self, language: Option<&str>) -> &'static str {
+        let primary = language
+            .and_then(|l| l.split(['-', '_']).next())
+            .unwrap_or_default()
+            .to_lowercase();
+        let (_, labels) = LABELS
+            .iter()
+            .find(|(lang, _)| *lang == primary)
+            .unwrap_or(&LABELS[0]);
+        labels[self as usize]
+    }
+}
+
+/// Labels per language, in [`Label`] declaration order
+#[rustfmt::skip]
+static LABELS: &[(&str, [&str; 11])] = &[
+    ("en", ["Note", "Tip", "Important", "Warning", "Caution", "Figure", "Table", "Equation", "Listing", "Contents", "Index"]),
+    ("de", ["Hinweis", "Tipp", "Wichtig", "Warnung", "Achtung", "Abbildung", "Tabelle", "Gleichung", "Listing", "Inhalt", "Index"]),
+    ("fr", ["Note", "Astuce", "Important", "Avertissement", "Attention", "Figure", "Tableau", "Équation", "Listing", "Table des matières", "Index"]),
+    ("es", ["Nota", "Consejo", "Importante", "Advertencia", "Precaución", "Figura", "Tabla", "Ecuación", "Listado", "Contenido", "Índice"]),
+    ("it", ["Nota", "Suggerimento", "Importante", "Avvertenza", "Attenzione", "Figura", "Tabella", "Equazione", "Listato", "Indice", "Indice analitico"]),
+    ("pt", ["Nota", "Dica", "Importante", "Aviso", "Cuidado", "Figura", "Tabela", "Equação", "Listagem", "Sumário", "Índice"]),
+    ("nl", ["Opmerking", "Tip", "Belangrijk", "Waarschuwing", "Let op", "Figuur", "Tabel", "Vergelijking", "Listing", "Inhoud", "Register"]),
+    ("ru", ["Примечание", "Совет", "Важно", "Предупреждение", "Осторожно", "Рисунок", "Таблица", "Уравнение", "Листинг", "Содержание", "Предметный указатель"]),
+    ("ja", ["注記", "ヒント", "重要", "警告", "注意", "図", "表", "式", "リスト", "目次", "索引"]),
+    ("zh", ["注", "提示", "重要", "警告", "小心", "图", "表", "公式", "代码清单", "目录", "索引"]),
+    ("ar", ["ملاحظة", "تلميح", "مهم", "تحذير", "تنبيه", "شكل", "جدول", "معادلة", "قائمة برمجية", "المحتويات", "الفهرس"]),
+    ("he", ["הערה", "טיפ", "חשוב", "אזהרה", "זהירות", "איור", "טבלה", "משוואה", "קטע קוד", "תוכן עניינים", "מפתח"]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localized() {
+        assert_eq!(Label::Figure.localized(Some("de-AT")), "Abbildung");
+        assert_eq!(Label::Index.localized(Some("ja")), "索引");
+        assert_eq!(Label::Warning.localized(Some("xx")), "Warning");
+        assert_eq!(Label::Note.localized(None), "Note");
+    }
+
+    #[test]
+    fn test_from_admonition() {
+        assert_eq!(Label::from_admonition("WARNING"), Some(Label::Warning));
+        assert_eq!(Label::from_admonition("custom"), None);
+    }
+}
@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Image type and dimension probing
+//!
+//! Reads just enough of an image's header to learn its MIME type and
+//! pixel size, without decoding it. Supports PNG, GIF, JPEG, WebP and SVG
+//! (when the root element has plain numeric `width`/`height` attributes).
+
+/// Bytes from the start of a file that [`probe`] needs; JPEGs with more
+/// metadata than this before their frame header are identified without a
+/// size
+pub const PROBE_BYTES: usize = 256 * 1024;
+
+/// What [`probe`] learned about an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub mime_type: &'static str,
+    /// Pixel size, when the header records one
+    pub size: Option<(u32, u32)>,
+}
+
+/// Identify an image from its bytes
+pub fn probe(bytes: &[u8]) -> Option<ImageInfo> {
+    let info = |mime_type, size| Some(ImageInfo { mime_type, size });
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // IHDR is always the first chunk
+        info("image/png", Some((be32(bytes, 16)?, be32(bytes, 20)?)))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        info("image/gif", Some((le16(bytes, 6)?, le16(bytes, 8)?)))
+    } else if bytes.starts_with(&[0xff, 0xd8]) {
+        info("image/jpeg", jpeg_size(bytes))
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        info("image/webp", webp_size(bytes))
+    } else {
+        // The root element is near the start; a cut character is harmless
+        let text = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+        let start = text.find("<svg")?;
+        info("image/svg+xml", svg_size(&text[start..]))
+    }
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 2)?;
+    Some(u16::from_be_bytes([b[0], b[1]]) as u32)
+}
+
+fn be32(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 2)?;
+    Some(u16::from_le_bytes([b[0], b[1]]) as u32)
+}
+
+fn le24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], 0]))
+}
+
+/// Walk the JPEG segments up to the first start-of-frame marker
+fn jpeg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *bytes.get(at)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // Fill bytes before a marker
+            0xff => at += 1,
+            // SOF0..SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                return Some((be16(bytes, at + 7)?, be16(bytes, at + 5)?));
+            }
+            _ => at += 2 + be16(bytes, at + 2)? as usize,
+        }
+    }
+}
+
+fn webp_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8X" => Some((le24(bytes, 24)? + 1, le24(bytes, 27)? + 1)),
+        b"VP8L" => {
+            let b = bytes.get(21..25)?;
+            let bits = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+            Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+        }
+        b"VP8 " => Some((le16(bytes, 26)? & 0x3fff, le16(bytes, 28)? & 0x3fff)),
+        _ => None,
+    }
+}
+
+fn svg_size(svg: &str) -> Option<(u32, u32)> {
+    let tag = &svg[..svg.find('>')?];
+    let attribute = |name: &str| -> Option<u32> {
+        let start = tag.find(&format!(" {}=", name))? + name.len() + 2;
+        let quote = tag[start..]
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\''))?;
+        let value = tag[start + quote.len_utf8()..].split(quote).next()?;
+        value.strip_suffix("px").unwrap_or(value).parse().ok()
+    };
+    Some((attribute("width")?, attribute("height")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_raster_formats() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        assert_eq!(
+            probe(&png),
+            Some(ImageInfo {
+                mime_type: "image/png",
+                size: Some((640, 480))
+            })
+        );
+
+        let gif = b"GIF89a\x20\x00\x10\x00";
+        assert_eq!(probe(gif).unwrap().size, Some((32, 16)));
+
+        // SOI, an APP0 segment, then SOF0 with height 200 and width 300
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x00,
+            0xc8, 0x01, 0x2c,
+        ];
+        assert_eq!(
+            probe(&jpeg),
+            Some(ImageInfo {
+                mime_type: "image/jpeg",
+                size: Some((300, 200))
+            })
+        );
+    }
+
+    #[test]
+    fn test_probe_svg() {
+        let svg = br#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="120" height='40px'>"#;
+        assert_eq!(
+            probe(svg),
+            Some(ImageInfo {
+                mime_type: "image/svg+xml",
+                size: Some((120, 40))
+            })
+        );
+        assert_eq!(probe(b"<svg viewBox=\"0 0 1 1\">").unwrap().size, None);
+        assert_eq!(probe(b"plain text"), None);
+        // Unquoted values, even ones starting with a multibyte character
+        let unquoted = probe("<svg width=é1é height=\"2\">".as_bytes()).unwrap();
+        assert_eq!(unquoted.size, None);
+    }
+}
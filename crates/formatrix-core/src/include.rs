@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Include/embed resolution
+//!
+//! Parsers turn format-specific include directives (`include::[]`,
+//! `.. include::`, `#+INCLUDE:`, `#include`) into [`Block::Include`]
+//! placeholders. The [`Transclusion`] transform then asks an
+//! [`IncludeResolver`] for each target, so the same document can pull its
+//! parts from disk, from the database, or from memory.
+//!
+//! [`Block::Include`]: crate::ast::Block::Include
+//! [`Transclusion`]: crate::transform::Transclusion
+
+use crate::ast::SourceFormat;
+use crate::traits::{ConversionError, Result};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Content returned by an [`IncludeResolver`]
+#[derive(Debug, Clone)]
+pub struct IncludedSource {
+    /// Canonical key for the resolved target, used for cycle detection and
+    /// as the base for nested relative includes
+    pub key: String,
+    /// Raw source text
+    pub content: String,
+    /// Source format, if the resolver knows it (otherwise detected)
+    pub format: Option<SourceFormat>,
+}
+
+/// Resolve include/embed targets to source text
+pub trait IncludeResolver: Send + Sync {
+    /// Resolve `target` as written in the including document.
+    ///
+    /// `base` is the key of the including document (if any), so relative
+    /// targets can be resolved against it.
+    fn resolve(&self, target: &str, base: Option<&str>) -> Result<IncludedSource>;
+}
+
+fn include_error(target: &str, message: impl Into<String>) -> ConversionError {
+    ConversionError::IncludeError {
+        target: target.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Resolve includes from files under a root directory
+///
+/// Targets are resolved relative to the including file; anything that
+/// would escape the root is rejected.
+#[derive(Debug, Clone)]
+pub struct FsIncludeResolver {
+    root: PathBuf,
+}
+
+impl FsIncludeResolver {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl IncludeResolver for FsIncludeResolver {
+    fn resolve(&self, target: &str, base: Option<&str>) -> Result<IncludedSource> {
+        let relative_to = base
+            .and_then(|b| Path::new(b).parent())
+            .unwrap_or_else(|| Path::new(""));
+        let key = normalize_path(&relative_to.join(target))
+            .ok_or_else(|| include_error(target, "path escapes the include root"))?;
+
+        let content = std::fs::read_to_string(self.root.join(&key))
+            .map_err(|e| include_error(target, e.to_string()))?;
+
+        Ok(IncludedSource {
+            format: crate::file_ops::format_from_extension(&key),
+            key: key.to_string_lossy().to_string(),
+            content,
+        })
+    }
+}
+
+/// Lexically normalize a relative path, refusing to climb above its root
+pub(crate) fn normalize_path(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+/// Resolve includes from an in-memory map of key to source
+#[derive(Debug, Clone, Default)]
+pub struct MemoryIncludeResolver {
+    sources: HashMap<String, (String, Option<SourceFormat>)>,
+}
+
+impl MemoryIncludeResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source under the given key
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        content: impl Into<String>,
+        format: Option<SourceFormat>,
+    ) {
+        self.sources.insert(key.into(), (content.into(), format));
+    }
+}
+
+impl IncludeResolver for MemoryIncludeResolver {
+    fn resolve(&self, target: &str, _base: Option<&str>) -> Result<IncludedSource> {
+        let (content, format) = self
+            .sources
+            .get(target)
+            .ok_or_else(|| include_error(target, "not found"))?;
+
+        Ok(IncludedSource {
+            key: target.to_string(),
+            content: content.clone(),
+            format: *format,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path(Path::new("a/./b/../c.md")),
+            Some(PathBuf::from("a/c.md"))
+        );
+        assert_eq!(normalize_path(Path::new("../secret")), None);
+        assert_eq!(normalize_path(Path::new("/etc/passwd")), None);
+    }
+
+    #[test]
+    fn test_fs_resolver_relative_to_base() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("parts")).unwrap();
+        std::fs::write(dir.path().join("parts/intro.md"), "# Intro").unwrap();
+
+        let resolver = FsIncludeResolver::new(dir.path());
+        let source = resolver.resolve("intro.md", Some("parts/book.md")).unwrap();
+
+        assert_eq!(source.key, "parts/intro.md");
+        assert_eq!(source.content, "# Intro");
+        assert_eq!(source.format, Some(SourceFormat::Markdown));
+        assert!(resolver
+            .resolve("../../x.md", Some("parts/book.md"))
+            .is_err());
+    }
+}
@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Incremental re-parse after editor edits
+//!
+//! [`reparse`] applies a [`TextEdit`] to the previous source and re-parses
+//! only the top-level blocks around it, reusing the rest of the previous
+//! [`Document`] with shifted spans. The region always includes one block
+//! either side of the edit, so edits that merge or split blocks come out
+//! the same as a full parse.
+//!
+//! Falls back to a full parse when it cannot be sure the region is
+//! self-contained: formats without blank-line block boundaries, documents
+//! parsed without spans, input that [`ParseConfig::normalize_input`]
+//! changes (spans index the normalized text), edits before the first
+//! block (front matter) and edits involving code fences.
+//! It also falls back when the edit may change the metadata: when the
+//! region holds metadata lines or the document's first heading, which
+//! formats may take as the title.
+
+use crate::ast::{Block, Document, DocumentMeta};
+use crate::segment::{has_fence, shift_spans};
+use crate::stream::supports_streaming;
+use crate::traits::{ConversionError, ParseConfig, Parser, Result};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Replace the bytes in `range` with `replacement`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, replacement: impl Into<String>) -> Self {
+        Self {
+            range,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// `source` with the edit applied
+    pub fn apply(&self, source: &str) -> Result<String> {
+        if source.get(self.range.clone()).is_none() {
+            return Err(ConversionError::ParseError {
+                line: 0,
+                column: 0,
+                message: format!(
+                    "edit range {:?} is not within the {}-byte source",
+                    self.range,
+                    source.len()
+                ),
+            });
+        }
+        let mut out = String::with_capacity(source.len() + self.replacement.len());
+        out.push_str(&source[..self.range.start]);
+        out.push_str(&self.replacement);
+        out.push_str(&source[self.range.end..]);
+        Ok(out)
+    }
+
+    /// Change in source length
+    fn delta(&self) -> isize {
+        self.replacement.len() as isize - self.range.len() as isize
+    }
+}
+
+/// Result of [`reparse`]
+#[derive(Debug, Clone)]
+pub struct Reparsed {
+    pub document: Document,
+    /// Indices in `document.content` of the blocks that were re-parsed
+    pub changed: Range<usize>,
+}
+
+/// Update `previous` (parsed from `source`) for `edit`
+pub fn reparse(
+    parser: &dyn Parser,
+    previous: &Document,
+    source: &str,
+    edit: &TextEdit,
+    config: &ParseConfig,
+) -> Result<Reparsed> {
+    let new_source = edit.apply(source)?;
+    let full_parse = |new_source: String| {
+        let document = parser.parse(&config.normalize_input(&new_source), config)?;
+        let changed = 0..document.content.len();
+        Ok(Reparsed { document, changed })
+    };
+
+    let Some(region) = affected_region(parser, previous, source, &new_source, edit, config) else {
+        return full_parse(new_source);
+    };
+    let blocks = &previous.content;
+
+    // Parse the edited region on its own; neither text needs normalizing
+    let new_end = (region.bytes.end as isize + edit.delta()) as usize;
+    let text = &new_source[region.bytes.start..new_end];
+    let region_doc = parser.parse(text, config)?;
+    let no_meta = DocumentMeta::default();
+    if region_doc.meta != no_meta
+        || (previous.meta != no_meta
+            && parser.parse(&source[region.bytes.clone()], config)?.meta != no_meta)
+        || (!blocks[..region.blocks.start].iter().any(is_heading)
+            && (region_doc.content.iter().any(is_heading)
+                || blocks[region.blocks.clone()].iter().any(is_heading)))
+    {
+        return full_parse(new_source);
+    }
+    let mut parsed = region_doc.content;
+    let lines_before = source[..region.bytes.start].matches('\n').count() as u32;
+    shift_spans(
+        &mut parsed,
+        region.bytes.start as isize,
+        lines_before as i64,
+    );
+
+    // Shift everything after it by the change in length
+    let line_delta = text.matches('\n').count() as i64
+        - source[region.bytes.clone()].matches('\n').count() as i64;
+    let mut after = blocks[region.blocks.end..].to_vec();
+    shift_spans(&mut after, edit.delta(), line_delta);
+
+    let changed = region.blocks.start..region.blocks.start + parsed.len();
+    let mut content = Vec::with_capacity(blocks.len() + parsed.len());
+    content.extend_from_slice(&blocks[..region.blocks.start]);
+    content.extend(parsed);
+    content.extend(after);
+
+    Ok(Reparsed {
+        document: Document {
+            source_format: previous.source_format,
+            meta: previous.meta.clone(),
+            content,
+            raw_source: config.preserve_raw_source.then_some(new_source),
+        },
+        changed,
+    })
+}
+
+/// Top-level blocks to re-parse and the source bytes they cover
+struct Region {
+    blocks: Range<usize>,
+    bytes: Range<usize>,
+}
+
+fn affected_region(
+    parser: &dyn Parser,
+    previous: &Document,
+    source: &str,
+    new_source: &str,
+    edit: &TextEdit,
+    config: &ParseConfig,
+) -> Option<Region> {
+    if !supports_streaming(parser.format()) || !config.preserve_spans {
+        return None;
+    }
+    let normalizes = |text| matches!(config.normalize_input(text), Cow::Owned(_));
+    if normalizes(source) || normalizes(new_source) {
+        return None;
+    }
+    let spans: Vec<_> = previous
+        .content
+        .iter()
+        .map(|b| b.span().map(|s| s.start..s.end))
+        .collect::<Option<_>>()?;
+
+    // Block containing (or preceding) the edit start, plus one before it
+    let first = spans.iter().rposition(|s| s.start <= edit.range.start)?;
+    let first = first.saturating_sub(1);
+    // Block containing (or following) the edit end, plus one after it
+    let last = spans
+        .iter()
+        .position(|s| s.end >= edit.range.end)
+        .map_or(spans.len() - 1, |i| (i + 1).min(spans.len() - 1));
+
+    let start = source[..spans[first].start]
+        .rfind('\n')
+        .map_or(0, |i| i + 1);
+    let end = if last + 1 == spans.len() {
+        source.len()
+    } else {
+        spans[last].end
+    };
+
+    let new_end = (end as isize + edit.delta()) as usize;
+    if has_fence(&source[start..end]) || has_fence(new_source.get(start..new_end)?) {
+        return None;
+    }
+
+    Some(Region {
+        blocks: first..last + 1,
+        bytes: start..end,
+    })
+}
+
+fn is_heading(block: &Block) -> bool {
+    matches!(block, Block::Heading { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::PlainTextHandler;
+
+    fn config() -> ParseConfig {
+        ParseConfig {
+            preserve_spans: true,
+            ..Default::default()
+        }
+    }
+
+    /// Incremental result must match a full parse of the edited text
+    fn check(source: &str, edit: TextEdit) -> Reparsed {
+        let handler = PlainTextHandler::new();
+        let previous = handler.parse(source, &config()).unwrap();
+        let reparsed = reparse(&handler, &previous, source, &edit, &config()).unwrap();
+        let full = handler
+            .parse(&edit.apply(source).unwrap(), &config())
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&reparsed.document.content).unwrap(),
+            serde_json::to_string(&full.content).unwrap(),
+            "edit {:?}",
+            edit
+        );
+        reparsed
+    }
+
+    #[test]
+    fn test_reparse_matches_full_parse() {
+        let source = "One\n\nTwo\n\nThree\n\nFour\n\nFive";
+
+        let reparsed = check(source, TextEdit::new(10..10, "and a half "));
+        assert_eq!(reparsed.changed, 1..4);
+
+        // Merge "Three" and "Four", split "Two", delete "Five"
+        check(source, TextEdit::new(15..17, " "));
+        check(source, TextEdit::new(6..6, "\n\n"));
+        check(source, TextEdit::new(20..source.len(), ""));
+        check(
+            source,
+            TextEdit::new(source.len()..source.len(), "\n\nSix\nlines"),
+        );
+    }
+
+    #[test]
+    fn test_reparse_falls_back_to_full_parse() {
+        let source = "  One\n\nTwo";
+        let reparsed = check(source, TextEdit::new(0..0, "Zero\n\n"));
+        assert_eq!(reparsed.changed, 0..3);
+
+        let reparsed = check(source, TextEdit::new(8..8, "```"));
+        assert_eq!(reparsed.changed, 0..2);
+    }
+
+    #[test]
+    fn test_reparse_normalized_input() {
+        let handler = PlainTextHandler::new();
+        let config = ParseConfig {
+            unicode_normalization: Some(crate::traits::UnicodeNormalization::Nfd),
+            ..config()
+        };
+        let source = "a\n\nb\n\néééé\n\nééé\n\nc\n\nd\n\ne\n\nf";
+        let previous = handler
+            .parse(&config.normalize_input(source), &config)
+            .unwrap();
+        for (at, _) in source.char_indices() {
+            let edit = TextEdit::new(at..at, "x");
+            let reparsed = reparse(&handler, &previous, source, &edit, &config).unwrap();
+            let edited = edit.apply(source).unwrap();
+            let full = handler
+                .parse(&config.normalize_input(&edited), &config)
+                .unwrap();
+            assert_eq!(
+                serde_json::to_string(&reparsed.document.content).unwrap(),
+                serde_json::to_string(&full.content).unwrap(),
+                "insert at {at}"
+            );
+        }
+    }
+
+    /// Plain text with the title taken from a `Title:` line
+    struct Titled;
+
+    impl Parser for Titled {
+        fn format(&self) -> crate::ast::SourceFormat {
+            crate::ast::SourceFormat::PlainText
+        }
+
+        fn parse(&self, input: &str, config: &ParseConfig) -> Result<Document> {
+            let mut doc = PlainTextHandler::new().parse(input, config)?;
+            doc.meta.title = input
+                .lines()
+                .find_map(|line| line.strip_prefix("Title: "))
+                .map(str::to_string);
+            Ok(doc)
+        }
+    }
+
+    #[test]
+    fn test_reparse_updates_meta() {
+        let source = "One\n\nTwo\n\nTitle: Old\n\nFour\n\nFive\n\nSix";
+        let previous = Titled.parse(source, &config()).unwrap();
+        let edit = TextEdit::new(17..20, "New");
+        let reparsed = reparse(&Titled, &previous, source, &edit, &config()).unwrap();
+        assert_eq!(reparsed.document.meta.title.as_deref(), Some("New"));
+
+        let edit = TextEdit::new(10..22, "");
+        let reparsed = reparse(&Titled, &previous, source, &edit, &config()).unwrap();
+        assert_eq!(reparsed.document.meta.title, None);
+
+        let edit = TextEdit::new(37..37, "teen");
+        let reparsed = reparse(&Titled, &previous, source, &edit, &config()).unwrap();
+        assert_eq!(reparsed.document.meta.title.as_deref(), Some("Old"));
+        assert_eq!(reparsed.changed, 4..6);
+    }
+
+    #[test]
+    fn test_edit_out_of_bounds() {
+        assert!(TextEdit::new(2..9, "x").apply("short").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! String interning for repeated tokens
+//!
+//! Code languages, diagram engines, raw formats and admonition kinds come
+//! from a small vocabulary but appear thousands of times in large
+//! documents and batch conversions. [`Interner`] hands out one shared
+//! [`Name`] per distinct string; run the [`InternNames`] transform after
+//! parsing to deduplicate a document's names.
+//!
+//! [`InternNames`]: crate::transform::InternNames
+
+use crate::ast::Name;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// A thread-safe pool of shared names
+#[derive(Debug, Default)]
+pub struct Interner {
+    names: Mutex<HashSet<Name>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared name equal to `name`, created on first use
+    pub fn intern(&self, name: &str) -> Name {
+        let mut names = self.names.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = names.get(name) {
+            return existing.clone();
+        }
+        let interned = Name::new(name);
+        names.insert(interned.clone());
+        interned
+    }
+
+    /// Replace `name` with the pooled copy, adopting it if it is new
+    pub fn intern_name(&self, name: &mut Name) {
+        let mut names = self.names.lock().unwrap_or_else(|e| e.into_inner());
+        match names.get(name.as_str()) {
+            Some(existing) => *name = existing.clone(),
+            None => {
+                names.insert(name.clone());
+            }
+        }
+    }
+
+    /// Number of distinct names in the pool
+    pub fn len(&self) -> usize {
+        self.names.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_shares_allocation() {
+        let interner = Interner::new();
+        let a = interner.intern("rust");
+        let b = interner.intern("rust");
+        assert!(Name::ptr_eq(&a, &b));
+
+        let mut c = Name::from("rust".to_string());
+        assert!(!Name::ptr_eq(&a, &c));
+        interner.intern_name(&mut c);
+        assert!(Name::ptr_eq(&a, &c));
+
+        interner.intern("python");
+        assert_eq!(interner.len(), 2);
+    }
+}
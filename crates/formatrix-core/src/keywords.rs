@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Keyword extraction and word frequencies
+//!
+//! Counts words and adjacent word pairs (bigrams) in the prose of a
+//! document, after lowercasing and removing stopwords, short words and
+//! numbers. Code blocks, inline code and raw blocks are never counted.
+//! The most frequent terms feed tag suggestions and related-document
+//! discovery in the database.
+
+use crate::ast::Document;
+use crate::stats::inline_text;
+use crate::visit;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Common English words never reported as keywords, whitespace-separated
+pub const ENGLISH_STOPWORDS: &str = "\
+    a about above after again against all also am an and any are as at be because been before \
+    being below between both but by can could did do does doing down during each few for from \
+    further had has have having he her here hers herself him himself his how however i if in \
+    into is it its itself just may me might more most must my myself no nor not now of off on \
+    once one only or other our ours ourselves out over own same shall she should so some such \
+    than that the their theirs them themselves then there these they this those through to too \
+    under until up use used using very was we were what when where which while who whom why \
+    will with would you your yours yourself yourselves";
+
+/// A term and the number of times it occurs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keyword {
+    /// Lowercased word, or two words separated by a space
+    pub term: String,
+    pub count: usize,
+}
+
+/// Options for [`Document::keywords`]
+#[derive(Debug, Clone)]
+pub struct KeywordOptions {
+    /// Maximum number of words and of bigrams returned
+    pub limit: usize,
+    /// Shortest word counted, in characters
+    pub min_length: usize,
+    /// Lowercased words to ignore
+    pub stopwords: HashSet<String>,
+}
+
+impl KeywordOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Ignore these words in addition to the current stopwords
+    pub fn with_stopwords<I, S>(mut self, words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.stopwords
+            .extend(words.into_iter().map(|w| w.as_ref().to_lowercase()));
+        self
+    }
+}
+
+impl Default for KeywordOptions {
+    fn default() -> Self {
+        Self {
+            limit: 10,
+            min_length: 3,
+            stopwords: ENGLISH_STOPWORDS
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+/// Top terms of a document, most frequent first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keywords {
+    pub words: Vec<Keyword>,
+    /// Adjacent keyword pairs occurring at least twice
+    pub bigrams: Vec<Keyword>,
+}
+
+impl Document {
+    /// Most frequent words and bigrams in the document's prose
+    pub fn keywords(&self, options: &KeywordOptions) -> Keywords {
+        let (words, bigrams) = self.count_terms(options);
+        Keywords {
+            words: top(words, 1, options.limit),
+            bigrams: top(bigrams, 2, options.limit),
+        }
+    }
+
+    /// Count of every keyword in the document, most frequent first
+    pub fn word_frequencies(&self, options: &KeywordOptions) -> Vec<Keyword> {
+        top(self.count_terms(options).0, 1, usize::MAX)
+    }
+
+    fn count_terms(
+        &self,
+        options: &KeywordOptions,
+    ) -> (HashMap<String, usize>, HashMap<String, usize>) {
+        let mut words = HashMap::new();
+        let mut bigrams = HashMap::new();
+
+        visit::walk_blocks(&self.content, &mut |block| {
+            for inlines in visit::block_inlines(block) {
+                let prose = inline_text(inlines).prose;
+                // Pairs only form across adjacent words, so a stopword or
+                // sentence end between two keywords breaks the pair
+                for sentence in prose.unicode_sentences() {
+                    let mut previous: Option<String> = None;
+                    for word in sentence.unicode_words() {
+                        let word = word.to_lowercase();
+                        if !is_keyword(&word, options) {
+                            previous = None;
+                            continue;
+                        }
+                        if let Some(previous) = &previous {
+                            *bigrams.entry(format!("{} {}", previous, word)).or_default() += 1;
+                        }
+                        *words.entry(word.clone()).or_default() += 1;
+                        previous = Some(word);
+                    }
+                }
+            }
+        });
+        (words, bigrams)
+    }
+}
+
+fn is_keyword(word: &str, options: &KeywordOptions) -> bool {
+    word.chars().count() >= options.min_length
+        && !word.chars().all(|c| c.is_numeric())
+        && !options.stopwords.contains(word)
+}
+
+/// Terms seen at least `min_count` times, by count then alphabetically
+fn top(counts: HashMap<String, usize>, min_count: usize, limit: usize) -> Vec<Keyword> {
+    let mut terms: Vec<Keyword> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= min_count)
+        .map(|(term, count)| Keyword { term, count })
+        .collect();
+    terms.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+    terms.truncate(limit);
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::PlainTextHandler;
+    use crate::traits::{ParseConfig, Parser};
+
+    fn parse(input: &str) -> Document {
+        PlainTextHandler::new()
+            .parse(input, &ParseConfig::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_keywords() {
+        let doc = parse(
+            "The document parser builds a syntax tree. Every document parser \
+             needs tests.\n\nA syntax tree is shared by the renderers in 2024.",
+        );
+        let keywords = doc.keywords(&KeywordOptions::new().with_limit(3));
+
+        let words: Vec<_> = keywords
+            .words
+            .iter()
+            .map(|k| (k.term.as_str(), k.count))
+            .collect();
+        assert_eq!(words, [("document", 2), ("parser", 2), ("syntax", 2)]);
+
+        let bigrams: Vec<_> = keywords.bigrams.iter().map(|k| k.term.as_str()).collect();
+        assert_eq!(bigrams, ["document parser", "syntax tree"]);
+    }
+
+    #[test]
+    fn test_stopwords_and_code() {
+        let mut doc = parse("Formatrix converts documents. Formatrix is fast.");
+        doc.content.push(crate::ast::Block::CodeBlock {
+            language: None,
+            content: "formatrix formatrix formatrix".to_string(),
+            span: None,
+        });
+
+        let options = KeywordOptions::new().with_stopwords(["Fast"]);
+        let frequencies = doc.word_frequencies(&options);
+        let terms: Vec<_> = frequencies.iter().map(|k| k.term.as_str()).collect();
+        assert_eq!(terms, ["formatrix", "converts", "documents"]);
+        assert_eq!(frequencies[0].count, 2);
+    }
+}
@@ -8,29 +8,68 @@
 //! - Implementations for 7 formats: TXT, MD, ADOC, DJOT, ORG, RST, TYP
 //! - C FFI exports for the Ada TUI (FD-M10)
 
-#![forbid(unsafe_code)]
+// The FFI module is the only place allowed to use unsafe code
+#![cfg_attr(not(feature = "ffi"), forbid(unsafe_code))]
+#![cfg_attr(feature = "ffi", deny(unsafe_code))]
 pub mod ast;
+pub mod borrowed;
+pub mod buffer;
+pub mod codec;
+pub mod compat;
+pub mod diagram;
 pub mod file_ops;
 pub mod formats;
+pub mod frontmatter;
+pub mod hash;
+pub mod i18n;
+pub mod image;
+pub mod include;
+pub mod incremental;
+pub mod intern;
+pub mod keywords;
+pub mod math;
+pub mod options;
+pub mod readability;
+pub mod recover;
+mod segment;
+pub mod spell;
+pub mod stats;
+pub mod stream;
+pub mod structure;
+pub mod tasks;
 pub mod traits;
+pub mod transform;
+pub mod visit;
+pub mod wrap;
 
 // FD-M10: C FFI exports for Ada TUI
 #[cfg(feature = "ffi")]
+#[allow(unsafe_code)]
 pub mod ffi;
 
-pub use ast::{Block, Document, DocumentMeta, Inline, SourceFormat};
+pub use ast::{Block, Document, DocumentMeta, Inline, MetaValue, Name, SourceFormat};
+pub use compat::{compatibility, FeatureLoss};
 pub use file_ops::{
     convert_file, convert_file_with_config, extension_for_format, format_from_content,
     format_from_extension, is_supported_extension, open_file, open_file_as,
     open_file_with_config, save_file, save_file_as, save_file_with_config, supported_extensions,
     FileError, FileInfo, FileResult, OpenedDocument,
 };
-pub use traits::{ConversionError, ParseConfig, Parser, RenderConfig, Renderer, Result};
+pub use readability::Readability;
+pub use stats::DocumentStats;
+pub use structure::{slugify, ConcatOptions, OutlineEntry};
+pub use traits::{
+    ConversionError, FormatHandler, FormatRegistry, ParseConfig, Parser, RenderConfig, Renderer,
+    Result, UnicodeNormalization,
+};
+pub use transform::Transform;
 
 // Re-export FFI types when enabled
 #[cfg(feature = "ffi")]
 pub use ffi::{
-    formatrix_block_count, formatrix_convert, formatrix_detect_format, formatrix_free_document,
-    formatrix_free_string, formatrix_get_format, formatrix_get_title, formatrix_parse,
-    formatrix_render, formatrix_version, DocumentHandle, FfiFormat, FfiResult,
+    formatrix_abi_version, formatrix_block_count, formatrix_check_abi, formatrix_convert,
+    formatrix_detect_format, formatrix_free_document, formatrix_free_string, formatrix_get_format,
+    formatrix_get_title, formatrix_parse, formatrix_parse_with_config, formatrix_render,
+    formatrix_render_with_config, formatrix_version, DocumentHandle, FfiFormat, FfiResult,
+    FORMATRIX_ABI_VERSION,
 };
@@ -0,0 +1,734 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Math notation conversion
+//!
+//! `Inline::Math` and `Inline::DisplayMath` hold source in the notation of
+//! the format they were parsed from: LaTeX for Markdown, Djot, Org,
+//! AsciiDoc and RST, Typst math for Typst. This module converts a common
+//! subset (letters, numbers, operators, Greek letters and the usual
+//! symbols, fractions, roots, sub/superscripts, text and named functions)
+//! between LaTeX and Typst, and renders either to MathML.
+//!
+//! Anything outside the subset (environments, unknown commands) is an
+//! error rather than a silent mistranslation.
+
+use crate::ast::SourceFormat;
+use crate::traits::{ConversionError, Result};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A math notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MathNotation {
+    Latex,
+    Typst,
+    /// Presentation MathML (output only)
+    MathMl,
+}
+
+impl MathNotation {
+    /// Notation used for math in documents of the given format
+    pub fn for_format(format: SourceFormat) -> Self {
+        match format {
+            SourceFormat::Typst => MathNotation::Typst,
+            _ => MathNotation::Latex,
+        }
+    }
+}
+
+/// Convert math source from one notation to another
+pub fn convert(source: &str, from: MathNotation, to: MathNotation) -> Result<String> {
+    if from == to {
+        return Ok(source.to_string());
+    }
+    let nodes = match from {
+        MathNotation::Latex => LatexParser::new(source).parse()?,
+        MathNotation::Typst => TypstParser::new(source).parse()?,
+        MathNotation::MathMl => {
+            return Err(syntax_error(0, "MathML input is not supported"));
+        }
+    };
+    Ok(match to {
+        MathNotation::Latex => latex_row(&nodes),
+        MathNotation::Typst => typst_row(&nodes),
+        MathNotation::MathMl => format!(
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>",
+            mathml_row(&nodes)
+        ),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SymbolKind {
+    Ident,
+    Operator,
+    Function,
+}
+
+/// A named symbol with its spelling in each notation
+#[derive(Debug, PartialEq)]
+struct Symbol {
+    latex: &'static str,
+    typst: &'static str,
+    unicode: &'static str,
+    kind: SymbolKind,
+}
+
+macro_rules! symbols {
+    ($($kind:ident $latex:literal $typst:literal $unicode:literal;)*) => {
+        &[$(Symbol { latex: $latex, typst: $typst, unicode: $unicode, kind: SymbolKind::$kind },)*]
+    };
+}
+
+static SYMBOLS: &[Symbol] = symbols! {
+    Ident "alpha" "alpha" "α"; Ident "beta" "beta" "β"; Ident "gamma" "gamma" "γ";
+    Ident "delta" "delta" "δ"; Ident "epsilon" "epsilon" "ε"; Ident "zeta" "zeta" "ζ";
+    Ident "eta" "eta" "η"; Ident "theta" "theta" "θ"; Ident "iota" "iota" "ι";
+    Ident "kappa" "kappa" "κ"; Ident "lambda" "lambda" "λ"; Ident "mu" "mu" "μ";
+    Ident "nu" "nu" "ν"; Ident "xi" "xi" "ξ"; Ident "pi" "pi" "π"; Ident "rho" "rho" "ρ";
+    Ident "sigma" "sigma" "σ"; Ident "tau" "tau" "τ"; Ident "upsilon" "upsilon" "υ";
+    Ident "phi" "phi" "φ"; Ident "chi" "chi" "χ"; Ident "psi" "psi" "ψ";
+    Ident "omega" "omega" "ω";
+    Ident "Gamma" "Gamma" "Γ"; Ident "Delta" "Delta" "Δ"; Ident "Theta" "Theta" "Θ";
+    Ident "Lambda" "Lambda" "Λ"; Ident "Xi" "Xi" "Ξ"; Ident "Pi" "Pi" "Π";
+    Ident "Sigma" "Sigma" "Σ"; Ident "Phi" "Phi" "Φ"; Ident "Psi" "Psi" "Ψ";
+    Ident "Omega" "Omega" "Ω";
+    Ident "infty" "infinity" "∞"; Ident "partial" "diff" "∂"; Ident "nabla" "nabla" "∇";
+    Operator "sum" "sum" "∑"; Operator "prod" "product" "∏"; Operator "int" "integral" "∫";
+    Operator "pm" "plus.minus" "±"; Operator "times" "times" "×"; Operator "cdot" "dot.op" "⋅";
+    Operator "leq" "<=" "≤"; Operator "geq" ">=" "≥"; Operator "neq" "!=" "≠";
+    Operator "approx" "approx" "≈"; Operator "equiv" "equiv" "≡";
+    Operator "to" "->" "→"; Operator "leftarrow" "<-" "←"; Operator "Rightarrow" "=>" "⇒";
+    Operator "in" "in" "∈"; Operator "notin" "in.not" "∉"; Operator "subset" "subset" "⊂";
+    Operator "cup" "union" "∪"; Operator "cap" "sect" "∩";
+    Operator "forall" "forall" "∀"; Operator "exists" "exists" "∃";
+    Operator "ldots" "dots" "…"; Operator "cdots" "dots.c" "⋯";
+    Function "sin" "sin" "sin"; Function "cos" "cos" "cos"; Function "tan" "tan" "tan";
+    Function "log" "log" "log"; Function "ln" "ln" "ln"; Function "exp" "exp" "exp";
+    Function "lim" "lim" "lim"; Function "max" "max" "max"; Function "min" "min" "min";
+};
+
+/// LaTeX spellings that are aliases of a symbol above
+const LATEX_ALIASES: &[(&str, &str)] = &[
+    ("le", "leq"),
+    ("ge", "geq"),
+    ("ne", "neq"),
+    ("rightarrow", "to"),
+    ("dots", "ldots"),
+    ("varepsilon", "epsilon"),
+];
+
+fn latex_symbol(name: &str) -> Option<&'static Symbol> {
+    let name = LATEX_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canonical)| canonical);
+    SYMBOLS.iter().find(|s| s.latex == name)
+}
+
+fn typst_symbol(name: &str) -> Option<&'static Symbol> {
+    SYMBOLS.iter().find(|s| s.typst == name)
+}
+
+/// Notation-independent math tree
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Ident(char),
+    Number(String),
+    Op(char),
+    Symbol(&'static Symbol),
+    Text(String),
+    Row(Vec<Node>),
+    Frac(Box<Node>, Box<Node>),
+    Sqrt(Option<Box<Node>>, Box<Node>),
+    Scripts {
+        base: Box<Node>,
+        sub: Option<Box<Node>>,
+        sup: Option<Box<Node>>,
+    },
+}
+
+impl Node {
+    fn row(mut nodes: Vec<Node>) -> Node {
+        if nodes.len() == 1 {
+            nodes.remove(0)
+        } else {
+            Node::Row(nodes)
+        }
+    }
+
+    fn is_empty_row(&self) -> bool {
+        matches!(self, Node::Row(nodes) if nodes.is_empty())
+    }
+}
+
+fn syntax_error(column: usize, message: impl Into<String>) -> ConversionError {
+    ConversionError::ParseError {
+        line: 1,
+        column: column as u32 + 1,
+        message: message.into(),
+    }
+}
+
+/// Attach a `_` or `^` script to the last node of a row
+fn attach_script(nodes: &mut Vec<Node>, superscript: bool, script: Node) {
+    let base = nodes.pop().unwrap_or(Node::Row(Vec::new()));
+    let (base, mut sub, mut sup) = match base {
+        Node::Scripts { base, sub, sup } => (base, sub, sup),
+        other => (Box::new(other), None, None),
+    };
+    if superscript {
+        sup = Some(Box::new(script));
+    } else {
+        sub = Some(Box::new(script));
+    }
+    nodes.push(Node::Scripts { base, sub, sup });
+}
+
+struct LatexParser<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> LatexParser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            chars: source.char_indices().peekable(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<Node>> {
+        self.row(None)
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |(i, _)| *i)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    /// Parse nodes until `close` (consumed) or end of input
+    fn row(&mut self, close: Option<char>) -> Result<Vec<Node>> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let Some(&(pos, c)) = self.chars.peek() else {
+                return match close {
+                    Some(close) => Err(syntax_error(
+                        self.source.len(),
+                        format!("missing '{}'", close),
+                    )),
+                    None => Ok(nodes),
+                };
+            };
+            if Some(c) == close {
+                self.chars.next();
+                return Ok(nodes);
+            }
+            match c {
+                '}' => return Err(syntax_error(pos, "unbalanced '}'")),
+                '^' | '_' => {
+                    self.chars.next();
+                    let script = self.script()?;
+                    attach_script(&mut nodes, c == '^', script);
+                }
+                _ => {
+                    let node = self.atom()?;
+                    if !node.is_empty_row() {
+                        nodes.push(node);
+                    }
+                }
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        let pos = self.position();
+        let Some((_, c)) = self.chars.next() else {
+            return Err(syntax_error(pos, "expected an expression"));
+        };
+        Ok(match c {
+            '{' => Node::row(self.row(Some('}'))?),
+            '\\' => self.command(pos)?,
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some((_, d)) =
+                    self.chars.next_if(|(_, d)| d.is_ascii_digit() || *d == '.')
+                {
+                    number.push(d);
+                }
+                Node::Number(number)
+            }
+            c if c.is_alphabetic() => Node::Ident(c),
+            c => Node::Op(c),
+        })
+    }
+
+    /// A script argument: a group or a single character (`x^12` is x¹2)
+    fn script(&mut self) -> Result<Node> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&(_, d)) if d.is_ascii_digit() => {
+                self.chars.next();
+                Ok(Node::Number(d.to_string()))
+            }
+            _ => self.atom(),
+        }
+    }
+
+    /// A required command argument
+    fn argument(&mut self) -> Result<Node> {
+        self.skip_whitespace();
+        self.script()
+    }
+
+    /// Raw text of a braced argument (for `\text{...}`)
+    fn text_argument(&mut self, pos: usize) -> Result<String> {
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, c)| *c == '{').is_none() {
+            return Err(syntax_error(pos, "expected '{'"));
+        }
+        let mut text = String::new();
+        let mut depth = 0;
+        for (_, c) in self.chars.by_ref() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(text),
+                '}' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+        Err(syntax_error(self.source.len(), "missing '}'"))
+    }
+
+    fn command(&mut self, pos: usize) -> Result<Node> {
+        let mut name = String::new();
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_alphabetic()) {
+            name.push(c);
+        }
+        if name.is_empty() {
+            return match self.chars.next() {
+                // Spacing commands
+                Some((_, ',' | ';' | ':' | '!' | ' ')) => Ok(Node::Row(Vec::new())),
+                Some((_, c @ ('{' | '}' | '%' | '#' | '&' | '_' | '|'))) => Ok(Node::Op(c)),
+                _ => Err(syntax_error(pos, "unsupported LaTeX escape")),
+            };
+        }
+
+        Ok(match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument()?;
+                let denominator = self.argument()?;
+                Node::Frac(Box::new(numerator), Box::new(denominator))
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                let index = if self.chars.next_if(|(_, c)| *c == '[').is_some() {
+                    Some(Box::new(Node::row(self.row(Some(']'))?)))
+                } else {
+                    None
+                };
+                Node::Sqrt(index, Box::new(self.argument()?))
+            }
+            "text" | "textrm" | "mathrm" | "operatorname" => Node::Text(self.text_argument(pos)?),
+            // Styling is dropped; the content is kept
+            "mathbf" | "mathit" | "mathsf" | "mathtt" | "boldsymbol" => self.argument()?,
+            // Delimiter sizing: the delimiter itself follows as an operator
+            "left" | "right" => Node::Row(Vec::new()),
+            "quad" | "qquad" => Node::Row(Vec::new()),
+            _ => Node::Symbol(latex_symbol(&name).ok_or_else(|| {
+                syntax_error(pos, format!("unsupported LaTeX command \\{}", name))
+            })?),
+        })
+    }
+}
+
+struct TypstParser<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+/// Typst shorthands recognised as symbols, longest first
+const TYPST_SHORTHANDS: &[&str] = &["<=", ">=", "!=", "->", "<-", "=>"];
+
+impl<'a> TypstParser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            chars: source.char_indices().peekable(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<Node>> {
+        Ok(self.row(&[])?.0)
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |(i, _)| *i)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    /// Parse nodes until one of `stops` (consumed and returned) or end of input
+    fn row(&mut self, stops: &[char]) -> Result<(Vec<Node>, Option<char>)> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let Some(&(pos, c)) = self.chars.peek() else {
+                if let Some(stop) = stops.last() {
+                    return Err(syntax_error(
+                        self.source.len(),
+                        format!("missing '{}'", stop),
+                    ));
+                }
+                return Ok((nodes, None));
+            };
+            if stops.contains(&c) {
+                self.chars.next();
+                return Ok((nodes, Some(c)));
+            }
+            match c {
+                '^' | '_' => {
+                    self.chars.next();
+                    let script = strip_parens(self.atom()?);
+                    attach_script(&mut nodes, c == '^', script);
+                }
+                '/' => {
+                    self.chars.next();
+                    let numerator = nodes
+                        .pop()
+                        .ok_or_else(|| syntax_error(pos, "fraction without numerator"))?;
+                    let mut denominator = self.atom()?;
+                    self.skip_whitespace();
+                    while let Some(&(_, s @ ('^' | '_'))) = self.chars.peek() {
+                        self.chars.next();
+                        let script = strip_parens(self.atom()?);
+                        let mut wrapped = vec![denominator];
+                        attach_script(&mut wrapped, s == '^', script);
+                        denominator = wrapped.remove(0);
+                        self.skip_whitespace();
+                    }
+                    nodes.push(Node::Frac(
+                        Box::new(strip_parens(numerator)),
+                        Box::new(strip_parens(denominator)),
+                    ));
+                }
+                _ => nodes.push(self.atom()?),
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        self.skip_whitespace();
+        let pos = self.position();
+        let rest = &self.source[pos.min(self.source.len())..];
+        if let Some(shorthand) = TYPST_SHORTHANDS.iter().find(|s| rest.starts_with(**s)) {
+            for _ in 0..shorthand.len() {
+                self.chars.next();
+            }
+            return Ok(Node::Symbol(
+                typst_symbol(shorthand).expect("shorthand in table"),
+            ));
+        }
+
+        let Some((_, c)) = self.chars.next() else {
+            return Err(syntax_error(pos, "expected an expression"));
+        };
+        Ok(match c {
+            '(' => {
+                let (mut inner, _) = self.row(&[')'])?;
+                inner.insert(0, Node::Op('('));
+                inner.push(Node::Op(')'));
+                Node::Row(inner)
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => text.extend(self.chars.next().map(|(_, c)| c)),
+                        Some((_, c)) => text.push(c),
+                        None => return Err(syntax_error(pos, "unterminated string")),
+                    }
+                }
+                Node::Text(text)
+            }
+            '\\' => match self.chars.next() {
+                Some((_, c)) => Node::Op(c),
+                None => return Err(syntax_error(pos, "dangling '\\'")),
+            },
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(&(i, d)) = self.chars.peek() {
+                    let decimal_point =
+                        d == '.' && self.source[i + 1..].starts_with(|n: char| n.is_ascii_digit());
+                    if !(d.is_ascii_digit() || decimal_point) {
+                        break;
+                    }
+                    number.push(d);
+                    self.chars.next();
+                }
+                Node::Number(number)
+            }
+            c if c.is_alphabetic() => {
+                let mut word = c.to_string();
+                while let Some(&(i, d)) = self.chars.peek() {
+                    let dotted =
+                        d == '.' && self.source[i + 1..].starts_with(|n: char| n.is_alphabetic());
+                    if !(d.is_alphabetic() || dotted) {
+                        break;
+                    }
+                    word.push(d);
+                    self.chars.next();
+                }
+                self.word(pos, word)?
+            }
+            c => Node::Op(c),
+        })
+    }
+
+    fn word(&mut self, pos: usize, word: String) -> Result<Node> {
+        if word.chars().count() == 1 {
+            return Ok(Node::Ident(word.chars().next().unwrap_or_default()));
+        }
+        match word.as_str() {
+            "frac" | "sqrt" | "root" => {
+                if self.chars.next_if(|(_, c)| *c == '(').is_none() {
+                    return Err(syntax_error(pos, format!("expected '(' after {}", word)));
+                }
+                let mut args = Vec::new();
+                loop {
+                    let (arg, stop) = self.row(&[',', ')'])?;
+                    args.push(Node::row(arg));
+                    if stop == Some(')') {
+                        break;
+                    }
+                }
+                let mut args = args.into_iter();
+                Ok(
+                    match (word.as_str(), args.next(), args.next(), args.next()) {
+                        ("frac", Some(a), Some(b), None) => Node::Frac(Box::new(a), Box::new(b)),
+                        ("sqrt", Some(a), None, None) => Node::Sqrt(None, Box::new(a)),
+                        ("root", Some(n), Some(a), None) => {
+                            Node::Sqrt(Some(Box::new(n)), Box::new(a))
+                        }
+                        _ => {
+                            return Err(syntax_error(
+                                pos,
+                                format!("wrong number of arguments to {}", word),
+                            ))
+                        }
+                    },
+                )
+            }
+            _ => typst_symbol(&word)
+                .map(Node::Symbol)
+                .ok_or_else(|| syntax_error(pos, format!("unsupported Typst symbol {}", word))),
+        }
+    }
+}
+
+/// Typst drops the parentheses around script and fraction operands
+fn strip_parens(node: Node) -> Node {
+    match node {
+        Node::Row(mut nodes)
+            if nodes.len() >= 2
+                && nodes.first() == Some(&Node::Op('('))
+                && nodes.last() == Some(&Node::Op(')')) =>
+        {
+            nodes.pop();
+            nodes.remove(0);
+            Node::row(nodes)
+        }
+        other => other,
+    }
+}
+
+fn latex_row(nodes: &[Node]) -> String {
+    nodes.iter().map(latex).collect::<Vec<_>>().join(" ")
+}
+
+fn latex_group(node: &Node) -> String {
+    match node {
+        Node::Ident(_) | Node::Symbol(_) => latex(node),
+        Node::Number(n) if n.len() == 1 => n.clone(),
+        Node::Row(nodes) => format!("{{{}}}", latex_row(nodes)),
+        other => format!("{{{}}}", latex(other)),
+    }
+}
+
+fn latex(node: &Node) -> String {
+    match node {
+        Node::Ident(c) => c.to_string(),
+        Node::Op(c @ ('{' | '}' | '%' | '#' | '&' | '_' | '$')) => format!("\\{}", c),
+        Node::Op(c) => c.to_string(),
+        Node::Number(n) => n.clone(),
+        Node::Symbol(s) => format!("\\{}", s.latex),
+        Node::Text(t) => format!("\\text{{{}}}", t),
+        Node::Row(nodes) => latex_row(nodes),
+        Node::Frac(a, b) => format!("\\frac{{{}}}{{{}}}", latex(a), latex(b)),
+        Node::Sqrt(None, a) => format!("\\sqrt{{{}}}", latex(a)),
+        Node::Sqrt(Some(n), a) => format!("\\sqrt[{}]{{{}}}", latex(n), latex(a)),
+        Node::Scripts { base, sub, sup } => {
+            let mut out = if base.is_empty_row() {
+                "{}".to_string()
+            } else {
+                latex_group(base)
+            };
+            if let Some(sub) = sub {
+                out.push('_');
+                out.push_str(&latex_group(sub));
+            }
+            if let Some(sup) = sup {
+                out.push('^');
+                out.push_str(&latex_group(sup));
+            }
+            out
+        }
+    }
+}
+
+fn typst_row(nodes: &[Node]) -> String {
+    nodes.iter().map(typst).collect::<Vec<_>>().join(" ")
+}
+
+/// Typst operand of a script: atoms as-is, everything else parenthesized
+fn typst_operand(node: &Node) -> String {
+    match node {
+        Node::Ident(_) | Node::Number(_) | Node::Symbol(_) | Node::Text(_) => typst(node),
+        Node::Frac(..) | Node::Sqrt(..) => typst(node),
+        other => format!("({})", typst(other)),
+    }
+}
+
+fn typst(node: &Node) -> String {
+    match node {
+        Node::Ident(c) => c.to_string(),
+        Node::Op(c @ ('/' | '^' | '_' | '"' | '\\' | '$' | '#')) => format!("\\{}", c),
+        Node::Op(c) => c.to_string(),
+        Node::Number(n) => n.clone(),
+        Node::Symbol(s) => s.typst.to_string(),
+        Node::Text(t) => format!("\"{}\"", t.replace('\\', "\\\\").replace('"', "\\\"")),
+        Node::Row(nodes) => typst_row(nodes),
+        Node::Frac(a, b) => format!("frac({}, {})", typst(a), typst(b)),
+        Node::Sqrt(None, a) => format!("sqrt({})", typst(a)),
+        Node::Sqrt(Some(n), a) => format!("root({}, {})", typst(n), typst(a)),
+        Node::Scripts { base, sub, sup } => {
+            let mut out = if base.is_empty_row() {
+                "\"\"".to_string()
+            } else {
+                typst_operand(base)
+            };
+            if let Some(sub) = sub {
+                out.push('_');
+                out.push_str(&typst_operand(sub));
+            }
+            if let Some(sup) = sup {
+                out.push('^');
+                out.push_str(&typst_operand(sup));
+            }
+            out
+        }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn mathml_row(nodes: &[Node]) -> String {
+    nodes.iter().map(mathml).collect()
+}
+
+/// A single MathML element (rows are wrapped in `<mrow>`)
+fn mathml_element(node: &Node) -> String {
+    match node {
+        Node::Row(nodes) => format!("<mrow>{}</mrow>", mathml_row(nodes)),
+        other => mathml(other),
+    }
+}
+
+fn mathml(node: &Node) -> String {
+    match node {
+        Node::Ident(c) => format!("<mi>{}</mi>", escape_xml(&c.to_string())),
+        Node::Number(n) => format!("<mn>{}</mn>", n),
+        Node::Op(c) => format!("<mo>{}</mo>", escape_xml(&c.to_string())),
+        Node::Symbol(s) => match s.kind {
+            SymbolKind::Operator => format!("<mo>{}</mo>", s.unicode),
+            SymbolKind::Ident | SymbolKind::Function => format!("<mi>{}</mi>", s.unicode),
+        },
+        Node::Text(t) => format!("<mtext>{}</mtext>", escape_xml(t)),
+        Node::Row(nodes) => format!("<mrow>{}</mrow>", mathml_row(nodes)),
+        Node::Frac(a, b) => format!("<mfrac>{}{}</mfrac>", mathml_element(a), mathml_element(b)),
+        Node::Sqrt(None, a) => format!("<msqrt>{}</msqrt>", mathml_element(a)),
+        Node::Sqrt(Some(n), a) => {
+            format!("<mroot>{}{}</mroot>", mathml_element(a), mathml_element(n))
+        }
+        Node::Scripts { base, sub, sup } => {
+            let base = mathml_element(base);
+            match (sub, sup) {
+                (Some(sub), Some(sup)) => format!(
+                    "<msubsup>{}{}{}</msubsup>",
+                    base,
+                    mathml_element(sub),
+                    mathml_element(sup)
+                ),
+                (Some(sub), None) => format!("<msub>{}{}</msub>", base, mathml_element(sub)),
+                (None, Some(sup)) => format!("<msup>{}{}</msup>", base, mathml_element(sup)),
+                (None, None) => base,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latex_to_typst() {
+        let typst = |latex| convert(latex, MathNotation::Latex, MathNotation::Typst).unwrap();
+
+        assert_eq!(typst(r"x^2 + y_{i j}"), "x^2 + y_(i j)");
+        assert_eq!(
+            typst(r"\frac{a+b}{2} \leq \sqrt[3]{\alpha}"),
+            "frac(a + b, 2) <= root(3, alpha)"
+        );
+        assert_eq!(typst(r"\sum_{i=1}^{n} i"), "sum_(i = 1)^n i");
+        assert_eq!(typst(r"\text{if } x"), "\"if \" x");
+    }
+
+    #[test]
+    fn test_typst_to_latex() {
+        let latex = |typst| convert(typst, MathNotation::Typst, MathNotation::Latex).unwrap();
+
+        assert_eq!(latex("(a + b)/2"), r"\frac{a + b}{2}");
+        assert_eq!(latex("x_(i+1)^2 != pi"), r"x_{i + 1}^2 \neq \pi");
+        assert_eq!(latex("sqrt(x) -> infinity"), r"\sqrt{x} \to \infty");
+    }
+
+    #[test]
+    fn test_to_mathml() {
+        let mathml = convert(r"\frac{1}{x^2}", MathNotation::Latex, MathNotation::MathMl).unwrap();
+        assert_eq!(
+            mathml,
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">\
+             <mfrac><mn>1</mn><msup><mi>x</mi><mn>2</mn></msup></mfrac></math>"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_input_is_an_error() {
+        assert!(convert(r"\begin{matrix}", MathNotation::Latex, MathNotation::Typst).is_err());
+        assert!(convert("frac(a", MathNotation::Typst, MathNotation::Latex).is_err());
+        assert!(convert("<math/>", MathNotation::MathMl, MathNotation::Latex).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Typed render style options
+//!
+//! Renderers read their style choices from
+//! [`RenderConfig::format_options`] through the [`StyleOption`] keys
+//! defined here, so each key has one documented meaning, default and set
+//! of accepted values. Keys are prefixed with the format's extension.
+//!
+//! | Key                | Values                | Default   |
+//! |--------------------|-----------------------|-----------|
+//! | `txt.bullet`       | any string            | none      |
+//! | `md.bullet`        | `-`, `*`, `+`         | `-`       |
+//! | `md.emphasis`      | `*`, `_`              | `*`       |
+//! | `org.heading_todo` | `true`, `false`       | `true`    |
+//! | `rst.heading_chars`| punctuation, by level | `=-~^"'`  |
+
+use crate::traits::{ConversionError, RenderConfig, Result};
+
+/// A typed key into [`RenderConfig::format_options`]
+pub struct StyleOption<T> {
+    /// Key in the options map
+    pub key: &'static str,
+    /// Accepted values, for error messages and option listings
+    pub expected: &'static str,
+    default: fn() -> T,
+    parse: fn(&str) -> Option<T>,
+}
+
+impl<T> StyleOption<T> {
+    /// The configured value, or the default when unset
+    pub fn get(&self, config: &RenderConfig) -> Result<T> {
+        match config.format_options.get(self.key) {
+            None => Ok((self.default)()),
+            Some(value) => (self.parse)(value).ok_or_else(|| ConversionError::InvalidOption {
+                key: self.key.to_string(),
+                value: value.clone(),
+                expected: self.expected.to_string(),
+            }),
+        }
+    }
+
+    /// The value used when the option is unset
+    pub fn default_value(&self) -> T {
+        (self.default)()
+    }
+}
+
+/// Plain text list item marker; items are run together when unset
+pub const TXT_BULLET: StyleOption<Option<String>> = StyleOption {
+    key: "txt.bullet",
+    expected: "a marker string",
+    default: || None,
+    parse: |s| Some(Some(s.to_string())),
+};
+
+/// Markdown bullet list marker
+pub const MD_BULLET: StyleOption<char> = StyleOption {
+    key: "md.bullet",
+    expected: "one of -, *, +",
+    default: || '-',
+    parse: |s| single_char(s, "-*+"),
+};
+
+/// Markdown emphasis delimiter (strong emphasis doubles it)
+pub const MD_EMPHASIS: StyleOption<char> = StyleOption {
+    key: "md.emphasis",
+    expected: "one of *, _",
+    default: || '*',
+    parse: |s| single_char(s, "*_"),
+};
+
+/// Whether Org headings keep their TODO/DONE keywords
+pub const ORG_HEADING_TODO: StyleOption<bool> = StyleOption {
+    key: "org.heading_todo",
+    expected: "true or false",
+    default: || true,
+    parse: |s| s.parse().ok(),
+};
+
+/// RST heading underline characters, one per level from level 1
+pub const RST_HEADING_CHARS: StyleOption<Vec<char>> = StyleOption {
+    key: "rst.heading_chars",
+    expected: "distinct punctuation characters, one per heading level",
+    default: || "=-~^\"'".chars().collect(),
+    parse: |s| {
+        let chars: Vec<char> = s.chars().collect();
+        let valid = !chars.is_empty()
+            && chars.iter().all(|c| c.is_ascii_punctuation())
+            && chars
+                .iter()
+                .enumerate()
+                .all(|(i, c)| !chars[..i].contains(c));
+        valid.then_some(chars)
+    },
+};
+
+/// Every key defined here, for validation and option listings
+pub const KEYS: &[&str] = &[
+    TXT_BULLET.key,
+    MD_BULLET.key,
+    MD_EMPHASIS.key,
+    ORG_HEADING_TODO.key,
+    RST_HEADING_CHARS.key,
+];
+
+/// Check that every option for a built-in format is known and valid
+///
+/// Keys without a built-in format prefix are left alone, so custom
+/// handlers can define their own.
+pub fn validate(config: &RenderConfig) -> Result<()> {
+    const PREFIXES: &[&str] = &["txt.", "md.", "adoc.", "djot.", "org.", "rst.", "typ."];
+
+    for (key, value) in &config.format_options {
+        if !KEYS.contains(&key.as_str()) && PREFIXES.iter().any(|p| key.starts_with(p)) {
+            return Err(ConversionError::InvalidOption {
+                key: key.clone(),
+                value: value.clone(),
+                expected: format!("a known option ({})", KEYS.join(", ")),
+            });
+        }
+    }
+    TXT_BULLET.get(config)?;
+    MD_BULLET.get(config)?;
+    MD_EMPHASIS.get(config)?;
+    ORG_HEADING_TODO.get(config)?;
+    RST_HEADING_CHARS.get(config)?;
+    Ok(())
+}
+
+fn single_char(s: &str, allowed: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if allowed.contains(c) => Some(c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_overrides() {
+        let config = RenderConfig::default();
+        assert_eq!(MD_BULLET.get(&config).unwrap(), '-');
+        assert!(ORG_HEADING_TODO.get(&config).unwrap());
+        assert_eq!(TXT_BULLET.get(&config).unwrap(), None);
+
+        let config = RenderConfig::default()
+            .with_option("md.emphasis", "_")
+            .with_option("rst.heading_chars", "#*=");
+        assert_eq!(MD_EMPHASIS.get(&config).unwrap(), '_');
+        assert_eq!(RST_HEADING_CHARS.get(&config).unwrap(), ['#', '*', '=']);
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let config = RenderConfig::default().with_option("md.bullet", "x");
+        assert_eq!(
+            MD_BULLET.get(&config).unwrap_err().to_string(),
+            "Invalid value \"x\" for option md.bullet: expected one of -, *, +"
+        );
+
+        let config = RenderConfig::default().with_option("rst.heading_chars", "==");
+        assert!(RST_HEADING_CHARS.get(&config).is_err());
+
+        let config = RenderConfig::default().with_option("md.bulet", "-");
+        assert!(validate(&config).is_err());
+        let config = RenderConfig::default().with_option("mine.style", "any");
+        assert!(validate(&config).is_ok());
+    }
+}
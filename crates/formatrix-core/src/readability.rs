@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Readability metrics
+//!
+//! Classic English readability formulas computed from the body text of a
+//! document (headings, code and math excluded). Syllables are estimated
+//! with a vowel-group heuristic, which is accurate enough for tracking a
+//! document's complexity over time but not for other languages.
+
+use crate::ast::{Block, Document};
+use crate::stats::inline_text;
+use crate::visit;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Readability scores for a document
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Readability {
+    /// Flesch reading ease (higher is easier; 60-70 is plain English)
+    pub flesch_reading_ease: f64,
+    /// Flesch-Kincaid grade level (US school grade)
+    pub flesch_kincaid_grade: f64,
+    /// SMOG grade (years of education needed)
+    pub smog_index: f64,
+    /// Words per sentence
+    pub average_sentence_length: f64,
+    pub average_syllables_per_word: f64,
+}
+
+impl Document {
+    /// Readability of the body text, or `None` if there is no prose
+    pub fn readability(&self) -> Option<Readability> {
+        let mut sentences = 0usize;
+        let mut words = 0usize;
+        let mut syllables = 0usize;
+        let mut polysyllables = 0usize;
+
+        visit::walk_blocks(&self.content, &mut |block| {
+            if matches!(block, Block::Heading { .. }) {
+                return;
+            }
+            for inlines in visit::block_inlines(block) {
+                let text = inline_text(inlines);
+                for sentence in text.prose.unicode_sentences() {
+                    let mut counted = false;
+                    for word in sentence.unicode_words() {
+                        let n = count_syllables(word);
+                        words += 1;
+                        syllables += n;
+                        if n >= 3 {
+                            polysyllables += 1;
+                        }
+                        counted = true;
+                    }
+                    if counted {
+                        sentences += 1;
+                    }
+                }
+            }
+        });
+
+        if words == 0 {
+            return None;
+        }
+
+        let words_per_sentence = words as f64 / sentences as f64;
+        let syllables_per_word = syllables as f64 / words as f64;
+        Some(Readability {
+            flesch_reading_ease: 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+            flesch_kincaid_grade: 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+            smog_index: 1.0430 * (polysyllables as f64 * 30.0 / sentences as f64).sqrt() + 3.1291,
+            average_sentence_length: words_per_sentence,
+            average_syllables_per_word: syllables_per_word,
+        })
+    }
+}
+
+/// Estimate the syllables in an English word
+pub fn count_syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let chars: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    if chars.is_empty() {
+        return 0;
+    }
+
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &chars {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // Silent final "e" ("make"), but not "-le" after a consonant ("table")
+    let n = chars.len();
+    if n > 2 && chars[n - 1] == 'e' && !is_vowel(chars[n - 2]) {
+        let syllabic_le = chars[n - 2] == 'l' && !is_vowel(chars[n - 3]);
+        if !syllabic_le {
+            count -= 1;
+        }
+    }
+
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, Inline, SourceFormat};
+
+    fn doc(text: &str) -> Document {
+        Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![Inline::Text {
+                    content: text.to_string(),
+                }],
+                span: None,
+            }],
+            raw_source: None,
+        }
+    }
+
+    #[test]
+    fn test_count_syllables() {
+        assert_eq!(count_syllables("cat"), 1);
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("readability"), 5);
+        assert_eq!(count_syllables("the"), 1);
+    }
+
+    #[test]
+    fn test_simple_text_scores_easier() {
+        let simple = doc("The cat sat on the mat. The dog ran.")
+            .readability()
+            .unwrap();
+        let complex = doc(
+            "Organizational interoperability necessitates comprehensive documentation \
+             of institutional responsibilities.",
+        )
+        .readability()
+        .unwrap();
+
+        assert_eq!(simple.average_sentence_length, 4.5);
+        assert!(simple.flesch_reading_ease > complex.flesch_reading_ease);
+        assert!(simple.flesch_kincaid_grade < complex.flesch_kincaid_grade);
+        assert!(simple.smog_index < complex.smog_index);
+    }
+
+    #[test]
+    fn test_no_prose() {
+        assert!(doc("").readability().is_none());
+    }
+}
@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Error-recovery parsing
+//!
+//! [`parse_lenient`] first tries a normal parse. If that fails with a
+//! [`ConversionError::ParseError`], it splits the input into top-level
+//! regions and parses each one on its own. A region that still fails is
+//! kept verbatim as a [`Block::Raw`] and reported as a [`Diagnostic`], so
+//! one malformed directive does not cost the rest of the document.
+//!
+//! Regions start at a non-indented line after a blank line outside any
+//! code fence, so indented directive bodies and fenced code stay with the
+//! construct that owns them.
+
+use crate::ast::{Block, Document, Name, Span};
+use crate::segment::{shift_spans, Fences};
+use crate::traits::{ConversionError, ParseConfig, Parser, Result};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// A parse failure that was recovered from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Line of the error in the whole input (1-based)
+    pub line: u32,
+    /// Column of the error (1-based, 0 when the parser did not report one)
+    pub column: u32,
+    pub message: String,
+    /// Input bytes kept as a raw block
+    pub range: Range<usize>,
+}
+
+/// Result of [`parse_lenient`]
+#[derive(Debug, Clone)]
+pub struct Recovered {
+    pub document: Document,
+    /// Regions that could not be parsed, in input order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parse `input`, replacing regions that fail to parse with raw blocks
+///
+/// Errors other than parse errors (I/O, includes) are returned as usual.
+pub fn parse_lenient(parser: &dyn Parser, input: &str, config: &ParseConfig) -> Result<Recovered> {
+    let input = config.normalize_input(input);
+    match parser.parse(&input, config) {
+        Ok(document) => {
+            return Ok(Recovered {
+                document,
+                diagnostics: Vec::new(),
+            })
+        }
+        Err(ConversionError::ParseError { .. }) => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut segment_config = config.clone();
+    segment_config.preserve_raw_source = false;
+
+    let mut meta = None;
+    let mut content = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut line = 0;
+    for range in regions(&input) {
+        let text = &input[range.clone()];
+        match parser.parse(text, &segment_config) {
+            Ok(mut doc) => {
+                shift_spans(&mut doc.content, range.start as isize, line as i64);
+                meta.get_or_insert(doc.meta);
+                content.extend(doc.content);
+            }
+            Err(ConversionError::ParseError {
+                line: error_line,
+                column,
+                message,
+            }) => {
+                let raw = text.trim_end();
+                content.push(Block::Raw {
+                    format: Some(Name::from(parser.format().extension())),
+                    content: raw.to_string(),
+                    span: config.preserve_spans.then_some(Span {
+                        start: range.start,
+                        end: range.start + raw.len(),
+                        line: line + 1,
+                        column: 1,
+                    }),
+                });
+                diagnostics.push(Diagnostic {
+                    line: line + error_line.max(1),
+                    column,
+                    message,
+                    range: range.start..range.start + raw.len(),
+                });
+            }
+            Err(e) => return Err(e),
+        }
+        line += text.matches('\n').count() as u32;
+    }
+
+    Ok(Recovered {
+        document: Document {
+            source_format: parser.format(),
+            meta: meta.unwrap_or_default(),
+            content,
+            raw_source: config.preserve_raw_source.then(|| input.into_owned()),
+        },
+        diagnostics,
+    })
+}
+
+/// Split `input` into top-level regions covering all of it
+fn regions(input: &str) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut after_blank = false;
+    let mut fences = Fences::default();
+
+    for line in input.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let indented = line.starts_with([' ', '\t']);
+        if after_blank && !fences.is_open() && !trimmed.is_empty() && !indented && offset > start {
+            regions.push(start..offset);
+            start = offset;
+        }
+        fences.update(line);
+        after_blank = trimmed.is_empty();
+        offset += line.len();
+    }
+    if offset > start {
+        regions.push(start..offset);
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::SourceFormat;
+    use crate::formats::PlainTextHandler;
+
+    /// Plain text that rejects any region containing `.. broken::`
+    struct Strict;
+
+    impl Parser for Strict {
+        fn format(&self) -> SourceFormat {
+            SourceFormat::ReStructuredText
+        }
+
+        fn parse(&self, input: &str, config: &ParseConfig) -> Result<Document> {
+            if let Some(i) = input.lines().position(|l| l.starts_with(".. broken::")) {
+                return Err(ConversionError::ParseError {
+                    line: i as u32 + 1,
+                    column: 1,
+                    message: "unknown directive".to_string(),
+                });
+            }
+            let mut doc = PlainTextHandler::new().parse(input, config)?;
+            doc.source_format = SourceFormat::ReStructuredText;
+            Ok(doc)
+        }
+    }
+
+    #[test]
+    fn test_recovers_broken_region() {
+        let input = "Intro\n\n.. broken::\n\n   indented body\n\nOutro\n";
+        let config = ParseConfig {
+            preserve_spans: true,
+            ..Default::default()
+        };
+        let recovered = parse_lenient(&Strict, input, &config).unwrap();
+
+        let blocks = &recovered.document.content;
+        assert_eq!(blocks.len(), 3);
+        assert!(matches!(
+            &blocks[1],
+            Block::Raw { content, .. } if content == ".. broken::\n\n   indented body"
+        ));
+        assert_eq!(blocks[2].span().unwrap().line, 7);
+
+        assert_eq!(recovered.diagnostics.len(), 1);
+        let diagnostic = &recovered.diagnostics[0];
+        assert_eq!(
+            (diagnostic.line, diagnostic.message.as_str()),
+            (3, "unknown directive")
+        );
+        assert_eq!(
+            &input[diagnostic.range.clone()],
+            ".. broken::\n\n   indented body"
+        );
+    }
+
+    #[test]
+    fn test_valid_input_has_no_diagnostics() {
+        let recovered = parse_lenient(&Strict, "One\n\nTwo", &ParseConfig::default()).unwrap();
+        assert_eq!(recovered.document.content.len(), 2);
+        assert!(recovered.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_regions_keep_fences_together() {
+        let input = "a\n\n```\nx\n\ny\n```\n\nb\n";
+        let texts: Vec<_> = regions(input).into_iter().map(|r| &input[r]).collect();
+        assert_eq!(texts, ["a\n\n", "```\nx\n\ny\n```\n\n", "b\n"]);
+    }
+}
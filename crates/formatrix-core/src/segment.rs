@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Helpers for parsing a document in pieces
+//!
+//! Streaming, error recovery and incremental reparsing all cut the input
+//! at blank lines, which must not fall inside a code fence, and parse each
+//! piece on its own, which leaves its spans relative to the piece. Fences
+//! follow CommonMark: three or more backticks or tildes, closed by a run
+//! of the same character at least as long with nothing after it.
+
+use crate::ast::Block;
+use crate::visit;
+
+/// The fence a line opens or closes, if it starts with one
+fn fence_marker(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let fence_char = match trimmed.chars().next() {
+        Some(c @ ('`' | '~')) => c,
+        _ => return None,
+    };
+    let len = trimmed.chars().take_while(|c| *c == fence_char).count();
+    (len >= 3).then(|| &trimmed[..len])
+}
+
+/// Whether any line of `text` opens or closes a code fence
+pub(crate) fn has_fence(text: &str) -> bool {
+    text.lines().any(|line| fence_marker(line).is_some())
+}
+
+/// Tracks whether a line-by-line reader is inside a code fence
+#[derive(Debug, Default)]
+pub(crate) struct Fences {
+    /// Marker of the fence being read
+    open: Option<String>,
+}
+
+impl Fences {
+    /// Note the next line of input
+    pub(crate) fn update(&mut self, line: &str) {
+        let Some(marker) = fence_marker(line) else {
+            return;
+        };
+        match &self.open {
+            Some(open) => {
+                let rest = &line.trim_start()[marker.len()..];
+                if marker.starts_with(open.as_str()) && rest.trim().is_empty() {
+                    self.open = None;
+                }
+            }
+            None => self.open = Some(marker.to_string()),
+        }
+    }
+
+    /// Whether the lines so far leave a fence open
+    pub(crate) fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+}
+
+/// Move the spans of `blocks` and their descendants by `bytes` and `lines`
+pub(crate) fn shift_spans(blocks: &mut [Block], bytes: isize, lines: i64) {
+    if bytes == 0 && lines == 0 {
+        return;
+    }
+    visit::walk_blocks_mut(blocks, &mut |block| {
+        if let Some(span) = block.span_mut() {
+            span.start = (span.start as isize + bytes) as usize;
+            span.end = (span.end as isize + bytes) as usize;
+            span.line = (span.line as i64 + lines) as u32;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fences() {
+        let mut fences = Fences::default();
+        for line in ["```\n", "code\n", "\n", "~~~\n", "``` not a close\n"] {
+            fences.update(line);
+            assert!(fences.is_open());
+        }
+        fences.update("````\n");
+        assert!(!fences.is_open());
+
+        assert!(has_fence("text\n  ~~~rust\n"));
+        assert!(!has_fence("inline ```code``` only\n``\n"));
+    }
+}
@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Spell checking
+//!
+//! [`check_document`] walks the prose of a document (`Text` inlines only,
+//! so code, math, URLs and raw blocks are never flagged) and asks a
+//! [`SpellChecker`] about every word. [`HunspellChecker`] talks to the
+//! `hunspell` binary in pipe mode; [`WordListChecker`] is an in-memory
+//! list for project dictionaries and tests.
+
+use crate::ast::{Block, Document, Inline, Span};
+use crate::traits::{ConversionError, Result};
+use crate::visit;
+use std::collections::HashSet;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A misspelled word
+#[derive(Debug, Clone)]
+pub struct Misspelling {
+    pub word: String,
+    pub suggestions: Vec<String>,
+    /// Span of the block containing the word
+    pub block_span: Option<Span>,
+    /// Byte offset of the word in the plain text of its inline sequence
+    /// (as produced by [`visit::inlines_to_text`])
+    pub offset: usize,
+}
+
+/// A spelling dictionary
+pub trait SpellChecker: Send + Sync {
+    /// Check a batch of words. For each word, `None` means it is spelled
+    /// correctly; otherwise the suggested replacements (possibly empty).
+    fn check_words(&self, words: &[&str]) -> Result<Vec<Option<Vec<String>>>>;
+}
+
+/// Words worth checking: skip anything with digits (versions, ids)
+fn is_checkable(word: &str) -> bool {
+    !word.chars().any(|c| c.is_numeric())
+}
+
+/// Find misspelled words in a document's prose
+pub fn check_document(doc: &Document, checker: &dyn SpellChecker) -> Result<Vec<Misspelling>> {
+    // (word, offset, block span) for every checkable word
+    let mut words: Vec<(&str, usize, Option<&Span>)> = Vec::new();
+
+    visit::walk_blocks(&doc.content, &mut |block: &Block| {
+        for inlines in visit::block_inlines(block) {
+            let mut offset = 0;
+            visit::walk_inline_tree(inlines, &mut |inline| match inline {
+                Inline::Text { content } => {
+                    for (i, word) in content.unicode_word_indices() {
+                        if is_checkable(word) {
+                            words.push((word, offset + i, block.span()));
+                        }
+                    }
+                    offset += content.len();
+                }
+                // Keep offsets aligned with `inlines_to_text`
+                Inline::Code { content, .. } | Inline::Math { content } => offset += content.len(),
+                Inline::Image { alt, .. } => offset += alt.len(),
+                Inline::Ruby { base, .. } => offset += base.len(),
+                Inline::LineBreak | Inline::SoftBreak => offset += 1,
+                _ => {}
+            });
+        }
+    });
+
+    let batch: Vec<&str> = words.iter().map(|(w, _, _)| *w).collect();
+    let results = checker.check_words(&batch)?;
+
+    Ok(words
+        .into_iter()
+        .zip(results)
+        .filter_map(|((word, offset, span), result)| {
+            result.map(|suggestions| Misspelling {
+                word: word.to_string(),
+                suggestions,
+                block_span: span.cloned(),
+                offset,
+            })
+        })
+        .collect())
+}
+
+/// An in-memory word list (case-insensitive); never suggests anything
+#[derive(Debug, Clone, Default)]
+pub struct WordListChecker {
+    words: HashSet<String>,
+}
+
+impl WordListChecker {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            words: words
+                .into_iter()
+                .map(|w| w.as_ref().to_lowercase())
+                .collect(),
+        }
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        self.words.insert(word.to_lowercase());
+    }
+}
+
+impl SpellChecker for WordListChecker {
+    fn check_words(&self, words: &[&str]) -> Result<Vec<Option<Vec<String>>>> {
+        Ok(words
+            .iter()
+            .map(|w| (!self.words.contains(&w.to_lowercase())).then(Vec::new))
+            .collect())
+    }
+}
+
+/// Spell checking through `hunspell -a` (Ispell pipe protocol)
+#[derive(Debug, Clone)]
+pub struct HunspellChecker {
+    program: String,
+    dictionary: String,
+}
+
+impl HunspellChecker {
+    /// Use the named dictionary (e.g. "en_US", "de_DE")
+    pub fn new(dictionary: &str) -> Self {
+        Self {
+            program: "hunspell".to_string(),
+            dictionary: dictionary.to_string(),
+        }
+    }
+
+    /// Run a different binary speaking the same protocol (e.g. "aspell")
+    pub fn with_program(mut self, program: &str) -> Self {
+        self.program = program.to_string();
+        self
+    }
+
+    fn error(&self, message: impl Into<String>) -> ConversionError {
+        ConversionError::SpellCheckError(format!(
+            "{} ({}): {}",
+            self.program,
+            self.dictionary,
+            message.into()
+        ))
+    }
+}
+
+impl SpellChecker for HunspellChecker {
+    fn check_words(&self, words: &[&str]) -> Result<Vec<Option<Vec<String>>>> {
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut child = Command::new(&self.program)
+            .args(["-a", "-d", &self.dictionary])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.error(e.to_string()))?;
+
+        // One word per line; '^' stops a line being read as a command
+        let mut input = String::new();
+        for word in words {
+            input.push('^');
+            input.push_str(word);
+            input.push('\n');
+        }
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(self.error(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+
+        let results = parse_pipe_output(&String::from_utf8_lossy(&output.stdout));
+        if results.len() != words.len() {
+            return Err(self.error("unexpected pipe output"));
+        }
+        Ok(results)
+    }
+}
+
+/// Parse Ispell pipe output: a banner line, then per input line one result
+/// line per word followed by a blank line
+fn parse_pipe_output(output: &str) -> Vec<Option<Vec<String>>> {
+    let mut results = Vec::new();
+    let mut current: Option<Option<Vec<String>>> = None;
+
+    for line in output.lines().skip(1) {
+        if line.is_empty() {
+            results.extend(current.take());
+            continue;
+        }
+        let result = match line.chars().next() {
+            // "& word count offset: a, b, c"
+            Some('&') => Some(
+                line.split_once(": ")
+                    .map(|(_, list)| list.split(", ").map(str::to_string).collect())
+                    .unwrap_or_default(),
+            ),
+            // "# word offset": no suggestions
+            Some('#') => Some(Vec::new()),
+            // "*", "+ root", "- compound": correct
+            _ => None,
+        };
+        // A "word" hunspell splits in two is wrong if either part is
+        current = Some(match (current.take().flatten(), result) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b);
+                Some(a)
+            }
+            (a, b) => a.or(b),
+        });
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    #[test]
+    fn test_check_document_skips_code() {
+        let doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![
+                    Inline::Text {
+                        content: "Teh ".to_string(),
+                    },
+                    Inline::Code {
+                        content: "fn_nmae".to_string(),
+                        language: None,
+                    },
+                    Inline::Text {
+                        content: " wrods v2".to_string(),
+                    },
+                ],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        let checker = WordListChecker::new(["the", "words"]);
+        let found = check_document(&doc, &checker).unwrap();
+
+        let words: Vec<(&str, usize)> = found.iter().map(|m| (m.word.as_str(), m.offset)).collect();
+        assert_eq!(words, vec![("Teh", 0), ("wrods", 12)]);
+    }
+
+    #[test]
+    fn test_parse_pipe_output() {
+        let output = "@(#) International Ispell Version 3.2.06 (but really Hunspell 1.7.2)\n\
+                      *\n\n\
+                      & teh 3 0: the, ten, tea\n\n\
+                      # qzxv 0\n\n";
+
+        assert_eq!(
+            parse_pipe_output(output),
+            vec![
+                None,
+                Some(vec![
+                    "the".to_string(),
+                    "ten".to_string(),
+                    "tea".to_string()
+                ]),
+                Some(Vec::new()),
+            ]
+        );
+    }
+}
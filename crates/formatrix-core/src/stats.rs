@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Document statistics
+//!
+//! Counts computed from the AST rather than the raw source, so markup
+//! (`**`, `#+TITLE:`, link URLs) never inflates word counts and the
+//! numbers agree across formats.
+
+use crate::ast::{Block, Document, Inline};
+use crate::visit;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Average adult silent reading speed used for reading time estimates
+pub const WORDS_PER_MINUTE: usize = 238;
+
+/// Statistics for a whole document
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentStats {
+    /// Words of prose (headings included, code excluded)
+    pub words: usize,
+    /// Sentences in body text (headings excluded)
+    pub sentences: usize,
+    pub paragraphs: usize,
+    pub headings: usize,
+    /// Characters of prose
+    pub text_characters: usize,
+    /// Characters of code blocks and inline code
+    pub code_characters: usize,
+    pub code_blocks: usize,
+    pub links: usize,
+    pub images: usize,
+    /// Estimated reading time at [`WORDS_PER_MINUTE`], rounded up
+    pub reading_time_seconds: u64,
+    /// Word counts per heading section, in document order
+    pub sections: Vec<SectionStats>,
+}
+
+/// Word count of one heading section (up to the next heading of any level)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionStats {
+    pub heading: String,
+    pub level: u8,
+    /// Words in the section body, not counting the heading itself
+    pub words: usize,
+}
+
+impl DocumentStats {
+    /// Share of characters that are code, from 0.0 to 1.0
+    pub fn code_ratio(&self) -> f64 {
+        let total = self.text_characters + self.code_characters;
+        if total == 0 {
+            0.0
+        } else {
+            self.code_characters as f64 / total as f64
+        }
+    }
+}
+
+/// Prose and code text of an inline sequence, plus link and image counts
+#[derive(Default)]
+pub(crate) struct InlineText {
+    pub prose: String,
+    code_characters: usize,
+    links: usize,
+    images: usize,
+}
+
+pub(crate) fn inline_text(inlines: &[Inline]) -> InlineText {
+    let mut out = InlineText::default();
+    visit::walk_inline_tree(inlines, &mut |inline| match inline {
+        Inline::Text { content } => out.prose.push_str(content),
+        Inline::Ruby { base, .. } => out.prose.push_str(base),
+        Inline::Code { content, .. } => out.code_characters += content.chars().count(),
+        Inline::Link { .. } => out.links += 1,
+        Inline::Image { .. } => out.images += 1,
+        Inline::LineBreak | Inline::SoftBreak => out.prose.push(' '),
+        _ => {}
+    });
+    out
+}
+
+impl Document {
+    /// Compute word, sentence, section, code and link statistics
+    pub fn stats(&self) -> DocumentStats {
+        let mut stats = DocumentStats::default();
+
+        visit::walk_blocks(&self.content, &mut |block| {
+            match block {
+                Block::Heading { level, content, .. } => {
+                    stats.headings += 1;
+                    stats.sections.push(SectionStats {
+                        heading: visit::inlines_to_text(content),
+                        level: *level,
+                        words: 0,
+                    });
+                }
+                Block::Paragraph { .. } => stats.paragraphs += 1,
+                Block::CodeBlock { content, .. } => {
+                    stats.code_blocks += 1;
+                    stats.code_characters += content.chars().count();
+                }
+                _ => {}
+            }
+
+            for inlines in visit::block_inlines(block) {
+                let text = inline_text(inlines);
+                let words = text.prose.unicode_words().count();
+
+                stats.words += words;
+                stats.text_characters += text.prose.chars().count();
+                stats.code_characters += text.code_characters;
+                stats.links += text.links;
+                stats.images += text.images;
+
+                if !matches!(block, Block::Heading { .. }) {
+                    stats.sentences += text
+                        .prose
+                        .unicode_sentences()
+                        .filter(|s| s.unicode_words().next().is_some())
+                        .count();
+                    if let Some(section) = stats.sections.last_mut() {
+                        section.words += words;
+                    }
+                }
+            }
+        });
+
+        stats.reading_time_seconds = (stats.words as u64 * 60).div_ceil(WORDS_PER_MINUTE as u64);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    fn text(s: &str) -> Inline {
+        Inline::Text {
+            content: s.to_string(),
+        }
+    }
+
+    fn heading(s: &str) -> Block {
+        Block::Heading {
+            level: 1,
+            content: vec![text(s)],
+            id: None,
+            span: None,
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![
+                Block::Paragraph {
+                    content: vec![text("Preamble here.")],
+                    span: None,
+                },
+                heading("Intro"),
+                Block::Paragraph {
+                    content: vec![
+                        text("One two three. Four five "),
+                        Inline::Link {
+                            url: "https://example.org".to_string(),
+                            title: None,
+                            content: vec![text("six")],
+                        },
+                        text(". "),
+                        Inline::Code {
+                            content: "let x".to_string(),
+                            language: None,
+                        },
+                    ],
+                    span: None,
+                },
+                Block::CodeBlock {
+                    language: Some("rust".into()),
+                    content: "fn main() {}".to_string(),
+                    span: None,
+                },
+            ],
+            raw_source: None,
+        };
+        let stats = doc.stats();
+
+        assert_eq!(stats.words, 9);
+        assert_eq!(stats.sentences, 3);
+        assert_eq!(stats.paragraphs, 2);
+        assert_eq!(stats.links, 1);
+        assert_eq!(stats.code_blocks, 1);
+        assert_eq!(stats.code_characters, 17);
+        assert_eq!(
+            stats.sections,
+            vec![SectionStats {
+                heading: "Intro".to_string(),
+                level: 1,
+                words: 6,
+            }]
+        );
+        assert_eq!(stats.reading_time_seconds, 3);
+    }
+
+    #[test]
+    fn test_empty_document() {
+        let doc = Document {
+            source_format: SourceFormat::PlainText,
+            meta: DocumentMeta::default(),
+            content: Vec::new(),
+            raw_source: None,
+        };
+        let stats = doc.stats();
+
+        assert_eq!(stats, DocumentStats::default());
+        assert_eq!(stats.code_ratio(), 0.0);
+    }
+}
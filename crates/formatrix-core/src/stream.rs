@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Streaming parse for very large documents
+//!
+//! [`parse_blocks`] reads its input in segments that end at a blank line
+//! outside any code fence, parses each segment on its own and yields the
+//! resulting blocks, so only one segment and its blocks are in memory at
+//! a time. Spans are shifted to be relative to the whole input, after
+//! normalization, and only the first segment can have front matter.
+//!
+//! Blank lines are block boundaries in plain text, Markdown and Djot, so
+//! segmenting gives the same blocks as a whole-document parse with a few
+//! exceptions: a loose list that straddles two segments becomes two
+//! lists, and Markdown reference links only resolve within their segment.
+
+use crate::ast::{Block, DocumentMeta, SourceFormat};
+use crate::segment::{shift_spans, Fences};
+use crate::traits::{ConversionError, ParseConfig, Parser, Result};
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+/// Default segment size; segments end at the first blank line after this
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Whether [`parse_blocks`] can segment this format
+pub fn supports_streaming(format: SourceFormat) -> bool {
+    matches!(
+        format,
+        SourceFormat::PlainText | SourceFormat::Markdown | SourceFormat::Djot
+    )
+}
+
+/// Parse `reader` incrementally, yielding top-level blocks
+pub fn parse_blocks<'a, R: BufRead>(
+    parser: &'a dyn Parser,
+    reader: R,
+    config: &ParseConfig,
+) -> Result<BlockStream<'a, R>> {
+    let format = parser.format();
+    if !supports_streaming(format) {
+        return Err(ConversionError::UnsupportedFeature {
+            format,
+            feature: "streaming parse".to_string(),
+        });
+    }
+
+    let mut config = config.clone();
+    config.preserve_raw_source = false;
+
+    Ok(BlockStream {
+        parser,
+        reader,
+        config,
+        chunk_size: DEFAULT_CHUNK_SIZE,
+        segment: String::new(),
+        fences: Fences::default(),
+        pending: VecDeque::new(),
+        meta: None,
+        offset: 0,
+        line: 0,
+        done: false,
+    })
+}
+
+/// Iterator over the blocks of a streamed document
+pub struct BlockStream<'a, R> {
+    parser: &'a dyn Parser,
+    reader: R,
+    config: ParseConfig,
+    chunk_size: usize,
+    segment: String,
+    fences: Fences,
+    pending: VecDeque<Block>,
+    meta: Option<DocumentMeta>,
+    /// Byte offset and line count of the input before `segment`
+    offset: usize,
+    line: u32,
+    done: bool,
+}
+
+impl<R: BufRead> BlockStream<'_, R> {
+    /// Segment size in bytes (default [`DEFAULT_CHUNK_SIZE`])
+    pub fn with_chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Metadata from the first segment (front matter), once it is parsed
+    pub fn meta(&self) -> Option<&DocumentMeta> {
+        self.meta.as_ref()
+    }
+
+    /// Read lines until the segment is full and ends at a block boundary
+    fn fill_segment(&mut self) -> Result<()> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                self.done = true;
+                return Ok(());
+            }
+            if self.parser.format() != SourceFormat::PlainText {
+                self.fences.update(&line);
+            }
+            self.segment.push_str(&line);
+
+            if line.trim().is_empty()
+                && !self.fences.is_open()
+                && self.segment.len() >= self.chunk_size
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_segment(&mut self) -> Result<()> {
+        let segment = std::mem::take(&mut self.segment);
+        let input = self.config.normalize_input(&segment);
+        let doc = if self.meta.is_none() {
+            self.parser.parse(&input, &self.config)?
+        } else {
+            // A blank line first, so a thematic break opening a later
+            // segment isn't read as front matter
+            let mut doc = self.parser.parse(&format!("\n{}", input), &self.config)?;
+            let (bytes, lines) = (self.offset as isize - 1, self.line as i64 - 1);
+            shift_spans(&mut doc.content, bytes, lines);
+            doc
+        };
+        self.offset += input.len();
+        self.line += input.matches('\n').count() as u32;
+
+        if self.meta.is_none() {
+            self.meta = Some(doc.meta);
+        }
+        self.pending.extend(doc.content);
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for BlockStream<'_, R> {
+    type Item = Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(block) = self.pending.pop_front() {
+                return Some(Ok(block));
+            }
+            if self.done {
+                return None;
+            }
+            let step = self.fill_segment().and_then(|()| self.parse_segment());
+            if let Err(e) = step {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::PlainTextHandler;
+
+    #[test]
+    fn test_stream_matches_whole_parse() {
+        // Spans count bytes of the normalized input
+        let input: String = (0..50)
+            .map(|i| format!("Paragraph {}\nline twe\u{301}\n\n", i))
+            .collect();
+        let handler = PlainTextHandler::new();
+        let config = ParseConfig {
+            preserve_spans: true,
+            unicode_normalization: Some(crate::UnicodeNormalization::Nfc),
+            ..Default::default()
+        };
+
+        let streamed: Vec<Block> = parse_blocks(&handler, input.as_bytes(), &config)
+            .unwrap()
+            .with_chunk_size(100)
+            .collect::<Result<_>>()
+            .unwrap();
+        let whole = handler
+            .parse(&config.normalize_input(&input), &config)
+            .unwrap()
+            .content;
+
+        assert_eq!(streamed.len(), 50);
+        assert_eq!(
+            serde_json::to_string(&streamed).unwrap(),
+            serde_json::to_string(&whole).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fences_are_not_split() {
+        let input = "```\ncode\n\nmore code\n````\n\nafter\n";
+        let mut stream = parse_blocks(&MarkdownLike, input.as_bytes(), &ParseConfig::default())
+            .unwrap()
+            .with_chunk_size(1);
+        stream.fill_segment().unwrap();
+        assert_eq!(stream.segment, "```\ncode\n\nmore code\n````\n\n");
+    }
+
+    #[test]
+    fn test_later_segments_have_no_front_matter() {
+        let input = "---\ntitle: Streamed\n---\n\nIntro\n\n---\nnot: meta\n---\n\nBody\n";
+        let config = ParseConfig {
+            preserve_spans: true,
+            ..Default::default()
+        };
+        let mut stream = parse_blocks(&MarkdownLike, input.as_bytes(), &config)
+            .unwrap()
+            .with_chunk_size(1);
+        let streamed: Vec<Block> = stream.by_ref().collect::<Result<_>>().unwrap();
+        assert_eq!(
+            stream.meta().and_then(|meta| meta.title.as_deref()),
+            Some("Streamed")
+        );
+
+        let whole = MarkdownLike.parse(input, &config).unwrap().content;
+        assert_eq!(streamed.len(), 3);
+        assert_eq!(
+            serde_json::to_string(&streamed).unwrap(),
+            serde_json::to_string(&whole).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(supports_streaming(SourceFormat::Markdown));
+        assert!(parse_blocks(&RstLike, &b""[..], &ParseConfig::default()).is_err());
+    }
+
+    struct MarkdownLike;
+    struct RstLike;
+
+    impl Parser for MarkdownLike {
+        fn format(&self) -> SourceFormat {
+            SourceFormat::Markdown
+        }
+
+        /// Front matter and plain text paragraphs, with spans into `input`
+        fn parse(&self, input: &str, config: &ParseConfig) -> Result<crate::ast::Document> {
+            let (meta, body) = crate::frontmatter::split_yaml(input, None)
+                .unwrap_or((DocumentMeta::default(), input));
+            let mut doc = PlainTextHandler::new().parse(body, config)?;
+            let header = &input[..input.len() - body.len()];
+            let lines = header.matches('\n').count() as i64;
+            shift_spans(&mut doc.content, header.len() as isize, lines);
+            doc.meta = meta;
+            Ok(doc)
+        }
+    }
+
+    impl Parser for RstLike {
+        fn format(&self) -> SourceFormat {
+            SourceFormat::ReStructuredText
+        }
+
+        fn parse(&self, input: &str, config: &ParseConfig) -> Result<crate::ast::Document> {
+            PlainTextHandler::new().parse(input, config)
+        }
+    }
+}
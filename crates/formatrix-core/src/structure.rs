@@ -0,0 +1,394 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Document structure operations
+//!
+//! Splitting a document into sections and reassembling documents, used to
+//! break large gists into linked child documents and to build books from
+//! collections of smaller ones.
+
+use crate::ast::{Block, Document, DocumentMeta, Inline, SourceFormat};
+use crate::visit;
+use serde::{Deserialize, Serialize};
+
+/// Options for [`Document::concat`]
+#[derive(Debug, Clone, Default)]
+pub struct ConcatOptions {
+    /// Title of the combined document (default: the first part's title)
+    pub title: Option<String>,
+    /// Demote every heading in each part by this many levels (capped at 6)
+    pub shift_headings: u8,
+    /// Insert each part's title as a heading of this level before its content
+    pub part_title_level: Option<u8>,
+}
+
+/// A heading in a document's outline (see [`Document::outline`])
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    pub level: u8,
+    pub text: String,
+    /// The heading's explicit id, or its [`slugify`]d text
+    pub id: String,
+}
+
+impl Document {
+    /// Split the document into one document per section at `level`.
+    ///
+    /// A section starts at every heading with a level of `level` or higher
+    /// (numerically lower) and runs until the next such heading. Content
+    /// before the first section becomes a leading document of its own.
+    /// Each part inherits the parent's metadata, with the title replaced by
+    /// the section heading.
+    pub fn split_at_level(&self, level: u8) -> Vec<Document> {
+        let mut parts = Vec::new();
+        let mut current: Vec<Block> = Vec::new();
+
+        for block in &self.content {
+            let starts_section = matches!(block, Block::Heading { level: l, .. } if *l <= level);
+            if starts_section && !current.is_empty() {
+                parts.push(self.part(std::mem::take(&mut current)));
+            }
+            current.push(block.clone());
+        }
+
+        if !current.is_empty() {
+            parts.push(self.part(current));
+        }
+
+        parts
+    }
+
+    /// Concatenate documents into one, e.g. to build a book from a
+    /// collection of gists.
+    ///
+    /// Metadata is merged: authors and tags are combined without duplicates,
+    /// and for front matter keys the first part to define a key wins. The
+    /// result takes its source format from the first part.
+    pub fn concat(parts: &[Document], options: &ConcatOptions) -> Document {
+        let mut meta = DocumentMeta {
+            title: options
+                .title
+                .clone()
+                .or_else(|| parts.first().and_then(|p| p.meta.title.clone())),
+            ..Default::default()
+        };
+        let mut content = Vec::new();
+
+        for part in parts {
+            for author in &part.meta.authors {
+                if !meta.authors.contains(author) {
+                    meta.authors.push(author.clone());
+                }
+            }
+            for tag in &part.meta.tags {
+                if !meta.tags.contains(tag) {
+                    meta.tags.push(tag.clone());
+                }
+            }
+            for (key, value) in &part.meta.frontmatter {
+                meta.frontmatter
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            if meta.date.is_none() {
+                meta.date = part.meta.date.clone();
+            }
+
+            if let (Some(level), Some(title)) = (options.part_title_level, &part.meta.title) {
+                content.push(Block::Heading {
+                    level,
+                    content: vec![Inline::Text {
+                        content: title.clone(),
+                    }],
+                    id: None,
+                    span: None,
+                });
+            }
+
+            let mut blocks = part.content.clone();
+            if options.shift_headings > 0 {
+                visit::walk_blocks_mut(&mut blocks, &mut |block| {
+                    if let Block::Heading { level, .. } = block {
+                        *level = level.saturating_add(options.shift_headings).min(6);
+                    }
+                });
+            }
+            content.extend(blocks);
+        }
+
+        Document {
+            source_format: parts
+                .first()
+                .map_or(SourceFormat::PlainText, |p| p.source_format),
+            meta,
+            content,
+            raw_source: None,
+        }
+    }
+
+    /// Extract the section whose heading has the given id or slug.
+    ///
+    /// The section is the heading plus everything after it up to the next
+    /// heading of the same or a higher level. Headings without an explicit
+    /// id are matched by the [`slugify`]d heading text. Returns `None` if no
+    /// heading matches.
+    pub fn section(&self, id_or_slug: &str) -> Option<Document> {
+        let start = self.content.iter().position(|block| match block {
+            Block::Heading { id, content, .. } => {
+                id.as_deref() == Some(id_or_slug)
+                    || slugify(&visit::inlines_to_text(content)) == id_or_slug
+            }
+            _ => false,
+        })?;
+
+        let Block::Heading { level, .. } = self.content[start] else {
+            unreachable!("position matched a heading");
+        };
+        let end = self.content[start + 1..]
+            .iter()
+            .position(|block| matches!(block, Block::Heading { level: l, .. } if *l <= level))
+            .map_or(self.content.len(), |offset| start + 1 + offset);
+
+        Some(self.part(self.content[start..end].to_vec()))
+    }
+
+    /// Every heading in document order, for a table of contents or a
+    /// navigation pane
+    pub fn outline(&self) -> Vec<OutlineEntry> {
+        let mut entries = Vec::new();
+        visit::walk_blocks(&self.content, &mut |block| {
+            if let Block::Heading {
+                level, content, id, ..
+            } = block
+            {
+                let text = visit::inlines_to_text(content);
+                entries.push(OutlineEntry {
+                    level: *level,
+                    id: id.clone().unwrap_or_else(|| slugify(&text)),
+                    text,
+                });
+            }
+        });
+        entries
+    }
+
+    /// All intra-document link targets: heading ids, figure ids and
+    /// explicit anchors, in document order
+    pub fn anchors(&self) -> Vec<String> {
+        let mut ids = Vec::new();
+        visit::walk_blocks(&self.content, &mut |block| match block {
+            Block::Heading { id: Some(id), .. } | Block::Figure { id: Some(id), .. } => {
+                ids.push(id.clone())
+            }
+            _ => {}
+        });
+        visit::walk_inlines(&self.content, &mut |inline| {
+            if let Inline::Anchor { id } = inline {
+                ids.push(id.clone());
+            }
+        });
+        ids
+    }
+
+    /// Build a document from some of this document's blocks, inheriting its
+    /// metadata. The title comes from a leading heading, if there is one.
+    fn part(&self, content: Vec<Block>) -> Document {
+        let mut meta = self.meta.clone();
+        if let Some(Block::Heading { content: title, .. }) = content.first() {
+            meta.title = Some(visit::inlines_to_text(title));
+        }
+
+        Document {
+            source_format: self.source_format,
+            meta,
+            content,
+            raw_source: None,
+        }
+    }
+}
+
+/// Turn heading text into a URL-friendly id ("Getting Started!" -> "getting-started")
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, Inline, SourceFormat};
+
+    fn heading(level: u8, text: &str) -> Block {
+        Block::Heading {
+            level,
+            content: vec![Inline::Text {
+                content: text.to_string(),
+            }],
+            id: None,
+            span: None,
+        }
+    }
+
+    fn para(text: &str) -> Block {
+        Block::Paragraph {
+            content: vec![Inline::Text {
+                content: text.to_string(),
+            }],
+            span: None,
+        }
+    }
+
+    fn doc(content: Vec<Block>) -> Document {
+        Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta {
+                title: Some("Book".to_string()),
+                tags: vec!["draft".to_string()],
+                ..Default::default()
+            },
+            content,
+            raw_source: None,
+        }
+    }
+
+    #[test]
+    fn test_split_at_level() {
+        let d = doc(vec![
+            para("preamble"),
+            heading(1, "One"),
+            heading(2, "One.A"),
+            para("text"),
+            heading(1, "Two"),
+        ]);
+        let parts = d.split_at_level(1);
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].meta.title.as_deref(), Some("Book"));
+        assert_eq!(parts[1].meta.title.as_deref(), Some("One"));
+        assert_eq!(parts[1].content.len(), 3);
+        assert_eq!(parts[2].meta.title.as_deref(), Some("Two"));
+        assert!(parts.iter().all(|p| p.meta.tags == vec!["draft"]));
+    }
+
+    #[test]
+    fn test_concat_with_part_titles() {
+        let mut a = doc(vec![heading(1, "Intro"), para("a")]);
+        a.meta.title = Some("Part A".to_string());
+        let mut b = doc(vec![heading(1, "Usage")]);
+        b.meta.title = Some("Part B".to_string());
+        b.meta.tags.push("guide".to_string());
+
+        let book = Document::concat(
+            &[a, b],
+            &ConcatOptions {
+                title: Some("Manual".to_string()),
+                shift_headings: 1,
+                part_title_level: Some(1),
+            },
+        );
+
+        let levels: Vec<u8> = book
+            .content
+            .iter()
+            .filter_map(|b| match b {
+                Block::Heading { level, .. } => Some(*level),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(levels, vec![1, 2, 1, 2]);
+        assert_eq!(book.meta.title.as_deref(), Some("Manual"));
+        assert_eq!(book.meta.tags, vec!["draft", "guide"]);
+    }
+
+    #[test]
+    fn test_section_by_slug_and_id() {
+        let mut d = doc(vec![
+            heading(1, "Getting Started"),
+            para("intro"),
+            heading(2, "Install"),
+            para("steps"),
+            heading(1, "Reference"),
+        ]);
+        if let Block::Heading { id, .. } = &mut d.content[2] {
+            *id = Some("setup".to_string());
+        }
+
+        let started = d.section("getting-started").unwrap();
+        assert_eq!(started.content.len(), 4);
+        assert_eq!(started.meta.title.as_deref(), Some("Getting Started"));
+
+        let setup = d.section("setup").unwrap();
+        assert_eq!(setup.content.len(), 2);
+
+        assert!(d.section("missing").is_none());
+    }
+
+    #[test]
+    fn test_anchors() {
+        let mut d = doc(vec![
+            heading(1, "Intro"),
+            Block::Paragraph {
+                content: vec![Inline::Anchor {
+                    id: "here".to_string(),
+                }],
+                span: None,
+            },
+        ]);
+        if let Block::Heading { id, .. } = &mut d.content[0] {
+            *id = Some("intro".to_string());
+        }
+
+        assert_eq!(d.anchors(), vec!["intro", "here"]);
+    }
+
+    #[test]
+    fn test_outline() {
+        let mut d = doc(vec![
+            heading(1, "Getting Started"),
+            para("x"),
+            heading(2, "Install"),
+        ]);
+        if let Block::Heading { id, .. } = &mut d.content[2] {
+            *id = Some("setup".to_string());
+        }
+
+        let outline: Vec<_> = d
+            .outline()
+            .into_iter()
+            .map(|e| (e.level, e.text, e.id))
+            .collect();
+        assert_eq!(
+            outline,
+            [
+                (
+                    1,
+                    "Getting Started".to_string(),
+                    "getting-started".to_string()
+                ),
+                (2, "Install".to_string(), "setup".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Getting Started!"), "getting-started");
+        assert_eq!(slugify("  A -- B_c "), "a-b-c");
+        assert_eq!(slugify("Über Café"), "über-café");
+    }
+
+    #[test]
+    fn test_split_without_headings() {
+        let d = doc(vec![para("a"), para("b")]);
+        let parts = d.split_at_level(2);
+
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].content.len(), 2);
+    }
+}
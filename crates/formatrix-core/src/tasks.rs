@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Task list aggregation
+//!
+//! Collects task list items (list items with a checkbox, `- [ ]` in
+//! Markdown, Djot and Org) together with the heading they sit under, for
+//! an "open tasks" view across one document or many.
+
+use crate::ast::{Block, Document, Span};
+use crate::visit;
+use serde::{Deserialize, Serialize};
+
+/// A task list item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    /// Text of the item's first paragraph (nested lists excluded)
+    pub text: String,
+    pub checked: bool,
+    /// Text of the nearest preceding heading, if any
+    pub heading: Option<String>,
+    /// Span of the item's first block, or of its list when that is missing
+    pub span: Option<Span>,
+}
+
+impl Document {
+    /// Every task list item in document order, nested lists included
+    pub fn tasks(&self) -> Vec<Task> {
+        let mut tasks = Vec::new();
+        let mut heading = None;
+
+        visit::walk_blocks(&self.content, &mut |block| match block {
+            Block::Heading { content, .. } => heading = Some(visit::inlines_to_text(content)),
+            Block::List { items, span, .. } => {
+                for item in items {
+                    let Some(checked) = item.checked else {
+                        continue;
+                    };
+                    let first = item.content.first();
+                    tasks.push(Task {
+                        text: first
+                            .and_then(|b| visit::block_inlines(b).first().copied())
+                            .map(visit::inlines_to_text)
+                            .unwrap_or_default(),
+                        checked,
+                        heading: heading.clone(),
+                        span: first.and_then(Block::span).or(span.as_ref()).cloned(),
+                    });
+                }
+            }
+            _ => {}
+        });
+        tasks
+    }
+
+    /// Task list items that are not checked
+    pub fn open_tasks(&self) -> Vec<Task> {
+        let mut tasks = self.tasks();
+        tasks.retain(|t| !t.checked);
+        tasks
+    }
+}
+
+/// Tasks across a set of documents, tagged with each document's key
+/// (a path or database id)
+pub fn collect_tasks<'a, K, I>(documents: I) -> Vec<(K, Task)>
+where
+    K: Clone,
+    I: IntoIterator<Item = (K, &'a Document)>,
+{
+    documents
+        .into_iter()
+        .flat_map(|(key, doc)| doc.tasks().into_iter().map(move |t| (key.clone(), t)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, Inline, ListItem, SourceFormat};
+
+    fn text(s: &str) -> Vec<Inline> {
+        vec![Inline::Text {
+            content: s.to_string(),
+        }]
+    }
+
+    fn item(s: &str, checked: Option<bool>, nested: Vec<Block>) -> ListItem {
+        let mut content = vec![Block::Paragraph {
+            content: text(s),
+            span: None,
+        }];
+        content.extend(nested);
+        ListItem { content, checked }
+    }
+
+    fn list(items: Vec<ListItem>) -> Block {
+        Block::List {
+            ordered: false,
+            start: None,
+            items,
+            span: None,
+        }
+    }
+
+    fn document() -> Document {
+        Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![
+                list(vec![item("Before any heading", Some(true), vec![])]),
+                Block::Heading {
+                    level: 2,
+                    content: text("Release"),
+                    id: None,
+                    span: None,
+                },
+                list(vec![
+                    item("Plain item", None, vec![]),
+                    item(
+                        "Tag the release",
+                        Some(false),
+                        vec![list(vec![item("Write notes", Some(false), vec![])])],
+                    ),
+                ]),
+            ],
+            raw_source: None,
+        }
+    }
+
+    #[test]
+    fn test_tasks() {
+        let doc = document();
+        let tasks: Vec<_> = doc
+            .tasks()
+            .into_iter()
+            .map(|t| (t.text, t.checked, t.heading))
+            .collect();
+        assert_eq!(
+            tasks,
+            [
+                ("Before any heading".to_string(), true, None),
+                (
+                    "Tag the release".to_string(),
+                    false,
+                    Some("Release".to_string())
+                ),
+                (
+                    "Write notes".to_string(),
+                    false,
+                    Some("Release".to_string())
+                ),
+            ]
+        );
+        assert_eq!(doc.open_tasks().len(), 2);
+    }
+
+    #[test]
+    fn test_collect_tasks() {
+        let (a, b) = (document(), document());
+        let tasks = collect_tasks([("a.md", &a), ("b.md", &b)]);
+        assert_eq!(tasks.len(), 6);
+        assert_eq!(tasks[3].0, "b.md");
+    }
+}
@@ -3,8 +3,10 @@
 //! Parser and Renderer traits for format handlers
 
 use crate::ast::{Document, SourceFormat};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::sync::OnceLock;
 
 /// Error type for parsing and rendering
 #[derive(Debug, thiserror::Error)]
@@ -24,6 +26,25 @@ pub enum ConversionError {
 
     #[error("Serialization error: {0}")]
     SerializationError(String),
+
+    #[error("Include error for {target}: {message}")]
+    IncludeError { target: String, message: String },
+
+    #[error("Diagram rendering failed ({engine}): {message}")]
+    DiagramError { engine: String, message: String },
+
+    #[error("Spell checker error: {0}")]
+    SpellCheckError(String),
+
+    #[error("Undefined template variable: {0}")]
+    UndefinedVariable(String),
+
+    #[error("Invalid value {value:?} for option {key}: expected {expected}")]
+    InvalidOption {
+        key: String,
+        value: String,
+        expected: String,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, ConversionError>;
@@ -39,6 +60,49 @@ pub struct ParseConfig {
     pub front_matter_delimiter: Option<String>,
     /// Format-specific options
     pub format_options: HashMap<String, String>,
+    /// Normalize input to this Unicode form before parsing
+    pub unicode_normalization: Option<UnicodeNormalization>,
+    /// Remove invisible zero-width characters (ZWSP, word joiner, stray BOMs)
+    pub strip_zero_width: bool,
+}
+
+/// Unicode normalization form applied to parser input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeNormalization {
+    /// Canonical composition (what most editors produce)
+    Nfc,
+    /// Canonical decomposition (macOS file names, some IMEs)
+    Nfd,
+}
+
+/// Zero-width characters removed by [`ParseConfig::strip_zero_width`].
+///
+/// ZWJ and ZWNJ are kept: they are meaningful in emoji sequences and in
+/// scripts such as Persian and Devanagari.
+const ZERO_WIDTH: &[char] = &['\u{200B}', '\u{2060}', '\u{FEFF}'];
+
+impl ParseConfig {
+    /// Apply the input normalization options, borrowing when nothing changes
+    pub fn normalize_input<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        use unicode_normalization::{
+            is_nfc_quick, is_nfd_quick, IsNormalized, UnicodeNormalization as _,
+        };
+
+        let mut text = Cow::Borrowed(input);
+        if self.strip_zero_width && text.contains(ZERO_WIDTH) {
+            text = Cow::Owned(text.replace(ZERO_WIDTH, ""));
+        }
+        match self.unicode_normalization {
+            Some(UnicodeNormalization::Nfc) if is_nfc_quick(text.chars()) != IsNormalized::Yes => {
+                text = Cow::Owned(text.nfc().collect());
+            }
+            Some(UnicodeNormalization::Nfd) if is_nfd_quick(text.chars()) != IsNormalized::Yes => {
+                text = Cow::Owned(text.nfd().collect());
+            }
+            _ => {}
+        }
+        text
+    }
 }
 
 /// Configuration for rendering
@@ -54,6 +118,14 @@ pub struct RenderConfig {
     pub format_options: HashMap<String, String>,
 }
 
+impl RenderConfig {
+    /// Set a format-specific option (see [`crate::options`])
+    pub fn with_option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.format_options.insert(key.into(), value.into());
+        self
+    }
+}
+
 impl Default for RenderConfig {
     fn default() -> Self {
         Self {
@@ -81,6 +153,22 @@ pub trait Renderer: Send + Sync {
 
     /// Render a Document to a string
     fn render(&self, doc: &Document, config: &RenderConfig) -> Result<String>;
+
+    /// Render a Document to a writer
+    ///
+    /// The default buffers the whole output via [`Renderer::render`];
+    /// renderers override it to write each block as it is produced, so
+    /// exports never hold the full output in memory.
+    fn render_to(
+        &self,
+        doc: &Document,
+        writer: &mut dyn Write,
+        config: &RenderConfig,
+    ) -> Result<()> {
+        let output = self.render(doc, config)?;
+        writer.write_all(output.as_bytes())?;
+        Ok(())
+    }
 }
 
 /// Extension trait for streaming operations (not dyn-compatible)
@@ -103,9 +191,7 @@ fn render_writer<W: Write>(
         writer: &mut W,
         config: &RenderConfig,
     ) -> Result<()> {
-        let output = self.render(doc, config)?;
-        writer.write_all(output.as_bytes())?;
-        Ok(())
+        self.render_to(doc, writer, config)
     }
 }
 
@@ -134,6 +220,30 @@ pub fn new() -> Self {
         }
     }
 
+    /// Registry with handlers for all seven built-in formats
+    pub fn with_builtin_handlers() -> Self {
+        use crate::formats::{
+            AsciidocHandler, DjotHandler, MarkdownHandler, OrgModeHandler, PlainTextHandler,
+            RstHandler, TypstHandler,
+        };
+
+        let mut registry = Self::new();
+        registry.register(Box::new(PlainTextHandler::new()));
+        registry.register(Box::new(MarkdownHandler::new()));
+        registry.register(Box::new(AsciidocHandler::new()));
+        registry.register(Box::new(DjotHandler::new()));
+        registry.register(Box::new(OrgModeHandler::new()));
+        registry.register(Box::new(RstHandler::new()));
+        registry.register(Box::new(TypstHandler::new()));
+        registry
+    }
+
+    /// Shared registry of the built-in handlers, created on first use
+    pub fn global() -> &'static FormatRegistry {
+        static GLOBAL: OnceLock<FormatRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(Self::with_builtin_handlers)
+    }
+
     pub fn register(&mut self, handler: Box<dyn FormatHandler>) {
         let format = Parser::format(handler.as_ref());
         self.handlers.insert(format, handler);
@@ -143,6 +253,22 @@ pub fn get(&self, format: SourceFormat) -> Option<&dyn FormatHandler> {
         self.handlers.get(&format).map(|h| h.as_ref())
     }
 
+    /// Look up a handler by format id (canonical extension), including
+    /// custom formats: `"md"`, `"org"`, `"textile"`
+    pub fn find(&self, id: &str) -> Option<&dyn FormatHandler> {
+        self.handlers
+            .iter()
+            .find(|(format, _)| format.extension() == id)
+            .map(|(_, h)| h.as_ref())
+    }
+
+    /// Every registered format, ordered by id
+    pub fn formats(&self) -> Vec<SourceFormat> {
+        let mut formats: Vec<_> = self.handlers.keys().copied().collect();
+        formats.sort_by_key(|format| format.extension());
+        formats
+    }
+
     /// Convert between formats
     pub fn convert(
         &self,
@@ -180,3 +306,107 @@ fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Block, DocumentMeta, Inline};
+
+    /// A third-party format: one paragraph per line, rendered in upper case
+    struct ShoutHandler;
+
+    impl Parser for ShoutHandler {
+        fn format(&self) -> SourceFormat {
+            SourceFormat::custom("shout")
+        }
+
+        fn parse(&self, input: &str, _config: &ParseConfig) -> Result<Document> {
+            Ok(Document {
+                source_format: SourceFormat::custom("shout"),
+                meta: DocumentMeta::default(),
+                content: input
+                    .lines()
+                    .map(|line| Block::Paragraph {
+                        content: vec![Inline::Text {
+                            content: line.to_string(),
+                        }],
+                        span: None,
+                    })
+                    .collect(),
+                raw_source: None,
+            })
+        }
+    }
+
+    impl Renderer for ShoutHandler {
+        fn format(&self) -> SourceFormat {
+            SourceFormat::custom("shout")
+        }
+
+        fn render(&self, doc: &Document, config: &RenderConfig) -> Result<String> {
+            let text = crate::formats::PlainTextHandler::new().render(doc, config)?;
+            Ok(text.to_uppercase())
+        }
+    }
+
+    impl FormatHandler for ShoutHandler {
+        fn supports_feature(&self, _feature: &str) -> bool {
+            false
+        }
+
+        fn supported_features(&self) -> &[&str] {
+            &[]
+        }
+    }
+
+    #[test]
+    fn test_custom_format_registration() {
+        let mut registry = FormatRegistry::new();
+        registry.register(Box::new(crate::formats::PlainTextHandler::new()));
+        registry.register(Box::new(ShoutHandler));
+
+        assert!(registry.get(SourceFormat::custom("shout")).is_some());
+        assert!(registry.find("shout").is_some());
+        assert!(registry.find("txt").is_some());
+        assert!(registry.find("whisper").is_none());
+        assert_eq!(
+            registry.formats(),
+            [SourceFormat::custom("shout"), SourceFormat::PlainText]
+        );
+
+        let output = registry
+            .convert(
+                "quiet please",
+                SourceFormat::PlainText,
+                SourceFormat::custom("shout"),
+                &ParseConfig::default(),
+                &RenderConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(output.trim(), "QUIET PLEASE");
+    }
+
+    #[test]
+    fn test_builtin_handlers() {
+        let registry = FormatRegistry::global();
+        for id in ["txt", "md", "adoc", "djot", "org", "rst", "typ"] {
+            let handler = registry.find(id).unwrap();
+            assert_eq!(Parser::format(handler).extension(), id);
+        }
+        assert!(std::ptr::eq(registry, FormatRegistry::global()));
+    }
+
+    #[test]
+    fn test_custom_format_serde() {
+        let format = SourceFormat::custom("textile");
+        let json = serde_json::to_string(&format).unwrap();
+        assert_eq!(json, r#"{"Custom":"textile"}"#);
+
+        let back: SourceFormat = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, format);
+        assert_eq!(back.extension(), "textile");
+
+        let builtin: SourceFormat = serde_json::from_str(r#""Markdown""#).unwrap();
+        assert_eq!(builtin, SourceFormat::Markdown);
+    }
+}
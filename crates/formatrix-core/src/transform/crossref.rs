@@ -0,0 +1,399 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Figure/table/equation numbering and cross-reference resolution
+//!
+//! Every [`Block::Figure`] is numbered per kind in document order. References
+//! are resolved in two forms:
+//! - `@fig:foo` in text (pandoc-crossref style)
+//! - links to `#fig:foo` with no link text
+//!
+//! Both become links reading "Figure 3"; each renderer then writes the link
+//! in its own syntax. Captions that already start with a label are
+//! relabelled rather than labelled twice, so applying the transform again
+//! is harmless.
+
+use crate::ast::{Block, Document, FigureKind, Inline};
+use crate::i18n::Label;
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+use std::collections::HashMap;
+
+/// Number figures and resolve references to them
+#[derive(Debug, Clone)]
+pub struct CrossReferences {
+    /// Label used for [`FigureKind::Figure`] (default "Figure")
+    pub figure_label: String,
+    /// Label used for [`FigureKind::Table`] (default "Table")
+    pub table_label: String,
+    /// Label used for [`FigureKind::Equation`] (default "Equation")
+    pub equation_label: String,
+    /// Label used for [`FigureKind::Listing`] (default "Listing")
+    pub listing_label: String,
+    /// Prefix captions with their label ("Figure 3: ...")
+    pub label_captions: bool,
+    /// Emit references as links to the target id (otherwise plain text)
+    pub link_references: bool,
+}
+
+impl Default for CrossReferences {
+    fn default() -> Self {
+        Self {
+            figure_label: "Figure".to_string(),
+            table_label: "Table".to_string(),
+            equation_label: "Equation".to_string(),
+            listing_label: "Listing".to_string(),
+            label_captions: true,
+            link_references: true,
+        }
+    }
+}
+
+impl CrossReferences {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Labels in the given language (e.g. the document's `meta.language`)
+    pub fn for_language(language: Option<&str>) -> Self {
+        Self {
+            figure_label: Label::Figure.localized(language).to_string(),
+            table_label: Label::Table.localized(language).to_string(),
+            equation_label: Label::Equation.localized(language).to_string(),
+            listing_label: Label::Listing.localized(language).to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The label for a figure kind
+    pub fn label(&self, kind: FigureKind) -> &str {
+        match kind {
+            FigureKind::Figure => &self.figure_label,
+            FigureKind::Table => &self.table_label,
+            FigureKind::Equation => &self.equation_label,
+            FigureKind::Listing => &self.listing_label,
+        }
+    }
+
+    /// Reference text for each figure id, e.g. `"fig:arch" -> "Figure 2"`
+    pub fn collect_labels(&self, blocks: &[Block]) -> HashMap<String, String> {
+        let mut counters: HashMap<FigureKind, u32> = HashMap::new();
+        let mut labels = HashMap::new();
+
+        visit::walk_blocks(blocks, &mut |block| {
+            if let Block::Figure { kind, id, .. } = block {
+                let n = counters.entry(*kind).or_insert(0);
+                *n += 1;
+                if let Some(id) = id {
+                    labels.insert(id.clone(), format!("{} {}", self.label(*kind), n));
+                }
+            }
+        });
+
+        labels
+    }
+
+    fn reference(&self, id: &str, label: &str) -> Inline {
+        let text = Inline::Text {
+            content: label.to_string(),
+        };
+        if self.link_references {
+            Inline::Link {
+                url: format!("#{}", id),
+                title: None,
+                content: vec![text],
+            }
+        } else {
+            text
+        }
+    }
+}
+
+impl Transform for CrossReferences {
+    fn name(&self) -> &str {
+        "cross-references"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        if self.label_captions {
+            let mut counters: HashMap<FigureKind, u32> = HashMap::new();
+            visit::walk_blocks_mut(&mut doc.content, &mut |block| {
+                if let Block::Figure { kind, caption, .. } = block {
+                    let n = counters.entry(*kind).or_insert(0);
+                    *n += 1;
+                    let label = format!("{} {}", self.label(*kind), n);
+                    let caption = caption.get_or_insert_with(Vec::new);
+                    let relabelled = match caption.first_mut() {
+                        Some(Inline::Text { content }) => {
+                            match label_prefix_len(content, self.label(*kind)) {
+                                Some(len) => {
+                                    content.replace_range(..len, "");
+                                    content.is_empty()
+                                }
+                                None => false,
+                            }
+                        }
+                        _ => false,
+                    };
+                    if relabelled {
+                        caption.remove(0);
+                    }
+                    if caption.is_empty() {
+                        caption.push(Inline::Text { content: label });
+                    } else {
+                        caption.insert(
+                            0,
+                            Inline::Text {
+                                content: format!("{}: ", label),
+                            },
+                        );
+                    }
+                }
+            });
+        }
+
+        let labels = self.collect_labels(&doc.content);
+        if labels.is_empty() {
+            return Ok(());
+        }
+
+        visit::walk_inline_lists_mut(&mut doc.content, &mut |inlines| {
+            let mut resolved = Vec::with_capacity(inlines.len());
+            for inline in inlines.drain(..) {
+                match inline {
+                    Inline::Text { content } => {
+                        self.split_references(&content, &labels, &mut resolved)
+                    }
+                    Inline::Link {
+                        url,
+                        title,
+                        content,
+                    } if content.is_empty() => {
+                        match url.strip_prefix('#').and_then(|id| labels.get(id)) {
+                            Some(label) => resolved.push(Inline::Link {
+                                content: vec![Inline::Text {
+                                    content: label.clone(),
+                                }],
+                                url,
+                                title,
+                            }),
+                            None => resolved.push(Inline::Link {
+                                url,
+                                title,
+                                content,
+                            }),
+                        }
+                    }
+                    other => resolved.push(other),
+                }
+            }
+            *inlines = resolved;
+        });
+
+        Ok(())
+    }
+}
+
+/// Length of the `"Figure 3: "` prefix, or whole `"Figure 3"` caption,
+/// that labelling left at the start of `text`
+fn label_prefix_len(text: &str, label: &str) -> Option<usize> {
+    let rest = text.strip_prefix(label)?.strip_prefix(' ')?;
+    let after = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    if after.len() == rest.len() {
+        return None;
+    }
+    let len = text.len() - after.len();
+    match after {
+        "" => Some(len),
+        _ => after.strip_prefix(": ").map(|_| len + 2),
+    }
+}
+
+impl CrossReferences {
+    /// Split a text run at `@id` references that name a known figure
+    fn split_references(
+        &self,
+        text: &str,
+        labels: &HashMap<String, String>,
+        out: &mut Vec<Inline>,
+    ) {
+        let mut rest = text;
+        let mut pending = String::new();
+
+        while let Some(at) = rest.find('@') {
+            let before = &rest[..at];
+            let after = &rest[at + 1..];
+
+            // "user@example.com" is not a reference
+            let prev = before
+                .chars()
+                .next_back()
+                .or_else(|| pending.chars().next_back());
+            let at_word_start = !prev.is_some_and(char::is_alphanumeric);
+
+            let id_len = after
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.')))
+                .unwrap_or(after.len());
+            let id = after[..id_len].trim_end_matches(['.', ':']);
+
+            pending.push_str(before);
+            match labels.get(id) {
+                Some(label) if at_word_start && !id.is_empty() => {
+                    if !pending.is_empty() {
+                        out.push(Inline::Text {
+                            content: std::mem::take(&mut pending),
+                        });
+                    }
+                    out.push(self.reference(id, label));
+                    rest = &after[id.len()..];
+                }
+                _ => {
+                    pending.push('@');
+                    rest = after;
+                }
+            }
+        }
+
+        pending.push_str(rest);
+        if !pending.is_empty() {
+            out.push(Inline::Text { content: pending });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    fn text(s: &str) -> Inline {
+        Inline::Text {
+            content: s.to_string(),
+        }
+    }
+
+    fn figure(kind: FigureKind, id: &str, caption: &str) -> Block {
+        Block::Figure {
+            kind,
+            id: Some(id.to_string()),
+            caption: Some(vec![text(caption)]),
+            content: Vec::new(),
+            span: None,
+        }
+    }
+
+    fn doc(content: Vec<Block>) -> Document {
+        Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content,
+            raw_source: None,
+        }
+    }
+
+    #[test]
+    fn test_numbers_per_kind() {
+        let transform = CrossReferences::new();
+        let labels = transform.collect_labels(&[
+            figure(FigureKind::Figure, "fig:a", "A"),
+            figure(FigureKind::Table, "tbl:b", "B"),
+            figure(FigureKind::Figure, "fig:c", "C"),
+        ]);
+
+        assert_eq!(labels["fig:a"], "Figure 1");
+        assert_eq!(labels["tbl:b"], "Table 1");
+        assert_eq!(labels["fig:c"], "Figure 2");
+    }
+
+    #[test]
+    fn test_localized_labels() {
+        let transform = CrossReferences::for_language(Some("fr"));
+        let labels = transform.collect_labels(&[figure(FigureKind::Table, "tbl:a", "A")]);
+
+        assert_eq!(labels["tbl:a"], "Tableau 1");
+    }
+
+    #[test]
+    fn test_resolves_text_references() {
+        let mut d = doc(vec![
+            figure(FigureKind::Figure, "fig:arch", "Architecture"),
+            Block::Paragraph {
+                content: vec![text("See @fig:arch. Mail me@fig:arch or @fig:missing.")],
+                span: None,
+            },
+        ]);
+        CrossReferences {
+            link_references: false,
+            ..Default::default()
+        }
+        .apply(&mut d)
+        .unwrap();
+
+        let Block::Paragraph { content, .. } = &d.content[1] else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(
+            visit::inlines_to_text(content),
+            "See Figure 1. Mail me@fig:arch or @fig:missing."
+        );
+
+        let Block::Figure { caption, .. } = &d.content[0] else {
+            panic!("expected figure");
+        };
+        assert_eq!(
+            visit::inlines_to_text(caption.as_ref().unwrap()),
+            "Figure 1: Architecture"
+        );
+    }
+
+    #[test]
+    fn test_resolves_empty_links() {
+        let mut d = doc(vec![
+            figure(FigureKind::Table, "results", "Results"),
+            Block::Paragraph {
+                content: vec![Inline::Link {
+                    url: "#results".to_string(),
+                    title: None,
+                    content: Vec::new(),
+                }],
+                span: None,
+            },
+        ]);
+        CrossReferences::new().apply(&mut d).unwrap();
+
+        let Block::Paragraph { content, .. } = &d.content[1] else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(visit::inlines_to_text(content), "Table 1");
+    }
+
+    #[test]
+    fn test_applying_twice_labels_once() {
+        let untitled = Block::Figure {
+            kind: FigureKind::Figure,
+            id: None,
+            caption: None,
+            content: Vec::new(),
+            span: None,
+        };
+        let mut d = doc(vec![
+            figure(FigureKind::Figure, "fig:a", "Figure 10 shows more"),
+            untitled,
+        ]);
+        let transform = CrossReferences::new();
+        transform.apply(&mut d).unwrap();
+        transform.apply(&mut d).unwrap();
+
+        let captions: Vec<String> = d
+            .content
+            .iter()
+            .map(|block| match block {
+                Block::Figure { caption, .. } => {
+                    visit::inlines_to_text(caption.as_deref().unwrap_or_default())
+                }
+                _ => String::new(),
+            })
+            .collect();
+        assert_eq!(captions, ["Figure 1: Figure 10 shows more", "Figure 2"]);
+    }
+}
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Diagram detection and rendering transforms
+
+use crate::ast::{Block, Document};
+use crate::diagram::{diagram_engine, DiagramRenderer};
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+
+/// Turn code blocks in a diagram language (```` ```mermaid ````) into
+/// [`Block::Diagram`], for parsers that only produce code blocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DetectDiagrams;
+
+impl Transform for DetectDiagrams {
+    fn name(&self) -> &str {
+        "detect-diagrams"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        visit::walk_blocks_mut(&mut doc.content, &mut |block| {
+            let Block::CodeBlock {
+                language: Some(language),
+                content,
+                span,
+            } = block
+            else {
+                return;
+            };
+            if let Some(engine) = diagram_engine(language) {
+                *block = Block::Diagram {
+                    engine: engine.into(),
+                    source: std::mem::take(content),
+                    span: span.take(),
+                };
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Replace diagrams with rendered SVG (as raw `svg` blocks)
+///
+/// Diagrams whose engine has no registered renderer are left untouched.
+#[derive(Default)]
+pub struct RenderDiagrams {
+    renderers: Vec<Box<dyn DiagramRenderer>>,
+}
+
+impl RenderDiagrams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a renderer for its engine
+    pub fn with_renderer(mut self, renderer: Box<dyn DiagramRenderer>) -> Self {
+        self.renderers.push(renderer);
+        self
+    }
+
+    fn renderer(&self, engine: &str) -> Option<&dyn DiagramRenderer> {
+        self.renderers
+            .iter()
+            .find(|r| r.engine() == engine)
+            .map(|r| r.as_ref())
+    }
+}
+
+impl Transform for RenderDiagrams {
+    fn name(&self) -> &str {
+        "render-diagrams"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let mut result = Ok(());
+        visit::walk_blocks_mut(&mut doc.content, &mut |block| {
+            if result.is_err() {
+                return;
+            }
+            let Block::Diagram {
+                engine,
+                source,
+                span,
+            } = block
+            else {
+                return;
+            };
+            let Some(renderer) = self.renderer(engine) else {
+                return;
+            };
+            match renderer.render_svg(source) {
+                Ok(svg) => {
+                    *block = Block::Raw {
+                        format: Some("svg".into()),
+                        content: svg,
+                        span: span.take(),
+                    }
+                }
+                Err(e) => result = Err(e),
+            }
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    struct FakeRenderer;
+
+    impl DiagramRenderer for FakeRenderer {
+        fn engine(&self) -> &str {
+            "mermaid"
+        }
+
+        fn render_svg(&self, source: &str) -> Result<String> {
+            Ok(format!("<svg>{}</svg>", source.len()))
+        }
+    }
+
+    fn code(language: &str) -> Block {
+        Block::CodeBlock {
+            language: Some(language.into()),
+            content: "graph TD; A-->B".to_string(),
+            span: None,
+        }
+    }
+
+    #[test]
+    fn test_detect_and_render() {
+        let mut doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![code("mermaid"), code("dot"), code("rust")],
+            raw_source: None,
+        };
+        DetectDiagrams.apply(&mut doc).unwrap();
+        RenderDiagrams::new()
+            .with_renderer(Box::new(FakeRenderer))
+            .apply(&mut doc)
+            .unwrap();
+
+        assert!(
+            matches!(&doc.content[0], Block::Raw { content, .. } if content == "<svg>15</svg>")
+        );
+        assert!(matches!(&doc.content[1], Block::Diagram { engine, .. } if engine == "graphviz"));
+        assert!(matches!(&doc.content[2], Block::CodeBlock { .. }));
+    }
+}
@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Local image probing and inlining
+//!
+//! Reads the headers of the local files referenced by [`Inline::Image`],
+//! records their pixel size, and optionally replaces the URL with a
+//! `data:` URI so an HTML export is a single self-contained file; only
+//! images small enough to embed are read whole. Remote and data URLs are
+//! left alone; local paths are resolved under a root directory and may
+//! not escape it.
+
+use crate::ast::{Document, Inline};
+use crate::image;
+use crate::include::normalize_path;
+use crate::traits::{ConversionError, Result};
+use crate::transform::Transform;
+use crate::visit;
+use base64::Engine;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Record image sizes and optionally embed images as data URIs
+#[derive(Debug, Clone)]
+pub struct InlineImages {
+    root: PathBuf,
+    /// Replace local URLs with `data:` URIs
+    pub embed: bool,
+    /// Images larger than this are probed but never embedded
+    pub max_embed_bytes: u64,
+}
+
+impl InlineImages {
+    /// Resolve image paths relative to `root` (usually the document's
+    /// directory)
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            embed: false,
+            max_embed_bytes: 2 * 1024 * 1024,
+        }
+    }
+
+    pub fn with_embed(mut self, embed: bool) -> Self {
+        self.embed = embed;
+        self
+    }
+
+    pub fn with_max_embed_bytes(mut self, bytes: u64) -> Self {
+        self.max_embed_bytes = bytes;
+        self
+    }
+
+    fn process(&self, inline: &mut Inline) -> Result<()> {
+        let Inline::Image {
+            url, width, height, ..
+        } = inline
+        else {
+            return Ok(());
+        };
+        let Some(relative) = local_path(url) else {
+            return Ok(());
+        };
+        let error = |message: String| ConversionError::IncludeError {
+            target: url.clone(),
+            message,
+        };
+
+        let relative = normalize_path(Path::new(relative))
+            .ok_or_else(|| error("path escapes the image root".to_string()))?;
+        let io_error = |e: std::io::Error| error(e.to_string());
+        let file = File::open(self.root.join(relative)).map_err(io_error)?;
+        let len = file.metadata().map_err(io_error)?.len();
+        let embed = self.embed && len <= self.max_embed_bytes;
+        let limit = if embed {
+            len
+        } else {
+            image::PROBE_BYTES as u64
+        };
+        let mut bytes = Vec::new();
+        file.take(limit).read_to_end(&mut bytes).map_err(io_error)?;
+        let info =
+            image::probe(&bytes).ok_or_else(|| error("not a known image type".to_string()))?;
+
+        if let Some((w, h)) = info.size {
+            *width = Some(w);
+            *height = Some(h);
+        }
+        if embed {
+            *url = format!(
+                "data:{};base64,{}",
+                info.mime_type,
+                base64::engine::general_purpose::STANDARD.encode(&bytes)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The file path of a local image URL, without query or fragment
+fn local_path(url: &str) -> Option<&str> {
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    if path.is_empty() || path.contains("://") || path.starts_with("data:") {
+        return None;
+    }
+    path.split(['?', '#']).next()
+}
+
+impl Transform for InlineImages {
+    fn name(&self) -> &str {
+        "inline-images"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let mut result = Ok(());
+        visit::walk_inline_lists_mut(&mut doc.content, &mut |inlines| {
+            for inline in inlines.iter_mut() {
+                if result.is_ok() {
+                    result = self.process(inline);
+                }
+            }
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Block, DocumentMeta, SourceFormat};
+
+    fn doc(urls: &[&str]) -> Document {
+        let images = urls
+            .iter()
+            .map(|url| Inline::Image {
+                url: url.to_string(),
+                alt: String::new(),
+                title: None,
+                width: None,
+                height: None,
+            })
+            .collect();
+        Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![Inline::Link {
+                    url: "https://example.org".to_string(),
+                    title: None,
+                    content: images,
+                }],
+                span: None,
+            }],
+            raw_source: None,
+        }
+    }
+
+    fn images(doc: &Document) -> Vec<(String, Option<u32>, Option<u32>)> {
+        let mut found = Vec::new();
+        visit::walk_inlines(&doc.content, &mut |inline| {
+            if let Inline::Image {
+                url, width, height, ..
+            } = inline
+            {
+                found.push((url.clone(), *width, *height));
+            }
+        });
+        found
+    }
+
+    #[test]
+    fn test_probe_and_embed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("dot.gif"), b"GIF89a\x02\x00\x03\x00").unwrap();
+
+        let mut d = doc(&["dot.gif?v=2", "https://example.org/remote.png"]);
+        InlineImages::new(dir.path()).apply(&mut d).unwrap();
+        assert_eq!(
+            images(&d),
+            [
+                ("dot.gif?v=2".to_string(), Some(2), Some(3)),
+                ("https://example.org/remote.png".to_string(), None, None),
+            ]
+        );
+
+        let mut d = doc(&["./dot.gif"]);
+        InlineImages::new(dir.path())
+            .with_embed(true)
+            .apply(&mut d)
+            .unwrap();
+        assert_eq!(images(&d)[0].0, "data:image/gif;base64,R0lGODlhAgADAA==");
+    }
+
+    #[test]
+    fn test_large_image_probed_not_embedded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut gif = b"GIF89a\x02\x00\x03\x00".to_vec();
+        gif.resize(image::PROBE_BYTES * 2, 0);
+        std::fs::write(dir.path().join("big.gif"), gif).unwrap();
+
+        let mut d = doc(&["big.gif"]);
+        InlineImages::new(dir.path())
+            .with_embed(true)
+            .with_max_embed_bytes(1024)
+            .apply(&mut d)
+            .unwrap();
+        assert_eq!(images(&d), [("big.gif".to_string(), Some(2), Some(3))]);
+    }
+
+    #[test]
+    fn test_missing_and_escaping_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let transform = InlineImages::new(dir.path());
+        assert!(transform.apply(&mut doc(&["missing.png"])).is_err());
+        assert!(transform.apply(&mut doc(&["../secret.png"])).is_err());
+    }
+}
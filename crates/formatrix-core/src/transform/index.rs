@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Back-of-book index generation
+//!
+//! Collects every [`Inline::IndexTerm`] and appends an alphabetized index
+//! section, where each entry links to the headings of the sections that
+//! mention it. Linked-to headings without an id are given one (their
+//! slug) so the links resolve; other headings are left alone.
+
+use crate::ast::{Block, Document, Inline, ListItem};
+use crate::i18n::Label;
+use crate::structure::slugify;
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Generate an index section from index terms
+#[derive(Debug, Clone)]
+pub struct GenerateIndex {
+    /// Title of the generated section
+    pub title: String,
+    /// Heading level of the generated section
+    pub heading_level: u8,
+}
+
+impl Default for GenerateIndex {
+    fn default() -> Self {
+        Self {
+            title: "Index".to_string(),
+            heading_level: 1,
+        }
+    }
+}
+
+/// One index entry: where a term (or term + subterm) is referenced
+#[derive(Debug, Default)]
+struct Entry {
+    /// Display form of the term (first spelling seen)
+    label: String,
+    /// (heading text, heading id) of each referencing section, in order
+    locations: Vec<(String, String)>,
+    subterms: BTreeMap<String, Entry>,
+}
+
+impl Entry {
+    fn add_location(&mut self, location: Option<&(String, String)>) {
+        if let Some(location) = location {
+            if !self.locations.contains(location) {
+                self.locations.push(location.clone());
+            }
+        }
+    }
+}
+
+impl GenerateIndex {
+    /// Index titled in the given language (e.g. the document's `meta.language`)
+    pub fn for_language(language: Option<&str>) -> Self {
+        Self {
+            title: Label::Index.localized(language).to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Transform for GenerateIndex {
+    fn name(&self) -> &str {
+        "index"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let mut index: BTreeMap<String, Entry> = BTreeMap::new();
+        // The current section's (heading text, anchor) and heading index
+        let mut section: Option<(String, String)> = None;
+        let mut heading = 0;
+        let mut linked = BTreeSet::new();
+
+        for (i, block) in doc.content.iter().enumerate() {
+            if let Block::Heading { content, id, .. } = block {
+                let text = visit::inlines_to_text(content);
+                let anchor = id.clone().unwrap_or_else(|| slugify(&text));
+                section = Some((text, anchor));
+                heading = i;
+            }
+
+            visit::walk_inlines(std::slice::from_ref(block), &mut |inline| {
+                let Inline::IndexTerm { term, subterm } = inline else {
+                    return;
+                };
+                if section.is_some() {
+                    linked.insert(heading);
+                }
+                let entry = index.entry(term.to_lowercase()).or_insert_with(|| Entry {
+                    label: term.clone(),
+                    ..Default::default()
+                });
+                match subterm {
+                    Some(sub) => entry
+                        .subterms
+                        .entry(sub.to_lowercase())
+                        .or_insert_with(|| Entry {
+                            label: sub.clone(),
+                            ..Default::default()
+                        })
+                        .add_location(section.as_ref()),
+                    None => entry.add_location(section.as_ref()),
+                }
+            });
+        }
+
+        if index.is_empty() {
+            return Ok(());
+        }
+        for i in linked {
+            if let Block::Heading { content, id, .. } = &mut doc.content[i] {
+                id.get_or_insert_with(|| slugify(&visit::inlines_to_text(content)));
+            }
+        }
+
+        doc.content.push(Block::Heading {
+            level: self.heading_level,
+            content: vec![Inline::Text {
+                content: self.title.clone(),
+            }],
+            id: Some(slugify(&self.title)),
+            span: None,
+        });
+        doc.content.push(index_list(&index));
+
+        Ok(())
+    }
+}
+
+fn index_list(entries: &BTreeMap<String, Entry>) -> Block {
+    let items = entries
+        .values()
+        .map(|entry| {
+            let mut line = vec![Inline::Text {
+                content: entry.label.clone(),
+            }];
+            for (i, (text, anchor)) in entry.locations.iter().enumerate() {
+                line.push(Inline::Text {
+                    content: if i == 0 { ", " } else { "; " }.to_string(),
+                });
+                line.push(Inline::Link {
+                    url: format!("#{}", anchor),
+                    title: None,
+                    content: vec![Inline::Text {
+                        content: text.clone(),
+                    }],
+                });
+            }
+
+            let mut content = vec![Block::Paragraph {
+                content: line,
+                span: None,
+            }];
+            if !entry.subterms.is_empty() {
+                content.push(index_list(&entry.subterms));
+            }
+
+            ListItem {
+                content,
+                checked: None,
+            }
+        })
+        .collect();
+
+    Block::List {
+        ordered: false,
+        start: None,
+        items,
+        span: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    fn heading(text: &str) -> Block {
+        Block::Heading {
+            level: 2,
+            content: vec![Inline::Text {
+                content: text.to_string(),
+            }],
+            id: None,
+            span: None,
+        }
+    }
+
+    fn mention(term: &str, subterm: Option<&str>) -> Block {
+        Block::Paragraph {
+            content: vec![
+                Inline::Text {
+                    content: "text".to_string(),
+                },
+                Inline::IndexTerm {
+                    term: term.to_string(),
+                    subterm: subterm.map(str::to_string),
+                },
+            ],
+            span: None,
+        }
+    }
+
+    #[test]
+    fn test_generates_sorted_index() {
+        let mut doc = Document {
+            source_format: SourceFormat::AsciiDoc,
+            meta: DocumentMeta::default(),
+            content: vec![
+                heading("Parsing"),
+                mention("Zebra", None),
+                mention("ast", None),
+                heading("Rendering"),
+                mention("AST", Some("rendering")),
+                mention("zebra", None),
+                heading("Unindexed"),
+            ],
+            raw_source: None,
+        };
+        GenerateIndex::default().apply(&mut doc).unwrap();
+
+        let Some(Block::List { items, .. }) = doc.content.last() else {
+            panic!("expected index list");
+        };
+        let lines: Vec<String> = items
+            .iter()
+            .map(|item| match &item.content[0] {
+                Block::Paragraph { content, .. } => visit::inlines_to_text(content),
+                _ => String::new(),
+            })
+            .collect();
+        assert_eq!(lines, vec!["ast, Parsing", "Zebra, Parsing; Rendering"]);
+        assert_eq!(items[0].content.len(), 2, "AST has a subterm list");
+
+        let Block::Heading { id, .. } = &doc.content[0] else {
+            panic!("expected heading");
+        };
+        assert_eq!(id.as_deref(), Some("parsing"));
+        let Block::Heading { id, .. } = &doc.content[6] else {
+            panic!("expected heading");
+        };
+        assert_eq!(id, &None, "unreferenced headings keep no id");
+    }
+
+    #[test]
+    fn test_no_terms_no_index() {
+        let mut doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![heading("Only")],
+            raw_source: None,
+        };
+        GenerateIndex::default().apply(&mut doc).unwrap();
+
+        assert_eq!(doc.content.len(), 1);
+        assert!(matches!(&doc.content[0], Block::Heading { id: None, .. }));
+    }
+}
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Name deduplication transform
+
+use crate::ast::{Block, Document, Inline};
+use crate::intern::Interner;
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+use std::sync::Arc;
+
+/// Make every [`Name`](crate::ast::Name) in a document share the
+/// interner's copy
+///
+/// Share one interner across a batch so all documents use the same
+/// allocations.
+#[derive(Debug, Clone, Default)]
+pub struct InternNames {
+    interner: Arc<Interner>,
+}
+
+impl InternNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use a shared interner
+    pub fn with_interner(interner: Arc<Interner>) -> Self {
+        Self { interner }
+    }
+
+    pub fn interner(&self) -> &Arc<Interner> {
+        &self.interner
+    }
+}
+
+impl Transform for InternNames {
+    fn name(&self) -> &str {
+        "intern-names"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let interner = &self.interner;
+        visit::walk_blocks_mut(&mut doc.content, &mut |block| match block {
+            Block::CodeBlock {
+                language: Some(name),
+                ..
+            }
+            | Block::Raw {
+                format: Some(name), ..
+            }
+            | Block::Language {
+                lang: Some(name), ..
+            }
+            | Block::Diagram { engine: name, .. }
+            | Block::Admonition { kind: name, .. } => interner.intern_name(name),
+            _ => {}
+        });
+        visit::walk_inline_lists_mut(&mut doc.content, &mut |inlines| {
+            for inline in inlines.iter_mut() {
+                match inline {
+                    Inline::Code {
+                        language: Some(name),
+                        ..
+                    }
+                    | Inline::RawInline {
+                        format: Some(name), ..
+                    }
+                    | Inline::Language {
+                        lang: Some(name), ..
+                    } => interner.intern_name(name),
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, Name, SourceFormat};
+
+    fn code(language: &str) -> Block {
+        Block::CodeBlock {
+            language: Some(language.to_string().into()),
+            content: String::new(),
+            span: None,
+        }
+    }
+
+    fn language(block: &Block) -> &Name {
+        match block {
+            Block::CodeBlock {
+                language: Some(name),
+                ..
+            } => name,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_intern_across_documents() {
+        let transform = InternNames::new();
+        let mut docs: Vec<Document> = (0..2)
+            .map(|_| Document {
+                source_format: SourceFormat::Markdown,
+                meta: DocumentMeta::default(),
+                content: vec![code("rust"), code("rust"), code("toml")],
+                raw_source: None,
+            })
+            .collect();
+        for doc in &mut docs {
+            transform.apply(doc).unwrap();
+        }
+
+        let first = language(&docs[0].content[0]);
+        assert!(Name::ptr_eq(first, language(&docs[0].content[1])));
+        assert!(Name::ptr_eq(first, language(&docs[1].content[0])));
+        assert_eq!(transform.interner().len(), 2);
+    }
+}
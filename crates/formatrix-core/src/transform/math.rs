@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Math notation retargeting
+
+use crate::ast::{Document, Inline};
+use crate::math::{self, MathNotation};
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+
+/// Convert every math inline to the notation of the target format
+///
+/// The source notation defaults to the one used by the document's
+/// source format (see [`MathNotation::for_format`]).
+#[derive(Debug, Clone, Copy)]
+pub struct RetargetMath {
+    pub from: Option<MathNotation>,
+    pub to: MathNotation,
+}
+
+impl RetargetMath {
+    pub fn new(to: MathNotation) -> Self {
+        Self { from: None, to }
+    }
+}
+
+impl Transform for RetargetMath {
+    fn name(&self) -> &str {
+        "retarget-math"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let from = self
+            .from
+            .unwrap_or_else(|| MathNotation::for_format(doc.source_format));
+        if from == self.to {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        visit::walk_inline_lists_mut(&mut doc.content, &mut |inlines| {
+            for inline in inlines.iter_mut() {
+                if let Inline::Math { content } | Inline::DisplayMath { content } = inline {
+                    match math::convert(content, from, self.to) {
+                        Ok(converted) => *content = converted,
+                        Err(e) if result.is_ok() => result = Err(e),
+                        Err(_) => {}
+                    }
+                }
+            }
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Block, DocumentMeta, SourceFormat};
+
+    #[test]
+    fn test_retarget_markdown_math_to_typst() {
+        let mut doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![
+                    Inline::Math {
+                        content: r"\alpha^2".to_string(),
+                    },
+                    Inline::DisplayMath {
+                        content: r"\frac{1}{2}".to_string(),
+                    },
+                ],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        RetargetMath::new(MathNotation::Typst)
+            .apply(&mut doc)
+            .unwrap();
+
+        let Block::Paragraph { content, .. } = &doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(&content[0], Inline::Math { content } if content == "alpha^2"));
+        assert!(matches!(&content[1], Inline::DisplayMath { content } if content == "frac(1, 2)"));
+    }
+}
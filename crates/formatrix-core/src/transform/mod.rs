@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! AST-to-AST transforms
+//!
+//! Transforms rewrite a parsed [`Document`] in place between parsing and
+//! rendering. They are format-agnostic: every transform operates on the
+//! unified AST, so the same pass works for any source/target pair.
+
+use crate::ast::Document;
+use crate::traits::Result;
+
+pub mod crossref;
+pub mod diagrams;
+pub mod images;
+pub mod index;
+pub mod intern;
+pub mod math;
+pub mod normalize;
+pub mod numbering;
+pub mod redact;
+pub mod template;
+pub mod transclude;
+pub mod typography;
+
+pub use crossref::CrossReferences;
+pub use diagrams::{DetectDiagrams, RenderDiagrams};
+pub use images::InlineImages;
+pub use index::GenerateIndex;
+pub use intern::InternNames;
+pub use math::RetargetMath;
+pub use normalize::Normalize;
+pub use numbering::HeadingNumbering;
+pub use redact::Redact;
+pub use template::Substitute;
+pub use transclude::Transclusion;
+pub use typography::{QuoteStyle, Typography};
+
+/// A document transform: rewrite a Document in place
+pub trait Transform: Send + Sync {
+    /// Short identifier for this transform (used in logs and pipelines)
+    fn name(&self) -> &str;
+
+    /// Apply the transform to a document
+    fn apply(&self, doc: &mut Document) -> Result<()>;
+}
@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! AST canonicalization
+//!
+//! Different parsers (and different spellings of the same markup) produce
+//! structurally different but equivalent trees: `**a****b**` vs `**ab**`,
+//! split text runs, empty emphasis, tags in a different order.
+//! Normalizing removes those differences so structural diffs and content
+//! hashes are stable. Custom front matter fields are kept in key order
+//! by [`DocumentMeta::frontmatter`](crate::ast::DocumentMeta) itself.
+
+use crate::ast::{Block, Document, Inline};
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+
+/// Canonicalize a document's AST
+///
+/// - merges adjacent `Text` inlines
+/// - drops empty text and empty formatting spans
+/// - collapses redundant nesting (`Strong` directly inside `Strong`)
+/// - merges adjacent spans of the same kind
+/// - removes empty paragraphs
+/// - sorts and de-duplicates tags
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Normalize;
+
+impl Transform for Normalize {
+    fn name(&self) -> &str {
+        "normalize"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        doc.normalize();
+        Ok(())
+    }
+}
+
+impl Document {
+    /// Canonicalize this document in place (see [`Normalize`])
+    pub fn normalize(&mut self) {
+        normalize_blocks(&mut self.content);
+        self.meta.tags.sort();
+        self.meta.tags.dedup();
+    }
+}
+
+fn normalize_blocks(blocks: &mut Vec<Block>) {
+    for block in blocks.iter_mut() {
+        for inlines in visit::block_inlines_mut(block) {
+            normalize_inlines(inlines);
+        }
+        for children in visit::child_blocks_mut(block) {
+            normalize_blocks(children);
+        }
+    }
+
+    blocks.retain(|block| match block {
+        Block::Paragraph { content, .. } => !is_blank(content),
+        _ => true,
+    });
+}
+
+/// Inline spans that can be flattened and merged without changing meaning
+fn is_mergeable_span(inline: &Inline) -> bool {
+    matches!(
+        inline,
+        Inline::Emphasis { .. } | Inline::Strong { .. } | Inline::Strikethrough { .. }
+    )
+}
+
+fn normalize_inlines(inlines: &mut Vec<Inline>) {
+    let mut normalized: Vec<Inline> = Vec::with_capacity(inlines.len());
+
+    for mut inline in inlines.drain(..) {
+        if let Some(children) = visit::inline_children_mut(&mut inline) {
+            normalize_inlines(children);
+        }
+
+        // Strong { Strong { x } } -> Strong { x }
+        if is_mergeable_span(&inline) {
+            let inner = visit::inline_children(&inline).and_then(|children| match children {
+                [only] if same_kind(only, &inline) => Some(only.clone()),
+                _ => None,
+            });
+            if let Some(inner) = inner {
+                inline = inner;
+            }
+        }
+
+        match (&mut inline, normalized.last_mut()) {
+            (Inline::Text { content }, _) if content.is_empty() => continue,
+            (Inline::Text { content }, Some(Inline::Text { content: prev })) => {
+                prev.push_str(content);
+                continue;
+            }
+            _ => {}
+        }
+
+        if is_mergeable_span(&inline) {
+            if visit::inline_children(&inline).is_some_and(|c| c.is_empty()) {
+                continue;
+            }
+            if let Some(prev) = normalized.last_mut().filter(|p| same_kind(p, &inline)) {
+                let mut children = std::mem::take(visit::inline_children_mut(&mut inline).unwrap());
+                let prev_children = visit::inline_children_mut(prev).unwrap();
+                prev_children.append(&mut children);
+                normalize_inlines(prev_children);
+                continue;
+            }
+        }
+
+        normalized.push(inline);
+    }
+
+    *inlines = normalized;
+}
+
+fn same_kind(a: &Inline, b: &Inline) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn is_blank(inlines: &[Inline]) -> bool {
+    inlines.iter().all(|inline| match inline {
+        Inline::Text { content } => content.trim().is_empty(),
+        Inline::SoftBreak | Inline::LineBreak => true,
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    fn text(s: &str) -> Inline {
+        Inline::Text {
+            content: s.to_string(),
+        }
+    }
+
+    fn strong(content: Vec<Inline>) -> Inline {
+        Inline::Strong { content }
+    }
+
+    fn para(content: Vec<Inline>) -> Block {
+        Block::Paragraph {
+            content,
+            span: None,
+        }
+    }
+
+    fn doc(content: Vec<Block>) -> Document {
+        Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content,
+            raw_source: None,
+        }
+    }
+
+    #[test]
+    fn test_merges_text_and_spans() {
+        let mut d = doc(vec![para(vec![
+            text("a"),
+            text(""),
+            text("b "),
+            strong(vec![text("c")]),
+            strong(vec![strong(vec![text("d")])]),
+            Inline::Emphasis {
+                content: vec![text("")],
+            },
+        ])]);
+        d.normalize();
+
+        let Block::Paragraph { content, .. } = &d.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(content.len(), 2);
+        assert!(matches!(&content[0], Inline::Text { content } if content == "ab "));
+        assert!(matches!(
+            &content[1],
+            Inline::Strong { content } if matches!(content.as_slice(), [Inline::Text { content }] if content == "cd")
+        ));
+    }
+
+    #[test]
+    fn test_removes_empty_paragraphs() {
+        let mut d = doc(vec![
+            para(vec![text("  "), Inline::SoftBreak]),
+            Block::BlockQuote {
+                content: vec![para(vec![]), para(vec![text("kept")])],
+                span: None,
+            },
+        ]);
+        d.normalize();
+
+        assert_eq!(d.content.len(), 1);
+        let Block::BlockQuote { content, .. } = &d.content[0] else {
+            panic!("expected block quote");
+        };
+        assert_eq!(content.len(), 1);
+    }
+
+    #[test]
+    fn test_sorts_attributes() {
+        let mut a = doc(vec![para(vec![text("x")])]);
+        let mut b = a.clone();
+        a.meta.tags = vec!["b".to_string(), "a".to_string(), "b".to_string()];
+        b.meta.tags = vec!["a".to_string(), "b".to_string()];
+        for key in ["z", "m", "a"] {
+            a.meta.frontmatter.insert(key.to_string(), key.into());
+        }
+        for key in ["a", "m", "z"] {
+            b.meta.frontmatter.insert(key.to_string(), key.into());
+        }
+        a.normalize();
+        b.normalize();
+
+        assert_eq!(a.meta.tags, ["a", "b"]);
+        assert_eq!(
+            serde_json::to_string(&a.meta).unwrap(),
+            serde_json::to_string(&b.meta).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_equivalent_inputs_serialize_identically() {
+        let mut a = doc(vec![para(vec![strong(vec![text("x"), text("y")])])]);
+        let mut b = doc(vec![para(vec![
+            strong(vec![text("x")]),
+            strong(vec![text("y")]),
+        ])]);
+        a.normalize();
+        b.normalize();
+
+        assert_eq!(
+            serde_json::to_string(&a.content).unwrap(),
+            serde_json::to_string(&b.content).unwrap()
+        );
+    }
+}
@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Hierarchical heading numbering (1., 1.2, 1.2.3)
+//!
+//! Numbers are computed relative to the shallowest heading level in the
+//! document, so a document that uses `##` for its top sections is numbered
+//! the same way as one that uses `#`.
+
+use crate::ast::{Block, Document, Inline};
+use crate::traits::Result;
+use crate::transform::Transform;
+
+/// Prefix headings with hierarchical section numbers
+#[derive(Debug, Clone)]
+pub struct HeadingNumbering {
+    /// Number of heading levels to number (1 = top-level sections only)
+    pub max_depth: u8,
+    /// Add numbers to headings (disable to only strip existing numbers)
+    pub number: bool,
+    /// Remove existing "1.2.3 " style prefixes before numbering
+    pub strip_existing: bool,
+}
+
+impl HeadingNumbering {
+    /// Number headings up to the given depth
+    pub fn new(max_depth: u8) -> Self {
+        Self {
+            max_depth,
+            number: true,
+            strip_existing: true,
+        }
+    }
+
+    /// Only strip existing numbering, without adding new numbers
+    pub fn strip_only() -> Self {
+        Self {
+            max_depth: 0,
+            number: false,
+            strip_existing: true,
+        }
+    }
+}
+
+impl Default for HeadingNumbering {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl Transform for HeadingNumbering {
+    fn name(&self) -> &str {
+        "number-headings"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let base_level = doc
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                Block::Heading { level, .. } => Some(*level),
+                _ => None,
+            })
+            .min()
+            .unwrap_or(1);
+
+        let mut counters = vec![0u32; self.max_depth as usize];
+
+        for block in &mut doc.content {
+            let Block::Heading { level, content, .. } = block else {
+                continue;
+            };
+
+            if self.strip_existing {
+                strip_number_prefix(content);
+            }
+
+            if !self.number {
+                continue;
+            }
+
+            let depth = (*level - base_level) as usize;
+            if depth >= counters.len() {
+                continue;
+            }
+
+            counters[depth] += 1;
+            for counter in &mut counters[depth + 1..] {
+                *counter = 0;
+            }
+
+            let prefix = format_number(&counters[..=depth]);
+            match content.first_mut() {
+                Some(Inline::Text { content: text }) => {
+                    text.insert_str(0, &prefix);
+                }
+                _ => content.insert(0, Inline::Text { content: prefix }),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Format a number path: "1. " for top-level sections, "1.2 " below that
+fn format_number(path: &[u32]) -> String {
+    let joined = path
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(".");
+    if path.len() == 1 {
+        format!("{}. ", joined)
+    } else {
+        format!("{} ", joined)
+    }
+}
+
+/// Remove a leading "1.", "1.2" or "1.2.3." prefix from the heading text
+fn strip_number_prefix(content: &mut Vec<Inline>) {
+    let Some(Inline::Text { content: text }) = content.first_mut() else {
+        return;
+    };
+
+    let len = number_prefix_len(text);
+    if len == 0 {
+        return;
+    }
+
+    text.replace_range(..len, "");
+    if text.is_empty() {
+        content.remove(0);
+    }
+}
+
+/// Byte length of a numbering prefix (including trailing whitespace), or 0
+fn number_prefix_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    let mut saw_digit = false;
+
+    loop {
+        let start = pos;
+        while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        if pos == start {
+            break;
+        }
+        saw_digit = true;
+        if pos < bytes.len() && bytes[pos] == b'.' {
+            pos += 1;
+        } else {
+            break;
+        }
+    }
+
+    if !saw_digit {
+        return 0;
+    }
+
+    // A prefix must be followed by whitespace ("2024 report" is not numbering
+    // unless it is "2024. report" or similar)
+    let ws_start = pos;
+    while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    if pos == ws_start {
+        return 0;
+    }
+    if !text[..ws_start].contains('.') {
+        return 0;
+    }
+
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    fn heading(level: u8, text: &str) -> Block {
+        Block::Heading {
+            level,
+            content: vec![Inline::Text {
+                content: text.to_string(),
+            }],
+            id: None,
+            span: None,
+        }
+    }
+
+    fn heading_texts(doc: &Document) -> Vec<String> {
+        doc.content
+            .iter()
+            .filter_map(|b| match b {
+                Block::Heading { content, .. } => match content.first() {
+                    Some(Inline::Text { content }) => Some(content.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn doc(content: Vec<Block>) -> Document {
+        Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content,
+            raw_source: None,
+        }
+    }
+
+    #[test]
+    fn test_hierarchical_numbers() {
+        let mut d = doc(vec![
+            heading(2, "Intro"),
+            heading(3, "Scope"),
+            heading(3, "Terms"),
+            heading(4, "Too deep"),
+            heading(2, "Design"),
+            heading(3, "Overview"),
+        ]);
+        HeadingNumbering::new(2).apply(&mut d).unwrap();
+
+        assert_eq!(
+            heading_texts(&d),
+            vec![
+                "1. Intro",
+                "1.1 Scope",
+                "1.2 Terms",
+                "Too deep",
+                "2. Design",
+                "2.1 Overview"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_renumber_replaces_existing() {
+        let mut d = doc(vec![heading(1, "3. Intro"), heading(2, "3.4 Scope")]);
+        HeadingNumbering::new(3).apply(&mut d).unwrap();
+
+        assert_eq!(heading_texts(&d), vec!["1. Intro", "1.1 Scope"]);
+    }
+
+    #[test]
+    fn test_strip_only() {
+        let mut d = doc(vec![
+            heading(1, "1. Intro"),
+            heading(2, "1.2.3 Detail"),
+            heading(2, "2024 report"),
+        ]);
+        HeadingNumbering::strip_only().apply(&mut d).unwrap();
+
+        assert_eq!(heading_texts(&d), vec!["Intro", "Detail", "2024 report"]);
+    }
+}
@@ -0,0 +1,236 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Redaction of personal data
+//!
+//! Masks e-mail addresses, phone numbers and custom patterns before a
+//! document drawn from private notes is shared publicly. Matches are
+//! replaced in text and in link and image URLs, so a `mailto:` link does
+//! not leak what its text hides; block structure is left as it was. Code
+//! is only redacted when asked, since patterns can break code samples.
+
+use crate::ast::{Block, Document, Inline};
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+use regex::Regex;
+use std::borrow::Cow;
+
+const EMAIL: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}";
+/// Digit runs with separators; matches with fewer than [`PHONE_DIGITS`]
+/// digits (dates, years, prices) are kept
+const PHONE: &str = r"\+?\(?\d[\d\s().-]{6,}\d";
+const PHONE_DIGITS: usize = 9;
+
+#[derive(Debug, Clone)]
+struct Rule {
+    regex: Regex,
+    min_digits: usize,
+}
+
+/// Mask configurable patterns in a document
+#[derive(Debug, Clone)]
+pub struct Redact {
+    rules: Vec<Rule>,
+    /// Replacement for every match
+    pub mask: String,
+    /// Also redact inline code and code blocks
+    pub code: bool,
+}
+
+impl Redact {
+    /// A transform with no patterns; add them with the `with_*` methods
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            mask: "[redacted]".to_string(),
+            code: false,
+        }
+    }
+
+    pub fn with_emails(self) -> Self {
+        self.with_rule(Regex::new(EMAIL).expect("valid pattern"), 0)
+    }
+
+    /// Phone numbers of at least nine digits, with or without separators
+    pub fn with_phone_numbers(self) -> Self {
+        self.with_rule(Regex::new(PHONE).expect("valid pattern"), PHONE_DIGITS)
+    }
+
+    pub fn with_regex(self, regex: Regex) -> Self {
+        self.with_rule(regex, 0)
+    }
+
+    /// Compile and add a custom pattern
+    pub fn with_pattern(self, pattern: &str) -> std::result::Result<Self, regex::Error> {
+        Ok(self.with_regex(Regex::new(pattern)?))
+    }
+
+    pub fn with_mask(mut self, mask: impl Into<String>) -> Self {
+        self.mask = mask.into();
+        self
+    }
+
+    pub fn with_code(mut self, code: bool) -> Self {
+        self.code = code;
+        self
+    }
+
+    fn with_rule(mut self, regex: Regex, min_digits: usize) -> Self {
+        self.rules.push(Rule { regex, min_digits });
+        self
+    }
+
+    /// `text` with every match masked
+    pub fn redact_str(&self, text: &str) -> String {
+        let mut text = Cow::Borrowed(text);
+        for rule in &self.rules {
+            let replaced = rule.regex.replace_all(&text, |caps: &regex::Captures| {
+                let matched = &caps[0];
+                if matched.chars().filter(char::is_ascii_digit).count() < rule.min_digits {
+                    matched.to_string()
+                } else {
+                    self.mask.clone()
+                }
+            });
+            if let Cow::Owned(replaced) = replaced {
+                text = Cow::Owned(replaced);
+            }
+        }
+        text.into_owned()
+    }
+
+    fn redact(&self, value: &mut String) {
+        let redacted = self.redact_str(value);
+        if redacted != *value {
+            *value = redacted;
+        }
+    }
+}
+
+impl Default for Redact {
+    /// E-mail addresses and phone numbers
+    fn default() -> Self {
+        Self::new().with_emails().with_phone_numbers()
+    }
+}
+
+impl Transform for Redact {
+    fn name(&self) -> &str {
+        "redact"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+        visit::walk_inline_lists_mut(&mut doc.content, &mut |inlines| {
+            for inline in inlines.iter_mut() {
+                match inline {
+                    Inline::Text { content } => self.redact(content),
+                    Inline::Link { url, title, .. } => {
+                        self.redact(url);
+                        title.iter_mut().for_each(|t| self.redact(t));
+                    }
+                    Inline::Image {
+                        url, alt, title, ..
+                    } => {
+                        self.redact(url);
+                        self.redact(alt);
+                        title.iter_mut().for_each(|t| self.redact(t));
+                    }
+                    Inline::Code { content, .. } if self.code => self.redact(content),
+                    _ => {}
+                }
+            }
+        });
+        if self.code {
+            visit::walk_blocks_mut(&mut doc.content, &mut |block| {
+                if let Block::CodeBlock { content, .. } = block {
+                    self.redact(content);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+
+    fn text(s: &str) -> Inline {
+        Inline::Text {
+            content: s.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_redact_str() {
+        let redact = Redact::default();
+        assert_eq!(
+            redact.redact_str("Mail jane.doe@example.co.uk or call +44 (0)20 7946 0958."),
+            "Mail [redacted] or call [redacted]."
+        );
+        // Dates and short numbers are not phone numbers
+        assert_eq!(
+            redact.redact_str("Released 2024-01-15, version 1.2.3"),
+            "Released 2024-01-15, version 1.2.3"
+        );
+
+        let custom = Redact::new()
+            .with_pattern(r"ACME-\d+")
+            .unwrap()
+            .with_mask("###");
+        assert_eq!(
+            custom.redact_str("Ticket ACME-42 closed"),
+            "Ticket ### closed"
+        );
+        assert!(Redact::new().with_pattern("(").is_err());
+    }
+
+    #[test]
+    fn test_redact_document() {
+        let mut doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![
+                Block::Paragraph {
+                    content: vec![
+                        text("Contact "),
+                        Inline::Link {
+                            url: "mailto:me@example.org".to_string(),
+                            title: None,
+                            content: vec![text("me@example.org")],
+                        },
+                        Inline::Code {
+                            content: "git config user.email me@example.org".to_string(),
+                            language: None,
+                        },
+                    ],
+                    span: None,
+                },
+                Block::CodeBlock {
+                    language: None,
+                    content: "me@example.org".to_string(),
+                    span: None,
+                },
+            ],
+            raw_source: None,
+        };
+        Redact::new().with_emails().apply(&mut doc).unwrap();
+
+        let json = serde_json::to_string(&doc.content).unwrap();
+        assert!(json.contains("mailto:[redacted]"));
+        assert_eq!(json.matches("me@example.org").count(), 2, "code is kept");
+
+        Redact::new()
+            .with_emails()
+            .with_code(true)
+            .apply(&mut doc)
+            .unwrap();
+        assert!(!serde_json::to_string(&doc.content)
+            .unwrap()
+            .contains("me@example.org"));
+    }
+}
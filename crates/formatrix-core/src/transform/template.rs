@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Template variable substitution
+//!
+//! Replaces `{{name}}` placeholders (whitespace inside the braces is
+//! allowed) in text and metadata, so a stored document can serve as a
+//! template. Code and raw content are left alone. Parsers may split text
+//! into several inlines; run [`Normalize`] first so a placeholder is never
+//! split across two of them.
+//!
+//! [`Normalize`]: crate::transform::Normalize
+
+use crate::ast::{Document, Inline, MetaValue};
+use crate::traits::{ConversionError, Result};
+use crate::transform::Transform;
+use crate::visit;
+use std::collections::HashMap;
+
+/// Substitute `{{variable}}` placeholders from a map
+#[derive(Debug, Clone, Default)]
+pub struct Substitute {
+    pub variables: HashMap<String, String>,
+    /// Fail on placeholders with no value (otherwise they are left as-is)
+    pub strict: bool,
+}
+
+impl Substitute {
+    pub fn new(variables: HashMap<String, String>) -> Self {
+        Self {
+            variables,
+            strict: false,
+        }
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Substitute placeholders in `text`
+    pub fn render(&self, text: &str) -> Result<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let placeholder = &rest[start..start + 2 + len + 2];
+            let name = rest[start + 2..start + 2 + len].trim();
+
+            out.push_str(&rest[..start]);
+            match self.variables.get(name) {
+                Some(value) => out.push_str(value),
+                None if self.strict => {
+                    return Err(ConversionError::UndefinedVariable(name.to_string()))
+                }
+                None => out.push_str(placeholder),
+            }
+            rest = &rest[start + placeholder.len()..];
+        }
+
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn render_in_place(&self, text: &mut String) -> Result<()> {
+        if text.contains("{{") {
+            *text = self.render(text)?;
+        }
+        Ok(())
+    }
+
+    fn render_value(&self, value: &mut MetaValue) -> Result<()> {
+        match value {
+            MetaValue::String(text) => self.render_in_place(text),
+            MetaValue::List(items) => items.iter_mut().try_for_each(|v| self.render_value(v)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Transform for Substitute {
+    fn name(&self) -> &str {
+        "substitute"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let meta = &mut doc.meta;
+        for text in meta
+            .title
+            .iter_mut()
+            .chain(meta.date.iter_mut())
+            .chain(meta.authors.iter_mut())
+            .chain(meta.tags.iter_mut())
+        {
+            self.render_in_place(text)?;
+        }
+        for value in meta.frontmatter.values_mut() {
+            self.render_value(value)?;
+        }
+
+        let mut result = Ok(());
+        visit::walk_inline_lists_mut(&mut doc.content, &mut |inlines| {
+            for inline in inlines.iter_mut() {
+                let text = match inline {
+                    Inline::Text { content } => content,
+                    Inline::Link { url, .. } | Inline::Image { url, .. } => url,
+                    _ => continue,
+                };
+                if let Err(e) = self.render_in_place(text) {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Block, DocumentMeta, SourceFormat};
+
+    fn vars() -> HashMap<String, String> {
+        HashMap::from([
+            ("name".to_string(), "Ada".to_string()),
+            ("project".to_string(), "Formatrix".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_render() {
+        let s = Substitute::new(vars());
+        assert_eq!(
+            s.render("Hi {{ name }}, welcome to {{project}}!").unwrap(),
+            "Hi Ada, welcome to Formatrix!"
+        );
+        assert_eq!(
+            s.render("{{missing}} {{ unclosed").unwrap(),
+            "{{missing}} {{ unclosed"
+        );
+        assert!(matches!(
+            s.strict().render("{{missing}}"),
+            Err(ConversionError::UndefinedVariable(name)) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_apply_to_text_and_meta() {
+        let mut doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta {
+                title: Some("{{project}} notes".to_string()),
+                ..Default::default()
+            },
+            content: vec![Block::Paragraph {
+                content: vec![
+                    Inline::Text {
+                        content: "By {{name}}: ".to_string(),
+                    },
+                    Inline::Code {
+                        content: "{{name}}".to_string(),
+                        language: None,
+                    },
+                ],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        Substitute::new(vars()).apply(&mut doc).unwrap();
+
+        assert_eq!(doc.meta.title.as_deref(), Some("Formatrix notes"));
+        let Block::Paragraph { content, .. } = &doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(&content[0], Inline::Text { content } if content == "By Ada: "));
+        assert!(matches!(&content[1], Inline::Code { content, .. } if content == "{{name}}"));
+    }
+}
@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Transclusion: replace include placeholders with the included content
+//!
+//! Included sources are parsed with their own format handler, so an
+//! AsciiDoc book can include Markdown chapters. Nested includes are
+//! resolved recursively up to `max_depth`; an include that (directly or
+//! indirectly) includes itself is reported as an error.
+
+use crate::ast::{Block, Document};
+use crate::file_ops;
+use crate::include::IncludeResolver;
+use crate::traits::{ConversionError, ParseConfig, Result};
+use crate::transform::Transform;
+use crate::visit;
+use std::sync::Arc;
+
+/// Resolve [`Block::Include`] placeholders through an [`IncludeResolver`]
+#[derive(Clone)]
+pub struct Transclusion {
+    resolver: Arc<dyn IncludeResolver>,
+    /// Key of the document being transformed, for relative targets
+    pub base: Option<String>,
+    /// Maximum include nesting depth
+    pub max_depth: usize,
+    /// Parse configuration for included sources
+    pub parse_config: ParseConfig,
+}
+
+impl Transclusion {
+    pub fn new(resolver: Arc<dyn IncludeResolver>) -> Self {
+        Self {
+            resolver,
+            base: None,
+            max_depth: 8,
+            parse_config: ParseConfig::default(),
+        }
+    }
+
+    /// Set the key of the document being transformed
+    pub fn with_base(mut self, base: impl Into<String>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+
+    fn expand(&self, blocks: &mut Vec<Block>, stack: &mut Vec<String>) -> Result<()> {
+        let mut i = 0;
+        while i < blocks.len() {
+            let Block::Include { target, .. } = &blocks[i] else {
+                for children in visit::child_blocks_mut(&mut blocks[i]) {
+                    self.expand(children, stack)?;
+                }
+                i += 1;
+                continue;
+            };
+
+            if stack.len() > self.max_depth {
+                return Err(ConversionError::IncludeError {
+                    target: target.clone(),
+                    message: format!("include depth exceeds {}", self.max_depth),
+                });
+            }
+
+            // "chapter.md#install" embeds only the "install" section
+            let (path, fragment) = match target.split_once('#') {
+                Some((path, fragment)) => (path, Some(fragment)),
+                None => (target.as_str(), None),
+            };
+
+            let source = self
+                .resolver
+                .resolve(path, stack.last().map(String::as_str))?;
+            if stack.contains(&source.key) {
+                return Err(ConversionError::IncludeError {
+                    target: target.clone(),
+                    message: format!("include cycle: {} -> {}", stack.join(" -> "), source.key),
+                });
+            }
+
+            let format = source
+                .format
+                .unwrap_or_else(|| file_ops::format_from_content(&source.content));
+            let mut included = file_ops::parse_content(&source.content, format, &self.parse_config)
+                .map_err(|e| ConversionError::IncludeError {
+                    target: target.clone(),
+                    message: e.to_string(),
+                })?;
+            if let Some(fragment) = fragment {
+                included =
+                    included
+                        .section(fragment)
+                        .ok_or_else(|| ConversionError::IncludeError {
+                            target: target.clone(),
+                            message: format!("no section with id '{}'", fragment),
+                        })?;
+            }
+            let mut included = included.content;
+
+            stack.push(source.key);
+            self.expand(&mut included, stack)?;
+            stack.pop();
+
+            let len = included.len();
+            blocks.splice(i..=i, included);
+            i += len;
+        }
+        Ok(())
+    }
+}
+
+impl Transform for Transclusion {
+    fn name(&self) -> &str {
+        "transclude"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        let mut stack: Vec<String> = self.base.iter().cloned().collect();
+        self.expand(&mut doc.content, &mut stack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DocumentMeta, SourceFormat};
+    use crate::include::MemoryIncludeResolver;
+
+    fn include(target: &str) -> Block {
+        Block::Include {
+            target: target.to_string(),
+            span: None,
+        }
+    }
+
+    fn doc(content: Vec<Block>) -> Document {
+        Document {
+            source_format: SourceFormat::PlainText,
+            meta: DocumentMeta::default(),
+            content,
+            raw_source: None,
+        }
+    }
+
+    #[test]
+    fn test_nested_includes() {
+        let mut resolver = MemoryIncludeResolver::new();
+        resolver.insert("a", "First\n\nSecond", Some(SourceFormat::PlainText));
+        let transform = Transclusion::new(Arc::new(resolver));
+
+        let mut d = doc(vec![
+            include("a"),
+            Block::BlockQuote {
+                content: vec![include("a")],
+                span: None,
+            },
+        ]);
+        transform.apply(&mut d).unwrap();
+
+        assert_eq!(d.content.len(), 3);
+        let Block::BlockQuote { content, .. } = &d.content[2] else {
+            panic!("expected block quote");
+        };
+        assert_eq!(content.len(), 2);
+    }
+
+    #[test]
+    fn test_section_embed() {
+        let mut resolver = MemoryIncludeResolver::new();
+        resolver.insert(
+            "guide",
+            "# Intro\n\nHello\n\n# Install\n\nRun it",
+            Some(SourceFormat::Markdown),
+        );
+        let transform = Transclusion::new(Arc::new(resolver));
+
+        let mut d = doc(vec![include("guide#install")]);
+        transform.apply(&mut d).unwrap();
+        assert_eq!(d.content.len(), 2);
+        assert!(matches!(d.content[0], Block::Heading { .. }));
+
+        assert!(transform
+            .apply(&mut doc(vec![include("guide#missing")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_cycle_is_an_error() {
+        let mut resolver = MemoryIncludeResolver::new();
+        resolver.insert("a", "", Some(SourceFormat::PlainText));
+        let transform = Transclusion::new(Arc::new(resolver)).with_base("a");
+
+        let err = transform.apply(&mut doc(vec![include("a")])).unwrap_err();
+        assert!(err.to_string().contains("cycle"));
+    }
+
+    #[test]
+    fn test_missing_target_is_an_error() {
+        let transform = Transclusion::new(Arc::new(MemoryIncludeResolver::new()));
+        assert!(transform.apply(&mut doc(vec![include("nope")])).is_err());
+    }
+}
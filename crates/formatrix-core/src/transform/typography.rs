@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Typographic refinement
+//!
+//! Replaces typewriter punctuation in text with typographic forms: curly
+//! quotes in the style of the document's language, en/em dashes,
+//! ellipses and (for French) non-breaking spaces. Only `Text` inlines are
+//! touched, so code, math and raw content keep their literal characters.
+//! Run it as an explicit step of a conversion rather than relying on a
+//! parser's "smart punctuation" option, so every format behaves the same.
+
+use crate::ast::{Document, Inline};
+use crate::traits::Result;
+use crate::transform::Transform;
+use crate::visit;
+
+/// Quotation mark conventions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    /// “double” and ‘single’
+    #[default]
+    English,
+    /// „double“ and ‚single‘
+    German,
+    /// « double » and ‹ single › with non-breaking spaces inside
+    French,
+    /// «double» and ‹single› without spacing (Swiss, Russian, ...)
+    Guillemets,
+}
+
+impl QuoteStyle {
+    /// Pick the conventional style for a BCP 47 language tag ("de-AT", "fr")
+    pub fn for_language(language: &str) -> Self {
+        let primary = language
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match primary.as_str() {
+            "de" | "cs" | "sk" | "pl" | "hu" | "ro" | "bg" | "lt" => {
+                if language.to_lowercase().ends_with("-ch") {
+                    QuoteStyle::Guillemets
+                } else {
+                    QuoteStyle::German
+                }
+            }
+            "fr" => QuoteStyle::French,
+            "ru" | "uk" | "be" | "es" | "it" | "el" | "nb" | "no" => QuoteStyle::Guillemets,
+            _ => QuoteStyle::English,
+        }
+    }
+
+    /// (open double, close double, open single, close single)
+    fn marks(self) -> (char, char, char, char) {
+        match self {
+            QuoteStyle::English => ('“', '”', '‘', '’'),
+            QuoteStyle::German => ('„', '“', '‚', '‘'),
+            QuoteStyle::French | QuoteStyle::Guillemets => ('«', '»', '‹', '›'),
+        }
+    }
+}
+
+/// Smart punctuation transform; each replacement can be toggled
+#[derive(Debug, Clone, Copy)]
+pub struct Typography {
+    /// Curly quotes and apostrophes
+    pub quotes: bool,
+    /// `--` to en dash, `---` to em dash
+    pub dashes: bool,
+    /// `...` to an ellipsis
+    pub ellipses: bool,
+    /// Non-breaking spaces before `; : ! ?` and inside guillemets
+    /// (only applies to [`QuoteStyle::French`])
+    pub non_breaking_spaces: bool,
+    pub quote_style: QuoteStyle,
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Self {
+            quotes: true,
+            dashes: true,
+            ellipses: true,
+            non_breaking_spaces: true,
+            quote_style: QuoteStyle::English,
+        }
+    }
+}
+
+impl Typography {
+    pub fn new(quote_style: QuoteStyle) -> Self {
+        Self {
+            quote_style,
+            ..Default::default()
+        }
+    }
+
+    fn french_spacing(&self) -> bool {
+        self.non_breaking_spaces && self.quote_style == QuoteStyle::French
+    }
+
+    /// Rewrite one text run. `prev` is the character before it (carried
+    /// across inlines) and decides whether a quote opens or closes.
+    fn refine(&self, text: &str, prev: &mut Option<char>) -> String {
+        let (open2, close2, open1, close1) = self.quote_style.marks();
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            let opening = prev.is_none_or(|p| {
+                p.is_whitespace() || "([{-–—".contains(p) || p == open2 || p == open1
+            });
+
+            let (replacement, consumed) = match c {
+                '-' if self.dashes && next == Some('-') => {
+                    if chars.get(i + 2) == Some(&'-') {
+                        ("—".to_string(), 3)
+                    } else {
+                        ("–".to_string(), 2)
+                    }
+                }
+                '.' if self.ellipses && chars[i..].starts_with(&['.', '.', '.']) => {
+                    ("…".to_string(), 3)
+                }
+                '"' if self.quotes => match (opening, self.french_spacing()) {
+                    (true, true) => (format!("{}\u{a0}", open2), 1),
+                    (true, false) => (open2.to_string(), 1),
+                    (false, true) => (format!("\u{a0}{}", close2), 1),
+                    (false, false) => (close2.to_string(), 1),
+                },
+                '\'' if self.quotes => {
+                    let apostrophe = prev.is_some_and(char::is_alphanumeric)
+                        && next.is_none_or(|n| n.is_alphabetic() || n.is_whitespace())
+                        && !opening;
+                    if apostrophe {
+                        ("’".to_string(), 1)
+                    } else if opening {
+                        (open1.to_string(), 1)
+                    } else {
+                        (close1.to_string(), 1)
+                    }
+                }
+                ' ' if self.french_spacing() && next.is_some_and(|n| ";:!?".contains(n)) => {
+                    ("\u{202f}".to_string(), 1)
+                }
+                c => (c.to_string(), 1),
+            };
+
+            *prev = replacement.chars().last();
+            out.push_str(&replacement);
+            i += consumed;
+        }
+
+        out
+    }
+}
+
+impl Transform for Typography {
+    fn name(&self) -> &str {
+        "typography"
+    }
+
+    fn apply(&self, doc: &mut Document) -> Result<()> {
+        visit::walk_inline_lists_mut(&mut doc.content, &mut |inlines| {
+            let mut prev = None;
+            for inline in inlines.iter_mut() {
+                match inline {
+                    Inline::Text { content } => *content = self.refine(content, &mut prev),
+                    Inline::SoftBreak | Inline::LineBreak => prev = None,
+                    // Anything else reads as a word for quote direction
+                    _ => prev = Some('x'),
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refine(typography: Typography, text: &str) -> String {
+        typography.refine(text, &mut None)
+    }
+
+    #[test]
+    fn test_english() {
+        let t = Typography::default();
+        assert_eq!(
+            refine(t, r#"She said "it's 1990--2000" -- 'really'..."#),
+            "She said “it’s 1990–2000” – ‘really’…"
+        );
+        assert_eq!(refine(t, "a---b"), "a—b");
+    }
+
+    #[test]
+    fn test_locale_styles() {
+        assert_eq!(QuoteStyle::for_language("de-AT"), QuoteStyle::German);
+        assert_eq!(QuoteStyle::for_language("de-CH"), QuoteStyle::Guillemets);
+        assert_eq!(
+            refine(Typography::new(QuoteStyle::German), r#""Hallo""#),
+            "„Hallo“"
+        );
+        assert_eq!(
+            refine(Typography::new(QuoteStyle::French), r#"Il dit "oui" !"#),
+            "Il dit «\u{a0}oui\u{a0}»\u{202f}!"
+        );
+    }
+
+    #[test]
+    fn test_toggles_and_code() {
+        use crate::ast::{Block, DocumentMeta, SourceFormat};
+
+        let mut doc = Document {
+            source_format: SourceFormat::Markdown,
+            meta: DocumentMeta::default(),
+            content: vec![Block::Paragraph {
+                content: vec![
+                    Inline::Text {
+                        content: "\"a\" -- ".to_string(),
+                    },
+                    Inline::Code {
+                        content: "x--y \"z\"".to_string(),
+                        language: None,
+                    },
+                ],
+                span: None,
+            }],
+            raw_source: None,
+        };
+        Typography {
+            quotes: false,
+            ..Default::default()
+        }
+        .apply(&mut doc)
+        .unwrap();
+
+        let Block::Paragraph { content, .. } = &doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(&content[0], Inline::Text { content } if content == "\"a\" – "));
+        assert!(matches!(&content[1], Inline::Code { content, .. } if content == "x--y \"z\""));
+    }
+}
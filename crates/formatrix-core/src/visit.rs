@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! AST traversal helpers
+//!
+//! Depth-first, document-order walks over blocks and inline sequences.
+//! Transforms and analyses use these instead of re-implementing the
+//! recursion over every container variant.
+
+use crate::ast::{Block, Inline};
+
+/// Visit every block (pre-order), including blocks nested in containers
+pub fn walk_blocks<'a>(blocks: &'a [Block], f: &mut impl FnMut(&'a Block)) {
+    for block in blocks {
+        f(block);
+        for children in child_blocks(block) {
+            walk_blocks(children, f);
+        }
+    }
+}
+
+/// Mutable variant of [`walk_blocks`]; children are visited after `f` runs
+pub fn walk_blocks_mut(blocks: &mut [Block], f: &mut impl FnMut(&mut Block)) {
+    for block in blocks {
+        f(block);
+        for children in child_blocks_mut(block) {
+            walk_blocks_mut(children, f);
+        }
+    }
+}
+
+/// Visit every inline element (pre-order) in a block tree
+pub fn walk_inlines<'a>(blocks: &'a [Block], f: &mut impl FnMut(&'a Inline)) {
+    walk_blocks(blocks, &mut |block| {
+        for inlines in block_inlines(block) {
+            walk_inline_tree(inlines, f);
+        }
+    });
+}
+
+/// Visit every inline sequence in a block tree, mutably
+///
+/// `f` sees each sequence once (paragraph content, heading text, table
+/// cells, captions, and the children of emphasis/links/etc.), so it can
+/// split or replace elements in place.
+pub fn walk_inline_lists_mut(blocks: &mut [Block], f: &mut impl FnMut(&mut Vec<Inline>)) {
+    walk_blocks_mut(blocks, &mut |block| {
+        for inlines in block_inlines_mut(block) {
+            walk_inline_list_tree_mut(inlines, f);
+        }
+    });
+}
+
+/// Visit every inline element in an inline sequence (pre-order)
+pub fn walk_inline_tree<'a>(inlines: &'a [Inline], f: &mut impl FnMut(&'a Inline)) {
+    for inline in inlines {
+        f(inline);
+        if let Some(children) = inline_children(inline) {
+            walk_inline_tree(children, f);
+        }
+    }
+}
+
+fn walk_inline_list_tree_mut(inlines: &mut Vec<Inline>, f: &mut impl FnMut(&mut Vec<Inline>)) {
+    f(inlines);
+    for inline in inlines.iter_mut() {
+        if let Some(children) = inline_children_mut(inline) {
+            walk_inline_list_tree_mut(children, f);
+        }
+    }
+}
+
+/// Direct child block sequences of a block
+pub fn child_blocks(block: &Block) -> Vec<&[Block]> {
+    match block {
+        Block::BlockQuote { content, .. }
+        | Block::Admonition { content, .. }
+        | Block::FootnoteDefinition { content, .. }
+        | Block::Figure { content, .. }
+        | Block::Details { content, .. }
+        | Block::Aside { content, .. }
+        | Block::Language { content, .. } => vec![content.as_slice()],
+        Block::List { items, .. } => items.iter().map(|i| i.content.as_slice()).collect(),
+        Block::DefinitionList { items, .. } => items.iter().map(|(_, d)| d.as_slice()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Direct child block sequences of a block, mutably
+pub fn child_blocks_mut(block: &mut Block) -> Vec<&mut Vec<Block>> {
+    match block {
+        Block::BlockQuote { content, .. }
+        | Block::Admonition { content, .. }
+        | Block::FootnoteDefinition { content, .. }
+        | Block::Figure { content, .. }
+        | Block::Details { content, .. }
+        | Block::Aside { content, .. }
+        | Block::Language { content, .. } => vec![content],
+        Block::List { items, .. } => items.iter_mut().map(|i| &mut i.content).collect(),
+        Block::DefinitionList { items, .. } => items.iter_mut().map(|(_, d)| d).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Inline sequences owned directly by a block (not by its child blocks)
+pub fn block_inlines(block: &Block) -> Vec<&[Inline]> {
+    match block {
+        Block::Paragraph { content, .. }
+        | Block::Heading { content, .. }
+        | Block::Details {
+            summary: content, ..
+        } => vec![content.as_slice()],
+        Block::Table { headers, rows, .. } => headers
+            .iter()
+            .chain(rows.iter().flatten())
+            .map(|cell| cell.as_slice())
+            .collect(),
+        Block::DefinitionList { items, .. } => items.iter().map(|(t, _)| t.as_slice()).collect(),
+        Block::LineBlock { lines, .. } => lines.iter().map(|l| l.as_slice()).collect(),
+        Block::Admonition { title, .. } | Block::Aside { title, .. } => {
+            title.iter().map(|t| t.as_slice()).collect()
+        }
+        Block::Figure { caption, .. } => caption.iter().map(|c| c.as_slice()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Inline sequences owned directly by a block, mutably
+pub fn block_inlines_mut(block: &mut Block) -> Vec<&mut Vec<Inline>> {
+    match block {
+        Block::Paragraph { content, .. }
+        | Block::Heading { content, .. }
+        | Block::Details {
+            summary: content, ..
+        } => vec![content],
+        Block::Table { headers, rows, .. } => headers
+            .iter_mut()
+            .chain(rows.iter_mut().flatten())
+            .collect(),
+        Block::DefinitionList { items, .. } => items.iter_mut().map(|(t, _)| t).collect(),
+        Block::LineBlock { lines, .. } => lines.iter_mut().collect(),
+        Block::Admonition { title, .. } | Block::Aside { title, .. } => title.iter_mut().collect(),
+        Block::Figure { caption, .. } => caption.iter_mut().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Child inlines of a container inline (emphasis, links, etc.)
+pub fn inline_children(inline: &Inline) -> Option<&[Inline]> {
+    match inline {
+        Inline::Emphasis { content }
+        | Inline::Strong { content }
+        | Inline::Link { content, .. }
+        | Inline::Strikethrough { content }
+        | Inline::Superscript { content }
+        | Inline::Subscript { content }
+        | Inline::Language { content, .. } => Some(content),
+        _ => None,
+    }
+}
+
+/// Child inlines of a container inline, mutably
+pub fn inline_children_mut(inline: &mut Inline) -> Option<&mut Vec<Inline>> {
+    match inline {
+        Inline::Emphasis { content }
+        | Inline::Strong { content }
+        | Inline::Link { content, .. }
+        | Inline::Strikethrough { content }
+        | Inline::Superscript { content }
+        | Inline::Subscript { content }
+        | Inline::Language { content, .. } => Some(content),
+        _ => None,
+    }
+}
+
+/// Concatenate the visible text of an inline sequence
+///
+/// Formatting is dropped; soft and hard breaks become spaces.
+pub fn inlines_to_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    walk_inline_tree(inlines, &mut |inline| match inline {
+        Inline::Text { content } | Inline::Code { content, .. } | Inline::Math { content } => {
+            text.push_str(content)
+        }
+        Inline::Image { alt, .. } => text.push_str(alt),
+        Inline::Ruby { base, .. } => text.push_str(base),
+        Inline::LineBreak | Inline::SoftBreak => text.push(' '),
+        _ => {}
+    });
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text {
+            content: s.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_walk_reaches_nested_inlines() {
+        let blocks = vec![Block::BlockQuote {
+            content: vec![Block::Paragraph {
+                content: vec![
+                    text("a "),
+                    Inline::Strong {
+                        content: vec![text("b")],
+                    },
+                ],
+                span: None,
+            }],
+            span: None,
+        }];
+
+        let mut seen = Vec::new();
+        walk_inlines(&blocks, &mut |i| {
+            if let Inline::Text { content } = i {
+                seen.push(content.clone());
+            }
+        });
+        assert_eq!(seen, vec!["a ", "b"]);
+    }
+
+    #[test]
+    fn test_inlines_to_text() {
+        let inlines = vec![
+            text("Hello"),
+            Inline::SoftBreak,
+            Inline::Emphasis {
+                content: vec![text("world")],
+            },
+        ];
+        assert_eq!(inlines_to_text(&inlines), "Hello world");
+    }
+}
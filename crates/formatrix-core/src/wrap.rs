@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Prose line wrapping for renderers
+//!
+//! [`fill`] breaks rendered paragraph text at spaces so that no line is
+//! longer than [`RenderConfig::line_width`] characters. Renderers pass the
+//! byte ranges of constructs that must stay on one line (code spans,
+//! links) as protected; code blocks, tables and other preformatted blocks
+//! are simply never passed to it. Existing line breaks are kept, and lines
+//! that already fit are left untouched.
+//!
+//! Words longer than the width are not split, so a line may still overflow
+//! when it holds a single long word or protected range.
+//!
+//! [`RenderConfig::line_width`]: crate::traits::RenderConfig::line_width
+
+use std::ops::Range;
+
+/// Wrap `text` to `width` characters (0 = no wrap)
+///
+/// Lines broken by wrapping continue with `continuation` (e.g. a list
+/// indent or a `> ` quote marker), which counts towards the width.
+pub fn fill(text: &str, width: usize, protected: &[Range<usize>], continuation: &str) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+    let continued_width = width.saturating_sub(continuation.chars().count()).max(1);
+
+    let mut out = String::with_capacity(text.len());
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let mut start = 0;
+        let mut limit = width;
+        while let Some((end, resume)) = break_point(&line[start..], limit, |i| {
+            protected.iter().any(|r| r.contains(&(offset + start + i)))
+        }) {
+            out.push_str(&line[start..start + end]);
+            out.push('\n');
+            out.push_str(continuation);
+            start += resume;
+            limit = continued_width;
+        }
+        out.push_str(&line[start..]);
+        offset += line.len();
+    }
+    out
+}
+
+/// Where to break `line` so it fits in `limit` characters: the end of the
+/// text kept on this line and the start of the rest
+fn break_point(
+    line: &str,
+    limit: usize,
+    protected: impl Fn(usize) -> bool,
+) -> Option<(usize, usize)> {
+    let content = line.strip_suffix('\n').unwrap_or(line);
+    if content.chars().count() <= limit {
+        return None;
+    }
+
+    // Last space that keeps the line within the limit, or failing that the
+    // first one after it
+    let mut best = None;
+    let mut seen_word = false;
+    for (col, (i, c)) in content.char_indices().enumerate() {
+        if c != ' ' {
+            seen_word = true;
+            continue;
+        }
+        if !seen_word || protected(i) {
+            continue;
+        }
+        if col > limit && best.is_some() {
+            break;
+        }
+        best = Some(i);
+        if col > limit {
+            break;
+        }
+    }
+
+    let space = best?;
+    let end = content[..space].trim_end_matches(' ').len();
+    let resume = space + content[space..].len() - content[space..].trim_start_matches(' ').len();
+    (resume < content.len()).then_some((end, resume))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            fill(text, 15, &[], ""),
+            "The quick brown\nfox jumps over\nthe lazy dog"
+        );
+        assert_eq!(fill(text, 0, &[], ""), text);
+        assert_eq!(fill(text, 80, &[], ""), text);
+
+        // Existing breaks are kept and continuation lines are prefixed
+        assert_eq!(
+            fill("short\nand then a longer line", 12, &[], "> "),
+            "short\nand then a\n> longer\n> line"
+        );
+    }
+
+    #[test]
+    fn test_fill_protected() {
+        let text = "see `a b c d` now";
+        let code = 4..13;
+        assert_eq!(fill(text, 8, &[code], ""), "see\n`a b c d`\nnow");
+
+        // A long word overflows rather than being split
+        assert_eq!(
+            fill("a supercalifragilistic word", 5, &[], ""),
+            "a\nsupercalifragilistic\nword"
+        );
+    }
+}
@@ -0,0 +1,455 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Users, ownership and share grants
+//!
+//! A document's owner can read, write, delete and share it. A
+//! [`ShareGrant`] gives another user read or write access to a `Shared` or
+//! `Public` document; grants on `Private` documents are kept but have no
+//! effect until the document is shared again. Everyone can read `Public`
+//! documents. Documents without an owner predate ownership and stay open to
+//! everyone.
+//!
+//! The `*_as` methods enforce these rules for the acting user, and
+//! [`DocumentQuery::with_reader`] applies them to any query. Documents the
+//! user cannot read are reported as [`DbError::NotFound`], so their
+//! existence is not revealed; other refusals are
+//! [`DbError::PermissionDenied`].
+
+use crate::audit::AuditAction;
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{
+    document_id, DocumentRef, SaveOptions, SearchResult, StoredDocument, Visibility, DOCUMENTS,
+    DOCUMENT_VERSIONS, GRANTS, TAGS, USERS,
+};
+use crate::page::{Page, PageRequest};
+use crate::query::DocumentQuery;
+use crate::search::SearchOptions;
+use crate::transaction::Transaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// AQL filter over the loop variable `d` for documents `@user` may read;
+/// needs the `@@grants` bind variable
+pub(crate) const READABLE_FILTER: &str = "FILTER d.owner == null OR d.owner == @user \
+     OR d.visibility == 'public' \
+     OR (d.visibility == 'shared' AND LENGTH(FOR g IN @@grants \
+         FILTER g.document == d._key AND g.user == @user LIMIT 1 RETURN 1) > 0)";
+
+/// A library user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    /// User name, unique
+    #[serde(rename = "_key")]
+    pub key: String,
+    pub display_name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl User {
+    pub fn new(key: impl Into<String>, display_name: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            display_name: display_name.into(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Level of access to a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Access to one document granted to one user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareGrant {
+    /// Document key
+    pub document: String,
+    /// User key
+    pub user: String,
+    pub access: Access,
+    pub granted_at: DateTime<Utc>,
+}
+
+/// Whether `user` owns `doc`; everyone owns a document without an owner
+pub fn is_owner(doc: &StoredDocument, user: &str) -> bool {
+    doc.owner.as_deref().is_none_or(|owner| owner == user)
+}
+
+/// The access `user` has to `doc`, given their grant on it
+pub fn access_for(doc: &StoredDocument, user: &str, grant: Option<Access>) -> Option<Access> {
+    if is_owner(doc, user) {
+        return Some(Access::Write);
+    }
+    match doc.visibility {
+        Visibility::Private => None,
+        Visibility::Shared => grant,
+        Visibility::Public => grant.max(Some(Access::Read)),
+    }
+}
+
+impl FormatrixDb {
+    pub async fn create_user(&self, user: &User) -> Result<()> {
+        self.insert_query::<Value>(
+            "INSERT @user INTO @@users",
+            HashMap::from([
+                ("user", serde_json::to_value(user)?),
+                ("@users", json!(USERS)),
+            ]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_user(&self, key: &str) -> Result<User> {
+        self.query(
+            "RETURN DOCUMENT(@id)",
+            HashMap::from([("id", json!(format!("{}/{}", USERS, key)))]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .flatten()
+        .ok_or_else(|| DbError::NotFound {
+            key: key.to_string(),
+        })
+    }
+
+    /// All users, by key
+    pub async fn list_users(&self) -> Result<Vec<User>> {
+        self.query(
+            "FOR u IN @@users SORT u._key RETURN u",
+            HashMap::from([("@users", json!(USERS))]),
+        )
+        .await
+    }
+
+    /// The document with `key` and the grant `user` holds on it
+    async fn document_with_grant(
+        &self,
+        user: &str,
+        key: &str,
+    ) -> Result<(StoredDocument, Option<Access>)> {
+        let rows = self
+            .query(DOCUMENT_WITH_GRANT, grant_vars(user, key))
+            .await?;
+        with_grant(rows, key)
+    }
+
+    /// The document with `key`, if `user` may read it
+    pub async fn get_document_as(&self, user: &str, key: &str) -> Result<StoredDocument> {
+        let (doc, grant) = self.document_with_grant(user, key).await?;
+        if access_for(&doc, user, grant).is_none() {
+            return Err(DbError::NotFound {
+                key: key.to_string(),
+            });
+        }
+        self.audit(Some(user), key, AuditAction::Read, None).await;
+        Ok(doc)
+    }
+
+    /// Save a document as `user`
+    ///
+    /// New documents are owned by `user`. Updating needs write access, and
+    /// only the owner may change the owner or visibility; a document without
+    /// an owner keeps the stored one.
+    pub async fn save_document_as(&self, user: &str, doc: &StoredDocument) -> Result<DocumentRef> {
+        let saved = self.save_checked(user, doc).await?;
+        self.audit(Some(user), &saved.key, AuditAction::Save, None)
+            .await;
+        Ok(saved)
+    }
+
+    /// [`FormatrixDb::save_document_as`] without the audit record
+    ///
+    /// The access check and the save run in one transaction, so a grant
+    /// revoked or an owner changed in between is not missed.
+    async fn save_checked(&self, user: &str, doc: &StoredDocument) -> Result<DocumentRef> {
+        let trx = self
+            .begin_transaction(&[DOCUMENTS, TAGS, DOCUMENT_VERSIONS, GRANTS])
+            .await?;
+        let result = trx.save_checked(user, doc).await;
+        let saved = trx.finish(result).await?;
+        #[cfg(feature = "git-history")]
+        self.record_git_save(&saved.key).await;
+        Ok(saved)
+    }
+
+    /// Delete a document as `user`, who must own it
+    pub async fn delete_document_as(&self, user: &str, key: &str) -> Result<()> {
+        self.require_owner(user, key).await?;
+        self.delete_document(key).await?;
+        self.audit(Some(user), key, AuditAction::Delete, None).await;
+        Ok(())
+    }
+
+    /// Unarchived documents `user` may read, most recent first
+    pub async fn get_recent_as(
+        &self,
+        user: &str,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        self.page_documents(
+            &format!("{READABLE_FILTER} FILTER d.archived != true"),
+            "d.updated_at DESC",
+            HashMap::from([("user", json!(user)), ("@grants", json!(GRANTS))]),
+            page,
+        )
+        .await
+    }
+
+    /// One page of the documents matching `query` that `user` may read
+    pub async fn find_documents_as(
+        &self,
+        user: &str,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let query = query.clone().with_reader(user);
+        self.find_documents(&query, page).await
+    }
+
+    /// Documents in one format that `user` may read, most recent first
+    pub async fn get_by_format_as(
+        &self,
+        user: &str,
+        format: &str,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let query = DocumentQuery::new().with_format(format);
+        self.find_documents_as(user, &query, page).await
+    }
+
+    /// Documents carrying all of `tags` that `user` may read, most recent
+    /// first
+    pub async fn search_by_tags_as(
+        &self,
+        user: &str,
+        tags: &[String],
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let query = DocumentQuery::new().with_all_tags(tags);
+        self.find_documents_as(user, &query, page).await
+    }
+
+    /// [`FormatrixDb::search_by_tag_prefix`] over documents `user` may read
+    pub async fn search_by_tag_prefix_as(
+        &self,
+        user: &str,
+        prefix: &str,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        self.tag_prefix_page(prefix, Some(user), page).await
+    }
+
+    /// [`FormatrixDb::search_fulltext_with`] over documents `user` may read
+    pub async fn search_fulltext_as(
+        &self,
+        user: &str,
+        query: &str,
+        options: &SearchOptions,
+        page: PageRequest,
+    ) -> Result<Page<SearchResult>> {
+        self.search_page(query, options, Some(user), page).await
+    }
+
+    /// The document with `key`, if `user` owns it
+    pub(crate) async fn require_owner(&self, user: &str, key: &str) -> Result<StoredDocument> {
+        let (doc, grant) = self.document_with_grant(user, key).await?;
+        if is_owner(&doc, user) {
+            Ok(doc)
+        } else if access_for(&doc, user, grant).is_some() {
+            Err(DbError::PermissionDenied {
+                key: key.to_string(),
+            })
+        } else {
+            Err(DbError::NotFound {
+                key: key.to_string(),
+            })
+        }
+    }
+
+    /// Give `grantee` `access` to a document owned by `user`, replacing any
+    /// earlier grant
+    pub async fn grant_access(
+        &self,
+        user: &str,
+        key: &str,
+        grantee: &str,
+        access: Access,
+    ) -> Result<()> {
+        self.require_owner(user, key).await?;
+        self.get_user(grantee).await?;
+        let grant = ShareGrant {
+            document: key.to_string(),
+            user: grantee.to_string(),
+            access,
+            granted_at: Utc::now(),
+        };
+        self.query::<Value>(
+            "UPSERT { document: @grant.document, user: @grant.user } \
+             INSERT @grant UPDATE @grant IN @@grants",
+            HashMap::from([
+                ("grant", serde_json::to_value(&grant)?),
+                ("@grants", json!(GRANTS)),
+            ]),
+        )
+        .await?;
+        self.audit(Some(user), key, AuditAction::Grant, Some(grantee))
+            .await;
+        Ok(())
+    }
+
+    /// Withdraw the grant `grantee` holds on a document owned by `user`
+    pub async fn revoke_access(&self, user: &str, key: &str, grantee: &str) -> Result<()> {
+        self.require_owner(user, key).await?;
+        self.query::<Value>(
+            "FOR g IN @@grants FILTER g.document == @key AND g.user == @grantee \
+             REMOVE g IN @@grants",
+            HashMap::from([
+                ("key", json!(key)),
+                ("grantee", json!(grantee)),
+                ("@grants", json!(GRANTS)),
+            ]),
+        )
+        .await?;
+        self.audit(Some(user), key, AuditAction::Revoke, Some(grantee))
+            .await;
+        Ok(())
+    }
+
+    /// Grants on a document owned by `user`
+    pub async fn list_grants(&self, user: &str, key: &str) -> Result<Vec<ShareGrant>> {
+        self.require_owner(user, key).await?;
+        self.query(
+            "FOR g IN @@grants FILTER g.document == @key SORT g.user \
+             RETURN UNSET(g, '_key', '_id', '_rev')",
+            HashMap::from([("key", json!(key)), ("@grants", json!(GRANTS))]),
+        )
+        .await
+    }
+}
+
+/// The document `@id` and the access `@user` is granted to it, or null
+const DOCUMENT_WITH_GRANT: &str = "LET d = DOCUMENT(@id) \
+     RETURN d == null ? null : { doc: d, grant: FIRST(FOR g IN @@grants \
+         FILTER g.document == @key AND g.user == @user RETURN g.access) }";
+
+fn grant_vars<'a>(user: &str, key: &str) -> HashMap<&'a str, Value> {
+    HashMap::from([
+        ("id", json!(document_id(key))),
+        ("key", json!(key)),
+        ("user", json!(user)),
+        ("@grants", json!(GRANTS)),
+    ])
+}
+
+/// A row of [`DOCUMENT_WITH_GRANT`]
+#[derive(Deserialize)]
+struct GrantRow {
+    doc: StoredDocument,
+    grant: Option<Access>,
+}
+
+fn with_grant(rows: Vec<Option<GrantRow>>, key: &str) -> Result<(StoredDocument, Option<Access>)> {
+    let row = rows
+        .into_iter()
+        .next()
+        .flatten()
+        .ok_or_else(|| DbError::NotFound {
+            key: key.to_string(),
+        })?;
+    Ok((row.doc, row.grant))
+}
+
+impl Transaction {
+    /// [`FormatrixDb::save_document_as`] inside the transaction, without
+    /// the audit record
+    async fn save_checked(&self, user: &str, doc: &StoredDocument) -> Result<DocumentRef> {
+        let owned = || {
+            let mut doc = doc.clone();
+            doc.owner = Some(user.to_string());
+            doc
+        };
+        let Some(key) = &doc.key else {
+            return self
+                .save_document_with(&owned(), SaveOptions::default())
+                .await;
+        };
+        let rows = self
+            .query(DOCUMENT_WITH_GRANT, grant_vars(user, key))
+            .await?;
+        let (stored, grant) = match with_grant(rows, key) {
+            Ok(found) => found,
+            // Saving under a fresh key creates the document
+            Err(DbError::NotFound { .. }) => {
+                return self
+                    .save_document_with(&owned(), SaveOptions::default())
+                    .await;
+            }
+            Err(err) => return Err(err),
+        };
+        match access_for(&stored, user, grant) {
+            None => Err(DbError::NotFound { key: key.clone() }),
+            Some(Access::Read) => Err(DbError::PermissionDenied { key: key.clone() }),
+            Some(Access::Write) => {
+                let mut doc = doc.clone();
+                // Leaving the owner out keeps the stored one
+                if doc.owner.is_none() {
+                    doc.owner = stored.owner.clone();
+                }
+                if !is_owner(&stored, user)
+                    && (doc.owner != stored.owner || doc.visibility != stored.visibility)
+                {
+                    return Err(DbError::PermissionDenied { key: key.clone() });
+                }
+                self.save_document_with(&doc, SaveOptions::default()).await
+            }
+        }
+    }
+
+    /// Drop every grant on the document with `key`
+    pub(crate) async fn remove_grants(&self, key: &str) -> Result<()> {
+        self.query::<Value>(
+            "FOR g IN @@grants FILTER g.document == @key REMOVE g IN @@grants",
+            HashMap::from([("key", json!(key)), ("@grants", json!(GRANTS))]),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_for() {
+        let doc = StoredDocument::new("Plan", "", "md").with_owner("ada");
+        assert_eq!(access_for(&doc, "ada", None), Some(Access::Write));
+        assert_eq!(access_for(&doc, "bob", Some(Access::Write)), None);
+
+        let shared = doc.clone().with_visibility(Visibility::Shared);
+        assert_eq!(access_for(&shared, "bob", None), None);
+        assert_eq!(
+            access_for(&shared, "bob", Some(Access::Read)),
+            Some(Access::Read)
+        );
+
+        let public = doc.with_visibility(Visibility::Public);
+        assert_eq!(access_for(&public, "bob", None), Some(Access::Read));
+        assert_eq!(
+            access_for(&public, "bob", Some(Access::Write)),
+            Some(Access::Write)
+        );
+
+        let legacy = StoredDocument::new("Old", "", "md");
+        assert!(is_owner(&legacy, "bob"));
+    }
+}
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! ArangoDB HTTP API for administration the driver does not cover
+//! (analyzers, views, indexes, bulk import)
+
+use crate::client::DbConfig;
+use crate::error::{DbError, Result};
+use crate::retry::RetryPolicy;
+use reqwest::{Client, Method, StatusCode};
+use serde_json::Value;
+
+/// Request body
+#[derive(Clone)]
+enum Payload<'a> {
+    Json(&'a Value),
+    /// Newline-delimited JSON
+    Lines(String),
+}
+
+/// Authenticated requests against one database's `/_api`
+pub(crate) struct AdminApi {
+    client: Client,
+    base_url: String,
+    username: String,
+    password: String,
+    retry: RetryPolicy,
+}
+
+impl AdminApi {
+    pub(crate) fn new(config: &DbConfig) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(config.retry.timeout)
+                .build()
+                .unwrap_or_default(),
+            base_url: format!(
+                "{}/_db/{}/_api",
+                config.url.trim_end_matches('/'),
+                config.database
+            ),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            retry: config.retry,
+        }
+    }
+
+    /// Send a request; `None` for a 404
+    ///
+    /// `POST` is sent once, since a request that timed out may still have
+    /// been applied; other methods are retried.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Payload<'_>>,
+    ) -> Result<Option<Value>> {
+        let retry = if method == Method::POST {
+            RetryPolicy {
+                max_retries: 0,
+                ..self.retry
+            }
+        } else {
+            self.retry
+        };
+        retry
+            .run(|| self.send_once(method.clone(), path, body.clone()))
+            .await
+    }
+
+    async fn send_once(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Payload<'_>>,
+    ) -> Result<Option<Value>> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .basic_auth(&self.username, Some(&self.password));
+        request = match body {
+            Some(Payload::Json(body)) => request.json(body),
+            Some(Payload::Lines(body)) => request
+                .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+                .body(body),
+            None => request,
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|e| DbError::Connection(e.to_string()))?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| DbError::Connection(e.to_string()))?;
+        if !status.is_success() {
+            let message = body["errorMessage"].as_str().unwrap_or("request failed");
+            return Err(DbError::Query(format!("HTTP {}: {}", status, message)));
+        }
+        Ok(Some(body))
+    }
+
+    pub(crate) async fn get(&self, path: &str) -> Result<Option<Value>> {
+        self.send(Method::GET, path, None).await
+    }
+
+    pub(crate) async fn post(&self, path: &str, body: &Value) -> Result<Value> {
+        self.send(Method::POST, path, Some(Payload::Json(body)))
+            .await?
+            .ok_or_else(|| DbError::Query(format!("{} not found", path)))
+    }
+
+    pub(crate) async fn put(&self, path: &str, body: &Value) -> Result<Value> {
+        self.send(Method::PUT, path, Some(Payload::Json(body)))
+            .await?
+            .ok_or_else(|| DbError::Query(format!("{} not found", path)))
+    }
+
+    /// `None` if nothing was there to delete
+    pub(crate) async fn delete(&self, path: &str) -> Result<Option<Value>> {
+        self.send(Method::DELETE, path, None).await
+    }
+
+    /// POST newline-delimited JSON
+    pub(crate) async fn post_lines(&self, path: &str, body: String) -> Result<Value> {
+        self.send(Method::POST, path, Some(Payload::Lines(body)))
+            .await?
+            .ok_or_else(|| DbError::Query(format!("{} not found", path)))
+    }
+}
@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Library statistics and link graph analytics for a library health view
+//!
+//! [`FormatrixDb::library_stats`] aggregates the whole library in one
+//! query. Orphans and hubs are plain AQL over the `links` edge index.
+//! PageRank is computed here rather than with Pregel, which recent
+//! ArangoDB releases drop: the link graph of a personal library fits in
+//! memory, so the edges are fetched once and ranked with power iteration.
+
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::{key_from_id, StoredDocument, TagInfo, Visibility, DOCUMENTS, LINKS, TAGS};
+use crate::page::{Page, PageRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+
+/// Probability of following a link rather than jumping anywhere
+pub const DEFAULT_DAMPING: f64 = 0.85;
+/// Iteration cap; ranking usually converges well before
+const MAX_ITERATIONS: usize = 100;
+/// Stop once no score moves by more than this
+const TOLERANCE: f64 = 1e-9;
+/// Tags listed in [`LibraryStats::top_tags`]
+pub const TOP_TAGS: usize = 10;
+
+/// Documents created in one month
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonthCount {
+    /// `YYYY-MM`
+    pub month: String,
+    pub count: u64,
+}
+
+/// Totals for a library dashboard
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryStats {
+    pub documents: u64,
+    /// Whitespace-separated words across all content
+    pub words: u64,
+    /// Documents per format id
+    pub by_format: BTreeMap<String, u64>,
+    pub by_visibility: HashMap<Visibility, u64>,
+    /// Documents by month of creation, oldest first
+    pub per_month: Vec<MonthCount>,
+    /// The [`TOP_TAGS`] most used tags
+    pub top_tags: Vec<TagInfo>,
+}
+
+/// Link counts of one document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkCounts {
+    pub key: String,
+    pub title: String,
+    pub inbound: u64,
+    pub outbound: u64,
+}
+
+/// Importance of one document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentRank {
+    pub key: String,
+    pub title: String,
+    /// Scores over the whole library sum to 1
+    pub score: f64,
+}
+
+/// PageRank over `nodes` linked by `edges` (pairs of node indices)
+///
+/// Nodes without outgoing links spread their score over every node, so
+/// the scores always sum to 1.
+pub fn pagerank(nodes: usize, edges: &[(usize, usize)], damping: f64) -> Vec<f64> {
+    if nodes == 0 {
+        return Vec::new();
+    }
+    let n = nodes as f64;
+    let mut out_degree = vec![0usize; nodes];
+    for &(from, _) in edges {
+        out_degree[from] += 1;
+    }
+
+    let mut scores = vec![1.0 / n; nodes];
+    for _ in 0..MAX_ITERATIONS {
+        let dangling: f64 = (0..nodes)
+            .filter(|&i| out_degree[i] == 0)
+            .map(|i| scores[i])
+            .sum();
+        let base = (1.0 - damping) / n + damping * dangling / n;
+        let mut next = vec![base; nodes];
+        for &(from, to) in edges {
+            next[to] += damping * scores[from] / out_degree[from] as f64;
+        }
+        let delta: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+        scores = next;
+        if delta < TOLERANCE {
+            break;
+        }
+    }
+    scores
+}
+
+impl FormatrixDb {
+    /// Document, word, format, visibility, monthly and tag totals
+    pub async fn library_stats(&self) -> Result<LibraryStats> {
+        let stats = self
+            .query(
+                "LET by_format = (FOR d IN @@documents \
+                     COLLECT format = d.format WITH COUNT INTO n RETURN [format, n]) \
+                 LET by_visibility = (FOR d IN @@documents \
+                     COLLECT visibility = NOT_NULL(d.visibility, 'private') WITH COUNT INTO n \
+                     RETURN [visibility, n]) \
+                 LET per_month = (FOR d IN @@documents \
+                     COLLECT month = DATE_FORMAT(d.created_at, '%yyyy-%mm') WITH COUNT INTO n \
+                     SORT month RETURN { month, count: n }) \
+                 LET words = SUM(FOR d IN @@documents LET text = TRIM(d.content) \
+                     RETURN text == '' ? 0 : LENGTH(REGEX_SPLIT(text, @space))) \
+                 LET top_tags = (FOR t IN @@tags SORT t.count DESC, t.name LIMIT @top \
+                     RETURN { name: t.name, count: t.count }) \
+                 RETURN { documents: SUM(by_format[*][1]), words, \
+                          by_format: ZIP(by_format[*][0], by_format[*][1]), \
+                          by_visibility: ZIP(by_visibility[*][0], by_visibility[*][1]), \
+                          per_month, top_tags }",
+                HashMap::from([
+                    ("space", json!(r"\s+")),
+                    ("top", json!(TOP_TAGS)),
+                    ("@documents", json!(DOCUMENTS)),
+                    ("@tags", json!(TAGS)),
+                ]),
+            )
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        Ok(stats)
+    }
+
+    /// Documents with no links to or from them, most recent first
+    pub async fn orphans(&self, page: PageRequest) -> Result<Page<StoredDocument>> {
+        self.page_documents(
+            "FILTER LENGTH(FOR l IN @@links \
+             FILTER l._from == d._id OR l._to == d._id LIMIT 1 RETURN 1) == 0",
+            "d.updated_at DESC",
+            HashMap::from([("@links", json!(LINKS))]),
+            page,
+        )
+        .await
+    }
+
+    /// The `limit` documents with the most links to them
+    pub async fn most_linked(&self, limit: usize) -> Result<Vec<LinkCounts>> {
+        self.query(
+            "FOR d IN @@documents \
+             LET inbound = LENGTH(FOR l IN @@links FILTER l._to == d._id RETURN 1) \
+             LET outbound = LENGTH(FOR l IN @@links FILTER l._from == d._id RETURN 1) \
+             FILTER inbound > 0 \
+             SORT inbound DESC, outbound DESC, d.title LIMIT @limit \
+             RETURN { key: d._key, title: d.title, inbound, outbound }",
+            HashMap::from([
+                ("limit", json!(limit)),
+                ("@documents", json!(DOCUMENTS)),
+                ("@links", json!(LINKS)),
+            ]),
+        )
+        .await
+    }
+
+    /// Every document ranked by PageRank over the links, highest first
+    pub async fn document_ranks(&self, damping: f64) -> Result<Vec<DocumentRank>> {
+        #[derive(Deserialize)]
+        struct Node {
+            key: String,
+            title: String,
+        }
+        #[derive(Deserialize)]
+        struct Edge {
+            from: String,
+            to: String,
+        }
+
+        let nodes: Vec<Node> = self
+            .query(
+                "FOR d IN @@documents RETURN { key: d._key, title: d.title }",
+                HashMap::from([("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+        let edges: Vec<Edge> = self
+            .query(
+                "FOR l IN @@links RETURN { from: l._from, to: l._to }",
+                HashMap::from([("@links", json!(LINKS))]),
+            )
+            .await?;
+
+        let index: HashMap<&str, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.key.as_str(), i))
+            .collect();
+        // Links to deleted documents are ignored
+        let edges: Vec<(usize, usize)> = edges
+            .iter()
+            .filter_map(|edge| {
+                let from = index.get(key_from_id(&edge.from))?;
+                let to = index.get(key_from_id(&edge.to))?;
+                Some((*from, *to))
+            })
+            .collect();
+
+        let scores = pagerank(nodes.len(), &edges, damping);
+        let mut ranks: Vec<DocumentRank> = nodes
+            .into_iter()
+            .zip(scores)
+            .map(|(node, score)| DocumentRank {
+                key: node.key,
+                title: node.title,
+                score,
+            })
+            .collect();
+        ranks.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.title.cmp(&b.title))
+        });
+        Ok(ranks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_library_stats_row() {
+        let stats: LibraryStats = serde_json::from_value(json!({
+            "documents": 3,
+            "words": 120,
+            "by_format": { "md": 2, "org": 1 },
+            "by_visibility": { "private": 2, "public": 1 },
+            "per_month": [{ "month": "2024-05", "count": 3 }],
+            "top_tags": [{ "name": "rust", "count": 2 }]
+        }))
+        .unwrap();
+        assert_eq!(stats.by_format["md"], 2);
+        assert_eq!(stats.by_visibility[&Visibility::Public], 1);
+        assert_eq!(stats.per_month[0].month, "2024-05");
+        assert_eq!(stats.top_tags[0].name, "rust");
+    }
+
+    #[test]
+    fn test_pagerank() {
+        // 0 and 1 both link to 2, which links back to 0; 3 is isolated
+        let scores = pagerank(4, &[(0, 2), (1, 2), (2, 0)], DEFAULT_DAMPING);
+        assert!((scores.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+        assert!(scores[2] > scores[0]);
+        assert!(scores[0] > scores[1]);
+        assert!((scores[1] - scores[3]).abs() < 1e-9);
+
+        assert!(pagerank(0, &[], DEFAULT_DAMPING).is_empty());
+        assert_eq!(pagerank(2, &[], DEFAULT_DAMPING), vec![0.5, 0.5]);
+    }
+}
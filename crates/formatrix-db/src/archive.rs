@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Archived documents
+//!
+//! Archiving hides a document from listings and search without deleting
+//! it: [`crate::DocumentQuery`] leaves archived documents out unless
+//! [`crate::DocumentQuery::including_archived`] is set. Like pinning, archiving is
+//! not an edit and leaves `updated_at` alone, so the age of a document is
+//! still its last real change.
+
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::{stored_time, DocumentRef, StoredDocument, DOCUMENTS};
+use crate::page::{Page, PageRequest};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::collections::HashMap;
+
+impl FormatrixDb {
+    pub async fn set_archived(&self, key: &str, archived: bool) -> Result<DocumentRef> {
+        self.set_flag(key, "archived", archived).await
+    }
+
+    /// Archive every unpinned document last updated before `before`,
+    /// returning how many were archived
+    pub async fn archive_older_than(&self, before: DateTime<Utc>) -> Result<u64> {
+        let archived: Vec<u64> = self
+            .query(
+                "LET n = LENGTH(FOR d IN @@documents \
+                     FILTER d.archived != true AND d.pinned != true \
+                     FILTER d.updated_at < @before \
+                     UPDATE d WITH { archived: true } IN @@documents RETURN 1) \
+                 RETURN n",
+                HashMap::from([
+                    ("before", json!(stored_time::format(&before))),
+                    ("@documents", json!(DOCUMENTS)),
+                ]),
+            )
+            .await?;
+        Ok(archived.into_iter().next().unwrap_or_default())
+    }
+
+    /// Archived documents, most recently updated first
+    pub async fn list_archived(&self, page: PageRequest) -> Result<Page<StoredDocument>> {
+        self.page_documents(
+            "FILTER d.archived == true",
+            "d.updated_at DESC",
+            HashMap::new(),
+            page,
+        )
+        .await
+    }
+}
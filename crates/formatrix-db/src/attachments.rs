@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Attachments
+//!
+//! Files a document refers to, such as images, are stored in `attachments`
+//! next to the document, one record per file with the bytes base64-encoded
+//! in `data`. Names are unique per document, so a document can link to
+//! `diagram.png` and get the file back by that name. Listings leave the
+//! data out; only [`FormatrixDb::get_attachment`] loads it.
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::ATTACHMENTS;
+use crate::transaction::Transaction;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Largest attachment accepted, in bytes
+pub const MAX_ATTACHMENT_SIZE: usize = 16 * 1024 * 1024;
+
+/// Attachment metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Key of the owning document
+    pub document: String,
+    /// File name, unique within the document
+    pub name: String,
+    /// MIME type, e.g. `image/png`
+    pub mime: String,
+    /// Size in bytes
+    pub size: usize,
+    /// Hex SHA-256 of the content
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Reject names that are empty or look like paths
+fn check_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(DbError::Invalid(format!(
+            "attachment name {:?} must be a plain file name",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl FormatrixDb {
+    /// Store `bytes` as the attachment `name` of a document, replacing any
+    /// attachment of that name
+    pub async fn add_attachment(
+        &self,
+        doc_key: &str,
+        name: &str,
+        bytes: &[u8],
+        mime: &str,
+    ) -> Result<Attachment> {
+        check_name(name)?;
+        if bytes.len() > MAX_ATTACHMENT_SIZE {
+            return Err(DbError::Invalid(format!(
+                "attachment is {} bytes, the limit is {}",
+                bytes.len(),
+                MAX_ATTACHMENT_SIZE
+            )));
+        }
+        self.get_document(doc_key).await?;
+
+        let attachment = Attachment {
+            document: doc_key.to_string(),
+            name: name.to_string(),
+            mime: mime.to_string(),
+            size: bytes.len(),
+            sha256: sha256_hex(bytes),
+            created_at: Utc::now(),
+        };
+        let mut record = serde_json::to_value(&attachment)?;
+        record["data"] = json!(STANDARD.encode(bytes));
+        self.query::<Value>(
+            "UPSERT { document: @record.document, name: @record.name } \
+             INSERT @record REPLACE @record IN @@attachments",
+            HashMap::from([("record", record), ("@attachments", json!(ATTACHMENTS))]),
+        )
+        .await?;
+        Ok(attachment)
+    }
+
+    /// Attachments of a document, by name
+    pub async fn list_attachments(&self, doc_key: &str) -> Result<Vec<Attachment>> {
+        self.query(
+            "FOR a IN @@attachments FILTER a.document == @doc SORT a.name \
+             RETURN UNSET(a, '_key', '_id', '_rev', 'data')",
+            HashMap::from([
+                ("doc", json!(doc_key)),
+                ("@attachments", json!(ATTACHMENTS)),
+            ]),
+        )
+        .await
+    }
+
+    /// An attachment's metadata and content
+    pub async fn get_attachment(&self, doc_key: &str, name: &str) -> Result<(Attachment, Vec<u8>)> {
+        #[derive(Deserialize)]
+        struct Row {
+            #[serde(flatten)]
+            attachment: Attachment,
+            data: String,
+        }
+
+        let row: Row = self
+            .query(
+                "FOR a IN @@attachments FILTER a.document == @doc AND a.name == @name \
+                 LIMIT 1 RETURN UNSET(a, '_key', '_id', '_rev')",
+                HashMap::from([
+                    ("doc", json!(doc_key)),
+                    ("name", json!(name)),
+                    ("@attachments", json!(ATTACHMENTS)),
+                ]),
+            )
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::NotFound {
+                key: format!("{}/{}", doc_key, name),
+            })?;
+        let bytes = STANDARD
+            .decode(&row.data)
+            .map_err(|e| DbError::Query(format!("corrupt attachment {}: {}", name, e)))?;
+        Ok((row.attachment, bytes))
+    }
+
+    pub async fn delete_attachment(&self, doc_key: &str, name: &str) -> Result<()> {
+        let removed: Vec<Value> = self
+            .query(
+                "FOR a IN @@attachments FILTER a.document == @doc AND a.name == @name \
+                 REMOVE a IN @@attachments RETURN 1",
+                HashMap::from([
+                    ("doc", json!(doc_key)),
+                    ("name", json!(name)),
+                    ("@attachments", json!(ATTACHMENTS)),
+                ]),
+            )
+            .await?;
+        if removed.is_empty() {
+            return Err(DbError::NotFound {
+                key: format!("{}/{}", doc_key, name),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Transaction {
+    /// Drop every attachment of the document with `key`
+    pub(crate) async fn remove_attachments(&self, key: &str) -> Result<()> {
+        self.query::<Value>(
+            "FOR a IN @@attachments FILTER a.document == @key REMOVE a IN @@attachments",
+            HashMap::from([("key", json!(key)), ("@attachments", json!(ATTACHMENTS))]),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_names() {
+        assert!(check_name("diagram.png").is_ok());
+        assert!(check_name("notes v2.txt").is_ok());
+        for name in ["", "  ", "..", "img/a.png", "a\\b"] {
+            assert!(check_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Access audit log
+//!
+//! With [`crate::DbConfig::audit`] set, every read, save and delete made
+//! through the `*_as` methods, every grant and revocation, and every share
+//! token created, revoked or used is recorded in `audit_log` with who did
+//! it and when. The plain methods don't know who is acting and are not
+//! recorded. A failure to record is logged rather than failing the
+//! operation. [`FormatrixDb::audit_events`] queries the log; it does no
+//! access checks of its own, so expose it to administrators only.
+
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::AUDIT_LOG;
+use crate::page::{Page, PageRequest};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// What was done to a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Read,
+    Save,
+    Delete,
+    Grant,
+    Revoke,
+    CreateShareToken,
+    RevokeShareToken,
+    /// A share token was validated; the holder is not known
+    UseShareToken,
+}
+
+/// One recorded access, in `audit_log`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    #[serde(rename = "_key", default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Key of the document
+    pub document: String,
+    /// Key of the acting user; `None` for share token use
+    pub user: Option<String>,
+    pub action: AuditAction,
+    /// The grantee for grants and revocations, the token id for share
+    /// token events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Which audit events to list; the default lists all of them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Events must be one of these; empty means any
+    #[serde(default)]
+    pub actions: Vec<AuditAction>,
+    /// Only events at or after this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<DateTime<Utc>>,
+    /// Only events before this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<DateTime<Utc>>,
+}
+
+impl AuditQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn for_document(mut self, key: impl Into<String>) -> Self {
+        self.document = Some(key.into());
+        self
+    }
+
+    pub fn by_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn with_actions(mut self, actions: impl IntoIterator<Item = AuditAction>) -> Self {
+        self.actions = actions.into_iter().collect();
+        self
+    }
+
+    /// Only events in `[after, before)`; either end may be open
+    pub fn between(mut self, after: Option<DateTime<Utc>>, before: Option<DateTime<Utc>>) -> Self {
+        self.after = after;
+        self.before = before;
+        self
+    }
+
+    /// The loop over `d` with its filters, and their bind variables
+    fn to_aql(&self) -> (String, HashMap<String, Value>) {
+        let mut aql = "FOR d IN @@audit".to_string();
+        let mut vars = HashMap::from([("@audit".to_string(), json!(AUDIT_LOG))]);
+        let mut filter = |clause: &str, var: &str, value: Value| {
+            aql.push_str(" FILTER ");
+            aql.push_str(clause);
+            vars.insert(var.to_string(), value);
+        };
+        if let Some(document) = &self.document {
+            filter("d.document == @document", "document", json!(document));
+        }
+        if let Some(user) = &self.user {
+            filter("d.user == @user", "user", json!(user));
+        }
+        if !self.actions.is_empty() {
+            filter("d.action IN @actions", "actions", json!(self.actions));
+        }
+        // Compared as timestamps: stored times differ in fractional digits
+        if let Some(after) = self.after {
+            filter(
+                "DATE_TIMESTAMP(d.at) >= DATE_TIMESTAMP(@after)",
+                "after",
+                json!(after),
+            );
+        }
+        if let Some(before) = self.before {
+            filter(
+                "DATE_TIMESTAMP(d.at) < DATE_TIMESTAMP(@before)",
+                "before",
+                json!(before),
+            );
+        }
+        (aql, vars)
+    }
+}
+
+impl FormatrixDb {
+    /// Record `action` on the document with `key` if auditing is on,
+    /// logging failures
+    pub(crate) async fn audit(
+        &self,
+        user: Option<&str>,
+        key: &str,
+        action: AuditAction,
+        detail: Option<&str>,
+    ) {
+        if !self.config().audit {
+            return;
+        }
+        let event = AuditEvent {
+            key: None,
+            document: key.to_string(),
+            user: user.map(str::to_string),
+            action,
+            detail: detail.map(str::to_string),
+            at: Utc::now(),
+        };
+        let result = async {
+            self.insert_query::<Value>(
+                "INSERT @event INTO @@audit",
+                HashMap::from([
+                    ("event", serde_json::to_value(&event)?),
+                    ("@audit", json!(AUDIT_LOG)),
+                ]),
+            )
+            .await
+        }
+        .await;
+        if let Err(err) = result {
+            tracing::warn!("failed to audit {:?} of {}: {}", action, key, err);
+        }
+    }
+
+    /// Audit events matching `query`, newest first
+    pub async fn audit_events(
+        &self,
+        query: &AuditQuery,
+        page: PageRequest,
+    ) -> Result<Page<AuditEvent>> {
+        let (source, vars) = query.to_aql();
+        self.page_query(&source, "d.at DESC, d._key DESC", vars, page)
+            .await
+    }
+
+    /// Delete events from before `before`, returning how many were removed
+    pub async fn purge_audit_events(&self, before: DateTime<Utc>) -> Result<usize> {
+        let removed: Vec<Value> = self
+            .query(
+                "FOR e IN @@audit FILTER DATE_TIMESTAMP(e.at) < DATE_TIMESTAMP(@before) \
+                 REMOVE e IN @@audit RETURN 1",
+                HashMap::from([("before", json!(before)), ("@audit", json!(AUDIT_LOG))]),
+            )
+            .await?;
+        Ok(removed.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_query_to_aql() {
+        let (aql, vars) = AuditQuery::new().to_aql();
+        assert_eq!(aql, "FOR d IN @@audit");
+        assert_eq!(vars.len(), 1);
+
+        let (aql, vars) = AuditQuery::new()
+            .for_document("1")
+            .with_actions([AuditAction::Read, AuditAction::UseShareToken])
+            .between(Some(Utc::now()), None)
+            .to_aql();
+        assert_eq!(
+            aql,
+            "FOR d IN @@audit FILTER d.document == @document FILTER d.action IN @actions \
+             FILTER DATE_TIMESTAMP(d.at) >= DATE_TIMESTAMP(@after)"
+        );
+        assert_eq!(vars["actions"], json!(["read", "use_share_token"]));
+    }
+}
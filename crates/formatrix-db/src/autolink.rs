@@ -0,0 +1,339 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Links derived from document content
+//!
+//! [`FormatrixDb::auto_link`] parses a document with its formatrix-core
+//! handler and links it to the documents its prose refers to: wikilinks
+//! (`[[Title]]`, `[[Title|text]]`, `[[key]]`), links whose URL is another
+//! document's title or key, and plain mentions of other documents' titles.
+//! The edges it makes are [`LinkType::Reference`] links marked
+//! [`DocumentLink::auto`]; running it again adds and removes only those,
+//! leaving curated links alone. Code is not searched, and titles shorter
+//! than [`MIN_MENTION_CHARS`] are only linked through wikilinks.
+//!
+//! Each reference from a document, auto or curated, is mirrored by an auto
+//! [`LinkType::Backlink`] edge from its target, added and removed along
+//! with it. [`FormatrixDb::save_with_linking`] does all of this as part of
+//! a save.
+
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::{
+    document_id, DocumentLink, DocumentRef, LinkType, SaveOptions, StoredDocument, DOCUMENTS,
+    DOCUMENT_VERSIONS, LINKS, TAGS,
+};
+use crate::transaction::Transaction;
+use chrono::Utc;
+use formatrix_core::visit::{block_inlines, walk_blocks, walk_inline_tree};
+use formatrix_core::{Document, FormatRegistry, Inline, ParseConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Shortest title linked by a plain mention
+pub const MIN_MENTION_CHARS: usize = 4;
+
+/// A document that content may refer to
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct LinkTarget {
+    pub key: String,
+    pub title: String,
+}
+
+/// Target keys of the auto links one [`FormatrixDb::auto_link`] changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl LinkChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The targets of the `[[...]]` wikilinks in `text`, without link text or
+/// heading anchors
+pub fn wikilinks(text: &str) -> Vec<&str> {
+    let mut targets = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        // For `[[a [[b]]`, the innermost opening bracket wins
+        let inner = rest[..end].rsplit("[[").next().unwrap_or_default();
+        let target = inner.split(['|', '#']).next().unwrap_or_default().trim();
+        if !target.is_empty() {
+            targets.push(target);
+        }
+        rest = &rest[end + 2..];
+    }
+    targets
+}
+
+/// Whether `text` contains `title` as whole words, ignoring case
+fn mentions(text: &str, title: &str) -> bool {
+    let text = text.to_lowercase();
+    let title = title.to_lowercase();
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    text.match_indices(&title).any(|(start, _)| {
+        !is_word(text[..start].chars().next_back())
+            && !is_word(text[start + title.len()..].chars().next())
+    })
+}
+
+/// The prose of each block of `doc`, and the URLs it links to
+fn prose(doc: &Document) -> (Vec<String>, Vec<String>) {
+    let mut runs = Vec::new();
+    let mut urls = Vec::new();
+    walk_blocks(&doc.content, &mut |block| {
+        for inlines in block_inlines(block) {
+            let mut text = String::new();
+            walk_inline_tree(inlines, &mut |inline| match inline {
+                Inline::Text { content } => text.push_str(content),
+                Inline::LineBreak | Inline::SoftBreak => text.push(' '),
+                Inline::Link { url, .. } => urls.push(url.clone()),
+                _ => {}
+            });
+            runs.push(text);
+        }
+    });
+    (runs, urls)
+}
+
+/// Keys of the `targets` that `doc` refers to, in order of discovery
+///
+/// Content in a format formatrix-core can't parse is searched as plain
+/// text.
+pub(crate) fn references(doc: &StoredDocument, targets: &[LinkTarget]) -> Vec<String> {
+    let parsed = FormatRegistry::global()
+        .find(&doc.format)
+        .and_then(|handler| handler.parse(&doc.content, &ParseConfig::default()).ok());
+    let (runs, urls) = match parsed {
+        Some(parsed) => prose(&parsed),
+        None => (vec![doc.content.clone()], Vec::new()),
+    };
+
+    let named = |name: &str| {
+        let title = name.to_lowercase();
+        targets
+            .iter()
+            .find(|target| target.key == name || target.title.to_lowercase() == title)
+    };
+    let mut keys: Vec<String> = Vec::new();
+    let mut add = |target: &LinkTarget| {
+        if !keys.contains(&target.key) {
+            keys.push(target.key.clone());
+        }
+    };
+    let local_urls = urls
+        .iter()
+        .filter(|url| !url.contains("://"))
+        .map(String::as_str);
+    for name in runs.iter().flat_map(|run| wikilinks(run)).chain(local_urls) {
+        if let Some(target) = named(name) {
+            add(target);
+        }
+    }
+    for target in targets {
+        if target.title.chars().count() >= MIN_MENTION_CHARS
+            && runs.iter().any(|run| mentions(run, &target.title))
+        {
+            add(target);
+        }
+    }
+    keys
+}
+
+impl FormatrixDb {
+    /// Link the document with `key` to the documents its content refers
+    /// to, replacing the auto links from an earlier run, and update the
+    /// backlinks mirroring its references
+    pub async fn auto_link(&self, key: &str) -> Result<LinkChanges> {
+        let doc = self.get_document(key).await?;
+        let trx = self.begin_transaction(&[LINKS]).await?;
+        let result = trx.link_from_content(key, &doc).await;
+        trx.finish(result).await
+    }
+
+    /// Save a document, then update its auto links and the backlinks of
+    /// the documents it refers to, in one transaction
+    pub async fn save_with_linking(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<(DocumentRef, LinkChanges)> {
+        let trx = self
+            .begin_transaction(&[DOCUMENTS, TAGS, DOCUMENT_VERSIONS, LINKS])
+            .await?;
+        let result = async {
+            let saved = trx.save_document_with(doc, options).await?;
+            let changes = trx.link_from_content(&saved.key, doc).await?;
+            Ok((saved, changes))
+        }
+        .await;
+        let (saved, changes) = trx.finish(result).await?;
+        #[cfg(feature = "git-history")]
+        self.record_git_save(&saved.key).await;
+        Ok((saved, changes))
+    }
+
+    /// Bring the auto backlinks of every document in line with its
+    /// references, returning how many documents were checked
+    pub async fn refresh_backlinks(&self) -> Result<usize> {
+        let keys: Vec<String> = self
+            .query(
+                "FOR d IN @@documents RETURN d._key",
+                HashMap::from([("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+        for key in &keys {
+            let trx = self.begin_transaction(&[LINKS]).await?;
+            let result = trx.sync_backlinks(key).await;
+            trx.finish(result).await?;
+        }
+        Ok(keys.len())
+    }
+}
+
+impl Transaction {
+    /// Update the auto links from the document with `key`, whose content
+    /// is `doc`'s, and the backlinks mirroring its references
+    async fn link_from_content(&self, key: &str, doc: &StoredDocument) -> Result<LinkChanges> {
+        let targets: Vec<LinkTarget> = self
+            .query(
+                "FOR d IN @@documents FILTER d._key != @key \
+                 RETURN { key: d._key, title: d.title }",
+                HashMap::from([("key", json!(key)), ("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+        let changes = self.set_auto_links(key, &references(doc, &targets)).await?;
+        self.sync_backlinks(key).await?;
+        Ok(changes)
+    }
+
+    /// Give every reference from the document with `key` an auto backlink
+    /// from its target, and drop the auto backlinks whose reference is gone
+    pub(crate) async fn sync_backlinks(&self, key: &str) -> Result<()> {
+        let vars = || HashMap::from([("id", json!(document_id(key))), ("@links", json!(LINKS))]);
+        self.query::<Value>(
+            "LET referenced = (FOR l IN @@links \
+                 FILTER l._from == @id AND l.link_type == 'reference' RETURN l._to) \
+             FOR l IN @@links \
+                 FILTER l._to == @id AND l.link_type == 'backlink' AND l.auto == true \
+                 FILTER l._from NOT IN referenced \
+                 REMOVE l IN @@links",
+            vars(),
+        )
+        .await?;
+        let mut vars = vars();
+        vars.insert("now", json!(Utc::now()));
+        self.query::<Value>(
+            "LET mirrored = (FOR l IN @@links \
+                 FILTER l._to == @id AND l.link_type == 'backlink' RETURN l._from) \
+             FOR l IN @@links \
+                 FILTER l._from == @id AND l.link_type == 'reference' \
+                 FILTER l._to != @id AND l._to NOT IN mirrored \
+                 COLLECT target = l._to \
+                 INSERT { _from: target, _to: @id, link_type: 'backlink', auto: true, \
+                          created_at: @now } INTO @@links",
+            vars,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Make the auto links from the document with `key` point at exactly
+    /// `referenced`, except where a curated reference already does
+    pub(crate) async fn set_auto_links(
+        &self,
+        key: &str,
+        referenced: &[String],
+    ) -> Result<LinkChanges> {
+        let existing: Vec<DocumentLink> = self
+            .query(
+                "FOR l IN @@links FILTER l._from == @id AND l.link_type == 'reference' RETURN l",
+                HashMap::from([("id", json!(document_id(key))), ("@links", json!(LINKS))]),
+            )
+            .await?;
+        let stale: Vec<&DocumentLink> = existing
+            .iter()
+            .filter(|link| link.auto && !referenced.iter().any(|k| k == link.to_key()))
+            .collect();
+        let added: Vec<&String> = referenced
+            .iter()
+            .filter(|k| !existing.iter().any(|link| link.to_key() == k.as_str()))
+            .collect();
+
+        if !stale.is_empty() {
+            let keys: Vec<&str> = stale.iter().filter_map(|l| l.key.as_deref()).collect();
+            self.query::<Value>(
+                "FOR k IN @keys REMOVE k IN @@links",
+                HashMap::from([("keys", json!(keys)), ("@links", json!(LINKS))]),
+            )
+            .await?;
+        }
+        if !added.is_empty() {
+            let links = added
+                .iter()
+                .map(|to| {
+                    serde_json::to_value(DocumentLink {
+                        auto: true,
+                        ..DocumentLink::new(key, to, LinkType::Reference)
+                    })
+                })
+                .collect::<serde_json::Result<Vec<_>>>()?;
+            self.query::<Value>(
+                "FOR l IN @links INSERT l INTO @@links",
+                HashMap::from([("links", json!(links)), ("@links", json!(LINKS))]),
+            )
+            .await?;
+        }
+        Ok(LinkChanges {
+            added: added.into_iter().cloned().collect(),
+            removed: stale.iter().map(|l| l.to_key().to_string()).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wikilinks() {
+        assert_eq!(
+            wikilinks("See [[Borrowing]], [[ownership rules|the rules]] and [[Traits#Objects]]"),
+            vec!["Borrowing", "ownership rules", "Traits"]
+        );
+        assert_eq!(wikilinks("[[a [[b]] [[]] [[unclosed"), vec!["b"]);
+        assert!(wikilinks("[single] brackets").is_empty());
+    }
+
+    #[test]
+    fn test_references() {
+        let targets = [
+            ("1", "Borrowing"),
+            ("2", "Lifetimes"),
+            ("3", "Go"),
+            ("4", "Traits"),
+            ("5", "Unused"),
+        ]
+        .map(|(key, title)| LinkTarget {
+            key: key.to_string(),
+            title: title.to_string(),
+        });
+        let doc = StoredDocument::new(
+            "Notes",
+            "Read [[go]] and [[4|the traits page]] first.\n\n\
+             Borrowing ties into LIFETIMES; unusedness and go-getters don't count.",
+            "txt",
+        );
+        assert_eq!(references(&doc, &targets), vec!["3", "4", "1", "2"]);
+        assert!(mentions("about Rust's traits.", "traits"));
+        assert!(!mentions("subtraits", "traits"));
+    }
+}
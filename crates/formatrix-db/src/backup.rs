@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Backup and restore
+//!
+//! A backup is JSON lines: a [`BackupHeader`] naming the schema version,
+//! then one `{ "collection": ..., "data": ... }` record per document, link
+//! and tag. [`FormatrixDb::backup`] reads all three collections in one
+//! read transaction, so the snapshot is consistent even while the library
+//! is in use. [`FormatrixDb::restore`] replaces their contents in one write
+//! transaction, so a failed restore leaves the database as it was.
+//!
+//! Version history, notebooks, users, sharing and attachments are not
+//! included.
+
+use crate::bulk::BATCH_SIZE;
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{DOCUMENTS, LINKS, SCHEMA_VERSION, TAGS};
+use crate::transaction::Transaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// Value of [`BackupHeader::format`]
+pub const BACKUP_FORMAT: &str = "formatrix-backup";
+
+/// Collections in a backup, in the order they are written
+const BACKUP_COLLECTIONS: [&str; 3] = [DOCUMENTS, LINKS, TAGS];
+
+/// First line of a backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupHeader {
+    pub format: String,
+    /// [`SCHEMA_VERSION`] of the database the backup was taken from
+    pub schema_version: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl BackupHeader {
+    fn new() -> Self {
+        Self {
+            format: BACKUP_FORMAT.to_string(),
+            schema_version: SCHEMA_VERSION,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Parse a header line, rejecting other files and backups from a
+    /// newer schema
+    fn parse(line: &str) -> Result<Self> {
+        let header: BackupHeader = serde_json::from_str(line)
+            .map_err(|e| DbError::Invalid(format!("not a backup header: {}", e)))?;
+        if header.format != BACKUP_FORMAT {
+            return Err(DbError::Invalid(format!(
+                "unknown backup format {:?}",
+                header.format
+            )));
+        }
+        if header.schema_version > SCHEMA_VERSION {
+            return Err(DbError::Invalid(format!(
+                "backup has schema version {}, this build supports up to {}",
+                header.schema_version, SCHEMA_VERSION
+            )));
+        }
+        Ok(header)
+    }
+}
+
+/// One stored record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BackupRecord {
+    collection: String,
+    data: Value,
+}
+
+/// Records written or restored, per collection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupSummary {
+    pub documents: usize,
+    pub links: usize,
+    pub tags: usize,
+}
+
+impl BackupSummary {
+    fn count(&mut self, collection: &str, n: usize) {
+        match collection {
+            DOCUMENTS => self.documents += n,
+            LINKS => self.links += n,
+            _ => self.tags += n,
+        }
+    }
+}
+
+impl FormatrixDb {
+    /// Write a consistent snapshot of documents, links and tags
+    pub async fn backup<W: Write>(&self, mut writer: W) -> Result<BackupSummary> {
+        let trx = self.begin_read_transaction(&BACKUP_COLLECTIONS).await?;
+        let result = async {
+            serde_json::to_writer(&mut writer, &BackupHeader::new())?;
+            writer.write_all(b"\n")?;
+            let mut summary = BackupSummary::default();
+            for collection in BACKUP_COLLECTIONS {
+                let written = dump_collection(&trx, collection, &mut writer).await?;
+                summary.count(collection, written);
+            }
+            writer.flush()?;
+            Ok(summary)
+        }
+        .await;
+        let summary = trx.finish(result).await?;
+        tracing::info!(
+            "backed up {} documents, {} links and {} tags",
+            summary.documents,
+            summary.links,
+            summary.tags
+        );
+        Ok(summary)
+    }
+
+    /// Replace documents, links and tags with the contents of a backup
+    ///
+    /// Backups from an older schema are accepted and migrated once
+    /// restored; ones from a newer schema are refused with
+    /// [`DbError::Invalid`] before anything changes.
+    pub async fn restore<R: BufRead>(&self, reader: R) -> Result<BackupSummary> {
+        let mut lines = reader.lines();
+        let header = match lines.next() {
+            Some(line) => BackupHeader::parse(&line?)?,
+            None => return Err(DbError::Invalid("backup is empty".to_string())),
+        };
+
+        let trx = self.begin_transaction(&BACKUP_COLLECTIONS).await?;
+        let result = async {
+            for collection in BACKUP_COLLECTIONS {
+                trx.query::<Value>(
+                    "FOR r IN @@collection REMOVE r IN @@collection",
+                    HashMap::from([("@collection", json!(collection))]),
+                )
+                .await?;
+            }
+
+            let mut summary = BackupSummary::default();
+            let mut batches: HashMap<&str, Vec<Value>> = HashMap::new();
+            for line in lines {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: BackupRecord = serde_json::from_str(&line)?;
+                let collection = BACKUP_COLLECTIONS
+                    .into_iter()
+                    .find(|name| *name == record.collection)
+                    .ok_or_else(|| {
+                        DbError::Invalid(format!(
+                            "backup names unknown collection {:?}",
+                            record.collection
+                        ))
+                    })?;
+                let batch = batches.entry(collection).or_default();
+                batch.push(record.data);
+                if batch.len() == BATCH_SIZE {
+                    insert_batch(&trx, collection, std::mem::take(batch)).await?;
+                    summary.count(collection, BATCH_SIZE);
+                }
+            }
+            for (collection, batch) in batches {
+                summary.count(collection, batch.len());
+                insert_batch(&trx, collection, batch).await?;
+            }
+            Ok(summary)
+        }
+        .await;
+        let summary = trx.finish(result).await?;
+        self.set_schema_version(header.schema_version).await?;
+        self.migrate().await?;
+        tracing::info!(
+            "restored {} documents, {} links and {} tags from a backup taken {}",
+            summary.documents,
+            summary.links,
+            summary.tags,
+            header.created_at
+        );
+        Ok(summary)
+    }
+}
+
+/// Write every record of `collection` as backup lines, in key order
+async fn dump_collection<W: Write>(
+    trx: &Transaction,
+    collection: &str,
+    writer: &mut W,
+) -> Result<usize> {
+    let mut after = String::new();
+    let mut written = 0;
+    loop {
+        let batch: Vec<Value> = trx
+            .query(
+                "FOR r IN @@collection FILTER r._key > @after SORT r._key LIMIT @limit \
+                 RETURN UNSET(r, '_id', '_rev')",
+                HashMap::from([
+                    ("@collection", json!(collection)),
+                    ("after", json!(after)),
+                    ("limit", json!(BATCH_SIZE)),
+                ]),
+            )
+            .await?;
+        for data in &batch {
+            serde_json::to_writer(
+                &mut *writer,
+                &json!({ "collection": collection, "data": data }),
+            )?;
+            writer.write_all(b"\n")?;
+        }
+        written += batch.len();
+        match batch.last().and_then(|data| data["_key"].as_str()) {
+            Some(key) if batch.len() == BATCH_SIZE => after = key.to_string(),
+            _ => return Ok(written),
+        }
+    }
+}
+
+async fn insert_batch(trx: &Transaction, collection: &str, records: Vec<Value>) -> Result<()> {
+    trx.query::<Value>(
+        "FOR r IN @records INSERT r INTO @@collection",
+        HashMap::from([
+            ("records", json!(records)),
+            ("@collection", json!(collection)),
+        ]),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_header() {
+        let line = serde_json::to_string(&BackupHeader::new()).unwrap();
+        let header = BackupHeader::parse(&line).unwrap();
+        assert_eq!(header.schema_version, SCHEMA_VERSION);
+
+        let newer = json!({
+            "format": BACKUP_FORMAT,
+            "schema_version": SCHEMA_VERSION + 1,
+            "created_at": Utc::now(),
+        });
+        assert!(BackupHeader::parse(&newer.to_string()).is_err());
+        assert!(BackupHeader::parse(r#"{"collection":"documents","data":{}}"#).is_err());
+    }
+
+    #[test]
+    fn test_backup_record() {
+        let record: BackupRecord =
+            serde_json::from_str(r#"{"collection":"links","data":{"_from":"documents/a"}}"#)
+                .unwrap();
+        assert_eq!(record.collection, LINKS);
+        let mut summary = BackupSummary::default();
+        summary.count(&record.collection, 2);
+        summary.count(DOCUMENTS, 1);
+        assert_eq!((summary.documents, summary.links, summary.tags), (1, 2, 0));
+    }
+}
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Bulk import and export
+//!
+//! Both directions use one JSON document per line, the format of
+//! ArangoDB's `/_api/import` endpoint and of `arangoexport --type jsonl`.
+//! Imports are sent in batches of [`BATCH_SIZE`]; exports walk the
+//! collection in key order with the same batch size. Imported documents
+//! get their content hash like saved ones, and those matching a document
+//! already stored under another key are counted in
+//! [`ImportSummary::duplicates`] and logged.
+
+use crate::client::FormatrixDb;
+use crate::dedup::content_hash;
+use crate::error::{DbError, Result};
+use crate::models::{StoredDocument, DOCUMENTS};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// Documents per import request or export query
+pub const BATCH_SIZE: usize = 1000;
+
+/// Counts reported by an import
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub created: u64,
+    pub updated: u64,
+    pub errors: u64,
+    /// Imported documents with the same content as a stored document of
+    /// another key; see [`FormatrixDb::find_duplicates`]
+    #[serde(default)]
+    pub duplicates: u64,
+    /// Server messages for the rejected documents
+    #[serde(default)]
+    pub details: Vec<String>,
+}
+
+impl ImportSummary {
+    fn merge(&mut self, other: ImportSummary) {
+        self.created += other.created;
+        self.updated += other.updated;
+        self.errors += other.errors;
+        self.duplicates += other.duplicates;
+        self.details.extend(other.details);
+    }
+}
+
+/// Serialise one batch as import lines
+fn to_lines(batch: &[StoredDocument]) -> Result<String> {
+    let mut lines = String::new();
+    for doc in batch {
+        let mut doc = doc.clone();
+        // Revisions belong to the source database
+        doc.rev = None;
+        doc.content_hash = Some(content_hash(&doc.content));
+        lines.push_str(&serde_json::to_string(&doc)?);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
+
+/// Documents from JSON lines, skipping blank lines
+pub fn read_jsonl<R: BufRead>(reader: R) -> impl Iterator<Item = Result<StoredDocument>> {
+    reader.lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(serde_json::from_str(&line).map_err(DbError::from)),
+        Err(err) => Some(Err(err.into())),
+    })
+}
+
+impl FormatrixDb {
+    /// Insert or replace documents in batches
+    ///
+    /// Documents with a key overwrite the stored document of that key.
+    /// Tag counts are recounted afterwards, but imports are not recorded in
+    /// the version history.
+    pub async fn import_documents<I>(&self, docs: I) -> Result<ImportSummary>
+    where
+        I: IntoIterator<Item = StoredDocument>,
+    {
+        self.import_stream(docs.into_iter().map(Ok)).await
+    }
+
+    /// [`FormatrixDb::import_documents`] from JSON lines, e.g. the output
+    /// of [`FormatrixDb::export_all`]
+    ///
+    /// The input is read one batch at a time; a malformed line stops the
+    /// import after the batches before it.
+    pub async fn import_jsonl<R: BufRead>(&self, reader: R) -> Result<ImportSummary> {
+        self.import_stream(read_jsonl(reader)).await
+    }
+
+    async fn import_stream<I>(&self, docs: I) -> Result<ImportSummary>
+    where
+        I: Iterator<Item = Result<StoredDocument>>,
+    {
+        let mut summary = ImportSummary::default();
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        for doc in docs {
+            batch.push(doc?);
+            if batch.len() == BATCH_SIZE {
+                summary.merge(self.import_batch(&batch).await?);
+                batch.clear();
+            }
+        }
+        if !batch.is_empty() {
+            summary.merge(self.import_batch(&batch).await?);
+        }
+        // Replaced documents may have dropped tags, so count from scratch
+        self.recount_tags().await?;
+        tracing::info!(
+            "imported {} new and {} updated documents ({} errors)",
+            summary.created,
+            summary.updated,
+            summary.errors
+        );
+        if summary.duplicates > 0 {
+            tracing::warn!(
+                "{} imported documents duplicate existing ones",
+                summary.duplicates
+            );
+        }
+        Ok(summary)
+    }
+
+    async fn import_batch(&self, batch: &[StoredDocument]) -> Result<ImportSummary> {
+        // Checked first, as the import replaces documents with their key
+        let hashes: Vec<(Option<String>, String)> = batch
+            .iter()
+            .map(|doc| (doc.key.clone(), content_hash(&doc.content)))
+            .collect();
+        let duplicates = self.count_duplicates(&hashes).await?;
+        let response = self
+            .admin()
+            .post_lines(
+                &format!(
+                    "/import?collection={}&type=documents&onDuplicate=replace&details=true",
+                    DOCUMENTS
+                ),
+                to_lines(batch)?,
+            )
+            .await?;
+        let summary: ImportSummary = serde_json::from_value(response)?;
+        Ok(ImportSummary {
+            duplicates,
+            ..summary
+        })
+    }
+
+    /// Write every document as JSON lines, returning how many were written
+    pub async fn export_all<W: Write>(&self, mut writer: W) -> Result<usize> {
+        let started = Utc::now();
+        let mut after = String::new();
+        let mut written = 0;
+        loop {
+            let batch: Vec<StoredDocument> = self
+                .query(
+                    "FOR d IN @@documents FILTER d._key > @after SORT d._key \
+                     LIMIT @limit RETURN d",
+                    HashMap::from([
+                        ("@documents", json!(DOCUMENTS)),
+                        ("after", json!(after)),
+                        ("limit", json!(BATCH_SIZE)),
+                    ]),
+                )
+                .await?;
+            for doc in &batch {
+                serde_json::to_writer(&mut writer, doc)?;
+                writer.write_all(b"\n")?;
+            }
+            written += batch.len();
+            match batch.last().and_then(|doc| doc.key.clone()) {
+                Some(key) if batch.len() == BATCH_SIZE => after = key,
+                _ => break,
+            }
+        }
+        writer.flush()?;
+        tracing::info!(
+            "exported {} documents in {}ms",
+            written,
+            (Utc::now() - started).num_milliseconds()
+        );
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let mut doc = StoredDocument::new("Notes", "# Notes", "md").with_tags(["a"]);
+        doc.key = Some("notes".to_string());
+        doc.rev = Some("_abc".to_string());
+
+        let lines = to_lines(&[doc.clone(), doc.clone()]).unwrap();
+        assert_eq!(lines.lines().count(), 2);
+
+        let read: Vec<StoredDocument> = read_jsonl(format!("{}\n\n", lines).as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].key.as_deref(), Some("notes"));
+        assert_eq!(read[0].rev, None);
+        assert_eq!(read[0].content_hash, Some(content_hash("# Notes")));
+        assert_eq!(read[0].tags, doc.tags);
+    }
+
+    #[test]
+    fn test_import_summary() {
+        let mut summary: ImportSummary = serde_json::from_value(json!({
+            "error": false,
+            "created": 2,
+            "errors": 1,
+            "empty": 0,
+            "updated": 0,
+            "ignored": 0,
+            "details": ["at position 1: unique constraint violated"]
+        }))
+        .unwrap();
+        summary.merge(ImportSummary {
+            updated: 3,
+            ..Default::default()
+        });
+        assert_eq!(
+            (summary.created, summary.updated, summary.errors),
+            (2, 3, 1)
+        );
+        assert_eq!(summary.details.len(), 1);
+    }
+}
@@ -0,0 +1,407 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Read-through cache
+//!
+//! [`CachedStore`] wraps any [`DocumentStore`] and keeps the most recently
+//! read documents, links and backlinks, so the GUI re-reading the current
+//! document and its backlinks doesn't go to the server each time. Writes
+//! through the wrapper invalidate what they touch; a read that raced with a
+//! write is not cached. Changes made by other clients are only seen once
+//! an entry is evicted or [`CachedStore::clear`] is called.
+
+use crate::error::Result;
+use crate::models::{
+    DocumentLink, DocumentRef, SaveOptions, SearchResult, StoredDocument, TagInfo,
+};
+use crate::page::{Page, PageRequest};
+use crate::query::DocumentQuery;
+use crate::store::DocumentStore;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Entries kept per cache by [`CachedStore::new`]
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// Least-recently-used map from keys to values
+#[derive(Debug)]
+struct Lru<V> {
+    capacity: usize,
+    entries: HashMap<String, (V, u64)>,
+    /// Keys by last use
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<V> {
+        let (value, used) = self.entries.get_mut(key)?;
+        self.tick += 1;
+        let key = self.order.remove(used)?;
+        *used = self.tick;
+        self.order.insert(self.tick, key);
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: &str, value: V) {
+        self.remove(key);
+        if self.entries.len() == self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key.to_string(), (value, self.tick));
+        self.order.insert(self.tick, key.to_string());
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((_, used)) = self.entries.remove(key) {
+            self.order.remove(&used);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// A [`DocumentStore`] with documents and links cached in memory
+pub struct CachedStore<S> {
+    inner: S,
+    documents: Mutex<Lru<StoredDocument>>,
+    links: Mutex<Lru<Vec<DocumentLink>>>,
+    backlinks: Mutex<Lru<Vec<DocumentLink>>>,
+    /// Bumped before and after every write, so reads that overlap one
+    /// aren't cached
+    writes: AtomicU64,
+}
+
+fn lock<V>(cache: &Mutex<Lru<V>>) -> MutexGuard<'_, Lru<V>> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<S: DocumentStore> CachedStore<S> {
+    /// Cache up to [`DEFAULT_CAPACITY`] entries of each kind
+    pub fn new(inner: S) -> Self {
+        Self::with_capacity(inner, DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(inner: S, capacity: usize) -> Self {
+        Self {
+            inner,
+            documents: Mutex::new(Lru::new(capacity)),
+            links: Mutex::new(Lru::new(capacity)),
+            backlinks: Mutex::new(Lru::new(capacity)),
+            writes: AtomicU64::new(0),
+        }
+    }
+
+    /// The wrapped store; writes made through it bypass invalidation
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Drop every cached entry
+    pub fn clear(&self) {
+        lock(&self.documents).clear();
+        self.forget_links();
+    }
+
+    fn forget_links(&self) {
+        lock(&self.links).clear();
+        lock(&self.backlinks).clear();
+    }
+
+    /// Read `key` from `cache`, or through `load`, caching the result
+    /// unless a write started meanwhile
+    async fn read_through<V: Clone>(
+        &self,
+        cache: &Mutex<Lru<V>>,
+        key: &str,
+        load: impl Future<Output = Result<V>>,
+    ) -> Result<V> {
+        if let Some(value) = lock(cache).get(key) {
+            return Ok(value);
+        }
+        let writes = self.writes.load(Ordering::SeqCst);
+        let value = load.await?;
+        let mut cache = lock(cache);
+        if self.writes.load(Ordering::SeqCst) == writes {
+            cache.insert(key, value.clone());
+        }
+        Ok(value)
+    }
+
+    /// Run `write`, marking it as in progress for [`Self::read_through`]
+    ///
+    /// The count is bumped again once it returns, so a read that started
+    /// during the write and finishes after it isn't cached either; callers
+    /// invalidate after this returns.
+    async fn write<T>(&self, write: impl Future<Output = T>) -> T {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        let result = write.await;
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: DocumentStore> DocumentStore for CachedStore<S> {
+    async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let result = self
+            .write(self.inner.save_document_with(doc, options))
+            .await;
+        if let Some(key) = &doc.key {
+            lock(&self.documents).remove(key);
+        }
+        result
+    }
+
+    async fn get_document(&self, key: &str) -> Result<StoredDocument> {
+        self.read_through(&self.documents, key, self.inner.get_document(key))
+            .await
+    }
+
+    async fn delete_document(&self, key: &str) -> Result<()> {
+        let result = self.write(self.inner.delete_document(key)).await;
+        lock(&self.documents).remove(key);
+        self.forget_links();
+        result
+    }
+
+    async fn find_documents(
+        &self,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        self.inner.find_documents(query, page).await
+    }
+
+    async fn search_fulltext(&self, query: &str, page: PageRequest) -> Result<Page<SearchResult>> {
+        self.inner.search_fulltext(query, page).await
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        self.inner.list_tags().await
+    }
+
+    async fn add_link(&self, link: &DocumentLink) -> Result<String> {
+        let result = self.write(self.inner.add_link(link)).await;
+        lock(&self.links).remove(link.from_key());
+        lock(&self.backlinks).remove(link.to_key());
+        result
+    }
+
+    async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>> {
+        let result = self.write(self.inner.add_links(links)).await;
+        let (mut from, mut to) = (lock(&self.links), lock(&self.backlinks));
+        for link in links {
+            from.remove(link.from_key());
+            to.remove(link.to_key());
+        }
+        result
+    }
+
+    /// Drops every cached backlink list, since the replaced links' targets
+    /// aren't known
+    async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+        let result = self.write(self.inner.replace_links(key, links)).await;
+        self.forget_links();
+        result
+    }
+
+    async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        self.read_through(&self.links, key, self.inner.get_links(key))
+            .await
+    }
+
+    async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        self.read_through(&self.backlinks, key, self.inner.get_backlinks(key))
+            .await
+    }
+
+    /// Drops every cached link list, since the replaced links' targets
+    /// aren't known
+    async fn save_with_links(
+        &self,
+        doc: &StoredDocument,
+        links: &[DocumentLink],
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let result = self
+            .write(self.inner.save_with_links(doc, links, options))
+            .await;
+        if let Some(key) = &doc.key {
+            lock(&self.documents).remove(key);
+        }
+        self.forget_links();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStore;
+    use crate::models::LinkType;
+    use std::time::Duration;
+
+    /// A [`MemoryStore`] whose saves take 50ms to land and whose document
+    /// reads take 100ms to come back
+    struct SlowStore(MemoryStore);
+
+    #[async_trait::async_trait]
+    impl DocumentStore for SlowStore {
+        async fn save_document_with(
+            &self,
+            doc: &StoredDocument,
+            options: SaveOptions,
+        ) -> Result<DocumentRef> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.0.save_document_with(doc, options).await
+        }
+
+        async fn get_document(&self, key: &str) -> Result<StoredDocument> {
+            let doc = self.0.get_document(key).await;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            doc
+        }
+
+        async fn delete_document(&self, key: &str) -> Result<()> {
+            self.0.delete_document(key).await
+        }
+
+        async fn find_documents(
+            &self,
+            query: &DocumentQuery,
+            page: PageRequest,
+        ) -> Result<Page<StoredDocument>> {
+            self.0.find_documents(query, page).await
+        }
+
+        async fn search_fulltext(
+            &self,
+            query: &str,
+            page: PageRequest,
+        ) -> Result<Page<SearchResult>> {
+            self.0.search_fulltext(query, page).await
+        }
+
+        async fn list_tags(&self) -> Result<Vec<TagInfo>> {
+            self.0.list_tags().await
+        }
+
+        async fn add_link(&self, link: &DocumentLink) -> Result<String> {
+            self.0.add_link(link).await
+        }
+
+        async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>> {
+            self.0.add_links(links).await
+        }
+
+        async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+            self.0.replace_links(key, links).await
+        }
+
+        async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>> {
+            self.0.get_links(key).await
+        }
+
+        async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>> {
+            self.0.get_backlinks(key).await
+        }
+
+        async fn save_with_links(
+            &self,
+            doc: &StoredDocument,
+            links: &[DocumentLink],
+            options: SaveOptions,
+        ) -> Result<DocumentRef> {
+            self.0.save_with_links(doc, links, options).await
+        }
+    }
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get("a"), Some(1));
+        lru.insert("c", 3);
+        assert_eq!(lru.get("b"), None);
+        assert_eq!(lru.get("a"), Some(1));
+        lru.insert("a", 4);
+        assert_eq!(lru.get("a"), Some(4));
+        assert_eq!(lru.entries.len(), 2);
+        assert_eq!(lru.order.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation() {
+        let store = CachedStore::new(MemoryStore::new());
+        let a = store
+            .save_document(&StoredDocument::new("A", "first", "md"))
+            .await
+            .unwrap();
+        let b = store
+            .save_document(&StoredDocument::new("B", "", "md"))
+            .await
+            .unwrap();
+        let mut doc = store.get_document(&a.key).await.unwrap();
+        assert!(store.get_backlinks(&b.key).await.unwrap().is_empty());
+
+        // Writes behind the cache's back go unseen
+        doc.content = "second".to_string();
+        doc.rev = Some(store.inner().save_document(&doc).await.unwrap().rev);
+        assert_eq!(store.get_document(&a.key).await.unwrap().content, "first");
+
+        doc.content = "third".to_string();
+        store.save_document(&doc).await.unwrap();
+        assert_eq!(store.get_document(&a.key).await.unwrap().content, "third");
+
+        store
+            .add_link(&DocumentLink::new(&a.key, &b.key, LinkType::Reference))
+            .await
+            .unwrap();
+        assert_eq!(store.get_backlinks(&b.key).await.unwrap().len(), 1);
+        store.delete_document(&a.key).await.unwrap();
+        assert!(store.get_backlinks(&b.key).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_overlapping_write_is_not_cached() {
+        let store = CachedStore::new(SlowStore(MemoryStore::new()));
+        let mut doc = StoredDocument::new("A", "old", "md");
+        let saved = store.save_document(&doc).await.unwrap();
+        doc.key = Some(saved.key.clone());
+        doc.rev = Some(saved.rev);
+        doc.content = "new".to_string();
+
+        // The read starts during the save, sees the old content and
+        // returns after the save has landed and invalidated
+        let read = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            store.get_document(&saved.key).await.unwrap()
+        };
+        let (written, read) = tokio::join!(store.save_document(&doc), read);
+        written.unwrap();
+        assert_eq!(read.content, "old");
+        assert_eq!(store.get_document(&saved.key).await.unwrap().content, "new");
+    }
+}
@@ -0,0 +1,554 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! ArangoDB client
+//!
+//! Every operation is a single parameterised AQL query, or several in a
+//! [`crate::transaction::Transaction`] when it writes to more than one
+//! collection. Collection and graph names are constants from
+//! [`crate::models`]; user input only ever reaches a query as a bind
+//! variable. The database handle is opened once on connect, and requests
+//! run under [`DbConfig::retry`].
+
+use crate::acl::READABLE_FILTER;
+use crate::admin::AdminApi;
+use crate::error::{DbError, Result};
+use crate::metrics::Metrics;
+use crate::models::{
+    document_id, DocumentLink, DocumentRef, SaveOptions, SearchResult, StoredDocument, TagInfo,
+    ATTACHMENTS, AUDIT_LOG, DOCUMENTS, DOCUMENT_VERSIONS, EMBEDDINGS, GRANTS, GRAPH, LINKS, META,
+    NOTEBOOKS, SAVED_SEARCHES, SHARE_TOKENS, TAGS, USERS,
+};
+use crate::page::{Page, PageRequest};
+use crate::query::DocumentQuery;
+use crate::retry::RetryPolicy;
+use crate::search::{self, SearchOptions, SearchQuery};
+use crate::snippets::search_result;
+use arangors::client::reqwest::ReqwestClient;
+use arangors::{Connection, Database};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+/// Connection settings
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    /// Server URL, e.g. `http://localhost:8529`
+    pub url: String,
+    pub database: String,
+    pub username: String,
+    pub password: String,
+    /// Key for signing share tokens; token APIs fail without one
+    pub share_secret: Option<String>,
+    /// Request timeout and retries
+    pub retry: RetryPolicy,
+    /// Record who reads, saves, deletes and shares each document in
+    /// `audit_log` (see [`crate::audit`])
+    pub audit: bool,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8529".to_string(),
+            database: "formatrix".to_string(),
+            username: "root".to_string(),
+            password: String::new(),
+            share_secret: None,
+            retry: RetryPolicy::default(),
+            audit: false,
+        }
+    }
+}
+
+/// Client for the gist library database
+pub struct FormatrixDb {
+    db: Database<ReqwestClient>,
+    admin: AdminApi,
+    config: DbConfig,
+    metrics: Mutex<Metrics>,
+    #[cfg(feature = "git-history")]
+    pub(crate) git_history: Option<crate::git_history::GitHistory>,
+}
+
+impl FormatrixDb {
+    /// Connect, make sure the collections exist and run any pending
+    /// [`crate::migrations`]
+    pub async fn connect(config: DbConfig) -> Result<Self> {
+        let database = config
+            .retry
+            .run(|| async {
+                let conn = Connection::establish_basic_auth(
+                    &config.url,
+                    &config.username,
+                    &config.password,
+                )
+                .await
+                .map_err(|e| DbError::Connection(e.to_string()))?;
+                Ok(conn.db(&config.database).await?)
+            })
+            .await?;
+        let db = Self {
+            db: database,
+            admin: AdminApi::new(&config),
+            config,
+            metrics: Mutex::default(),
+            #[cfg(feature = "git-history")]
+            git_history: None,
+        };
+        db.ensure_collections().await?;
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    pub(crate) fn db(&self) -> &Database<ReqwestClient> {
+        &self.db
+    }
+
+    pub(crate) fn config(&self) -> &DbConfig {
+        &self.config
+    }
+
+    pub(crate) fn admin(&self) -> &AdminApi {
+        &self.admin
+    }
+
+    pub(crate) fn metrics_lock(&self) -> MutexGuard<'_, Metrics> {
+        self.metrics.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Await `request`, recording its latency and outcome in the metrics
+    pub(crate) async fn timed<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        let start = Instant::now();
+        let result = request.await;
+        self.metrics_lock()
+            .record(start.elapsed(), result.as_ref().err());
+        result
+    }
+
+    /// Run `aql` with bind variables, retrying per [`DbConfig::retry`]
+    pub(crate) async fn query<T: DeserializeOwned>(
+        &self,
+        aql: &str,
+        vars: HashMap<&str, Value>,
+    ) -> Result<Vec<T>> {
+        self.timed(
+            self.config
+                .retry
+                .run(|| async { Ok(self.db.aql_bind_vars(aql, vars.clone()).await?) }),
+        )
+        .await
+    }
+
+    /// Run `aql` like [`FormatrixDb::query`], for a write that must not be
+    /// applied twice, such as a plain `INSERT`: attempts that timed out are
+    /// not retried
+    pub(crate) async fn insert_query<T: DeserializeOwned>(
+        &self,
+        aql: &str,
+        vars: HashMap<&str, Value>,
+    ) -> Result<Vec<T>> {
+        self.timed(
+            self.config
+                .retry
+                .run_write(|| async { Ok(self.db.aql_bind_vars(aql, vars.clone()).await?) }),
+        )
+        .await
+    }
+
+    /// Create any missing collections, the graph, indexes and the search
+    /// view
+    pub async fn ensure_collections(&self) -> Result<()> {
+        let db = self.db();
+        let existing: Vec<String> = self
+            .config
+            .retry
+            .run(|| async { Ok(db.accessible_collections().await?) })
+            .await?
+            .into_iter()
+            .map(|info| info.name)
+            .collect();
+
+        for name in [
+            DOCUMENTS,
+            TAGS,
+            DOCUMENT_VERSIONS,
+            NOTEBOOKS,
+            USERS,
+            GRANTS,
+            SHARE_TOKENS,
+            ATTACHMENTS,
+            SAVED_SEARCHES,
+            EMBEDDINGS,
+            AUDIT_LOG,
+            META,
+        ] {
+            if !existing.iter().any(|e| e == name) {
+                db.create_collection(name).await?;
+            }
+        }
+        self.ensure_graph().await?;
+        self.repair_indexes().await?;
+        self.ensure_search_view().await
+    }
+
+    /// Create the search analyzer and view, or bring an existing view's
+    /// field list up to date
+    async fn ensure_search_view(&self) -> Result<()> {
+        // Idempotent for an unchanged definition
+        for analyzer in [
+            search::analyzer_definition(),
+            search::ngram_analyzer_definition(),
+        ] {
+            self.admin.post("/analyzer", &analyzer).await?;
+        }
+
+        let path = format!("/view/{}", search::VIEW);
+        if self.admin.get(&path).await?.is_none() {
+            self.admin.post("/view", &search::view_definition()).await?;
+        } else {
+            self.admin
+                .put(
+                    &format!("{}/properties", path),
+                    &json!({ "links": search::view_links() }),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Drop and recreate the search view, so every document is indexed
+    /// afresh; searches miss documents until the new view has caught up
+    pub async fn rebuild_search_view(&self) -> Result<()> {
+        self.admin
+            .delete(&format!("/view/{}", search::VIEW))
+            .await?;
+        self.ensure_search_view().await
+    }
+
+    // ------------------------------------------------------------------
+    // Documents
+    // ------------------------------------------------------------------
+
+    /// Insert or update a document and snapshot it into its version
+    /// history
+    ///
+    /// A document read from the database carries its `rev`; if the stored
+    /// copy has changed since, the save fails with [`DbError::Conflict`].
+    pub async fn save_document(&self, doc: &StoredDocument) -> Result<DocumentRef> {
+        self.save_document_with(doc, SaveOptions::default()).await
+    }
+
+    /// [`FormatrixDb::save_document`], optionally overwriting concurrent
+    /// changes
+    pub async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let trx = self
+            .begin_transaction(&[DOCUMENTS, TAGS, DOCUMENT_VERSIONS])
+            .await?;
+        let result = trx.save_document_with(doc, options).await;
+        let saved = trx.finish(result).await?;
+        #[cfg(feature = "git-history")]
+        self.record_git_save(&saved.key).await;
+        Ok(saved)
+    }
+
+    pub async fn get_document(&self, key: &str) -> Result<StoredDocument> {
+        self.query(
+            "RETURN DOCUMENT(@id)",
+            HashMap::from([("id", json!(document_id(key)))]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .flatten()
+        .ok_or_else(|| DbError::NotFound {
+            key: key.to_string(),
+        })
+    }
+
+    /// Delete a document with its version history, links, attachments,
+    /// share grants, share tokens and embedding, and take it out of its
+    /// notebooks
+    pub async fn delete_document(&self, key: &str) -> Result<()> {
+        let trx = self
+            .begin_transaction(&[
+                DOCUMENTS,
+                TAGS,
+                LINKS,
+                DOCUMENT_VERSIONS,
+                NOTEBOOKS,
+                GRANTS,
+                SHARE_TOKENS,
+                ATTACHMENTS,
+                EMBEDDINGS,
+            ])
+            .await?;
+        let result = trx.delete_document(key).await;
+        trx.finish(result).await?;
+        #[cfg(feature = "git-history")]
+        self.record_git_delete(key).await;
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------
+    // Listings
+    // ------------------------------------------------------------------
+
+    /// One page of the documents, or other records, produced by `source`,
+    /// ordered by `sort`
+    ///
+    /// `source` is a `FOR d IN ...` loop with its filters and `sort` an
+    /// expression over `d`; both come from this crate, never from callers.
+    pub(crate) async fn page_query<T: DeserializeOwned>(
+        &self,
+        source: &str,
+        sort: &str,
+        mut vars: HashMap<String, Value>,
+        page: PageRequest,
+    ) -> Result<Page<T>> {
+        #[derive(Deserialize)]
+        struct Row<T> {
+            total: usize,
+            items: Vec<T>,
+        }
+
+        let aql = format!(
+            "LET total = FIRST({source} COLLECT WITH COUNT INTO n RETURN n) \
+             LET items = ({source} SORT {sort} LIMIT @offset, @limit RETURN d) \
+             RETURN {{ total, items }}"
+        );
+        vars.insert("offset".to_string(), json!(page.offset));
+        vars.insert("limit".to_string(), json!(page.limit));
+
+        let vars = vars.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+        let row: Row<T> = self
+            .query(&aql, vars)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::Query("page query returned no row".to_string()))?;
+        Ok(Page::new(row.items, row.total, page))
+    }
+
+    /// One page of the documents matching `filter`, ordered by `sort`
+    ///
+    /// For filters [`DocumentQuery`] can't express; `filter` is an AQL
+    /// fragment over `d` from this crate, as for
+    /// [`FormatrixDb::page_query`].
+    pub(crate) async fn page_documents(
+        &self,
+        filter: &str,
+        sort: &str,
+        vars: HashMap<&str, Value>,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let mut vars: HashMap<String, Value> =
+            vars.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        vars.insert("@documents".to_string(), json!(DOCUMENTS));
+        self.page_query(&format!("FOR d IN @@documents {filter}"), sort, vars, page)
+            .await
+    }
+
+    /// Pinned documents, then the rest, each by most recent update
+    pub async fn get_recent(&self, page: PageRequest) -> Result<Page<StoredDocument>> {
+        self.find_documents(&DocumentQuery::new().pinned_first(), page)
+            .await
+    }
+
+    /// Documents in one format, most recent first
+    pub async fn get_by_format(
+        &self,
+        format: &str,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        self.find_documents(&DocumentQuery::new().with_format(format), page)
+            .await
+    }
+
+    /// Documents carrying all of `tags`, most recent first
+    pub async fn search_by_tags(
+        &self,
+        tags: &[String],
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        self.find_documents(&DocumentQuery::new().with_all_tags(tags), page)
+            .await
+    }
+
+    /// Documents matching `query` (see [`SearchQuery`]), best match first,
+    /// leaving out archived ones
+    pub async fn search_fulltext(
+        &self,
+        query: &str,
+        page: PageRequest,
+    ) -> Result<Page<SearchResult>> {
+        self.search_fulltext_with(query, &SearchOptions::default(), page)
+            .await
+    }
+
+    /// [`FormatrixDb::search_fulltext`], matching as `options` say
+    pub async fn search_fulltext_with(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+        page: PageRequest,
+    ) -> Result<Page<SearchResult>> {
+        self.search_page(query, options, None, page).await
+    }
+
+    /// [`FormatrixDb::search_fulltext_with`], limited to what `reader` may
+    /// read if given
+    pub(crate) async fn search_page(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+        reader: Option<&str>,
+        page: PageRequest,
+    ) -> Result<Page<SearchResult>> {
+        #[derive(Deserialize)]
+        struct Hit {
+            #[serde(flatten)]
+            doc: StoredDocument,
+            score: f64,
+        }
+        #[derive(Deserialize)]
+        struct Row {
+            total: usize,
+            items: Vec<Hit>,
+        }
+
+        let query = SearchQuery::parse(query);
+        if query.is_empty() {
+            return Ok(Page::new(Vec::new(), 0, page));
+        }
+        options.validate()?;
+        let (search, mut vars) = query.to_aql_with(options);
+        let mut filters = "FILTER d.archived != true".to_string();
+        if let Some(reader) = reader {
+            filters.push(' ');
+            filters.push_str(READABLE_FILTER);
+            vars.insert("user".to_string(), json!(reader));
+            vars.insert("@grants".to_string(), json!(GRANTS));
+        }
+        let aql = format!(
+            "LET total = FIRST(FOR d IN @@view SEARCH {search} {filters} \
+                 COLLECT WITH COUNT INTO n RETURN n) \
+             LET items = (FOR d IN @@view SEARCH {search} {filters} \
+                 LET score = BM25(d) SORT score DESC LIMIT @offset, @limit \
+                 RETURN MERGE(d, {{ score }})) \
+             RETURN {{ total, items }}"
+        );
+        vars.insert("@view".to_string(), json!(search::VIEW));
+        vars.insert("offset".to_string(), json!(page.offset));
+        vars.insert("limit".to_string(), json!(page.limit));
+
+        let vars = vars.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+        let row: Row = self
+            .query(&aql, vars)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::Query("search returned no row".to_string()))?;
+        let items = row
+            .items
+            .into_iter()
+            .map(|hit| search_result(hit.doc, hit.score, &query))
+            .collect();
+        Ok(Page::new(items, row.total, page))
+    }
+
+    /// All tags with their document counts, most used first
+    pub async fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        self.query(
+            "FOR t IN @@tags SORT t.count DESC, t.name RETURN { name: t.name, count: t.count }",
+            HashMap::from([("@tags", json!(TAGS))]),
+        )
+        .await
+    }
+
+    // ------------------------------------------------------------------
+    // Links
+    // ------------------------------------------------------------------
+
+    /// Store a link, returning its key
+    pub async fn add_link(&self, link: &DocumentLink) -> Result<String> {
+        self.insert_query(
+            "INSERT @link INTO @@links RETURN NEW._key",
+            HashMap::from([
+                ("link", serde_json::to_value(link)?),
+                ("@links", json!(LINKS)),
+            ]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DbError::Query("insert returned no key".to_string()))
+    }
+
+    /// Store many links in one query, all or nothing, returning their keys
+    /// in order
+    pub async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>> {
+        if links.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.insert_query(
+            "FOR l IN @links INSERT l INTO @@links RETURN NEW._key",
+            HashMap::from([
+                ("links", serde_json::to_value(links)?),
+                ("@links", json!(LINKS)),
+            ]),
+        )
+        .await
+    }
+
+    /// Replace the links from the document with `key` by `links` in one
+    /// transaction
+    ///
+    /// The links' `from` is set to the document, as for
+    /// [`FormatrixDb::save_with_links`].
+    pub async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+        let trx = self.begin_transaction(&[LINKS]).await?;
+        let result = trx.replace_links(key, links).await;
+        trx.finish(result).await
+    }
+
+    /// Links from the document with `key`
+    pub async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        self.query(
+            "FOR l IN @@links FILTER l._from == @id RETURN l",
+            HashMap::from([("id", json!(document_id(key))), ("@links", json!(LINKS))]),
+        )
+        .await
+    }
+
+    /// Links to the document with `key`
+    pub async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        self.query(
+            "FOR l IN @@links FILTER l._to == @id RETURN l",
+            HashMap::from([("id", json!(document_id(key))), ("@links", json!(LINKS))]),
+        )
+        .await
+    }
+
+    /// Documents reachable from `key` in up to `depth` hops, in either
+    /// direction
+    pub async fn traverse_graph(&self, key: &str, depth: u32) -> Result<Vec<StoredDocument>> {
+        self.query(
+            "FOR v IN 1..@depth ANY @start GRAPH @graph \
+             OPTIONS { uniqueVertices: 'global', order: 'bfs' } RETURN v",
+            HashMap::from([
+                ("start", json!(document_id(key))),
+                ("depth", json!(depth.max(1))),
+                ("graph", json!(GRAPH)),
+            ]),
+        )
+        .await
+    }
+}
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Duplicate and near-duplicate detection
+//!
+//! Every save stores [`content_hash`] of the document's content in
+//! `content_hash`, so exact copies are a lookup on its index. The hash
+//! ignores line endings and trailing whitespace, which editors and
+//! clipboards change freely. Near-duplicates are found by comparing word
+//! shingles (runs of three words) with the Jaccard index, which survives
+//! small edits, reflowed paragraphs and changed capitalisation.
+
+use crate::bulk::BATCH_SIZE;
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::DOCUMENTS;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+/// Words per shingle
+const SHINGLE_SIZE: usize = 3;
+
+/// SHA-256 of `content` with line endings, trailing whitespace and
+/// surrounding blank lines normalised, as lowercase hex
+pub fn content_hash(content: &str) -> String {
+    let normalised = content
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Sha256::digest(normalised.trim_matches('\n').as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Lowercased runs of [`SHINGLE_SIZE`] words; texts shorter than that
+/// are a single shingle
+fn shingles(content: &str) -> HashSet<String> {
+    let words: Vec<String> = content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < SHINGLE_SIZE {
+        return HashSet::from([words.join(" ")]);
+    }
+    words
+        .windows(SHINGLE_SIZE)
+        .map(|window| window.join(" "))
+        .collect()
+}
+
+/// Jaccard index of two shingle sets, from 0 (nothing shared) to 1
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// How alike two texts are, from 0 to 1
+pub fn similarity(a: &str, b: &str) -> f64 {
+    jaccard(&shingles(a), &shingles(b))
+}
+
+/// One of a set of identical documents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateDocument {
+    pub key: String,
+    pub title: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Documents sharing a content hash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub content_hash: String,
+    /// Oldest first, so the first is usually the original
+    pub documents: Vec<DuplicateDocument>,
+}
+
+/// A document resembling another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarDocument {
+    pub key: String,
+    pub title: String,
+    /// From 0 to 1; see [`similarity`]
+    pub similarity: f64,
+}
+
+impl FormatrixDb {
+    /// Groups of documents with identical content, largest first
+    pub async fn find_duplicates(&self) -> Result<Vec<DuplicateGroup>> {
+        self.query(
+            "FOR d IN @@documents FILTER d.content_hash != null \
+             COLLECT content_hash = d.content_hash \
+                 INTO documents = { key: d._key, title: d.title, updated_at: d.updated_at } \
+             FILTER LENGTH(documents) > 1 \
+             SORT LENGTH(documents) DESC, content_hash \
+             RETURN { content_hash, \
+                      documents: (FOR doc IN documents SORT doc.updated_at RETURN doc) }",
+            HashMap::from([("@documents", json!(DOCUMENTS))]),
+        )
+        .await
+    }
+
+    /// Documents whose content is at least `threshold` (0 to 1) similar to
+    /// the document with `key`, most similar first
+    ///
+    /// Every other document is compared, one batch at a time.
+    pub async fn find_similar(&self, key: &str, threshold: f64) -> Result<Vec<SimilarDocument>> {
+        #[derive(Deserialize)]
+        struct Row {
+            key: String,
+            title: String,
+            content: String,
+        }
+
+        let target = shingles(&self.get_document(key).await?.content);
+        let mut similar = Vec::new();
+        let mut after = String::new();
+        loop {
+            let batch: Vec<Row> = self
+                .query(
+                    "FOR d IN @@documents FILTER d._key > @after SORT d._key LIMIT @limit \
+                     RETURN { key: d._key, title: d.title, content: d.content }",
+                    HashMap::from([
+                        ("@documents", json!(DOCUMENTS)),
+                        ("after", json!(after)),
+                        ("limit", json!(BATCH_SIZE)),
+                    ]),
+                )
+                .await?;
+            let full = batch.len() == BATCH_SIZE;
+            if let Some(last) = batch.last() {
+                after = last.key.clone();
+            }
+            similar.extend(batch.into_iter().filter_map(|row| {
+                let similarity = jaccard(&target, &shingles(&row.content));
+                (row.key != key && similarity >= threshold).then_some(SimilarDocument {
+                    key: row.key,
+                    title: row.title,
+                    similarity,
+                })
+            }));
+            if !full {
+                break;
+            }
+        }
+        similar.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        Ok(similar)
+    }
+
+    /// How many of `docs` have the content of a stored document with a
+    /// different key
+    pub(crate) async fn count_duplicates(&self, docs: &[(Option<String>, String)]) -> Result<u64> {
+        let docs: Vec<Value> = docs
+            .iter()
+            .map(|(key, hash)| json!({ "key": key, "hash": hash }))
+            .collect();
+        let counts: Vec<u64> = self
+            .query(
+                "RETURN LENGTH(FOR doc IN @docs \
+                     FILTER LENGTH(FOR d IN @@documents \
+                         FILTER d.content_hash == doc.hash AND d._key != doc.key \
+                         LIMIT 1 RETURN 1) > 0 \
+                     RETURN 1)",
+                HashMap::from([("docs", json!(docs)), ("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+        Ok(counts.into_iter().next().unwrap_or_default())
+    }
+
+    /// Store the content hash of documents saved before hashes were kept,
+    /// returning how many were updated
+    pub async fn backfill_content_hashes(&self) -> Result<usize> {
+        #[derive(Deserialize)]
+        struct Row {
+            key: String,
+            content: String,
+        }
+
+        let mut updated = 0;
+        loop {
+            let batch: Vec<Row> = self
+                .query(
+                    "FOR d IN @@documents FILTER d.content_hash == null LIMIT @limit \
+                     RETURN { key: d._key, content: d.content }",
+                    HashMap::from([
+                        ("@documents", json!(DOCUMENTS)),
+                        ("limit", json!(BATCH_SIZE)),
+                    ]),
+                )
+                .await?;
+            let hashes: Vec<Value> = batch
+                .iter()
+                .map(|row| json!({ "_key": row.key, "content_hash": content_hash(&row.content) }))
+                .collect();
+            self.query::<Value>(
+                "FOR h IN @hashes UPDATE h IN @@documents",
+                HashMap::from([("hashes", json!(hashes)), ("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+            updated += batch.len();
+            if batch.len() < BATCH_SIZE {
+                break;
+            }
+        }
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash() {
+        let hash = content_hash("# Notes\n\nSome text\n");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash("# Notes  \r\n\r\nSome text"));
+        assert_eq!(hash, content_hash("\n# Notes\n\nSome text\n\n"));
+        assert_ne!(hash, content_hash("# Notes\nSome text"));
+    }
+
+    #[test]
+    fn test_similarity() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(similarity(text, text), 1.0);
+        assert_eq!(similarity(text, &text.to_uppercase()), 1.0);
+        assert_eq!(
+            similarity(text, "Something else entirely, nothing shared"),
+            0.0
+        );
+
+        let edited = "The quick brown fox jumps over the sleepy dog";
+        let score = similarity(text, edited);
+        assert!(score > 0.4 && score < 1.0, "{}", score);
+        assert_eq!(similarity("", ""), 1.0);
+    }
+}
@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Semantic search over document embeddings
+//!
+//! An [`Embedder`] turns text into a vector; which model it runs, and
+//! whether locally or behind an API, is up to the caller.
+//! [`FormatrixDb::embed_document`] stores one vector per document in
+//! `embeddings`, keyed like the document and tagged with the model and a
+//! hash of the text embedded, so [`FormatrixDb::embed_stale`] only
+//! re-embeds what changed. [`FormatrixDb::semantic_search`] ranks documents
+//! by cosine similarity with ArangoDB's `COSINE_SIMILARITY` (3.9 and
+//! later); vectors from different models are never compared.
+
+use crate::client::FormatrixDb;
+use crate::dedup::content_hash;
+use crate::error::{DbError, Result};
+use crate::models::{StoredDocument, DOCUMENTS, EMBEDDINGS};
+use crate::page::PageRequest;
+use crate::query::{DocumentQuery, DocumentSort};
+use crate::transaction::Transaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Documents read per page by [`FormatrixDb::embed_stale`]
+const EMBED_BATCH: usize = 100;
+
+/// A text embedding model
+#[async_trait::async_trait]
+pub trait Embedder: Send + Sync {
+    /// Name of the model, stored with each vector
+    fn model(&self) -> &str;
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+}
+
+/// The text embedded for `doc`: its title, then its content
+pub fn embedding_text(doc: &StoredDocument) -> String {
+    format!("{}\n\n{}", doc.title, doc.content)
+}
+
+/// The vector of one document, in `embeddings`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Embedding {
+    /// Key of the document
+    #[serde(rename = "_key")]
+    pub key: String,
+    pub model: String,
+    pub vector: Vec<f32>,
+    /// [`content_hash`] of the [`embedding_text`] the vector is of
+    pub text_hash: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Embedding {
+    /// Whether this is `model`'s vector of `doc` as it is now
+    pub fn is_current(&self, model: &str, doc: &StoredDocument) -> bool {
+        self.model == model && self.text_hash == content_hash(&embedding_text(doc))
+    }
+}
+
+/// A document ranked by [`FormatrixDb::semantic_search`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SemanticResult {
+    pub key: String,
+    pub title: String,
+    /// Cosine similarity with the query, from -1 to 1; higher is closer
+    pub score: f64,
+}
+
+impl FormatrixDb {
+    /// Store a document's vector, replacing any earlier one
+    pub async fn store_embedding(&self, embedding: &Embedding) -> Result<()> {
+        self.query::<Value>(
+            "INSERT @embedding INTO @@embeddings OPTIONS { overwriteMode: 'replace' }",
+            HashMap::from([
+                ("embedding", serde_json::to_value(embedding)?),
+                ("@embeddings", json!(EMBEDDINGS)),
+            ]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_embedding(&self, key: &str) -> Result<Option<Embedding>> {
+        Ok(self
+            .query(
+                "RETURN DOCUMENT(@@embeddings, @key)",
+                HashMap::from([("key", json!(key)), ("@embeddings", json!(EMBEDDINGS))]),
+            )
+            .await?
+            .into_iter()
+            .next()
+            .flatten())
+    }
+
+    /// Embed the document with `key` unless its stored vector is current,
+    /// returning whether it was embedded
+    pub async fn embed_document(&self, key: &str, embedder: &dyn Embedder) -> Result<bool> {
+        let doc = self.get_document(key).await?;
+        let stored = self.get_embedding(key).await?;
+        if stored.is_some_and(|stored| stored.is_current(embedder.model(), &doc)) {
+            return Ok(false);
+        }
+        self.embed(&doc, embedder).await?;
+        Ok(true)
+    }
+
+    /// Embed every document, archived ones included, whose vector from
+    /// `embedder`'s model is missing or out of date, returning how many
+    pub async fn embed_stale(&self, embedder: &dyn Embedder) -> Result<usize> {
+        let hashes: HashMap<String, String> = self
+            .query::<(String, String)>(
+                "FOR e IN @@embeddings FILTER e.model == @model RETURN [e._key, e.text_hash]",
+                HashMap::from([
+                    ("model", json!(embedder.model())),
+                    ("@embeddings", json!(EMBEDDINGS)),
+                ]),
+            )
+            .await?
+            .into_iter()
+            .collect();
+        let query = DocumentQuery::new()
+            .including_archived()
+            .sorted_by(DocumentSort::OldestFirst);
+        let mut page = PageRequest::first(EMBED_BATCH);
+        let mut embedded = 0;
+        loop {
+            let found = self.find_documents(&query, page).await?;
+            for doc in &found.items {
+                let key = doc.key.as_deref().unwrap_or_default();
+                if hashes.get(key) != Some(&content_hash(&embedding_text(doc))) {
+                    self.embed(doc, embedder).await?;
+                    embedded += 1;
+                }
+            }
+            match found.next_page() {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+        Ok(embedded)
+    }
+
+    async fn embed(&self, doc: &StoredDocument, embedder: &dyn Embedder) -> Result<()> {
+        let key = doc
+            .key
+            .clone()
+            .ok_or_else(|| DbError::Invalid("cannot embed an unsaved document".to_string()))?;
+        let text = embedding_text(doc);
+        let vector = embedder.embed(&text).await?;
+        if vector.is_empty() {
+            return Err(DbError::Invalid(format!(
+                "model '{}' returned an empty vector",
+                embedder.model()
+            )));
+        }
+        self.store_embedding(&Embedding {
+            key,
+            model: embedder.model().to_string(),
+            vector,
+            text_hash: content_hash(&text),
+            updated_at: Utc::now(),
+        })
+        .await
+    }
+
+    /// The `limit` unarchived documents closest in meaning to `query`, by
+    /// their vectors from `embedder`'s model
+    pub async fn semantic_search(
+        &self,
+        query: &str,
+        embedder: &dyn Embedder,
+        limit: usize,
+    ) -> Result<Vec<SemanticResult>> {
+        let vector = embedder.embed(query).await?;
+        self.query(
+            "FOR e IN @@embeddings FILTER e.model == @model \
+                 LET score = COSINE_SIMILARITY(e.vector, @vector) \
+                 FILTER score != null \
+                 LET d = DOCUMENT(@@documents, e._key) \
+                 FILTER d != null AND d.archived != true \
+                 SORT score DESC LIMIT @limit \
+                 RETURN { key: d._key, title: d.title, score }",
+            HashMap::from([
+                ("model", json!(embedder.model())),
+                ("vector", json!(vector)),
+                ("limit", json!(limit)),
+                ("@embeddings", json!(EMBEDDINGS)),
+                ("@documents", json!(DOCUMENTS)),
+            ]),
+        )
+        .await
+    }
+}
+
+impl Transaction {
+    /// Drop the vector of the document with `key`
+    pub(crate) async fn remove_embedding(&self, key: &str) -> Result<()> {
+        self.query::<Value>(
+            "REMOVE { _key: @key } IN @@embeddings OPTIONS { ignoreErrors: true }",
+            HashMap::from([("key", json!(key)), ("@embeddings", json!(EMBEDDINGS))]),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_current() {
+        let mut doc = StoredDocument::new("Borrowing", "Shared or mutable.", "md");
+        let embedding = Embedding {
+            key: "1".to_string(),
+            model: "minilm".to_string(),
+            vector: vec![0.5, 0.5],
+            text_hash: content_hash(&embedding_text(&doc)),
+            updated_at: Utc::now(),
+        };
+        assert!(embedding.is_current("minilm", &doc));
+        assert!(!embedding.is_current("mpnet", &doc));
+        doc.title = "Borrowing rules".to_string();
+        assert!(!embedding.is_current("minilm", &doc));
+    }
+}
@@ -0,0 +1,379 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Client-side content encryption (feature `encryption`)
+//!
+//! [`EncryptedStore`] wraps any [`DocumentStore`] and seals the content of
+//! [`Visibility::Private`] documents with AES-256-GCM before it leaves the
+//! client, opening it again on read, so the server only ever holds
+//! ciphertext. Titles, tags and other metadata stay in the clear, and
+//! full-text search can't see into sealed content.
+//!
+//! Sealed content is `formatrix:aes-256-gcm:<key id>:<base64>`, where the
+//! key id is a short fingerprint of the [`ContentKey`] and the base64 holds
+//! the nonce followed by the ciphertext. The id lets
+//! [`EncryptedStore::rekey`] be resumed: keep the old key until a re-key
+//! has finished, since documents it hasn't reached yet still need it
+//! (see [`EncryptedStore::with_previous_key`]).
+
+use crate::error::{DbError, Result};
+use crate::models::{
+    DocumentLink, DocumentRef, SaveOptions, SearchResult, StoredDocument, TagInfo, Visibility,
+};
+use crate::page::{Page, PageRequest};
+use crate::query::{DocumentQuery, DocumentSort};
+use crate::store::DocumentStore;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+const SEALED_PREFIX: &str = "formatrix:aes-256-gcm:";
+const NONCE_LEN: usize = 12;
+
+/// Documents re-encrypted per page by [`EncryptedStore::rekey`]
+const REKEY_BATCH: usize = 100;
+
+/// A 256-bit key for sealing document content
+#[derive(Clone, PartialEq, Eq)]
+pub struct ContentKey([u8; 32]);
+
+impl ContentKey {
+    /// A new random key
+    pub fn generate() -> Self {
+        Self(rand::random())
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The key from its [`ContentKey::to_base64`] form
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| DbError::Encryption(format!("invalid key: {e}")))?;
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| DbError::Encryption("a key must be 32 bytes".to_string()))?;
+        Ok(Self(bytes))
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.0)
+    }
+
+    /// Fingerprint naming the key in sealed content, safe to show
+    pub fn id(&self) -> String {
+        Sha256::digest(self.0)[..8]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+
+    /// `plaintext` sealed under this key
+    pub fn seal(&self, plaintext: &str) -> Result<String> {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            self.cipher()
+                .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+                .map_err(|_| DbError::Encryption("encryption failed".to_string()))?,
+        );
+        Ok(format!(
+            "{SEALED_PREFIX}{}:{}",
+            self.id(),
+            STANDARD.encode(sealed)
+        ))
+    }
+
+    /// The plaintext of content sealed under this key
+    pub fn open(&self, sealed: &str) -> Result<String> {
+        let (id, body) = split_sealed(sealed)
+            .ok_or_else(|| DbError::Encryption("content is not sealed".to_string()))?;
+        if id != self.id() {
+            return Err(DbError::Encryption(format!(
+                "content is sealed with key {id}"
+            )));
+        }
+        let bytes = STANDARD
+            .decode(body)
+            .map_err(|e| DbError::Encryption(format!("corrupt sealed content: {e}")))?;
+        if bytes.len() < NONCE_LEN {
+            return Err(DbError::Encryption("corrupt sealed content".to_string()));
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| DbError::Encryption("sealed content failed to verify".to_string()))?;
+        String::from_utf8(plaintext)
+            .map_err(|_| DbError::Encryption("sealed content is not UTF-8".to_string()))
+    }
+}
+
+/// Shows only the key id
+impl fmt::Debug for ContentKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ContentKey").field(&self.id()).finish()
+    }
+}
+
+/// The key id and base64 body of sealed content
+fn split_sealed(content: &str) -> Option<(&str, &str)> {
+    content.strip_prefix(SEALED_PREFIX)?.split_once(':')
+}
+
+pub fn is_sealed(content: &str) -> bool {
+    split_sealed(content).is_some()
+}
+
+#[derive(Debug)]
+struct Keys {
+    current: ContentKey,
+    /// The key being re-keyed away from
+    previous: Option<ContentKey>,
+}
+
+/// A [`DocumentStore`] that encrypts private documents' content
+pub struct EncryptedStore<S> {
+    inner: S,
+    keys: Mutex<Keys>,
+}
+
+impl<S: DocumentStore> EncryptedStore<S> {
+    pub fn new(inner: S, key: ContentKey) -> Self {
+        Self {
+            inner,
+            keys: Mutex::new(Keys {
+                current: key,
+                previous: None,
+            }),
+        }
+    }
+
+    /// Also open content sealed with `key`, to finish an interrupted
+    /// [`EncryptedStore::rekey`]
+    pub fn with_previous_key(self, key: ContentKey) -> Self {
+        self.keys().previous = Some(key);
+        self
+    }
+
+    /// The wrapped store, which sees sealed content
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn keys(&self) -> MutexGuard<'_, Keys> {
+        self.keys.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// `doc` with its content sealed if it is private
+    fn seal(&self, doc: &StoredDocument) -> Result<StoredDocument> {
+        let mut doc = doc.clone();
+        if doc.visibility == Visibility::Private && !is_sealed(&doc.content) {
+            doc.content = self.keys().current.seal(&doc.content)?;
+        }
+        Ok(doc)
+    }
+
+    /// `doc` with its content opened if it is sealed
+    fn open(&self, mut doc: StoredDocument) -> Result<StoredDocument> {
+        let Some((id, _)) = split_sealed(&doc.content) else {
+            return Ok(doc);
+        };
+        let keys = self.keys();
+        let key = [Some(&keys.current), keys.previous.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|key| key.id() == id)
+            .ok_or_else(|| DbError::Encryption(format!("no key {id} to open {:?}", doc.key)))?;
+        doc.content = key.open(&doc.content)?;
+        Ok(doc)
+    }
+
+    /// Switch to `key` and re-seal every private document under it,
+    /// returning how many were re-sealed
+    ///
+    /// Private documents stored before encryption was enabled are sealed
+    /// too. If it fails part way, call it again with the same key.
+    pub async fn rekey(&self, key: ContentKey) -> Result<usize> {
+        {
+            let mut keys = self.keys();
+            if keys.current != key {
+                let previous = std::mem::replace(&mut keys.current, key.clone());
+                keys.previous = Some(previous);
+            }
+        }
+        let id = key.id();
+        let query = DocumentQuery::new()
+            .with_visibility(Visibility::Private)
+            .including_archived()
+            .sorted_by(DocumentSort::OldestFirst);
+        let mut page = PageRequest::first(REKEY_BATCH);
+        let mut resealed = 0;
+        loop {
+            let found = self.inner.find_documents(&query, page).await?;
+            for doc in &found.items {
+                if split_sealed(&doc.content).is_some_and(|(sealed_with, _)| sealed_with == id) {
+                    continue;
+                }
+                let doc = self.open(doc.clone())?;
+                self.save_document(&doc).await?;
+                resealed += 1;
+            }
+            match found.next_page() {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+        self.keys().previous = None;
+        Ok(resealed)
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: DocumentStore> DocumentStore for EncryptedStore<S> {
+    async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let doc = self.seal(doc)?;
+        self.inner.save_document_with(&doc, options).await
+    }
+
+    async fn get_document(&self, key: &str) -> Result<StoredDocument> {
+        self.open(self.inner.get_document(key).await?)
+    }
+
+    async fn delete_document(&self, key: &str) -> Result<()> {
+        self.inner.delete_document(key).await
+    }
+
+    /// Text in the query only matches private documents by title
+    async fn find_documents(
+        &self,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let found = self.inner.find_documents(query, page).await?;
+        let items = found
+            .items
+            .into_iter()
+            .map(|doc| self.open(doc))
+            .collect::<Result<_>>()?;
+        Ok(Page::new(items, found.total, page))
+    }
+
+    /// Private documents only match by title, with no snippets
+    async fn search_fulltext(&self, query: &str, page: PageRequest) -> Result<Page<SearchResult>> {
+        let mut found = self.inner.search_fulltext(query, page).await?;
+        for result in &mut found.items {
+            if is_sealed(&result.snippet) {
+                result.snippet.clear();
+            }
+        }
+        Ok(found)
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        self.inner.list_tags().await
+    }
+
+    async fn add_link(&self, link: &DocumentLink) -> Result<String> {
+        self.inner.add_link(link).await
+    }
+
+    async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>> {
+        self.inner.add_links(links).await
+    }
+
+    async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+        self.inner.replace_links(key, links).await
+    }
+
+    async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        self.inner.get_links(key).await
+    }
+
+    async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        self.inner.get_backlinks(key).await
+    }
+
+    async fn save_with_links(
+        &self,
+        doc: &StoredDocument,
+        links: &[DocumentLink],
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let doc = self.seal(doc)?;
+        self.inner.save_with_links(&doc, links, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStore;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = ContentKey::generate();
+        let sealed = key.seal("dear diary").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_ne!(key.seal("dear diary").unwrap(), sealed);
+        assert_eq!(key.open(&sealed).unwrap(), "dear diary");
+        assert!(ContentKey::generate().open(&sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        tampered.pop();
+        tampered.push(if sealed.ends_with('A') { 'B' } else { 'A' });
+        assert!(key.open(&tampered).is_err());
+
+        let copy = ContentKey::from_base64(&key.to_base64()).unwrap();
+        assert_eq!(copy, key);
+        assert!(!format!("{key:?}").contains(&key.to_base64()));
+    }
+
+    #[tokio::test]
+    async fn test_store_and_rekey() {
+        let old = ContentKey::generate();
+        let store = EncryptedStore::new(MemoryStore::new(), old.clone());
+        let private =
+            StoredDocument::new("Diary", "dear diary", "md").with_visibility(Visibility::Private);
+        let private = store.save_document(&private).await.unwrap();
+        let public =
+            StoredDocument::new("Notes", "hello", "md").with_visibility(Visibility::Public);
+        let public = store.save_document(&public).await.unwrap();
+
+        let stored = store.inner().get_document(&private.key).await.unwrap();
+        assert!(is_sealed(&stored.content));
+        let stored = store.inner().get_document(&public.key).await.unwrap();
+        assert_eq!(stored.content, "hello");
+        let doc = store.get_document(&private.key).await.unwrap();
+        assert_eq!(doc.content, "dear diary");
+
+        let new = ContentKey::generate();
+        assert_eq!(store.rekey(new.clone()).await.unwrap(), 1);
+        assert_eq!(store.rekey(new.clone()).await.unwrap(), 0);
+        let stored = store.inner().get_document(&private.key).await.unwrap();
+        assert!(new.open(&stored.content).is_ok());
+        assert!(old.open(&stored.content).is_err());
+        let found = store
+            .find_documents(&DocumentQuery::new(), PageRequest::first(10))
+            .await
+            .unwrap();
+        assert!(found.items.iter().any(|doc| doc.content == "dear diary"));
+    }
+}
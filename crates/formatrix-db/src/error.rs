@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Database errors
+
+use arangors::ClientError;
+
+/// Errors from database operations
+#[derive(Debug, thiserror::Error)]
+pub enum DbError {
+    /// The server is unreachable or rejected the credentials
+    #[error("Connection error: {0}")]
+    Connection(String),
+
+    /// ArangoDB rejected a query or request
+    #[error("Query error: {0}")]
+    Query(String),
+
+    #[error("Document not found: {key}")]
+    NotFound { key: String },
+
+    /// The user lacks the access the operation needs
+    #[error("Permission denied on {key}")]
+    PermissionDenied { key: String },
+
+    /// An argument was rejected before reaching the database
+    #[error("Invalid input: {0}")]
+    Invalid(String),
+
+    /// A share token is malformed, forged, expired or revoked
+    #[error("Invalid share token: {0}")]
+    InvalidToken(String),
+
+    /// The client is missing a setting the operation needs
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    /// Reading or committing to the git history failed
+    #[error("Git history error: {0}")]
+    History(String),
+
+    /// Sealed content could not be opened, or a key is malformed
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    /// The stored document changed since the saved copy was read
+    #[error("Document {key} was modified concurrently")]
+    Conflict { key: String },
+
+    /// A request got no answer within [`crate::RetryPolicy::timeout`]
+    #[error("Timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    /// Reading an import or writing an export failed
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, DbError>;
+
+/// ArangoDB error numbers
+pub(crate) const ERROR_CONFLICT: u16 = 1200;
+pub(crate) const ERROR_DOCUMENT_NOT_FOUND: u16 = 1202;
+
+/// Whether `err` is the ArangoDB error with number `error_num`
+pub(crate) fn is_arango_error(err: &ClientError, error_num: u16) -> bool {
+    matches!(err, ClientError::Arango(err) if err.error_num() == error_num)
+}
+
+/// Error for a failed write to the document with `key`, telling revision
+/// conflicts and missing documents apart
+pub(crate) fn write_error(err: ClientError, key: &str) -> DbError {
+    let key = key.to_string();
+    if is_arango_error(&err, ERROR_CONFLICT) {
+        DbError::Conflict { key }
+    } else if is_arango_error(&err, ERROR_DOCUMENT_NOT_FOUND) {
+        DbError::NotFound { key }
+    } else {
+        err.into()
+    }
+}
+
+impl From<ClientError> for DbError {
+    fn from(err: ClientError) -> Self {
+        match err {
+            ClientError::Arango(err) => DbError::Query(err.message().to_string()),
+            ClientError::Serde(err) => DbError::Serialization(err),
+            other => DbError::Connection(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflict() -> ClientError {
+        let err: arangors::ArangoError = serde_json::from_value(serde_json::json!({
+            "code": 409,
+            "errorNum": 1200,
+            "errorMessage": "conflict, _rev values do not match"
+        }))
+        .unwrap();
+        ClientError::Arango(err)
+    }
+
+    #[test]
+    fn test_arango_error_numbers() {
+        let err = conflict();
+        assert!(is_arango_error(&err, ERROR_CONFLICT));
+        assert!(!is_arango_error(&err, ERROR_DOCUMENT_NOT_FOUND));
+        assert!(matches!(DbError::from(err), DbError::Query(_)));
+    }
+
+    #[test]
+    fn test_write_error() {
+        assert!(matches!(
+            write_error(conflict(), "42"),
+            DbError::Conflict { key } if key == "42"
+        ));
+    }
+}
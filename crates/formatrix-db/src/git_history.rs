@@ -0,0 +1,307 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Git-backed document history (feature `git-history`)
+//!
+//! With a [`GitHistory`] attached through [`FormatrixDb::with_git_history`],
+//! every save and delete is also committed to a local git repository, one
+//! file per gist named `<key>.<format>`. Metadata sits in front matter
+//! between `---` lines, one `field: value` per line with JSON values (which
+//! YAML readers accept too), and the content follows unchanged. Commits are
+//! authored by the document's owner, so `git log` is an audit trail that
+//! works without the database, and [`FormatrixDb::restore_from_git`]
+//! rebuilds documents from the repository.
+//!
+//! The database stays the source of truth: a failed commit is logged, not
+//! reported as a failed save. Commands run through the `git` program.
+
+use crate::bulk::ImportSummary;
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::StoredDocument;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Front matter fields, in the order they are written
+const FIELDS: &[&str] = &[
+    "_key",
+    "title",
+    "format",
+    "tags",
+    "visibility",
+    "owner",
+    "parent",
+    "pinned",
+    "favorite",
+    "archived",
+    "created_at",
+    "updated_at",
+];
+
+/// Author of commits for documents without an owner
+const DEFAULT_AUTHOR: &str = "formatrix";
+
+/// A document as a front matter file
+pub fn to_file(doc: &StoredDocument) -> Result<String> {
+    let Value::Object(fields) = serde_json::to_value(doc)? else {
+        unreachable!("documents serialize to objects");
+    };
+    let mut out = String::from("---\n");
+    for name in FIELDS {
+        if let Some(value) = fields.get(*name) {
+            out.push_str(&format!("{}: {}\n", name, value));
+        }
+    }
+    out.push_str("---\n");
+    out.push_str(&doc.content);
+    Ok(out)
+}
+
+/// Read a file written by [`to_file`]
+pub fn from_file(text: &str) -> Result<StoredDocument> {
+    let invalid = |reason: &str| DbError::History(format!("bad document file: {}", reason));
+    let rest = text
+        .strip_prefix("---\n")
+        .ok_or_else(|| invalid("no front matter"))?;
+    let (front, content) = rest
+        .split_once("\n---\n")
+        .or_else(|| rest.strip_suffix("\n---").map(|front| (front, "")))
+        .ok_or_else(|| invalid("unterminated front matter"))?;
+
+    let mut fields = Map::new();
+    for line in front.lines() {
+        let (name, value) = line
+            .split_once(": ")
+            .ok_or_else(|| invalid("front matter line without a value"))?;
+        fields.insert(name.to_string(), serde_json::from_str(value)?);
+    }
+    fields.insert("content".to_string(), Value::String(content.to_string()));
+    Ok(serde_json::from_value(Value::Object(fields))?)
+}
+
+/// File extension for a format id
+fn extension(format: &str) -> &str {
+    if !format.is_empty() && format.chars().all(|c| c.is_ascii_alphanumeric()) {
+        format
+    } else {
+        "txt"
+    }
+}
+
+/// A local git repository holding one file per document
+#[derive(Debug, Clone)]
+pub struct GitHistory {
+    root: PathBuf,
+}
+
+impl GitHistory {
+    /// Use the repository at `root`, creating it if needed
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let history = Self { root: root.into() };
+        fs::create_dir_all(&history.root)?;
+        if !history.root.join(".git").exists() {
+            history.git(&["init", "--quiet"], None)?;
+        }
+        Ok(history)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Run git in the repository, committing as `author` if given
+    fn git(&self, args: &[&str], author: Option<&str>) -> Result<String> {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.root).args(args);
+        if let Some(author) = author {
+            let email = format!("{}@formatrix.local", author);
+            command
+                .env("GIT_AUTHOR_NAME", author)
+                .env("GIT_AUTHOR_EMAIL", &email)
+                .env("GIT_COMMITTER_NAME", author)
+                .env("GIT_COMMITTER_EMAIL", &email);
+        }
+        let output = command
+            .output()
+            .map_err(|e| DbError::History(format!("failed to run git: {}", e)))?;
+        if !output.status.success() {
+            return Err(DbError::History(format!(
+                "git {}: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Files holding the document with `key`, whatever their format
+    fn files_of(&self, key: &str) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            let stem = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.rsplit_once('.'))
+                .map(|(stem, _)| stem);
+            if stem == Some(key) {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Stage everything and commit, unless nothing changed
+    fn commit(&self, message: &str, author: &str) -> Result<()> {
+        self.git(&["add", "--all"], None)?;
+        if self
+            .git(&["status", "--porcelain"], None)?
+            .trim()
+            .is_empty()
+        {
+            return Ok(());
+        }
+        self.git(&["commit", "--quiet", "--message", message], Some(author))?;
+        Ok(())
+    }
+
+    /// Commit the saved state of a document
+    pub fn record_save(&self, doc: &StoredDocument) -> Result<()> {
+        let key = doc
+            .key
+            .as_deref()
+            .ok_or_else(|| DbError::Invalid("only saved documents have history".to_string()))?;
+        // A format change renames the file
+        for old in self.files_of(key)? {
+            fs::remove_file(old)?;
+        }
+        let file = self
+            .root
+            .join(format!("{}.{}", key, extension(&doc.format)));
+        fs::write(file, to_file(doc)?)?;
+        self.commit(
+            &format!("Save {}: {}", key, doc.title),
+            doc.owner.as_deref().unwrap_or(DEFAULT_AUTHOR),
+        )
+    }
+
+    /// Commit the deletion of the document with `key`
+    pub fn record_delete(&self, key: &str) -> Result<()> {
+        for file in self.files_of(key)? {
+            fs::remove_file(file)?;
+        }
+        self.commit(&format!("Delete {}", key), DEFAULT_AUTHOR)
+    }
+
+    /// Every document in the working tree
+    pub fn read_documents(&self) -> Result<Vec<StoredDocument>> {
+        let mut docs = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if path.is_file() && !hidden {
+                docs.push(from_file(&fs::read_to_string(&path)?)?);
+            }
+        }
+        Ok(docs)
+    }
+}
+
+impl FormatrixDb {
+    /// Commit every later save and delete to `history`
+    pub fn with_git_history(mut self, history: GitHistory) -> Self {
+        self.git_history = Some(history);
+        self
+    }
+
+    /// Commit the stored state of the document with `key`, logging failures
+    pub(crate) async fn record_git_save(&self, key: &str) {
+        let Some(history) = self.git_history.clone() else {
+            return;
+        };
+        let result = match self.get_document(key).await {
+            Ok(doc) => tokio::task::spawn_blocking(move || history.record_save(&doc))
+                .await
+                .unwrap_or_else(|e| Err(DbError::History(e.to_string()))),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            tracing::warn!("failed to record {} in git history: {}", key, err);
+        }
+    }
+
+    /// Commit the deletion of the document with `key`, logging failures
+    pub(crate) async fn record_git_delete(&self, key: &str) {
+        let Some(history) = self.git_history.clone() else {
+            return;
+        };
+        let key = key.to_string();
+        let result = tokio::task::spawn_blocking({
+            let key = key.clone();
+            move || history.record_delete(&key)
+        })
+        .await
+        .unwrap_or_else(|e| Err(DbError::History(e.to_string())));
+        if let Err(err) = result {
+            tracing::warn!(
+                "failed to record deletion of {} in git history: {}",
+                key,
+                err
+            );
+        }
+    }
+
+    /// Import every document in `history`'s working tree, replacing stored
+    /// documents with the same key
+    ///
+    /// Documents missing from the repository are left alone; restore into
+    /// an empty database to reproduce the repository exactly.
+    pub async fn restore_from_git(&self, history: &GitHistory) -> Result<ImportSummary> {
+        let history = history.clone();
+        let docs = tokio::task::spawn_blocking(move || history.read_documents())
+            .await
+            .map_err(|e| DbError::History(e.to_string()))??;
+        self.import_documents(docs).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Visibility;
+
+    #[test]
+    fn test_file_round_trip() {
+        let mut doc = StoredDocument::new("Plan: \"v2\"", "# Plan\n\n---\nDone.\n", "md")
+            .with_tags(["work/plans"])
+            .with_owner("ada")
+            .with_visibility(Visibility::Shared);
+        doc.key = Some("plan".to_string());
+        doc.rev = Some("_abc".to_string());
+
+        let file = to_file(&doc).unwrap();
+        assert!(file.starts_with("---\n_key: \"plan\"\ntitle: \"Plan: \\\"v2\\\"\"\n"));
+        assert!(!file.contains("_rev"));
+
+        let read = from_file(&file).unwrap();
+        assert_eq!(read.content, doc.content);
+        assert_eq!(read.owner, doc.owner);
+        assert_eq!(read.created_at, doc.created_at);
+        assert_eq!(read.rev, None);
+
+        let empty = StoredDocument::new("Empty", "", "md");
+        assert_eq!(from_file(&to_file(&empty).unwrap()).unwrap().content, "");
+        assert!(from_file("no front matter").is_err());
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(extension("md"), "md");
+        assert_eq!(extension("../x"), "txt");
+        assert_eq!(extension(""), "txt");
+    }
+}
@@ -0,0 +1,430 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Queries over the `doc_graph` named graph
+//!
+//! Paths follow links in either direction, as in
+//! [`FormatrixDb::traverse_graph`]: how two notes relate matters more
+//! here than which one links to the other. [`GraphExport`] renders the
+//! graph, or part of it, for drawing: as DOT for Graphviz, GraphML for
+//! desktop tools, or the `{ nodes, links }` JSON that D3 force layouts
+//! take.
+//!
+//! The graph and its `links` edge collection are created through the HTTP
+//! API by [`FormatrixDb::ensure_collections`].
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{document_id, DocumentLink, LinkType, StoredDocument, DOCUMENTS, GRAPH, LINKS};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// A chain of documents joined by links
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentPath {
+    /// From the start document to the end document
+    pub documents: Vec<StoredDocument>,
+    /// `links[i]` joins `documents[i]` and `documents[i + 1]`, pointing
+    /// either way
+    pub links: Vec<DocumentLink>,
+}
+
+impl DocumentPath {
+    /// Number of links walked
+    pub fn hops(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Keys of the documents along the path
+    pub fn keys(&self) -> Vec<&str> {
+        self.documents
+            .iter()
+            .filter_map(|doc| doc.key.as_deref())
+            .collect()
+    }
+}
+
+/// One vertex of a SHORTEST_PATH result and the edge that reached it
+#[derive(Deserialize)]
+struct Step {
+    document: StoredDocument,
+    link: Option<DocumentLink>,
+}
+
+/// Assemble a path from its steps; `None` for an empty result
+fn path_from_steps(steps: Vec<Step>) -> Option<DocumentPath> {
+    if steps.is_empty() {
+        return None;
+    }
+    let mut path = DocumentPath {
+        documents: Vec::with_capacity(steps.len()),
+        links: Vec::with_capacity(steps.len() - 1),
+    };
+    for step in steps {
+        path.documents.push(step.document);
+        path.links.extend(step.link);
+    }
+    Some(path)
+}
+
+/// Which part of the graph to export; the default is all of it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphFilter {
+    /// Only documents carrying all of these tags
+    pub tags: Vec<String>,
+    /// Only documents in this format
+    pub format: Option<String>,
+    /// Only links of these types; empty for every type
+    pub link_types: Vec<LinkType>,
+}
+
+impl GraphFilter {
+    pub fn with_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    pub fn with_link_types(mut self, link_types: impl IntoIterator<Item = LinkType>) -> Self {
+        self.link_types = link_types.into_iter().collect();
+        self
+    }
+}
+
+/// A document in an exported graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub key: String,
+    pub title: String,
+    pub format: String,
+    pub tags: Vec<String>,
+}
+
+/// A link in an exported graph, between node keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub link_type: LinkType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Documents and the links between them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphExport {
+    pub nodes: Vec<GraphNode>,
+    /// Only links with both ends among `nodes`
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Quote `s` as a DOT string
+fn dot_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// Escape `s` for XML text and attribute values
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl GraphExport {
+    /// Graphviz DOT, nodes labelled with their titles and edges with
+    /// their link types
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph formatrix {\n");
+        for node in &self.nodes {
+            let _ = writeln!(
+                out,
+                "  {} [label={}];",
+                dot_string(&node.key),
+                dot_string(&node.title)
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "  {} -> {} [label={}];",
+                dot_string(&edge.source),
+                dot_string(&edge.target),
+                dot_string(edge.link_type.as_str())
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// GraphML, with title, format and tags as node data and the link
+    /// type and label as edge data
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        );
+        for (id, target, name) in [
+            ("title", "node", "title"),
+            ("format", "node", "format"),
+            ("tags", "node", "tags"),
+            ("type", "edge", "link_type"),
+            ("label", "edge", "label"),
+        ] {
+            let _ = writeln!(
+                out,
+                "  <key id=\"{id}\" for=\"{target}\" attr.name=\"{name}\" attr.type=\"string\"/>"
+            );
+        }
+        out.push_str("  <graph edgedefault=\"directed\">\n");
+        for node in &self.nodes {
+            let _ = writeln!(
+                out,
+                "    <node id=\"{}\"><data key=\"title\">{}</data>\
+                 <data key=\"format\">{}</data><data key=\"tags\">{}</data></node>",
+                xml_escape(&node.key),
+                xml_escape(&node.title),
+                xml_escape(&node.format),
+                xml_escape(&node.tags.join(","))
+            );
+        }
+        for edge in &self.edges {
+            let label = edge
+                .label
+                .as_deref()
+                .map(|label| format!("<data key=\"label\">{}</data>", xml_escape(label)))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"type\">{}</data>{}</edge>",
+                xml_escape(&edge.source),
+                xml_escape(&edge.target),
+                edge.link_type.as_str(),
+                label
+            );
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    /// `{ nodes, links }` JSON for a D3 force layout: nodes have an `id`,
+    /// `title`, `format` and `tags`, links a `source`, `target`, `type`
+    /// and optional `label`
+    pub fn to_d3(&self) -> Value {
+        json!({
+            "nodes": self.nodes.iter().map(|node| json!({
+                "id": node.key,
+                "title": node.title,
+                "format": node.format,
+                "tags": node.tags,
+            })).collect::<Vec<_>>(),
+            "links": self.edges.iter().map(|edge| json!({
+                "source": edge.source,
+                "target": edge.target,
+                "type": edge.link_type,
+                "label": edge.label,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Collection type number of edge collections in `/_api/collection`
+const EDGE_COLLECTION: u64 = 3;
+
+/// Graph definition for `POST /_api/gharial`: links between documents
+pub(crate) fn graph_definition() -> Value {
+    json!({
+        "name": GRAPH,
+        "edgeDefinitions": [{
+            "collection": LINKS,
+            "from": [DOCUMENTS],
+            "to": [DOCUMENTS]
+        }]
+    })
+}
+
+impl FormatrixDb {
+    /// Create the `links` edge collection and the `doc_graph` named graph
+    /// over it, if missing
+    pub(crate) async fn ensure_graph(&self) -> Result<()> {
+        match self.admin().get(&format!("/collection/{LINKS}")).await? {
+            None => {
+                self.admin()
+                    .post(
+                        "/collection",
+                        &json!({ "name": LINKS, "type": EDGE_COLLECTION }),
+                    )
+                    .await?;
+                tracing::info!("created edge collection '{}'", LINKS);
+            }
+            Some(info) if info["type"] != EDGE_COLLECTION => {
+                return Err(DbError::Config(format!(
+                    "collection '{LINKS}' exists but is not an edge collection"
+                )));
+            }
+            Some(_) => {}
+        }
+        if self
+            .admin()
+            .get(&format!("/gharial/{GRAPH}"))
+            .await?
+            .is_none()
+        {
+            self.admin().post("/gharial", &graph_definition()).await?;
+            tracing::info!("created graph '{}'", GRAPH);
+        }
+        Ok(())
+    }
+
+    /// The documents matching `filter` and the links between them
+    pub async fn export_graph(&self, filter: &GraphFilter) -> Result<GraphExport> {
+        self.query(
+            "LET nodes = (FOR d IN @@documents \
+                 FILTER @tags ALL IN d.tags AND (@format == null OR d.format == @format) \
+                 SORT d.title \
+                 RETURN { key: d._key, title: d.title, format: d.format, tags: d.tags }) \
+             LET ids = nodes[* RETURN CONCAT(@documents, '/', CURRENT.key)] \
+             LET edges = (FOR l IN @@links \
+                 FILTER l._from IN ids AND l._to IN ids \
+                 FILTER LENGTH(@types) == 0 OR l.link_type IN @types \
+                 RETURN { source: PARSE_IDENTIFIER(l._from).key, \
+                          target: PARSE_IDENTIFIER(l._to).key, \
+                          link_type: l.link_type, label: l.label }) \
+             RETURN { nodes, edges }",
+            HashMap::from([
+                ("tags", json!(filter.tags)),
+                ("format", json!(filter.format)),
+                ("types", json!(filter.link_types)),
+                ("documents", json!(DOCUMENTS)),
+                ("@documents", json!(DOCUMENTS)),
+                ("@links", json!(LINKS)),
+            ]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DbError::Query("graph export returned no row".to_string()))
+    }
+
+    /// The shortest chain of links between two documents
+    ///
+    /// `None` if they aren't connected or either doesn't exist. A document's
+    /// path to itself is just that document.
+    pub async fn shortest_path(
+        &self,
+        from_key: &str,
+        to_key: &str,
+    ) -> Result<Option<DocumentPath>> {
+        let steps: Vec<Step> = self
+            .query(
+                "FOR v, e IN ANY SHORTEST_PATH @from TO @to GRAPH @graph \
+                 RETURN { document: v, link: e }",
+                HashMap::from([
+                    ("from", json!(document_id(from_key))),
+                    ("to", json!(document_id(to_key))),
+                    ("graph", json!(GRAPH)),
+                ]),
+            )
+            .await?;
+        Ok(path_from_steps(steps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LinkType;
+
+    fn step(key: &str, link: Option<DocumentLink>) -> Step {
+        let mut document = StoredDocument::new(key, "", "md");
+        document.key = Some(key.to_string());
+        Step { document, link }
+    }
+
+    #[test]
+    fn test_path_from_steps() {
+        assert!(path_from_steps(Vec::new()).is_none());
+
+        let path = path_from_steps(vec![
+            step("a", None),
+            step("b", Some(DocumentLink::new("a", "b", LinkType::Reference))),
+            // Walked against the link's direction
+            step("c", Some(DocumentLink::new("c", "b", LinkType::Related))),
+        ])
+        .unwrap();
+        assert_eq!(path.keys(), ["a", "b", "c"]);
+        assert_eq!(path.hops(), 2);
+
+        let itself = path_from_steps(vec![step("a", None)]).unwrap();
+        assert_eq!(itself.hops(), 0);
+    }
+
+    fn export() -> GraphExport {
+        let node = |key: &str, title: &str| GraphNode {
+            key: key.to_string(),
+            title: title.to_string(),
+            format: "md".to_string(),
+            tags: vec!["notes".to_string()],
+        };
+        GraphExport {
+            nodes: vec![node("a", "Say \"hi\""), node("b", "R&D <draft>")],
+            edges: vec![GraphEdge {
+                source: "a".to_string(),
+                target: "b".to_string(),
+                link_type: LinkType::Related,
+                label: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_dot_and_graphml() {
+        let export = export();
+        let dot = export.to_dot();
+        assert!(dot.contains(r#""a" [label="Say \"hi\""];"#));
+        assert!(dot.contains(r#""a" -> "b" [label="related"];"#));
+
+        let graphml = export.to_graphml();
+        assert!(graphml.contains("<data key=\"title\">R&amp;D &lt;draft&gt;</data>"));
+        assert!(graphml
+            .contains("<edge source=\"a\" target=\"b\"><data key=\"type\">related</data></edge>"));
+    }
+
+    #[test]
+    fn test_graph_definition() {
+        let definition = graph_definition();
+        assert_eq!(definition["name"], GRAPH);
+        assert_eq!(definition["edgeDefinitions"][0]["collection"], LINKS);
+        assert_eq!(definition["edgeDefinitions"][0]["to"][0], DOCUMENTS);
+    }
+
+    #[test]
+    fn test_d3() {
+        let d3 = export().to_d3();
+        assert_eq!(d3["nodes"][1]["id"], "b");
+        assert_eq!(d3["links"][0]["source"], "a");
+        assert_eq!(d3["links"][0]["type"], "related");
+    }
+}
@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Index management
+//!
+//! [`INDEXES`] lists the secondary indexes the queries in this crate rely
+//! on. [`FormatrixDb::ensure_collections`] creates any that are missing and
+//! replaces any whose definition has drifted; [`FormatrixDb::check_indexes`]
+//! reports the same without changing anything.
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{ATTACHMENTS, AUDIT_LOG, DOCUMENTS, GRANTS, SAVED_SEARCHES};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// A secondary index the crate expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexSpec {
+    pub collection: &'static str,
+    pub name: &'static str,
+    pub fields: &'static [&'static str],
+    /// Leave out documents where a field is missing or null
+    pub sparse: bool,
+}
+
+/// Persistent indexes for tag, format, recency, content-hash, grant,
+/// attachment, saved-search and audit lookups
+pub const INDEXES: &[IndexSpec] = &[
+    IndexSpec {
+        collection: DOCUMENTS,
+        name: "idx_documents_tags",
+        fields: &["tags[*]"],
+        sparse: false,
+    },
+    IndexSpec {
+        collection: DOCUMENTS,
+        name: "idx_documents_format",
+        fields: &["format"],
+        sparse: false,
+    },
+    IndexSpec {
+        collection: DOCUMENTS,
+        name: "idx_documents_updated_at",
+        fields: &["updated_at"],
+        sparse: false,
+    },
+    IndexSpec {
+        collection: DOCUMENTS,
+        name: "idx_documents_content_hash",
+        fields: &["content_hash"],
+        sparse: true,
+    },
+    IndexSpec {
+        collection: GRANTS,
+        name: "idx_grants_document_user",
+        fields: &["document", "user"],
+        sparse: false,
+    },
+    IndexSpec {
+        collection: ATTACHMENTS,
+        name: "idx_attachments_document_name",
+        fields: &["document", "name"],
+        sparse: false,
+    },
+    IndexSpec {
+        collection: SAVED_SEARCHES,
+        name: "idx_saved_searches_name",
+        fields: &["name"],
+        sparse: false,
+    },
+    IndexSpec {
+        collection: AUDIT_LOG,
+        name: "idx_audit_log_document_at",
+        fields: &["document", "at"],
+        sparse: false,
+    },
+    IndexSpec {
+        collection: AUDIT_LOG,
+        name: "idx_audit_log_user_at",
+        fields: &["user", "at"],
+        sparse: false,
+    },
+];
+
+/// An index as reported by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexInfo {
+    /// `collection/number`
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub sparse: bool,
+    #[serde(default)]
+    pub unique: bool,
+}
+
+impl IndexSpec {
+    /// Whether `index` is this index with the expected definition
+    pub fn matches(&self, index: &IndexInfo) -> bool {
+        index.name == self.name
+            && index.kind == "persistent"
+            && index.fields == self.fields
+            && index.sparse == self.sparse
+            && !index.unique
+    }
+
+    fn definition(&self) -> serde_json::Value {
+        json!({
+            "type": "persistent",
+            "name": self.name,
+            "fields": self.fields,
+            "sparse": self.sparse,
+            "unique": false,
+        })
+    }
+}
+
+/// State of one expected index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexStatus {
+    Ok,
+    Missing,
+    /// An index of that name exists with a different definition
+    Mismatched,
+}
+
+/// Result of checking or repairing one expected index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexReport {
+    pub collection: String,
+    pub name: String,
+    /// Status before any repair
+    pub status: IndexStatus,
+    /// Whether a repair created or replaced the index
+    pub repaired: bool,
+}
+
+/// Compare the expected indexes of `collection` with `existing`
+fn compare(collection: &str, existing: &[IndexInfo]) -> Vec<(&'static IndexSpec, IndexStatus)> {
+    INDEXES
+        .iter()
+        .filter(|spec| spec.collection == collection)
+        .map(|spec| {
+            let status = match existing.iter().find(|index| index.name == spec.name) {
+                None => IndexStatus::Missing,
+                Some(index) if spec.matches(index) => IndexStatus::Ok,
+                Some(_) => IndexStatus::Mismatched,
+            };
+            (spec, status)
+        })
+        .collect()
+}
+
+impl FormatrixDb {
+    /// All indexes of `collection`, including the primary and edge indexes
+    pub async fn list_indexes(&self, collection: &str) -> Result<Vec<IndexInfo>> {
+        #[derive(Deserialize)]
+        struct Response {
+            indexes: Vec<IndexInfo>,
+        }
+
+        let response = self
+            .admin()
+            .get(&format!("/index?collection={}", collection))
+            .await?
+            .ok_or_else(|| DbError::NotFound {
+                key: collection.to_string(),
+            })?;
+        let response: Response = serde_json::from_value(response)?;
+        Ok(response.indexes)
+    }
+
+    /// Compare the indexes in [`INDEXES`] with the server's
+    pub async fn check_indexes(&self) -> Result<Vec<IndexReport>> {
+        self.reconcile_indexes(false).await
+    }
+
+    /// Create missing indexes and recreate mismatched ones
+    pub async fn repair_indexes(&self) -> Result<Vec<IndexReport>> {
+        self.reconcile_indexes(true).await
+    }
+
+    async fn reconcile_indexes(&self, repair: bool) -> Result<Vec<IndexReport>> {
+        let mut collections: Vec<&str> = INDEXES.iter().map(|spec| spec.collection).collect();
+        collections.dedup();
+
+        let mut reports = Vec::new();
+        for collection in collections {
+            let existing = self.list_indexes(collection).await?;
+            for (spec, status) in compare(collection, &existing) {
+                let repaired = repair && status != IndexStatus::Ok;
+                if repaired {
+                    if let Some(old) = existing.iter().find(|index| index.name == spec.name) {
+                        self.admin().delete(&format!("/index/{}", old.id)).await?;
+                    }
+                    self.admin()
+                        .post(
+                            &format!("/index?collection={}", collection),
+                            &spec.definition(),
+                        )
+                        .await?;
+                    tracing::info!("created index '{}' on '{}'", spec.name, collection);
+                }
+                reports.push(IndexReport {
+                    collection: collection.to_string(),
+                    name: spec.name.to_string(),
+                    status,
+                    repaired,
+                });
+            }
+        }
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(name: &str, fields: &[&str], sparse: bool) -> IndexInfo {
+        IndexInfo {
+            id: format!("documents/{}", name.len()),
+            name: name.to_string(),
+            kind: "persistent".to_string(),
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            sparse,
+            unique: false,
+        }
+    }
+
+    #[test]
+    fn test_compare_indexes() {
+        let existing = vec![
+            index("primary", &["_key"], false),
+            index("idx_documents_tags", &["tags[*]"], false),
+            index("idx_documents_format", &["format", "title"], false),
+            index("idx_documents_content_hash", &["content_hash"], true),
+        ];
+        let statuses: Vec<(&str, IndexStatus)> = compare(DOCUMENTS, &existing)
+            .into_iter()
+            .map(|(spec, status)| (spec.name, status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("idx_documents_tags", IndexStatus::Ok),
+                ("idx_documents_format", IndexStatus::Mismatched),
+                ("idx_documents_updated_at", IndexStatus::Missing),
+                ("idx_documents_content_hash", IndexStatus::Ok),
+            ]
+        );
+        assert!(compare("tags", &existing).is_empty());
+    }
+
+    #[test]
+    fn test_index_info_from_server() {
+        let info: IndexInfo = serde_json::from_value(json!({
+            "id": "documents/12",
+            "name": "idx_documents_tags",
+            "type": "persistent",
+            "fields": ["tags[*]"],
+            "sparse": false,
+            "unique": false,
+            "deduplicate": true
+        }))
+        .unwrap();
+        assert!(INDEXES[0].matches(&info));
+    }
+}
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Background maintenance jobs
+//!
+//! Rebuilding the search view, recounting tags, re-embedding documents and
+//! refreshing backlinks all walk the whole library. A [`JobQueue`] runs
+//! them one at a time on a Tokio task, so callers can queue one and carry
+//! on, then poll [`JobQueue::status`]. A kind of job already waiting in the
+//! queue is not queued twice. Statuses are kept in memory until
+//! [`JobQueue::clear_finished`]; they do not survive a restart.
+
+use crate::client::FormatrixDb;
+use crate::embeddings::Embedder;
+use crate::error::{DbError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::sync::mpsc;
+
+/// A kind of maintenance job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// [`FormatrixDb::rebuild_search_view`]
+    RebuildSearchView,
+    /// [`FormatrixDb::recount_tags`]
+    RecountTags,
+    /// [`FormatrixDb::embed_stale`], with the queue's embedder
+    EmbedStale,
+    /// [`FormatrixDb::refresh_backlinks`]
+    RefreshBacklinks,
+}
+
+/// Where a job is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobState::Succeeded | JobState::Failed)
+    }
+}
+
+/// A queued, running or finished job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub kind: JobKind,
+    pub state: JobState,
+    /// What the job counted: distinct tags, documents embedded or
+    /// documents refreshed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub queued_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// The statuses of a queue's jobs, by id
+#[derive(Debug, Default)]
+struct Registry {
+    next_id: u64,
+    jobs: BTreeMap<u64, JobStatus>,
+}
+
+impl Registry {
+    /// Queue a job of `kind`, returning its id and whether it is new, or
+    /// the id of the one already waiting
+    fn queue(&mut self, kind: JobKind) -> (u64, bool) {
+        let waiting = self
+            .jobs
+            .values()
+            .find(|job| job.kind == kind && job.state == JobState::Queued);
+        if let Some(job) = waiting {
+            return (job.id, false);
+        }
+        self.next_id += 1;
+        let id = self.next_id;
+        self.jobs.insert(
+            id,
+            JobStatus {
+                id,
+                kind,
+                state: JobState::Queued,
+                count: None,
+                error: None,
+                queued_at: Utc::now(),
+                started_at: None,
+                finished_at: None,
+            },
+        );
+        (id, true)
+    }
+
+    fn start(&mut self, id: u64) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.state = JobState::Running;
+            job.started_at = Some(Utc::now());
+        }
+    }
+
+    fn finish(&mut self, id: u64, result: Result<Option<usize>>) {
+        let Some(job) = self.jobs.get_mut(&id) else {
+            return;
+        };
+        match result {
+            Ok(count) => {
+                job.state = JobState::Succeeded;
+                job.count = count;
+            }
+            Err(err) => {
+                job.state = JobState::Failed;
+                job.error = Some(err.to_string());
+            }
+        }
+        job.finished_at = Some(Utc::now());
+    }
+}
+
+/// Runs maintenance jobs against one database in the background
+pub struct JobQueue {
+    registry: Arc<Mutex<Registry>>,
+    sender: mpsc::UnboundedSender<(u64, JobKind)>,
+    has_embedder: bool,
+}
+
+impl JobQueue {
+    /// Start the worker task; must be called within a Tokio runtime
+    ///
+    /// Without an embedder, [`JobKind::EmbedStale`] jobs are refused. The
+    /// worker stops once the queue is dropped and its current job is done.
+    pub fn new(db: Arc<FormatrixDb>, embedder: Option<Arc<dyn Embedder>>) -> Self {
+        let registry = Arc::new(Mutex::new(Registry::default()));
+        let (sender, mut receiver) = mpsc::unbounded_channel::<(u64, JobKind)>();
+        let has_embedder = embedder.is_some();
+        let worker = Arc::clone(&registry);
+        tokio::spawn(async move {
+            let lock = || worker.lock().unwrap_or_else(PoisonError::into_inner);
+            while let Some((id, kind)) = receiver.recv().await {
+                lock().start(id);
+                tracing::info!("job {} ({:?}) started", id, kind);
+                let result = run(&db, embedder.as_deref(), kind).await;
+                if let Err(err) = &result {
+                    tracing::warn!("job {} ({:?}) failed: {}", id, kind, err);
+                }
+                lock().finish(id, result);
+            }
+        });
+        Self {
+            registry,
+            sender,
+            has_embedder,
+        }
+    }
+
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queue a job of `kind`, returning its id
+    pub fn submit(&self, kind: JobKind) -> Result<u64> {
+        if kind == JobKind::EmbedStale && !self.has_embedder {
+            return Err(DbError::Config("the job queue has no embedder".to_string()));
+        }
+        let mut registry = self.registry();
+        let (id, new) = registry.queue(kind);
+        if new && self.sender.send((id, kind)).is_err() {
+            registry.finish(
+                id,
+                Err(DbError::Config("the job worker stopped".to_string())),
+            );
+        }
+        Ok(id)
+    }
+
+    pub fn status(&self, id: u64) -> Option<JobStatus> {
+        self.registry().jobs.get(&id).cloned()
+    }
+
+    /// Every job still known, oldest first
+    pub fn jobs(&self) -> Vec<JobStatus> {
+        self.registry().jobs.values().cloned().collect()
+    }
+
+    /// Forget finished jobs, returning how many
+    pub fn clear_finished(&self) -> usize {
+        let mut registry = self.registry();
+        let before = registry.jobs.len();
+        registry.jobs.retain(|_, job| !job.state.is_finished());
+        before - registry.jobs.len()
+    }
+}
+
+async fn run(
+    db: &FormatrixDb,
+    embedder: Option<&dyn Embedder>,
+    kind: JobKind,
+) -> Result<Option<usize>> {
+    match kind {
+        JobKind::RebuildSearchView => db.rebuild_search_view().await.map(|()| None),
+        JobKind::RecountTags => db.recount_tags().await.map(Some),
+        JobKind::EmbedStale => match embedder {
+            Some(embedder) => db.embed_stale(embedder).await.map(Some),
+            None => Err(DbError::Config("the job queue has no embedder".to_string())),
+        },
+        JobKind::RefreshBacklinks => db.refresh_backlinks().await.map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::default();
+        let (tags, new) = registry.queue(JobKind::RecountTags);
+        assert!(new);
+        assert_eq!(registry.queue(JobKind::RecountTags), (tags, false));
+
+        registry.start(tags);
+        let (again, new) = registry.queue(JobKind::RecountTags);
+        assert!(new && again != tags);
+        registry.finish(tags, Ok(Some(12)));
+        assert_eq!(registry.jobs[&tags].state, JobState::Succeeded);
+        assert_eq!(registry.jobs[&tags].count, Some(12));
+
+        registry.start(again);
+        registry.finish(again, Err(DbError::Config("down".to_string())));
+        let failed = &registry.jobs[&again];
+        assert_eq!(failed.state, JobState::Failed);
+        assert!(failed.error.as_deref().is_some_and(|e| e.contains("down")));
+        assert!(failed.finished_at >= failed.started_at);
+    }
+}
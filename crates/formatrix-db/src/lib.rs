@@ -1,3 +1,100 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
-// Stub for formatrix-db
+//! Formatrix DB - ArangoDB storage for the gist library
+//!
+//! Documents live in the `documents` collection, typed links between them
+//! in the `links` edge collection (traversed through the `doc_graph` named
+//! graph), tag usage counts in `tags`, ordered groupings in `notebooks`,
+//! users, their share grants and share links in `users`, `grants` and
+//! `share_tokens`, files documents refer to in `attachments`, named
+//! library views in `saved_searches`, a snapshot of every save in
+//! `document_versions`, vectors for semantic search in `embeddings`, an
+//! optional record of who accessed what in `audit_log`, and the schema
+//! version in `meta`. Full-text search goes through the
+//! `documents_search` ArangoSearch view. [`FormatrixDb`] wraps a
+//! connection and runs parameterised AQL for every operation.
+
+#![forbid(unsafe_code)]
+
+pub mod acl;
+mod admin;
+pub mod analytics;
+pub mod archive;
+pub mod attachments;
+pub mod audit;
+pub mod autolink;
+pub mod backup;
+pub mod bulk;
+pub mod cache;
+pub mod client;
+pub mod dedup;
+pub mod embeddings;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod error;
+#[cfg(feature = "git-history")]
+pub mod git_history;
+pub mod graph;
+pub mod indexes;
+pub mod jobs;
+pub mod link_audit;
+pub mod memory;
+pub mod metrics;
+pub mod migrations;
+pub mod models;
+pub mod notebooks;
+pub mod page;
+pub mod pins;
+pub mod query;
+pub mod retry;
+pub mod saved_searches;
+pub mod search;
+pub mod share_tokens;
+pub mod snippets;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod store;
+pub mod tags;
+pub mod transaction;
+pub mod versions;
+
+pub use acl::{Access, ShareGrant, User};
+pub use analytics::{DocumentRank, LibraryStats, LinkCounts, MonthCount};
+pub use attachments::Attachment;
+pub use audit::{AuditAction, AuditEvent, AuditQuery};
+pub use autolink::LinkChanges;
+pub use backup::{BackupHeader, BackupSummary};
+pub use bulk::ImportSummary;
+pub use cache::CachedStore;
+pub use client::{DbConfig, FormatrixDb};
+pub use dedup::{DuplicateDocument, DuplicateGroup, SimilarDocument};
+pub use embeddings::{Embedder, Embedding, SemanticResult};
+#[cfg(feature = "encryption")]
+pub use encryption::{ContentKey, EncryptedStore};
+pub use error::{DbError, Result};
+#[cfg(feature = "git-history")]
+pub use git_history::GitHistory;
+pub use graph::{DocumentPath, GraphEdge, GraphExport, GraphFilter, GraphNode};
+pub use indexes::{IndexInfo, IndexReport, IndexStatus};
+pub use jobs::{JobKind, JobQueue, JobState, JobStatus};
+pub use link_audit::LinkAudit;
+pub use memory::MemoryStore;
+pub use metrics::{ErrorCounts, Metrics, ServerVersion};
+pub use models::{
+    DocumentLink, DocumentRef, LinkType, SaveOptions, SearchResult, StoredDocument, TagInfo,
+    Visibility,
+};
+pub use notebooks::Notebook;
+pub use page::{Page, PageRequest};
+pub use query::{DocumentQuery, DocumentSort};
+pub use retry::RetryPolicy;
+pub use saved_searches::SavedSearch;
+pub use search::{SearchMode, SearchOptions, SearchQuery};
+pub use share_tokens::{ShareAccess, ShareToken, ShareTokenRecord};
+pub use snippets::{MatchSpan, SearchField, Snippet};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use store::DocumentStore;
+pub use tags::TagNode;
+pub use transaction::Transaction;
+pub use versions::{DocumentVersion, VersionInfo};
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Dangling link audit
+//!
+//! Deleting a document through this crate removes its links, but links
+//! written by other clients, partial restores and edits made in the web UI
+//! can leave edges whose source or target is gone, and `parent` keys
+//! naming deleted documents. [`FormatrixDb::audit_links`] finds both;
+//! [`FormatrixDb::repair_links`] also removes the edges and clears the
+//! parents.
+
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::{DocumentLink, DOCUMENTS, LINKS};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// What [`FormatrixDb::audit_links`] found
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkAudit {
+    /// Links whose source or target document no longer exists
+    pub dangling_links: Vec<DocumentLink>,
+    /// Keys of documents whose `parent` no longer exists
+    pub missing_parents: Vec<String>,
+    /// Whether a repair removed the links and cleared the parents
+    pub repaired: bool,
+}
+
+impl LinkAudit {
+    /// Whether nothing dangles
+    pub fn is_clean(&self) -> bool {
+        self.dangling_links.is_empty() && self.missing_parents.is_empty()
+    }
+}
+
+impl FormatrixDb {
+    /// Find links and parents that point at missing documents
+    pub async fn audit_links(&self) -> Result<LinkAudit> {
+        self.reconcile_links(false).await
+    }
+
+    /// Find links and parents that point at missing documents, then
+    /// remove the links and clear the parents
+    pub async fn repair_links(&self) -> Result<LinkAudit> {
+        self.reconcile_links(true).await
+    }
+
+    async fn reconcile_links(&self, repair: bool) -> Result<LinkAudit> {
+        let dangling_links: Vec<DocumentLink> = self
+            .query(
+                "FOR l IN @@links \
+                 FILTER DOCUMENT(l._from) == null OR DOCUMENT(l._to) == null RETURN l",
+                HashMap::from([("@links", json!(LINKS))]),
+            )
+            .await?;
+        let missing_parents: Vec<String> = self
+            .query(
+                "FOR d IN @@documents \
+                 FILTER d.parent != null AND DOCUMENT(@@documents, d.parent) == null \
+                 RETURN d._key",
+                HashMap::from([("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+        let mut audit = LinkAudit {
+            dangling_links,
+            missing_parents,
+            repaired: false,
+        };
+        if !repair || audit.is_clean() {
+            return Ok(audit);
+        }
+
+        let links: Vec<&str> = audit
+            .dangling_links
+            .iter()
+            .filter_map(|link| link.key.as_deref())
+            .collect();
+        let trx = self.begin_transaction(&[LINKS, DOCUMENTS]).await?;
+        let result = async {
+            trx.query::<Value>(
+                "FOR k IN @keys REMOVE k IN @@links OPTIONS { ignoreErrors: true }",
+                HashMap::from([("keys", json!(links)), ("@links", json!(LINKS))]),
+            )
+            .await?;
+            trx.query::<Value>(
+                "FOR k IN @keys \
+                 UPDATE k WITH { parent: null } IN @@documents OPTIONS { ignoreErrors: true }",
+                HashMap::from([
+                    ("keys", json!(audit.missing_parents)),
+                    ("@documents", json!(DOCUMENTS)),
+                ]),
+            )
+            .await?;
+            Ok(())
+        }
+        .await;
+        trx.finish(result).await?;
+        tracing::info!(
+            "removed {} dangling links and cleared {} missing parents",
+            links.len(),
+            audit.missing_parents.len()
+        );
+        audit.repaired = true;
+        Ok(audit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LinkType;
+
+    #[test]
+    fn test_is_clean() {
+        let mut audit = LinkAudit::default();
+        assert!(audit.is_clean());
+        audit
+            .dangling_links
+            .push(DocumentLink::new("1", "2", LinkType::Reference));
+        assert!(!audit.is_clean());
+    }
+}
@@ -0,0 +1,446 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! In-memory backend
+//!
+//! [`MemoryStore`] implements [`DocumentStore`] on plain maps, for
+//! integration tests and demo mode without a database. It honours the
+//! same contract as [`crate::FormatrixDb`]: revision checks, tag counts,
+//! [`DocumentQuery`] filters and sorts, and search syntax. There are no
+//! share grants, so a query's reader sees only their own, unowned and
+//! `Public` documents. Search matches lower-cased words without stemming,
+//! scoring one per occurrence and two in the title. Nothing is persisted.
+
+use crate::acl::access_for;
+use crate::dedup::content_hash;
+use crate::error::{DbError, Result};
+use crate::models::{
+    document_id, DocumentLink, DocumentRef, SaveOptions, SearchResult, StoredDocument, TagInfo,
+};
+use crate::page::{Page, PageRequest};
+use crate::query::{DocumentQuery, DocumentSort};
+use crate::search::SearchQuery;
+use crate::snippets::search_result;
+use crate::store::DocumentStore;
+use chrono::Utc;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+#[derive(Debug, Default)]
+struct State {
+    documents: BTreeMap<String, StoredDocument>,
+    links: BTreeMap<String, DocumentLink>,
+    /// Last key or revision handed out
+    sequence: u64,
+}
+
+impl State {
+    fn next(&mut self) -> String {
+        self.sequence += 1;
+        self.sequence.to_string()
+    }
+
+    fn save(&mut self, doc: &StoredDocument, options: SaveOptions) -> Result<DocumentRef> {
+        if let (Some(key), Some(rev), false) = (&doc.key, &doc.rev, options.force) {
+            match self.documents.get(key) {
+                None => {
+                    return Err(DbError::NotFound { key: key.clone() });
+                }
+                Some(stored) if stored.rev.as_ref() != Some(rev) => {
+                    return Err(DbError::Conflict { key: key.clone() });
+                }
+                Some(_) => {}
+            }
+        }
+        let key = doc.key.clone().unwrap_or_else(|| self.next());
+        let rev = self.next();
+        let mut stored = doc.clone();
+        stored.key = Some(key.clone());
+        stored.rev = Some(rev.clone());
+        stored.updated_at = Utc::now();
+        stored.content_hash = Some(content_hash(&doc.content));
+        self.documents.insert(key.clone(), stored);
+        Ok(DocumentRef { key, rev })
+    }
+
+    fn add_link(&mut self, link: &DocumentLink) -> String {
+        let key = self.next();
+        let mut stored = link.clone();
+        stored.key = Some(key.clone());
+        self.links.insert(key.clone(), stored);
+        key
+    }
+
+    fn replace_links(&mut self, key: &str, links: &[DocumentLink]) {
+        self.links.retain(|_, link| link.from_key() != key);
+        for link in links {
+            self.add_link(&DocumentLink {
+                from: document_id(key),
+                ..link.clone()
+            });
+        }
+    }
+}
+
+/// A document library held in memory
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    state: Mutex<State>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Lower-cased words of `text`
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Occurrences of the word sequence `needle` in `haystack`
+fn occurrences(haystack: &[String], needle: &[String]) -> usize {
+    if needle.is_empty() {
+        return 0;
+    }
+    haystack
+        .windows(needle.len())
+        .filter(|window| *window == needle)
+        .count()
+}
+
+/// Relevance of `doc` to `search`; `None` if it doesn't match
+fn score(search: &SearchQuery, doc: &StoredDocument) -> Option<f64> {
+    let title = words(&doc.title);
+    let content = words(&doc.content);
+    let count = |needle: &[String]| 2 * occurrences(&title, needle) + occurrences(&content, needle);
+
+    let excluded = search.excluded.iter().any(|word| count(&words(word)) > 0);
+    let phrases: Option<Vec<usize>> = search
+        .phrases
+        .iter()
+        .map(|phrase| Some(count(&words(phrase))).filter(|n| *n > 0))
+        .collect();
+    let terms: usize = search
+        .terms
+        .iter()
+        .flat_map(|term| words(term))
+        .map(|word| count(&[word]))
+        .sum();
+    if excluded || (!search.terms.is_empty() && terms == 0) {
+        return None;
+    }
+    Some((terms + phrases?.iter().sum::<usize>()) as f64)
+}
+
+/// Whether `doc` passes `query`'s filters other than the text
+fn passes(query: &DocumentQuery, doc: &StoredDocument) -> bool {
+    let in_range = |time, after: Option<_>, before: Option<_>| {
+        after.is_none_or(|after| time >= after) && before.is_none_or(|before| time < before)
+    };
+    query.all_tags.iter().all(|tag| doc.tags.contains(tag))
+        && (query.any_tags.is_empty() || query.any_tags.iter().any(|tag| doc.tags.contains(tag)))
+        && query
+            .format
+            .as_ref()
+            .is_none_or(|format| doc.format == *format)
+        && query
+            .visibility
+            .is_none_or(|visibility| doc.visibility == visibility)
+        && query
+            .parent
+            .as_ref()
+            .is_none_or(|parent| doc.parent.as_ref() == Some(parent))
+        && query.pinned.is_none_or(|pinned| doc.pinned == pinned)
+        && query
+            .favorite
+            .is_none_or(|favorite| doc.favorite == favorite)
+        && (query.include_archived || !doc.archived)
+        && query
+            .reader
+            .as_ref()
+            .is_none_or(|user| access_for(doc, user, None).is_some())
+        && in_range(doc.created_at, query.created_after, query.created_before)
+        && in_range(doc.updated_at, query.updated_after, query.updated_before)
+}
+
+/// One page of the documents matching `query`, with their scores
+fn find(state: &State, query: &DocumentQuery, page: PageRequest) -> Page<(StoredDocument, f64)> {
+    let search = SearchQuery::parse(query.text.as_deref().unwrap_or_default());
+    let text = !search.is_empty();
+    let mut found: Vec<(&StoredDocument, f64)> = state
+        .documents
+        .values()
+        .filter(|doc| passes(query, doc))
+        .filter_map(|doc| {
+            let score = if text { score(&search, doc)? } else { 0.0 };
+            Some((doc, score))
+        })
+        .collect();
+
+    let order = |(a, a_score): &(&StoredDocument, f64), (b, b_score): &(&StoredDocument, f64)| {
+        let primary = match query.sort {
+            DocumentSort::Relevance if text => b_score.total_cmp(a_score),
+            DocumentSort::Relevance | DocumentSort::RecentlyUpdated => {
+                b.updated_at.cmp(&a.updated_at)
+            }
+            DocumentSort::Title => a.title.cmp(&b.title),
+            DocumentSort::NewestFirst => b.created_at.cmp(&a.created_at),
+            DocumentSort::OldestFirst => a.created_at.cmp(&b.created_at),
+            DocumentSort::LeastRecentlyUpdated => a.updated_at.cmp(&b.updated_at),
+        };
+        let pinned = if query.pinned_first {
+            b.pinned.cmp(&a.pinned)
+        } else {
+            Ordering::Equal
+        };
+        pinned.then(primary).then_with(|| a.key.cmp(&b.key))
+    };
+    found.sort_by(order);
+
+    let total = found.len();
+    let items = found
+        .into_iter()
+        .skip(page.offset)
+        .take(page.limit)
+        .map(|(doc, score)| (doc.clone(), score))
+        .collect();
+    Page::new(items, total, page)
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for MemoryStore {
+    async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        self.state().save(doc, options)
+    }
+
+    async fn get_document(&self, key: &str) -> Result<StoredDocument> {
+        self.state()
+            .documents
+            .get(key)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound {
+                key: key.to_string(),
+            })
+    }
+
+    async fn delete_document(&self, key: &str) -> Result<()> {
+        let mut state = self.state();
+        if state.documents.remove(key).is_none() {
+            return Err(DbError::NotFound {
+                key: key.to_string(),
+            });
+        }
+        state
+            .links
+            .retain(|_, link| link.from_key() != key && link.to_key() != key);
+        Ok(())
+    }
+
+    async fn find_documents(
+        &self,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let found = find(&self.state(), query, page);
+        let items = found.items.into_iter().map(|(doc, _)| doc).collect();
+        Ok(Page::new(items, found.total, page))
+    }
+
+    async fn search_fulltext(&self, query: &str, page: PageRequest) -> Result<Page<SearchResult>> {
+        let parsed = SearchQuery::parse(query);
+        if parsed.is_empty() {
+            return Ok(Page::new(Vec::new(), 0, page));
+        }
+        let found = find(&self.state(), &DocumentQuery::new().with_text(query), page);
+        let items = found
+            .items
+            .into_iter()
+            .map(|(doc, score)| search_result(doc, score, &parsed))
+            .collect();
+        Ok(Page::new(items, found.total, page))
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        let mut counts: HashMap<&str, u64> = HashMap::new();
+        let state = self.state();
+        for doc in state.documents.values() {
+            let mut tags: Vec<&str> = doc.tags.iter().map(String::as_str).collect();
+            tags.sort_unstable();
+            tags.dedup();
+            for tag in tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        let mut tags: Vec<TagInfo> = counts
+            .into_iter()
+            .map(|(name, count)| TagInfo {
+                name: name.to_string(),
+                count,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+        Ok(tags)
+    }
+
+    async fn add_link(&self, link: &DocumentLink) -> Result<String> {
+        Ok(self.state().add_link(link))
+    }
+
+    async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>> {
+        let mut state = self.state();
+        Ok(links.iter().map(|link| state.add_link(link)).collect())
+    }
+
+    async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+        self.state().replace_links(key, links);
+        Ok(())
+    }
+
+    async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        let state = self.state();
+        Ok(state
+            .links
+            .values()
+            .filter(|link| link.from_key() == key)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        let state = self.state();
+        Ok(state
+            .links
+            .values()
+            .filter(|link| link.to_key() == key)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_with_links(
+        &self,
+        doc: &StoredDocument,
+        links: &[DocumentLink],
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let mut state = self.state();
+        let saved = state.save(doc, options)?;
+        state.replace_links(&saved.key, links);
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LinkType;
+
+    #[test]
+    fn test_score() {
+        let doc = StoredDocument::new("Borrow checker", "The borrow checker rejects it.", "md");
+        let score = |query: &str| score(&SearchQuery::parse(query), &doc);
+        assert_eq!(score("borrow"), Some(3.0));
+        assert_eq!(score("BORROW go"), Some(3.0));
+        assert_eq!(score("\"rejects it\""), Some(1.0));
+        assert_eq!(score("borrow \"it rejects\""), None);
+        assert_eq!(score("borrow -checker"), None);
+        assert_eq!(score("go"), None);
+    }
+
+    #[tokio::test]
+    async fn test_store() {
+        let store = MemoryStore::new();
+        let a = store
+            .save_document(&StoredDocument::new("Rust", "Ownership", "md").with_tags(["rust"]))
+            .await
+            .unwrap();
+        let b = store
+            .save_document(&StoredDocument::new("Go", "Goroutines", "org").with_tags(["go"]))
+            .await
+            .unwrap();
+
+        let mut doc = store.get_document(&a.key).await.unwrap();
+        doc.content = "Ownership and borrowing".to_string();
+        let links = [DocumentLink::new("", &b.key, LinkType::Related)];
+        store
+            .save_with_links(&doc, &links, SaveOptions::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.save_document(&doc).await,
+            Err(DbError::Conflict { .. })
+        ));
+        assert_eq!(
+            store.get_backlinks(&b.key).await.unwrap()[0].from_key(),
+            a.key
+        );
+
+        let found = store
+            .find_documents(
+                &DocumentQuery::new()
+                    .with_format("md")
+                    .sorted_by(DocumentSort::Title),
+                PageRequest::first(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.total, 1);
+        let mut pinned = store.get_document(&b.key).await.unwrap();
+        pinned.pinned = true;
+        store.save_document(&pinned).await.unwrap();
+        let recent = store
+            .find_documents(&DocumentQuery::new().pinned_first(), PageRequest::first(10))
+            .await
+            .unwrap();
+        assert_eq!(recent.items[0].key, Some(b.key.clone()));
+        let hits = store
+            .search_fulltext("borrowing", PageRequest::first(10))
+            .await
+            .unwrap();
+        assert_eq!(hits.items[0].key, a.key);
+        assert!(!hits.items[0].matches.is_empty());
+        assert_eq!(store.list_tags().await.unwrap().len(), 2);
+
+        let mut archived = store.get_document(&a.key).await.unwrap();
+        archived.archived = true;
+        store.save_document(&archived).await.unwrap();
+        let hits = store
+            .search_fulltext("borrowing", PageRequest::first(10))
+            .await
+            .unwrap();
+        assert_eq!(hits.total, 0);
+        let all = DocumentQuery::new().including_archived();
+        let found = store
+            .find_documents(&all, PageRequest::first(10))
+            .await
+            .unwrap();
+        assert_eq!(found.total, 2);
+
+        let private = StoredDocument::new("Plan", "", "md").with_owner("ada");
+        store.save_document(&private).await.unwrap();
+        for (reader, total) in [("ada", 3), ("bob", 2)] {
+            let query = all.clone().with_reader(reader);
+            let found = store
+                .find_documents(&query, PageRequest::first(10))
+                .await
+                .unwrap();
+            assert_eq!(found.total, total);
+        }
+
+        store.delete_document(&b.key).await.unwrap();
+        assert!(store.get_links(&a.key).await.unwrap().is_empty());
+        assert!(store.delete_document(&b.key).await.is_err());
+    }
+}
@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Health checks and client metrics
+//!
+//! [`FormatrixDb::ping`] and [`FormatrixDb::server_version`] are meant for
+//! health endpoints. Every query the client runs outside a transaction is
+//! recorded in [`Metrics`]: how many ran, how long they took, including
+//! retries, and how many failed, by kind of error. [`FormatrixDb::metrics`]
+//! returns the counts since connecting; they only grow, so a dashboard
+//! plots the difference between two snapshots.
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::retry::RetryPolicy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the latency histogram buckets
+pub const LATENCY_BUCKETS: [Duration; 10] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+
+/// Failed queries by kind of error
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorCounts {
+    pub connection: u64,
+    pub timeout: u64,
+    pub query: u64,
+    pub conflict: u64,
+    pub not_found: u64,
+    pub other: u64,
+}
+
+impl ErrorCounts {
+    pub fn total(&self) -> u64 {
+        self.connection + self.timeout + self.query + self.conflict + self.not_found + self.other
+    }
+
+    fn count(&mut self, err: &DbError) {
+        let counter = match err {
+            DbError::Connection(_) => &mut self.connection,
+            DbError::Timeout(_) => &mut self.timeout,
+            DbError::Query(_) => &mut self.query,
+            DbError::Conflict { .. } => &mut self.conflict,
+            DbError::NotFound { .. } => &mut self.not_found,
+            _ => &mut self.other,
+        };
+        *counter += 1;
+    }
+}
+
+/// Query counts and latencies since the client connected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub queries: u64,
+    pub errors: ErrorCounts,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+    /// Queries per [`LATENCY_BUCKETS`] entry, counting those no slower
+    /// than its bound and slower than the one before; the extra last entry
+    /// counts the slowest
+    pub latency_histogram: Vec<u64>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            queries: 0,
+            errors: ErrorCounts::default(),
+            total_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            latency_histogram: vec![0; LATENCY_BUCKETS.len() + 1],
+        }
+    }
+}
+
+impl Metrics {
+    /// Average query latency; `None` before the first query
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let queries = u32::try_from(self.queries).ok().filter(|n| *n > 0)?;
+        Some(self.total_latency / queries)
+    }
+
+    pub(crate) fn record(&mut self, latency: Duration, error: Option<&DbError>) {
+        self.queries += 1;
+        if let Some(err) = error {
+            self.errors.count(err);
+        }
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_histogram[bucket] += 1;
+    }
+}
+
+/// Answer of the server's version endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerVersion {
+    /// Always `arango`
+    pub server: String,
+    pub version: String,
+    /// `community` or `enterprise`
+    #[serde(default)]
+    pub license: Option<String>,
+}
+
+impl FormatrixDb {
+    /// Run a trivial query, returning the round trip time
+    ///
+    /// Not retried, so an unreachable server is reported after one
+    /// [`RetryPolicy::timeout`] rather than after every retry.
+    pub async fn ping(&self) -> Result<Duration> {
+        let start = Instant::now();
+        let policy = RetryPolicy {
+            max_retries: 0,
+            ..self.config().retry
+        };
+        policy
+            .run(|| async {
+                let _: Vec<Value> = self.db().aql_bind_vars("RETURN 1", HashMap::new()).await?;
+                Ok(())
+            })
+            .await?;
+        Ok(start.elapsed())
+    }
+
+    /// Version and edition of the ArangoDB server
+    pub async fn server_version(&self) -> Result<ServerVersion> {
+        let version = self
+            .admin()
+            .get("/version")
+            .await?
+            .ok_or_else(|| DbError::Query("version endpoint not found".to_string()))?;
+        Ok(serde_json::from_value(version)?)
+    }
+
+    /// Query counts and latencies since connecting
+    pub fn metrics(&self) -> Metrics {
+        self.metrics_lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut metrics = Metrics::default();
+        assert_eq!(metrics.mean_latency(), None);
+
+        metrics.record(Duration::from_micros(500), None);
+        metrics.record(Duration::from_millis(30), None);
+        metrics.record(
+            Duration::from_secs(7),
+            Some(&DbError::Timeout(Duration::from_secs(7))),
+        );
+        metrics.record(
+            Duration::from_millis(2),
+            Some(&DbError::Conflict {
+                key: "42".to_string(),
+            }),
+        );
+
+        assert_eq!(metrics.queries, 4);
+        assert_eq!(metrics.errors.timeout, 1);
+        assert_eq!(metrics.errors.conflict, 1);
+        assert_eq!(metrics.errors.total(), 2);
+        assert_eq!(metrics.max_latency, Duration::from_secs(7));
+        assert_eq!(metrics.latency_histogram[0], 1);
+        assert_eq!(metrics.latency_histogram[1], 1);
+        assert_eq!(metrics.latency_histogram[4], 1);
+        assert_eq!(metrics.latency_histogram[LATENCY_BUCKETS.len()], 1);
+        assert_eq!(metrics.latency_histogram.iter().sum::<u64>(), 4);
+    }
+
+    #[test]
+    fn test_server_version() {
+        let version: ServerVersion =
+            serde_json::from_str(r#"{"server":"arango","version":"3.12.4","license":"community"}"#)
+                .unwrap();
+        assert_eq!(version.version, "3.12.4");
+        assert_eq!(version.license.as_deref(), Some("community"));
+    }
+}
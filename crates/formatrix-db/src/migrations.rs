@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Schema migrations
+//!
+//! The database records the [`SCHEMA_VERSION`] of its data in the `meta`
+//! collection. On connect, [`FormatrixDb::migrate`] runs every migration
+//! newer than the stored version, in order, and stores the version after
+//! each one, so an interrupted upgrade resumes where it stopped. Clients
+//! connecting at the same time may both run a step, so steps must be safe
+//! to run twice.
+//!
+//! A database without a stored version is new if it holds no documents,
+//! and otherwise has the shape from before versions were recorded, version
+//! 1. A database with a newer version than this build is refused.
+//!
+//! To change a stored shape, bump [`SCHEMA_VERSION`] and append a
+//! migration with the new version to `MIGRATIONS`.
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{stored_time, DOCUMENTS, META, SCHEMA_VERSION};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Key of the schema version record in `meta`
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+type StepFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// One upgrade step
+struct Migration {
+    /// Schema version after the step
+    version: u32,
+    description: &'static str,
+    run: fn(&FormatrixDb) -> StepFuture<'_>,
+}
+
+/// Documents rewritten per query by [`rewrite_document_times`]
+const BATCH_SIZE: usize = 500;
+
+/// Every migration, oldest first
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "store content hashes for duplicate detection",
+        run: store_content_hashes,
+    },
+    Migration {
+        version: 3,
+        description: "store document times at a fixed precision",
+        run: rewrite_document_times,
+    },
+];
+
+fn store_content_hashes(db: &FormatrixDb) -> StepFuture<'_> {
+    Box::pin(async move {
+        let updated = db.backfill_content_hashes().await?;
+        tracing::info!("stored content hashes of {} documents", updated);
+        Ok(())
+    })
+}
+
+fn rewrite_document_times(db: &FormatrixDb) -> StepFuture<'_> {
+    Box::pin(async move {
+        #[derive(Deserialize)]
+        struct Row {
+            key: String,
+            created_at: DateTime<Utc>,
+            updated_at: DateTime<Utc>,
+        }
+
+        let mut updated = 0;
+        loop {
+            let batch: Vec<Row> = db
+                .query(
+                    "FOR d IN @@documents \
+                     FILTER LENGTH(d.created_at) != @len OR LENGTH(d.updated_at) != @len \
+                     LIMIT @limit \
+                     RETURN { key: d._key, created_at: d.created_at, updated_at: d.updated_at }",
+                    HashMap::from([
+                        ("@documents", json!(DOCUMENTS)),
+                        ("len", json!(stored_time::LEN)),
+                        ("limit", json!(BATCH_SIZE)),
+                    ]),
+                )
+                .await?;
+            let times: Vec<Value> = batch
+                .iter()
+                .map(|row| {
+                    json!({
+                        "_key": row.key,
+                        "created_at": stored_time::format(&row.created_at),
+                        "updated_at": stored_time::format(&row.updated_at),
+                    })
+                })
+                .collect();
+            db.query::<Value>(
+                "FOR t IN @times UPDATE t IN @@documents",
+                HashMap::from([("times", json!(times)), ("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+            updated += batch.len();
+            if batch.len() < BATCH_SIZE {
+                break;
+            }
+        }
+        tracing::info!("rewrote the times of {} documents", updated);
+        Ok(())
+    })
+}
+
+/// Migrations to run on a database at version `from`
+fn pending(from: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |m| m.version > from)
+}
+
+impl FormatrixDb {
+    /// Schema version stored in the database; `None` if none is recorded
+    pub async fn schema_version(&self) -> Result<Option<u32>> {
+        let versions: Vec<Option<u32>> = self
+            .query(
+                "RETURN DOCUMENT(@id).version",
+                HashMap::from([("id", json!(format!("{}/{}", META, SCHEMA_VERSION_KEY)))]),
+            )
+            .await?;
+        Ok(versions.into_iter().next().flatten())
+    }
+
+    pub(crate) async fn set_schema_version(&self, version: u32) -> Result<()> {
+        self.query::<Value>(
+            "UPSERT { _key: @key } \
+             INSERT { _key: @key, version: @version, updated_at: @now } \
+             UPDATE { version: @version, updated_at: @now } IN @@meta",
+            HashMap::from([
+                ("@meta", json!(META)),
+                ("key", json!(SCHEMA_VERSION_KEY)),
+                ("version", json!(version)),
+                ("now", json!(Utc::now())),
+            ]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Bring the database up to [`SCHEMA_VERSION`], returning the versions
+    /// migrated to
+    ///
+    /// Fails with [`DbError::Config`] if the database was written by a
+    /// newer build.
+    pub async fn migrate(&self) -> Result<Vec<u32>> {
+        let stored = self.schema_version().await?;
+        let from = match stored {
+            Some(version) => version,
+            None => {
+                let documents: Vec<u64> = self
+                    .query(
+                        "RETURN LENGTH(@@documents)",
+                        HashMap::from([("@documents", json!(DOCUMENTS))]),
+                    )
+                    .await?;
+                if documents.first().copied().unwrap_or_default() == 0 {
+                    SCHEMA_VERSION
+                } else {
+                    1
+                }
+            }
+        };
+        if from > SCHEMA_VERSION {
+            return Err(DbError::Config(format!(
+                "database has schema version {}, this build supports up to {}",
+                from, SCHEMA_VERSION
+            )));
+        }
+
+        let mut applied = Vec::new();
+        for migration in pending(from) {
+            tracing::info!(
+                "migrating to schema version {}: {}",
+                migration.version,
+                migration.description
+            );
+            (migration.run)(self).await?;
+            self.set_schema_version(migration.version).await?;
+            applied.push(migration.version);
+        }
+        if stored.is_none() && applied.is_empty() {
+            self.set_schema_version(from).await?;
+        }
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<u32> = (2..=SCHEMA_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn test_pending() {
+        assert_eq!(pending(1).count(), MIGRATIONS.len());
+        assert_eq!(pending(SCHEMA_VERSION).count(), 0);
+        assert!(pending(1).all(|m| !m.description.is_empty()));
+    }
+}
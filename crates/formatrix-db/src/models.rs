@@ -0,0 +1,352 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Stored documents, links and tags
+
+use crate::snippets::{MatchSpan, Snippet};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Name of the document collection
+pub const DOCUMENTS: &str = "documents";
+/// Name of the link edge collection
+pub const LINKS: &str = "links";
+/// Name of the tag count collection
+pub const TAGS: &str = "tags";
+/// Name of the document snapshot collection
+pub const DOCUMENT_VERSIONS: &str = "document_versions";
+/// Name of the notebook collection
+pub const NOTEBOOKS: &str = "notebooks";
+/// Name of the user collection
+pub const USERS: &str = "users";
+/// Name of the document share grant collection
+pub const GRANTS: &str = "grants";
+/// Name of the share token collection
+pub const SHARE_TOKENS: &str = "share_tokens";
+/// Name of the attachment collection
+pub const ATTACHMENTS: &str = "attachments";
+/// Name of the saved search collection
+pub const SAVED_SEARCHES: &str = "saved_searches";
+/// Name of the document vector collection
+pub const EMBEDDINGS: &str = "embeddings";
+/// Name of the access audit collection
+pub const AUDIT_LOG: &str = "audit_log";
+/// Name of the collection of database-wide records, such as the schema
+/// version
+pub const META: &str = "meta";
+/// Name of the graph over documents and links
+pub const GRAPH: &str = "doc_graph";
+
+/// Version of the stored document, link and tag shapes
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// The `_id` of the document with `key`
+pub fn document_id(key: &str) -> String {
+    format!("{}/{}", DOCUMENTS, key)
+}
+
+/// The `_key` part of a document `_id`
+pub fn key_from_id(id: &str) -> &str {
+    id.rsplit_once('/').map_or(id, |(_, key)| key)
+}
+
+/// Document times as stored: RFC 3339 in UTC with nanoseconds, always the
+/// same width, so AQL can compare and sort them as strings and use the
+/// indexes on them
+pub(crate) mod stored_time {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::Serializer;
+
+    /// Length of a stored time
+    pub const LEN: usize = 30;
+
+    pub fn format(time: &DateTime<Utc>) -> String {
+        time.to_rfc3339_opts(SecondsFormat::Nanos, true)
+    }
+
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(time))
+    }
+}
+
+/// Who may see a document
+///
+/// `Private` documents are visible to their owner only, `Shared` ones also
+/// to the users they are granted to (see [`crate::acl`]) and `Public` ones
+/// to everyone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Private,
+    Shared,
+    Public,
+}
+
+/// A gist in the library
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredDocument {
+    /// Assigned by the database on first save
+    #[serde(rename = "_key", default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// Revision of the stored copy this was read from
+    #[serde(rename = "_rev", default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+
+    pub title: String,
+
+    /// Source text, in `format`
+    pub content: String,
+
+    /// Format id ("md", "org", "adoc", ...)
+    pub format: String,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    #[serde(default)]
+    pub visibility: Visibility,
+
+    /// Key of the owning user; documents without an owner are open to
+    /// everyone, as in a single-user library
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+
+    /// Key of the document this one was split from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+
+    /// [`crate::dedup::content_hash`] of `content`, set on save
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+
+    /// Listed first among recent documents
+    #[serde(default)]
+    pub pinned: bool,
+
+    /// Starred by the user
+    #[serde(default)]
+    pub favorite: bool,
+
+    /// Left out of listings and search unless asked for
+    #[serde(default)]
+    pub archived: bool,
+
+    #[serde(serialize_with = "stored_time::serialize")]
+    pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "stored_time::serialize")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StoredDocument {
+    /// A new, unsaved private document
+    pub fn new(
+        title: impl Into<String>,
+        content: impl Into<String>,
+        format: impl Into<String>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            key: None,
+            rev: None,
+            title: title.into(),
+            content: content.into(),
+            format: format.into(),
+            tags: Vec::new(),
+            visibility: Visibility::Private,
+            owner: None,
+            parent: None,
+            content_hash: None,
+            pinned: false,
+            favorite: false,
+            archived: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn with_tags(mut self, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+}
+
+/// Key and revision of a saved document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentRef {
+    pub key: String,
+    /// Pass back as [`StoredDocument::rev`] on the next save
+    pub rev: String,
+}
+
+/// How [`crate::FormatrixDb::save_document_with`] treats concurrent edits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// Overwrite the stored document even if it changed since `rev` was
+    /// read
+    pub force: bool,
+}
+
+impl SaveOptions {
+    pub fn force() -> Self {
+        Self { force: true }
+    }
+}
+
+/// How one document relates to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkType {
+    /// The source links to or mentions the target
+    Reference,
+    /// The target references the source
+    Backlink,
+    /// The target was split from the source
+    Child,
+    /// Curated "see also"
+    Related,
+}
+
+impl LinkType {
+    /// The stored name, e.g. `reference`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkType::Reference => "reference",
+            LinkType::Backlink => "backlink",
+            LinkType::Child => "child",
+            LinkType::Related => "related",
+        }
+    }
+}
+
+/// An edge in the `links` collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentLink {
+    #[serde(rename = "_key", default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// `_id` of the source document
+    #[serde(rename = "_from")]
+    pub from: String,
+
+    /// `_id` of the target document
+    #[serde(rename = "_to")]
+    pub to: String,
+
+    pub link_type: LinkType,
+
+    /// Link text or note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// Derived from the source's content by [`crate::FormatrixDb::auto_link`]
+    #[serde(default)]
+    pub auto: bool,
+
+    pub created_at: DateTime<Utc>,
+}
+
+impl DocumentLink {
+    /// A link between the documents with keys `from` and `to`
+    pub fn new(from: &str, to: &str, link_type: LinkType) -> Self {
+        Self {
+            key: None,
+            from: document_id(from),
+            to: document_id(to),
+            link_type,
+            label: None,
+            auto: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn from_key(&self) -> &str {
+        key_from_id(&self.from)
+    }
+
+    pub fn to_key(&self) -> &str {
+        key_from_id(&self.to)
+    }
+}
+
+/// A tag and the number of documents carrying it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagInfo {
+    pub name: String,
+    pub count: u64,
+}
+
+/// A full-text search hit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub key: String,
+    pub title: String,
+    pub format: String,
+    pub tags: Vec<String>,
+    /// The first of `snippets`, or the first 200 characters of the content
+    /// when only the title matched
+    pub snippet: String,
+    /// Excerpts of the content around its matches
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+    /// Every match in the title and content
+    #[serde(default)]
+    pub matches: Vec<MatchSpan>,
+    /// BM25 relevance; higher is better
+    #[serde(default)]
+    pub score: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_document_serde() {
+        let doc = StoredDocument::new("Notes", "# Notes", "md").with_tags(["rust"]);
+        let json = serde_json::to_value(&doc).unwrap();
+        assert!(json.get("_key").is_none(), "new documents get a server key");
+        assert_eq!(json["visibility"], "private");
+        let whole_second = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(
+            stored_time::format(&whole_second),
+            "2023-11-14T22:13:20.000000000Z"
+        );
+        assert_eq!(json["updated_at"].as_str().unwrap().len(), stored_time::LEN);
+
+        let mut stored = json;
+        stored["_key"] = "123".into();
+        stored["_rev"] = "_abc".into();
+        let doc: StoredDocument = serde_json::from_value(stored).unwrap();
+        assert_eq!(doc.key.as_deref(), Some("123"));
+        assert_eq!(doc.tags, ["rust"]);
+    }
+
+    #[test]
+    fn test_link_ids() {
+        let link = DocumentLink::new("a", "b", LinkType::Reference);
+        assert_eq!(link.from, "documents/a");
+        assert_eq!((link.from_key(), link.to_key()), ("a", "b"));
+        let json = serde_json::to_value(&link).unwrap();
+        assert_eq!(json["_to"], "documents/b");
+        assert_eq!(json["link_type"], "reference");
+    }
+}
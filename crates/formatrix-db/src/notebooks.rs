@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Notebooks: curated, ordered collections of documents
+//!
+//! Unlike tags, a notebook keeps its members in the order the user chose,
+//! e.g. the chapters of a book outline. A document can be in any number of
+//! notebooks, at most once in each. Member changes read the notebook and
+//! write it back with its revision, so two concurrent edits fail with
+//! [`DbError::Conflict`] instead of losing one of them.
+
+use crate::client::FormatrixDb;
+use crate::error::{self, DbError, Result};
+use crate::models::{DocumentRef, StoredDocument, DOCUMENTS, NOTEBOOKS};
+use crate::transaction::Transaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// An ordered set of documents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notebook {
+    #[serde(rename = "_key", default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(rename = "_rev", default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Document keys, in reading order
+    #[serde(default)]
+    pub members: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Notebook {
+    /// A new, unsaved and empty notebook
+    pub fn new(name: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            key: None,
+            rev: None,
+            name: name.into(),
+            description: String::new(),
+            members: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Put `doc` at `position`, or at the end for `None`; a document that
+    /// is already a member is moved there
+    pub fn insert_member(&mut self, doc: &str, position: Option<usize>) {
+        self.remove_member(doc);
+        let position = position.map_or(self.members.len(), |p| p.min(self.members.len()));
+        self.members.insert(position, doc.to_string());
+    }
+
+    /// Returns whether `doc` was a member
+    pub fn remove_member(&mut self, doc: &str) -> bool {
+        let before = self.members.len();
+        self.members.retain(|member| member != doc);
+        self.members.len() != before
+    }
+}
+
+impl FormatrixDb {
+    /// Insert or update a notebook
+    ///
+    /// As with documents, a notebook read from the database carries its
+    /// `rev` and the save fails with [`DbError::Conflict`] if it changed
+    /// since.
+    pub async fn save_notebook(&self, notebook: &Notebook) -> Result<DocumentRef> {
+        let mut stored = notebook.clone();
+        stored.rev = None;
+        stored.updated_at = Utc::now();
+
+        let (aql, mut vars) = match (&notebook.key, &notebook.rev) {
+            (None, _) => (
+                "INSERT @notebook INTO @@notebooks RETURN { key: NEW._key, rev: NEW._rev }",
+                HashMap::new(),
+            ),
+            (Some(key), Some(rev)) => (
+                "UPDATE { _key: @key, _rev: @rev } WITH @notebook IN @@notebooks \
+                 OPTIONS { ignoreRevs: false } \
+                 RETURN { key: NEW._key, rev: NEW._rev }",
+                HashMap::from([("key", json!(key)), ("rev", json!(rev))]),
+            ),
+            (Some(key), None) => (
+                "UPSERT { _key: @key } INSERT @notebook UPDATE @notebook IN @@notebooks \
+                 RETURN { key: NEW._key, rev: NEW._rev }",
+                HashMap::from([("key", json!(key))]),
+            ),
+        };
+        vars.insert("notebook", serde_json::to_value(&stored)?);
+        vars.insert("@notebooks", json!(NOTEBOOKS));
+
+        let key = notebook.key.as_deref().unwrap_or_default();
+        let saved: Vec<DocumentRef> = self
+            .timed(self.config().retry.run_write(|| async {
+                self.db()
+                    .aql_bind_vars(aql, vars.clone())
+                    .await
+                    .map_err(|err| error::write_error(err, key))
+            }))
+            .await?;
+        saved
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::Query("save returned no key".to_string()))
+    }
+
+    pub async fn get_notebook(&self, key: &str) -> Result<Notebook> {
+        self.query(
+            "RETURN DOCUMENT(@id)",
+            HashMap::from([("id", json!(format!("{}/{}", NOTEBOOKS, key)))]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .flatten()
+        .ok_or_else(|| DbError::NotFound {
+            key: key.to_string(),
+        })
+    }
+
+    /// All notebooks, by name
+    pub async fn list_notebooks(&self) -> Result<Vec<Notebook>> {
+        self.query(
+            "FOR n IN @@notebooks SORT n.name RETURN n",
+            HashMap::from([("@notebooks", json!(NOTEBOOKS))]),
+        )
+        .await
+    }
+
+    /// Delete a notebook; its documents are kept
+    pub async fn delete_notebook(&self, key: &str) -> Result<()> {
+        let removed: Vec<String> = self
+            .query(
+                "REMOVE { _key: @key } IN @@notebooks OPTIONS { ignoreErrors: true } \
+                 RETURN OLD._key",
+                HashMap::from([("key", json!(key)), ("@notebooks", json!(NOTEBOOKS))]),
+            )
+            .await?;
+        if removed.is_empty() {
+            return Err(DbError::NotFound {
+                key: key.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Add the document `doc` to a notebook at `position` (the end for
+    /// `None`), or move it there if it is already a member
+    pub async fn add_to_notebook(
+        &self,
+        notebook: &str,
+        doc: &str,
+        position: Option<usize>,
+    ) -> Result<Notebook> {
+        self.get_document(doc).await?;
+        let mut nb = self.get_notebook(notebook).await?;
+        nb.insert_member(doc, position);
+        self.save_notebook(&nb).await?;
+        self.get_notebook(notebook).await
+    }
+
+    pub async fn remove_from_notebook(&self, notebook: &str, doc: &str) -> Result<Notebook> {
+        let mut nb = self.get_notebook(notebook).await?;
+        if nb.remove_member(doc) {
+            self.save_notebook(&nb).await?;
+        }
+        self.get_notebook(notebook).await
+    }
+
+    /// The documents of a notebook in order, skipping deleted ones
+    pub async fn documents_in_notebook(&self, key: &str) -> Result<Vec<StoredDocument>> {
+        let rows: Vec<Option<Vec<StoredDocument>>> = self
+            .query(
+                "LET n = DOCUMENT(@id) \
+                 RETURN n == null ? null : (FOR k IN n.members \
+                     LET d = DOCUMENT(CONCAT(@documents, '/', k)) FILTER d != null RETURN d)",
+                HashMap::from([
+                    ("id", json!(format!("{}/{}", NOTEBOOKS, key))),
+                    ("documents", json!(DOCUMENTS)),
+                ]),
+            )
+            .await?;
+        rows.into_iter()
+            .next()
+            .flatten()
+            .ok_or_else(|| DbError::NotFound {
+                key: key.to_string(),
+            })
+    }
+
+    /// Notebooks containing the document `doc`, by name
+    pub async fn notebooks_of(&self, doc: &str) -> Result<Vec<Notebook>> {
+        self.query(
+            "FOR n IN @@notebooks FILTER @doc IN n.members SORT n.name RETURN n",
+            HashMap::from([("doc", json!(doc)), ("@notebooks", json!(NOTEBOOKS))]),
+        )
+        .await
+    }
+}
+
+impl Transaction {
+    /// Drop the document `doc` from every notebook
+    pub(crate) async fn remove_from_notebooks(&self, doc: &str) -> Result<()> {
+        self.query::<Value>(
+            "FOR n IN @@notebooks FILTER @doc IN n.members \
+             UPDATE n WITH { members: REMOVE_VALUE(n.members, @doc) } IN @@notebooks",
+            HashMap::from([("doc", json!(doc)), ("@notebooks", json!(NOTEBOOKS))]),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_member_order() {
+        let mut nb = Notebook::new("Book").with_description("Outline");
+        nb.insert_member("intro", None);
+        nb.insert_member("end", None);
+        nb.insert_member("middle", Some(1));
+        assert_eq!(nb.members, ["intro", "middle", "end"]);
+
+        nb.insert_member("intro", Some(99));
+        assert_eq!(nb.members, ["middle", "end", "intro"]);
+
+        assert!(nb.remove_member("end"));
+        assert!(!nb.remove_member("end"));
+        assert_eq!(nb.members, ["middle", "intro"]);
+    }
+}
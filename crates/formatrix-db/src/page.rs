@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Paged listings
+//!
+//! Listing queries take a [`PageRequest`] and return a [`Page`] holding one
+//! slice of the results and the total number of matches, so a library view
+//! can page through thousands of gists without loading them all.
+
+use serde::{Deserialize, Serialize};
+
+/// Offset and size of a requested page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRequest {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl PageRequest {
+    pub const DEFAULT_LIMIT: usize = 50;
+    /// Larger limits are clamped to this
+    pub const MAX_LIMIT: usize = 1000;
+
+    /// `limit` items starting at `offset`; the limit is clamped to
+    /// `1..=MAX_LIMIT`
+    pub fn new(offset: usize, limit: usize) -> Self {
+        Self {
+            offset,
+            limit: limit.clamp(1, Self::MAX_LIMIT),
+        }
+    }
+
+    pub fn first(limit: usize) -> Self {
+        Self::new(0, limit)
+    }
+
+    /// The page that follows this one
+    pub fn next(self) -> Self {
+        Self::new(self.offset + self.limit, self.limit)
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::first(Self::DEFAULT_LIMIT)
+    }
+}
+
+/// One page of results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Number of matches across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: usize, request: PageRequest) -> Self {
+        Self {
+            items,
+            total,
+            offset: request.offset,
+            limit: request.limit,
+        }
+    }
+
+    /// Whether matches remain after this page
+    pub fn has_more(&self) -> bool {
+        self.offset + self.items.len() < self.total
+    }
+
+    /// The request for the following page, if there is one
+    pub fn next_page(&self) -> Option<PageRequest> {
+        self.has_more()
+            .then(|| PageRequest::new(self.offset, self.limit).next())
+    }
+
+    /// Number of pages of this size needed for all matches
+    pub fn page_count(&self) -> usize {
+        self.total.div_ceil(self.limit.max(1))
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_request() {
+        assert_eq!(PageRequest::default().limit, PageRequest::DEFAULT_LIMIT);
+        assert_eq!(PageRequest::new(0, 0).limit, 1);
+        assert_eq!(PageRequest::new(0, 1 << 20).limit, PageRequest::MAX_LIMIT);
+        assert_eq!(PageRequest::first(20).next(), PageRequest::new(20, 20));
+    }
+
+    #[test]
+    fn test_page_navigation() {
+        let page = Page::new(vec![1, 2, 3], 7, PageRequest::first(3));
+        assert!(page.has_more());
+        assert_eq!(page.next_page(), Some(PageRequest::new(3, 3)));
+        assert_eq!(page.page_count(), 3);
+
+        let last = Page::new(vec![7], 7, PageRequest::new(6, 3)).map(|n| n * 10);
+        assert_eq!(last.items, [70]);
+        assert_eq!(last.next_page(), None);
+    }
+}
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Pinned and favorite documents
+//!
+//! Pinned documents head [`FormatrixDb::get_recent`]; favorites are a
+//! separate starred list. Setting either flag is not an edit: it takes no
+//! version snapshot and leaves `updated_at` alone, but it does change the
+//! document's revision, which is returned for the next save.
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{DocumentRef, StoredDocument, DOCUMENTS};
+use crate::page::{Page, PageRequest};
+use crate::query::{DocumentQuery, DocumentSort};
+use serde_json::json;
+use std::collections::HashMap;
+
+impl FormatrixDb {
+    pub async fn set_pinned(&self, key: &str, pinned: bool) -> Result<DocumentRef> {
+        self.set_flag(key, "pinned", pinned).await
+    }
+
+    pub async fn set_favorite(&self, key: &str, favorite: bool) -> Result<DocumentRef> {
+        self.set_flag(key, "favorite", favorite).await
+    }
+
+    /// Set one boolean field of a stored document
+    pub(crate) async fn set_flag(&self, key: &str, flag: &str, value: bool) -> Result<DocumentRef> {
+        self.query(
+            "UPDATE { _key: @key } WITH { [@flag]: @value } IN @@documents \
+             OPTIONS { ignoreErrors: true } RETURN { key: NEW._key, rev: NEW._rev }",
+            HashMap::from([
+                ("key", json!(key)),
+                ("flag", json!(flag)),
+                ("value", json!(value)),
+                ("@documents", json!(DOCUMENTS)),
+            ]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DbError::NotFound {
+            key: key.to_string(),
+        })
+    }
+
+    /// Pinned documents, most recently updated first
+    pub async fn list_pinned(&self, page: PageRequest) -> Result<Page<StoredDocument>> {
+        self.find_documents(&DocumentQuery::new().with_pinned(true), page)
+            .await
+    }
+
+    /// Favorite documents by title
+    pub async fn list_favorites(&self, page: PageRequest) -> Result<Page<StoredDocument>> {
+        let query = DocumentQuery::new()
+            .with_favorite(true)
+            .sorted_by(DocumentSort::Title);
+        self.find_documents(&query, page).await
+    }
+}
@@ -0,0 +1,379 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Typed document queries
+//!
+//! A [`DocumentQuery`] describes which documents to list and in what
+//! order; [`FormatrixDb::find_documents`] compiles it to one AQL query,
+//! with every value passed as a bind variable. With a text query the
+//! documents come from the search view and can be ranked by relevance;
+//! otherwise they come straight from `documents`. Ties are broken by key,
+//! so pages stay stable.
+
+use crate::acl::READABLE_FILTER;
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::{stored_time, StoredDocument, Visibility, DOCUMENTS, GRANTS};
+use crate::page::{Page, PageRequest};
+use crate::search::{self, SearchQuery};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Order of query results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentSort {
+    /// Best text match first; most recently updated without a text query
+    #[default]
+    Relevance,
+    Title,
+    NewestFirst,
+    OldestFirst,
+    RecentlyUpdated,
+    LeastRecentlyUpdated,
+}
+
+impl DocumentSort {
+    fn to_aql(self, text: bool) -> &'static str {
+        match self {
+            DocumentSort::Relevance if text => "BM25(d) DESC, d._key",
+            DocumentSort::Relevance | DocumentSort::RecentlyUpdated => "d.updated_at DESC, d._key",
+            DocumentSort::Title => "d.title, d._key",
+            DocumentSort::NewestFirst => "d.created_at DESC, d._key",
+            DocumentSort::OldestFirst => "d.created_at, d._key",
+            DocumentSort::LeastRecentlyUpdated => "d.updated_at, d._key",
+        }
+    }
+}
+
+/// Which documents to list; the default lists every document
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentQuery {
+    /// Full-text query, in [`SearchQuery`] syntax
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Documents must carry all of these
+    #[serde(default)]
+    pub all_tags: Vec<String>,
+    /// Documents must carry at least one of these
+    #[serde(default)]
+    pub any_tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    /// Key of the document these were split from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Only documents created at or after this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only documents created before this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+    /// Only documents updated at or after this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_after: Option<DateTime<Utc>>,
+    /// Only documents updated before this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_before: Option<DateTime<Utc>>,
+    /// Only documents that are, or aren't, pinned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<bool>,
+    /// Only documents that are, or aren't, favorites
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favorite: Option<bool>,
+    #[serde(default)]
+    pub sort: DocumentSort,
+    /// Put pinned documents before the rest, each in `sort` order
+    #[serde(default)]
+    pub pinned_first: bool,
+    /// List archived documents too
+    #[serde(default)]
+    pub include_archived: bool,
+    /// Only documents this user may read (see [`crate::acl`]); backends
+    /// without share grants show `Shared` documents to their owner only
+    ///
+    /// Never serialised, so a saved query doesn't carry one user's access
+    /// to another.
+    #[serde(skip)]
+    pub reader: Option<String>,
+}
+
+impl DocumentQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn with_all_tags(mut self, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.all_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_any_tags(mut self, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.any_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = Some(visibility);
+        self
+    }
+
+    pub fn with_parent(mut self, key: impl Into<String>) -> Self {
+        self.parent = Some(key.into());
+        self
+    }
+
+    /// Only documents created in `[after, before)`; either end may be open
+    pub fn created_between(
+        mut self,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.created_after = after;
+        self.created_before = before;
+        self
+    }
+
+    /// Only documents updated in `[after, before)`; either end may be open
+    pub fn updated_between(
+        mut self,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.updated_after = after;
+        self.updated_before = before;
+        self
+    }
+
+    pub fn with_pinned(mut self, pinned: bool) -> Self {
+        self.pinned = Some(pinned);
+        self
+    }
+
+    pub fn with_favorite(mut self, favorite: bool) -> Self {
+        self.favorite = Some(favorite);
+        self
+    }
+
+    pub fn sorted_by(mut self, sort: DocumentSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn pinned_first(mut self) -> Self {
+        self.pinned_first = true;
+        self
+    }
+
+    pub fn including_archived(mut self) -> Self {
+        self.include_archived = true;
+        self
+    }
+
+    pub fn with_reader(mut self, user: impl Into<String>) -> Self {
+        self.reader = Some(user.into());
+        self
+    }
+
+    /// The loop over `d` with its filters, the sort expression and their
+    /// bind variables
+    pub(crate) fn to_aql(&self) -> (String, String, HashMap<String, Value>) {
+        let search = SearchQuery::parse(self.text.as_deref().unwrap_or_default());
+        let text = !search.is_empty();
+        let (mut aql, mut vars) = if text {
+            let (expression, mut vars) = search.to_aql();
+            vars.insert("@view".to_string(), json!(search::VIEW));
+            (format!("FOR d IN @@view SEARCH {expression}"), vars)
+        } else {
+            let vars = HashMap::from([("@documents".to_string(), json!(DOCUMENTS))]);
+            ("FOR d IN @@documents".to_string(), vars)
+        };
+
+        if !self.include_archived {
+            aql.push_str(" FILTER d.archived != true");
+        }
+        if let Some(reader) = &self.reader {
+            aql.push(' ');
+            aql.push_str(READABLE_FILTER);
+            vars.insert("user".to_string(), json!(reader));
+            vars.insert("@grants".to_string(), json!(GRANTS));
+        }
+        let mut filter = |clause: &str, var: &str, value: Value| {
+            aql.push_str(" FILTER ");
+            aql.push_str(clause);
+            vars.insert(var.to_string(), value);
+        };
+        if !self.all_tags.is_empty() {
+            filter("@all_tags ALL IN d.tags", "all_tags", json!(self.all_tags));
+        }
+        if !self.any_tags.is_empty() {
+            filter("@any_tags ANY IN d.tags", "any_tags", json!(self.any_tags));
+        }
+        if let Some(format) = &self.format {
+            filter("d.format == @format", "format", json!(format));
+        }
+        if let Some(visibility) = self.visibility {
+            filter(
+                "d.visibility == @visibility",
+                "visibility",
+                json!(visibility),
+            );
+        }
+        if let Some(parent) = &self.parent {
+            filter("d.parent == @parent", "parent", json!(parent));
+        }
+        // Documents saved before the flags existed have none
+        if let Some(pinned) = self.pinned {
+            filter("(d.pinned == true) == @pinned", "pinned", json!(pinned));
+        }
+        if let Some(favorite) = self.favorite {
+            filter(
+                "(d.favorite == true) == @favorite",
+                "favorite",
+                json!(favorite),
+            );
+        }
+        // Stored times are fixed-width strings, so they compare directly and
+        // the indexes on them apply
+        for (field, var, after, before) in [
+            (
+                "created_at",
+                "created",
+                self.created_after,
+                self.created_before,
+            ),
+            (
+                "updated_at",
+                "updated",
+                self.updated_after,
+                self.updated_before,
+            ),
+        ] {
+            if let Some(after) = after {
+                filter(
+                    &format!("d.{field} >= @{var}_after"),
+                    &format!("{var}_after"),
+                    json!(stored_time::format(&after)),
+                );
+            }
+            if let Some(before) = before {
+                filter(
+                    &format!("d.{field} < @{var}_before"),
+                    &format!("{var}_before"),
+                    json!(stored_time::format(&before)),
+                );
+            }
+        }
+        let mut sort = self.sort.to_aql(text).to_string();
+        if self.pinned_first {
+            sort.insert_str(0, "d.pinned == true DESC, ");
+        }
+        (aql, sort, vars)
+    }
+}
+
+impl FormatrixDb {
+    /// One page of the documents matching `query`
+    pub async fn find_documents(
+        &self,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let (source, sort, vars) = query.to_aql();
+        self.page_query(&source, &sort, vars, page).await
+    }
+
+    /// Documents updated at or after `since`, oldest change first
+    ///
+    /// For incremental sync and backup: keep the `updated_at` of the last
+    /// document handled and pass it back next time. The bound is inclusive,
+    /// so documents saved at the same instant are not missed but may be
+    /// seen twice. Deleted documents are not reported.
+    pub async fn get_modified_since(
+        &self,
+        since: DateTime<Utc>,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let query = DocumentQuery::new()
+            .updated_between(Some(since), None)
+            .sorted_by(DocumentSort::LeastRecentlyUpdated)
+            .including_archived();
+        self.find_documents(&query, page).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_query() {
+        let (aql, sort, vars) = DocumentQuery::new().to_aql();
+        assert_eq!(aql, "FOR d IN @@documents FILTER d.archived != true");
+        assert_eq!(sort, "d.updated_at DESC, d._key");
+        assert_eq!(vars.len(), 1);
+    }
+
+    #[test]
+    fn test_query_to_aql() {
+        let (aql, sort, vars) = DocumentQuery::new()
+            .with_text("borrow checker")
+            .with_any_tags(["rust", "go"])
+            .with_visibility(Visibility::Shared)
+            .with_parent("42")
+            .updated_between(None, Some(Utc::now()))
+            .to_aql();
+        assert!(aql.starts_with("FOR d IN @@view SEARCH ANALYZER("));
+        assert!(aql.contains(" FILTER @any_tags ANY IN d.tags FILTER d.visibility == @visibility"));
+        assert!(aql.contains(" FILTER d.archived != true"));
+        assert!(aql.contains("@updated_before"));
+        assert!(!aql.contains("@all_tags") && !aql.contains("@updated_after"));
+        assert!(!aql.contains("created_at"));
+        assert_eq!(sort, "BM25(d) DESC, d._key");
+        assert_eq!(vars["terms"], "borrow checker");
+        assert_eq!(vars["visibility"], "shared");
+        assert_eq!(vars["parent"], "42");
+
+        let after = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let (aql, _, vars) = DocumentQuery::new()
+            .created_between(Some(after), Some(Utc::now()))
+            .to_aql();
+        assert!(aql.contains(" FILTER d.created_at >= @created_after"));
+        assert!(aql.contains(" FILTER d.created_at < @created_before"));
+        assert_eq!(vars["created_after"], "2023-11-14T22:13:20.000000000Z");
+
+        let (aql, sort, vars) = DocumentQuery::new()
+            .with_favorite(false)
+            .pinned_first()
+            .including_archived()
+            .to_aql();
+        assert!(aql.ends_with(" FILTER (d.favorite == true) == @favorite"));
+        assert_eq!(vars["favorite"], false);
+        assert!(!vars.contains_key("user"));
+        assert_eq!(sort, "d.pinned == true DESC, d.updated_at DESC, d._key");
+
+        let (_, sort, _) = DocumentQuery::new()
+            .with_text("rust")
+            .sorted_by(DocumentSort::Title)
+            .to_aql();
+        assert_eq!(sort, "d.title, d._key");
+
+        let (aql, _, vars) = DocumentQuery::new().with_reader("bob").to_aql();
+        assert!(aql.ends_with(READABLE_FILTER));
+        assert_eq!(vars["user"], "bob");
+        assert_eq!(vars["@grants"], GRANTS);
+    }
+}
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Request timeouts and retries
+//!
+//! Every request outside a transaction runs under a [`RetryPolicy`]: each
+//! attempt is cut off after [`RetryPolicy::timeout`], and attempts that
+//! fail to reach the server ([`DbError::is_transient`]) are retried with
+//! exponential backoff. Other errors, such as a rejected query or a
+//! revision conflict, are returned at once. A write that timed out may
+//! still have been applied, so writes that can't safely be repeated, such
+//! as plain inserts, are only retried when they failed to connect; use
+//! [`RetryPolicy::no_retries`] to retry nothing. Queries inside a
+//! [`crate::Transaction`] are not retried, since the transaction as a whole
+//! is the unit that succeeds or fails.
+
+use crate::error::{DbError, Result};
+use std::future::Future;
+use std::time::Duration;
+
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Timeout and retry settings, part of [`crate::DbConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Limit for one attempt at a request
+    pub timeout: Duration,
+    /// Attempts after the first; 0 disables retrying
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry, for callers that handle failures themselves
+    pub fn no_retries() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Wait before retry number `attempt`, counting from 0
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_BACKOFF)
+    }
+
+    /// Run `op` until it succeeds, fails for good or runs out of retries
+    pub(crate) async fn run<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run_with(op, true).await
+    }
+
+    /// Like [`RetryPolicy::run`] for a write that must not be applied
+    /// twice, so an attempt that timed out is not repeated
+    pub(crate) async fn run_write<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run_with(op, false).await
+    }
+
+    async fn run_with<T, F, Fut>(&self, mut op: F, retry_timeouts: bool) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = tokio::time::timeout(self.timeout, op())
+                .await
+                .unwrap_or(Err(DbError::Timeout(self.timeout)));
+            let retry = |err: &DbError| {
+                err.is_transient() && (retry_timeouts || !matches!(err, DbError::Timeout(_)))
+            };
+            match result {
+                Err(err) if retry(&err) && attempt < self.max_retries => {
+                    let delay = self.delay(attempt);
+                    tracing::warn!("{}; retrying in {:?}", err, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl DbError {
+    /// Whether the request failed on the way to the server, so trying
+    /// again may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DbError::Connection(_) | DbError::Timeout(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(3), Duration::from_millis(800));
+        assert_eq!(policy.delay(40), MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_only() {
+        let policy = RetryPolicy {
+            backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let calls = Cell::new(0);
+        let result = policy
+            .run(|| async {
+                calls.set(calls.get() + 1);
+                if calls.get() < 3 {
+                    Err(DbError::Connection("refused".to_string()))
+                } else {
+                    Ok(calls.get())
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        calls.set(0);
+        let result: Result<()> = policy
+            .run(|| async {
+                calls.set(calls.get() + 1);
+                Err(DbError::Query("syntax error".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+
+        calls.set(0);
+        let result: Result<()> = policy
+            .run(|| async {
+                calls.set(calls.get() + 1);
+                Err(DbError::Connection("refused".to_string()))
+            })
+            .await;
+        assert!(result.unwrap_err().is_transient());
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_writes_are_not_repeated() {
+        let policy = RetryPolicy {
+            timeout: Duration::from_millis(10),
+            backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let calls = Cell::new(0);
+        let result: Result<()> = policy
+            .run_write(|| async {
+                calls.set(calls.get() + 1);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(DbError::Timeout(_))));
+        assert_eq!(calls.get(), 1);
+
+        calls.set(0);
+        let result = policy
+            .run_write(|| async {
+                calls.set(calls.get() + 1);
+                if calls.get() < 2 {
+                    Err(DbError::Connection("refused".to_string()))
+                } else {
+                    Ok(calls.get())
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 2);
+    }
+}
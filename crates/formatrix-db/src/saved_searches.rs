@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Saved searches
+//!
+//! A [`SavedSearch`] is a named [`DocumentQuery`] kept in
+//! `saved_searches`, so a library view such as "drafts tagged rust from
+//! this month" can be pinned and run again by name.
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{StoredDocument, SAVED_SEARCHES};
+use crate::page::{Page, PageRequest};
+use crate::query::DocumentQuery;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// A named query definition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// Unique name
+    pub name: String,
+    #[serde(flatten)]
+    pub query: DocumentQuery,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SavedSearch {
+    pub fn new(name: impl Into<String>, query: DocumentQuery) -> Self {
+        Self {
+            name: name.into(),
+            query,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl FormatrixDb {
+    /// Store a search, replacing any saved search of the same name
+    pub async fn save_search(&self, search: &SavedSearch) -> Result<()> {
+        if search.name.trim().is_empty() {
+            return Err(DbError::Invalid(
+                "saved search name must not be empty".to_string(),
+            ));
+        }
+        self.query::<Value>(
+            "UPSERT { name: @search.name } INSERT @search REPLACE @search IN @@searches",
+            HashMap::from([
+                ("search", serde_json::to_value(search)?),
+                ("@searches", json!(SAVED_SEARCHES)),
+            ]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_saved_search(&self, name: &str) -> Result<SavedSearch> {
+        self.query(
+            "FOR s IN @@searches FILTER s.name == @name LIMIT 1 \
+             RETURN UNSET(s, '_key', '_id', '_rev')",
+            HashMap::from([("name", json!(name)), ("@searches", json!(SAVED_SEARCHES))]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DbError::NotFound {
+            key: name.to_string(),
+        })
+    }
+
+    /// All saved searches, by name
+    pub async fn list_saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        self.query(
+            "FOR s IN @@searches SORT s.name RETURN UNSET(s, '_key', '_id', '_rev')",
+            HashMap::from([("@searches", json!(SAVED_SEARCHES))]),
+        )
+        .await
+    }
+
+    pub async fn delete_saved_search(&self, name: &str) -> Result<()> {
+        let removed: Vec<Value> = self
+            .query(
+                "FOR s IN @@searches FILTER s.name == @name REMOVE s IN @@searches RETURN 1",
+                HashMap::from([("name", json!(name)), ("@searches", json!(SAVED_SEARCHES))]),
+            )
+            .await?;
+        if removed.is_empty() {
+            return Err(DbError::NotFound {
+                key: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// One page of the documents matched by the saved search `name`
+    pub async fn run_saved_search(
+        &self,
+        name: &str,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let search = self.get_saved_search(name).await?;
+        self.find_documents(&search.query, page).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_search_layout() {
+        let search = SavedSearch::new(
+            "rust notes",
+            DocumentQuery::new()
+                .with_text("borrow checker")
+                .with_all_tags(["rust"])
+                .updated_between(Some(Utc::now()), None),
+        );
+        let value = serde_json::to_value(&search).unwrap();
+        assert_eq!(value["name"], "rust notes");
+        assert_eq!(value["text"], "borrow checker");
+        assert_eq!(value["all_tags"], json!(["rust"]));
+        assert!(value.get("updated_before").is_none());
+
+        let read: SavedSearch = serde_json::from_value(value).unwrap();
+        assert_eq!(read, search);
+    }
+}
@@ -0,0 +1,314 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Full-text search over an ArangoSearch view
+//!
+//! Titles and content are indexed by a `text` analyzer (lower-cased,
+//! accent-folded, stemmed, Unicode word segmentation) and ranked with
+//! BM25, title matches counting double. Query syntax is deliberately
+//! small: bare words match any of them, `"quoted phrases"` must appear
+//! verbatim, and `-word` excludes documents containing the word.
+//!
+//! In [`SearchMode::Fuzzy`], bare words also match words within a few
+//! edits of them (`LEVENSHTEIN_MATCH` on the analysed word, so `borowing`
+//! finds `borrowing`) and words they are part of (`NGRAM_MATCH` on
+//! trigrams, so `borr` finds `borrow`). Phrases and exclusions stay exact.
+
+use crate::error::{DbError, Result};
+use crate::models::DOCUMENTS;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Analyzer used for titles, content and queries
+pub const ANALYZER: &str = "formatrix_text";
+/// Trigram analyzer used by fuzzy search
+pub const NGRAM_ANALYZER: &str = "formatrix_ngram";
+/// ArangoSearch view over the document collection
+pub const VIEW: &str = "documents_search";
+
+/// Analyzer definition for `POST /_api/analyzer`
+pub(crate) fn analyzer_definition() -> Value {
+    json!({
+        "name": ANALYZER,
+        "type": "text",
+        "properties": {
+            "locale": "en",
+            "case": "lower",
+            "accent": false,
+            "stemming": true,
+            "stopwords": []
+        },
+        // Positions are needed for PHRASE
+        "features": ["frequency", "norm", "position"]
+    })
+}
+
+/// Trigram analyzer definition for `POST /_api/analyzer`: lower-cased,
+/// accent-folded UTF-8 character trigrams
+pub(crate) fn ngram_analyzer_definition() -> Value {
+    json!({
+        "name": NGRAM_ANALYZER,
+        "type": "pipeline",
+        "properties": {
+            "pipeline": [
+                {
+                    "type": "norm",
+                    "properties": { "locale": "en", "case": "lower", "accent": false }
+                },
+                {
+                    "type": "ngram",
+                    "properties": {
+                        "min": 3,
+                        "max": 3,
+                        "preserveOriginal": false,
+                        "streamType": "utf8"
+                    }
+                }
+            ]
+        },
+        // NGRAM_MATCH needs positions too
+        "features": ["frequency", "norm", "position"]
+    })
+}
+
+/// View definition for `POST /_api/view`
+pub(crate) fn view_definition() -> Value {
+    json!({
+        "name": VIEW,
+        "type": "arangosearch",
+        "links": view_links()
+    })
+}
+
+/// The view's links, also used to update an existing view
+pub(crate) fn view_links() -> Value {
+    json!({
+        DOCUMENTS: {
+            "includeAllFields": false,
+            "fields": {
+                "title": { "analyzers": [ANALYZER, NGRAM_ANALYZER] },
+                "content": { "analyzers": [ANALYZER, NGRAM_ANALYZER] }
+            }
+        }
+    })
+}
+
+/// How bare words match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Words match words with the same stem
+    #[default]
+    Exact,
+    /// Words also match misspellings and longer words containing them
+    Fuzzy,
+}
+
+/// Largest edit distance `LEVENSHTEIN_MATCH` allows with transpositions
+pub const MAX_DISTANCE: u32 = 3;
+
+/// How a search matches, for [`crate::FormatrixDb::search_fulltext_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    /// Edits (insertions, deletions, substitutions, transpositions) a
+    /// fuzzy word may be from a stored one
+    pub max_distance: u32,
+    /// Share of a fuzzy word's trigrams a stored word must contain, from
+    /// 0 to 1
+    pub ngram_threshold: f64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::Exact,
+            max_distance: 1,
+            ngram_threshold: 0.7,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Fuzzy matching with the default distance and threshold
+    pub fn fuzzy() -> Self {
+        Self {
+            mode: SearchMode::Fuzzy,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_distance(mut self, max_distance: u32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_ngram_threshold(mut self, threshold: f64) -> Self {
+        self.ngram_threshold = threshold;
+        self
+    }
+
+    /// Reject settings the server would
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.max_distance > MAX_DISTANCE {
+            return Err(DbError::Invalid(format!(
+                "max_distance must be at most {MAX_DISTANCE}"
+            )));
+        }
+        // Written so that NaN fails too
+        if !(self.ngram_threshold > 0.0 && self.ngram_threshold <= 1.0) {
+            return Err(DbError::Invalid(
+                "ngram_threshold must be above 0 and at most 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A parsed search query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Bare words; a document must match at least one
+    pub terms: Vec<String>,
+    /// Quoted phrases; a document must contain all of them
+    pub phrases: Vec<String>,
+    /// `-word` exclusions
+    pub excluded: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = Self::default();
+        let mut rest = input;
+        while let Some(start) = rest.find('"') {
+            query.add_words(&rest[..start]);
+            let after = &rest[start + 1..];
+            let end = after.find('"').unwrap_or(after.len());
+            let phrase = after[..end].trim();
+            if !phrase.is_empty() {
+                query.phrases.push(phrase.to_string());
+            }
+            rest = after.get(end + 1..).unwrap_or("");
+        }
+        query.add_words(rest);
+        query
+    }
+
+    fn add_words(&mut self, text: &str) {
+        for word in text.split_whitespace() {
+            match word.strip_prefix('-') {
+                Some(excluded) if !excluded.is_empty() => self.excluded.push(excluded.to_string()),
+                _ => self.terms.push(word.to_string()),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.phrases.is_empty()
+    }
+
+    /// The `SEARCH` expression over the loop variable `d`, with its bind
+    /// variables
+    pub(crate) fn to_aql(&self) -> (String, HashMap<String, Value>) {
+        self.to_aql_with(&SearchOptions::default())
+    }
+
+    /// [`SearchQuery::to_aql`] matching as `options` say
+    pub(crate) fn to_aql_with(&self, options: &SearchOptions) -> (String, HashMap<String, Value>) {
+        let mut clauses = Vec::new();
+        let mut vars = HashMap::new();
+        vars.insert("analyzer".to_string(), json!(ANALYZER));
+
+        if !self.terms.is_empty() && options.mode == SearchMode::Fuzzy {
+            vars.insert("ngram_analyzer".to_string(), json!(NGRAM_ANALYZER));
+            vars.insert("distance".to_string(), json!(options.max_distance));
+            vars.insert("threshold".to_string(), json!(options.ngram_threshold));
+            let mut fuzzy = Vec::new();
+            for (i, term) in self.terms.iter().enumerate() {
+                let var = format!("term{}", i);
+                // Compared with the stored words, which are stemmed
+                let word = format!("FIRST(TOKENS(@{var}, @analyzer))");
+                fuzzy.push(format!(
+                    "BOOST(LEVENSHTEIN_MATCH(d.title, {word}, @distance), 2) \
+                     OR LEVENSHTEIN_MATCH(d.content, {word}, @distance) \
+                     OR BOOST(NGRAM_MATCH(d.title, @{var}, @threshold, @ngram_analyzer), 2) \
+                     OR NGRAM_MATCH(d.content, @{var}, @threshold, @ngram_analyzer)"
+                ));
+                vars.insert(var, json!(term));
+            }
+            clauses.push(format!("({})", fuzzy.join(" OR ")));
+        } else if !self.terms.is_empty() {
+            vars.insert("terms".to_string(), json!(self.terms.join(" ")));
+            clauses.push(
+                "(BOOST(d.title IN TOKENS(@terms, @analyzer), 2) \
+                 OR d.content IN TOKENS(@terms, @analyzer))"
+                    .to_string(),
+            );
+        }
+        for (i, phrase) in self.phrases.iter().enumerate() {
+            let var = format!("phrase{}", i);
+            clauses.push(format!(
+                "(BOOST(PHRASE(d.title, @{var}), 2) OR PHRASE(d.content, @{var}))"
+            ));
+            vars.insert(var, json!(phrase));
+        }
+        if !self.excluded.is_empty() {
+            vars.insert("excluded".to_string(), json!(self.excluded.join(" ")));
+            clauses.push(
+                "NOT (d.title IN TOKENS(@excluded, @analyzer) \
+                 OR d.content IN TOKENS(@excluded, @analyzer))"
+                    .to_string(),
+            );
+        }
+        (
+            format!("ANALYZER({}, @analyzer)", clauses.join(" AND ")),
+            vars,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let query = SearchQuery::parse(r#"graph "knowledge base" -draft  café "unclosed"#);
+        assert_eq!(query.terms, ["graph", "café"]);
+        assert_eq!(query.phrases, ["knowledge base", "unclosed"]);
+        assert_eq!(query.excluded, ["draft"]);
+        assert!(SearchQuery::parse("  -only").is_empty());
+    }
+
+    #[test]
+    fn test_query_to_aql() {
+        let (aql, vars) = SearchQuery::parse(r#"rust "borrow checker""#).to_aql();
+        assert!(aql.starts_with("ANALYZER("));
+        assert!(aql.contains("PHRASE(d.content, @phrase0)"));
+        assert!(!aql.contains("@excluded"));
+        assert_eq!(vars["terms"], "rust");
+        assert_eq!(vars["phrase0"], "borrow checker");
+    }
+
+    #[test]
+    fn test_fuzzy_to_aql() {
+        let query = SearchQuery::parse(r#"borowing lifetim "exact phrase""#);
+        let (aql, vars) = query.to_aql_with(&SearchOptions::fuzzy().with_max_distance(2));
+        assert!(aql.contains("LEVENSHTEIN_MATCH(d.content, FIRST(TOKENS(@term1, @analyzer))"));
+        assert!(aql.contains("NGRAM_MATCH(d.title, @term0, @threshold, @ngram_analyzer)"));
+        assert!(aql.contains("PHRASE(d.content, @phrase0)"));
+        assert!(!aql.contains("@terms"));
+        assert_eq!(vars["distance"], 2);
+        assert_eq!(vars["term1"], "lifetim");
+
+        assert!(SearchOptions::fuzzy().validate().is_ok());
+        assert!(SearchOptions::fuzzy()
+            .with_max_distance(4)
+            .validate()
+            .is_err());
+        assert!(SearchOptions::fuzzy()
+            .with_ngram_threshold(0.0)
+            .validate()
+            .is_err());
+    }
+}
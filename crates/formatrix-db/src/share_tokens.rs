@@ -0,0 +1,325 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Expiring share tokens
+//!
+//! A share token lets anyone holding it open one document without an
+//! account, for public links to `Shared` and `Public` documents. The token
+//! handed out is `<id>.<signature>`: the id names a record in
+//! `share_tokens`, and the signature is an HMAC-SHA256 over the record's
+//! fields keyed with [`crate::DbConfig::share_secret`], so a token can't be
+//! forged from database contents alone or altered to outlive its expiry.
+//! Revoking deletes the record.
+
+use crate::audit::AuditAction;
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{Visibility, DOCUMENTS, SHARE_TOKENS};
+use crate::transaction::Transaction;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+/// A stored share token, without its signature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareTokenRecord {
+    #[serde(rename = "_key")]
+    pub id: String,
+    /// Document key
+    pub document: String,
+    pub read_only: bool,
+    pub expires_at: DateTime<Utc>,
+    /// User key of the owner who created it
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ShareTokenRecord {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at
+    }
+
+    /// The signed fields, one per line
+    fn payload(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}",
+            self.id,
+            self.document,
+            self.read_only,
+            self.expires_at.timestamp()
+        )
+    }
+
+    fn mac(&self, secret: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(self.payload().as_bytes());
+        mac
+    }
+
+    /// The token to hand out for this record
+    pub fn sign(&self, secret: &[u8]) -> String {
+        let signature = self.mac(secret).finalize().into_bytes();
+        format!("{}.{}", self.id, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// Whether `signature` (the part of a token after the `.`) was made by
+    /// [`ShareTokenRecord::sign`] with `secret`
+    pub fn verify(&self, secret: &[u8], signature: &str) -> bool {
+        URL_SAFE_NO_PAD
+            .decode(signature)
+            .is_ok_and(|signature| self.mac(secret).verify_slice(&signature).is_ok())
+    }
+}
+
+/// A newly created token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareToken {
+    /// Pass this to [`FormatrixDb::validate_share_token`]
+    pub token: String,
+    pub record: ShareTokenRecord,
+}
+
+/// What a valid token grants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareAccess {
+    pub document: String,
+    pub read_only: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Split a token into its id and signature
+fn parse_token(token: &str) -> Result<(&str, &str)> {
+    token
+        .split_once('.')
+        .filter(|(id, signature)| !id.is_empty() && !signature.is_empty())
+        .ok_or_else(|| DbError::InvalidToken("malformed token".to_string()))
+}
+
+impl FormatrixDb {
+    fn share_secret(&self) -> Result<&[u8]> {
+        self.config()
+            .share_secret
+            .as_deref()
+            .map(str::as_bytes)
+            .ok_or_else(|| DbError::Config("share_secret is not set".to_string()))
+    }
+
+    /// Create a token for a `Shared` or `Public` document owned by `user`,
+    /// valid for `ttl`
+    pub async fn create_share_token(
+        &self,
+        user: &str,
+        key: &str,
+        ttl: Duration,
+        read_only: bool,
+    ) -> Result<ShareToken> {
+        let secret = self.share_secret()?;
+        let doc = self.require_owner(user, key).await?;
+        if doc.visibility == Visibility::Private {
+            return Err(DbError::PermissionDenied {
+                key: key.to_string(),
+            });
+        }
+
+        let mut id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut id);
+        let now = Utc::now();
+        let record = ShareTokenRecord {
+            id: URL_SAFE_NO_PAD.encode(id),
+            document: key.to_string(),
+            read_only,
+            expires_at: now + ttl,
+            created_by: user.to_string(),
+            created_at: now,
+        };
+        self.insert_query::<Value>(
+            "INSERT @record INTO @@tokens",
+            HashMap::from([
+                ("record", serde_json::to_value(&record)?),
+                ("@tokens", json!(SHARE_TOKENS)),
+            ]),
+        )
+        .await?;
+        self.audit(
+            Some(user),
+            key,
+            AuditAction::CreateShareToken,
+            Some(&record.id),
+        )
+        .await;
+        Ok(ShareToken {
+            token: record.sign(secret),
+            record,
+        })
+    }
+
+    /// Check a token and return the access it grants
+    ///
+    /// Fails with [`DbError::InvalidToken`] if the token is malformed,
+    /// forged, expired or revoked, or its document has since been made
+    /// private or deleted.
+    pub async fn validate_share_token(&self, token: &str) -> Result<ShareAccess> {
+        #[derive(Deserialize)]
+        struct Row {
+            record: ShareTokenRecord,
+            visibility: Option<Visibility>,
+        }
+
+        let secret = self.share_secret()?;
+        let (id, signature) = parse_token(token)?;
+        let row: Option<Row> = self
+            .query(
+                "LET t = DOCUMENT(@id) \
+                 RETURN t == null ? null : { record: t, \
+                     visibility: DOCUMENT(CONCAT(@documents, '/', t.document)).visibility }",
+                HashMap::from([
+                    ("id", json!(format!("{}/{}", SHARE_TOKENS, id))),
+                    ("documents", json!(DOCUMENTS)),
+                ]),
+            )
+            .await?
+            .into_iter()
+            .next()
+            .flatten();
+
+        let invalid = |reason: &str| DbError::InvalidToken(reason.to_string());
+        let row = row.ok_or_else(|| invalid("unknown or revoked token"))?;
+        if !row.record.verify(secret, signature) {
+            return Err(invalid("bad signature"));
+        }
+        if row.record.is_expired(Utc::now()) {
+            return Err(invalid("token expired"));
+        }
+        if !matches!(
+            row.visibility,
+            Some(Visibility::Shared | Visibility::Public)
+        ) {
+            return Err(invalid("document is no longer shared"));
+        }
+        let record = row.record;
+        self.audit(
+            None,
+            &record.document,
+            AuditAction::UseShareToken,
+            Some(&record.id),
+        )
+        .await;
+        Ok(ShareAccess {
+            document: record.document,
+            read_only: record.read_only,
+            expires_at: record.expires_at,
+        })
+    }
+
+    /// Tokens for a document owned by `user`, newest first
+    pub async fn list_share_tokens(&self, user: &str, key: &str) -> Result<Vec<ShareTokenRecord>> {
+        self.require_owner(user, key).await?;
+        self.query(
+            "FOR t IN @@tokens FILTER t.document == @key SORT t.created_at DESC RETURN t",
+            HashMap::from([("key", json!(key)), ("@tokens", json!(SHARE_TOKENS))]),
+        )
+        .await
+    }
+
+    /// Revoke the token with `id` on a document owned by `user`
+    pub async fn revoke_share_token(&self, user: &str, id: &str) -> Result<()> {
+        let record: ShareTokenRecord = self
+            .query(
+                "RETURN DOCUMENT(@id)",
+                HashMap::from([("id", json!(format!("{}/{}", SHARE_TOKENS, id)))]),
+            )
+            .await?
+            .into_iter()
+            .next()
+            .flatten()
+            .ok_or_else(|| DbError::NotFound {
+                key: id.to_string(),
+            })?;
+        self.require_owner(user, &record.document).await?;
+        self.query::<Value>(
+            "REMOVE { _key: @id } IN @@tokens OPTIONS { ignoreErrors: true }",
+            HashMap::from([("id", json!(id)), ("@tokens", json!(SHARE_TOKENS))]),
+        )
+        .await?;
+        self.audit(
+            Some(user),
+            &record.document,
+            AuditAction::RevokeShareToken,
+            Some(id),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Delete expired tokens, returning how many were removed
+    pub async fn purge_expired_share_tokens(&self) -> Result<usize> {
+        let removed: Vec<Value> = self
+            .query(
+                "FOR t IN @@tokens FILTER DATE_TIMESTAMP(t.expires_at) <= DATE_NOW() \
+                 REMOVE t IN @@tokens RETURN 1",
+                HashMap::from([("@tokens", json!(SHARE_TOKENS))]),
+            )
+            .await?;
+        Ok(removed.len())
+    }
+}
+
+impl Transaction {
+    /// Drop every share token for the document with `key`
+    pub(crate) async fn remove_share_tokens(&self, key: &str) -> Result<()> {
+        self.query::<Value>(
+            "FOR t IN @@tokens FILTER t.document == @key REMOVE t IN @@tokens",
+            HashMap::from([("key", json!(key)), ("@tokens", json!(SHARE_TOKENS))]),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ShareTokenRecord {
+        let now = Utc::now();
+        ShareTokenRecord {
+            id: "abc".to_string(),
+            document: "42".to_string(),
+            read_only: true,
+            expires_at: now + Duration::hours(1),
+            created_by: "ada".to_string(),
+            created_at: now,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let record = record();
+        let token = record.sign(b"secret");
+        let (id, signature) = parse_token(&token).unwrap();
+        assert_eq!(id, "abc");
+        assert!(record.verify(b"secret", signature));
+        assert!(!record.verify(b"other", signature));
+        assert!(!record.verify(b"secret", "not base64!"));
+
+        let mut widened = record.clone();
+        widened.read_only = false;
+        assert!(!widened.verify(b"secret", signature));
+
+        assert!(parse_token("abc").is_err());
+        assert!(parse_token(".sig").is_err());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let record = record();
+        assert!(!record.is_expired(record.created_at));
+        assert!(record.is_expired(record.expires_at));
+    }
+}
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Search snippets and match offsets
+//!
+//! ArangoDB only reports where a search matched in its Enterprise Edition,
+//! so every backend finds matches on the client, the same way. A word
+//! matches a query word with the same stem, approximated as a shared
+//! prefix of at least four characters leaving at most three over on
+//! either side (`borrowing` matches `borrow` and `borrowed`); phrases
+//! match ignoring case. Offsets count characters, not bytes.
+
+use crate::models::{SearchResult, StoredDocument};
+use crate::search::SearchQuery;
+use serde::{Deserialize, Serialize};
+
+/// Most characters in a snippet
+pub const SNIPPET_CHARS: usize = 200;
+/// Most snippets per result
+pub const MAX_SNIPPETS: usize = 3;
+/// Characters of context kept before the first match in a snippet
+const LEAD_CHARS: usize = 60;
+
+/// A searched field of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Title,
+    Content,
+}
+
+/// Where a search matched, as character offsets `start..end` into a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchSpan {
+    pub field: SearchField,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// An excerpt of the content around one or more matches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub text: String,
+    /// Character offset of `text` in the content
+    pub start: usize,
+    /// The matches in `text`, as character offsets `start..end` into it
+    pub highlights: Vec<(usize, usize)>,
+}
+
+fn lower(c: char) -> char {
+    // One char for one, to keep offsets
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Whether two lower-case words look like forms of the same word
+fn same_stem(word: &str, term: &str) -> bool {
+    if word == term {
+        return true;
+    }
+    let common = word
+        .chars()
+        .zip(term.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    common >= 4 && word.chars().count() - common <= 3 && term.chars().count() - common <= 3
+}
+
+/// Character spans of `text` matching `query`, in order and not
+/// overlapping
+pub fn find_matches(query: &SearchQuery, text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().map(lower).collect();
+    let terms: Vec<String> = query
+        .terms
+        .iter()
+        .map(|term| term.chars().map(lower).collect())
+        .collect();
+    let mut spans = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_alphanumeric() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && chars[i].is_alphanumeric() {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        if terms.iter().any(|term| same_stem(&word, term)) {
+            spans.push((start, i));
+        }
+    }
+    for phrase in &query.phrases {
+        let needle: Vec<char> = phrase.chars().map(lower).collect();
+        let mut at = 0;
+        while !needle.is_empty() && at + needle.len() <= chars.len() {
+            if chars[at..at + needle.len()] == needle[..] {
+                spans.push((at, at + needle.len()));
+                at += needle.len();
+            } else {
+                at += 1;
+            }
+        }
+    }
+
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start < last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Up to [`MAX_SNIPPETS`] excerpts of `content` around `matches`, as from
+/// [`find_matches`]
+pub fn snippets(content: &str, matches: &[(usize, usize)]) -> Vec<Snippet> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut snippets: Vec<Snippet> = Vec::new();
+    let mut covered = 0;
+    for &(start, end) in matches {
+        if let Some(last) = snippets.last_mut() {
+            if end <= covered {
+                last.highlights.push((start - last.start, end - last.start));
+                continue;
+            }
+        }
+        if snippets.len() == MAX_SNIPPETS {
+            break;
+        }
+        // Start and end on word boundaries where the length allows
+        let mut from = start.saturating_sub(LEAD_CHARS).max(covered);
+        while from > covered && from < start && chars[from - 1].is_alphanumeric() {
+            from += 1;
+        }
+        let mut to = (from + SNIPPET_CHARS).min(chars.len()).max(end);
+        while to > end && to < chars.len() && chars[to].is_alphanumeric() {
+            to -= 1;
+        }
+        snippets.push(Snippet {
+            text: chars[from..to].iter().collect(),
+            start: from,
+            highlights: vec![(start - from, end - from)],
+        });
+        covered = to;
+    }
+    snippets
+}
+
+/// The search result for `doc`, found by `query` with relevance `score`
+pub(crate) fn search_result(doc: StoredDocument, score: f64, query: &SearchQuery) -> SearchResult {
+    let title_matches = find_matches(query, &doc.title);
+    let content_matches = find_matches(query, &doc.content);
+    // Matches inside ciphertext mean nothing
+    #[cfg(feature = "encryption")]
+    let content_matches = if crate::encryption::is_sealed(&doc.content) {
+        Vec::new()
+    } else {
+        content_matches
+    };
+    let snippets = snippets(&doc.content, &content_matches);
+    let snippet = match snippets.first() {
+        Some(first) => first.text.clone(),
+        None => doc.content.chars().take(SNIPPET_CHARS).collect(),
+    };
+    let span = |field| move |(start, end)| MatchSpan { field, start, end };
+    let matches = title_matches
+        .into_iter()
+        .map(span(SearchField::Title))
+        .chain(content_matches.into_iter().map(span(SearchField::Content)))
+        .collect();
+    SearchResult {
+        key: doc.key.unwrap_or_default(),
+        title: doc.title,
+        format: doc.format,
+        tags: doc.tags,
+        snippet,
+        snippets,
+        matches,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches() {
+        let query = SearchQuery::parse(r#"Borrowing "shared REF""#);
+        let text = "Borrowed values, a borrow, a shared ref to borrowers; bore.";
+        let spans: Vec<String> = find_matches(&query, text)
+            .into_iter()
+            .map(|(start, end)| text.chars().skip(start).take(end - start).collect())
+            .collect();
+        assert_eq!(spans, ["Borrowed", "borrow", "shared ref", "borrowers"]);
+        assert_eq!(find_matches(&query, "ñandú borrow"), [(6, 12)]);
+    }
+
+    #[test]
+    fn test_snippets() {
+        let content = format!(
+            "{}needle {} needle needle",
+            "word ".repeat(30),
+            "x".repeat(300)
+        );
+        let query = SearchQuery::parse("needle");
+        let found = snippets(&content, &find_matches(&query, &content));
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].start, 90);
+        assert_eq!(found[0].highlights, [(60, 66)]);
+        assert!(found[0].text.chars().count() <= SNIPPET_CHARS);
+        assert_eq!(found[1].highlights.len(), 2);
+        let (start, end) = found[1].highlights[1];
+        let text: String = found[1]
+            .text
+            .chars()
+            .skip(start)
+            .take(end - start)
+            .collect();
+        assert_eq!(text, "needle");
+
+        let doc = StoredDocument::new("Needle notes", "no match here", "md");
+        let result = search_result(doc, 1.0, &query);
+        assert_eq!(result.snippet, "no match here");
+        assert_eq!(result.matches[0].field, SearchField::Title);
+        assert!(result.snippets.is_empty());
+    }
+}
@@ -0,0 +1,706 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! SQLite backend (feature `sqlite`)
+//!
+//! [`SqliteStore`] implements [`DocumentStore`] on a single SQLite file, so
+//! a single-user install needs no ArangoDB server. Each document is kept as
+//! JSON next to the columns queries filter and sort on, with its tags in
+//! `document_tags` and its title and content in the FTS5 table
+//! `documents_fts`, ranked with BM25 with title matches counting double.
+//! Links are rows of `links`, keyed by the documents they join.
+//!
+//! Keys are assigned in sequence and revisions are random, as opaque to
+//! callers as ArangoDB's. Calls run on the blocking thread pool, one at a
+//! time per store.
+
+use crate::dedup::content_hash;
+use crate::error::{DbError, Result};
+use crate::models::{
+    document_id, DocumentLink, DocumentRef, SaveOptions, SearchResult, StoredDocument, TagInfo,
+    Visibility,
+};
+use crate::page::{Page, PageRequest};
+use crate::query::{DocumentQuery, DocumentSort};
+use crate::search::SearchQuery;
+use crate::snippets::search_result;
+use crate::store::DocumentStore;
+use chrono::Utc;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection, OptionalExtension, Transaction};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS documents (
+    key TEXT PRIMARY KEY,
+    rev TEXT NOT NULL,
+    title TEXT NOT NULL,
+    format TEXT NOT NULL,
+    visibility TEXT NOT NULL,
+    parent TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS document_tags (
+    key TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (key, tag)
+);
+CREATE INDEX IF NOT EXISTS idx_document_tags_tag ON document_tags (tag);
+CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5 (
+    key UNINDEXED, title, content,
+    tokenize = 'porter unicode61 remove_diacritics 2'
+);
+CREATE TABLE IF NOT EXISTS links (
+    key TEXT PRIMARY KEY,
+    source TEXT NOT NULL,
+    target TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_links_source ON links (source);
+CREATE INDEX IF NOT EXISTS idx_links_target ON links (target);
+";
+
+/// BM25 over `documents_fts`; lower is better
+const RANK: &str = "bm25(documents_fts, 0.0, 2.0, 1.0)";
+
+impl From<rusqlite::Error> for DbError {
+    fn from(err: rusqlite::Error) -> Self {
+        DbError::Query(err.to_string())
+    }
+}
+
+/// A document library in one SQLite database
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Open the database at `path`, creating it and its tables if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// A database that lives as long as the store, for tests
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run `op` on the connection on the blocking thread pool
+    async fn run<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = Arc::clone(&self.conn);
+        tokio::task::spawn_blocking(move || {
+            op(&mut conn.lock().unwrap_or_else(PoisonError::into_inner))
+        })
+        .await
+        .map_err(|e| DbError::Query(e.to_string()))?
+    }
+}
+
+/// The next key in `table`'s sequence
+fn next_key(trx: &Transaction, table: &str) -> Result<String> {
+    let last: i64 = trx.query_row(
+        &format!("SELECT COALESCE(MAX(CAST(key AS INTEGER)), 0) FROM {table}"),
+        [],
+        |row| row.get(0),
+    )?;
+    Ok((last + 1).to_string())
+}
+
+fn new_rev() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// The stored name of `visibility`, e.g. `private`
+fn visibility_name(visibility: Visibility) -> Result<String> {
+    match serde_json::to_value(visibility)? {
+        serde_json::Value::String(name) => Ok(name),
+        other => Ok(other.to_string()),
+    }
+}
+
+fn not_found(key: &str) -> DbError {
+    DbError::NotFound {
+        key: key.to_string(),
+    }
+}
+
+fn read_document(key: String, rev: String, data: &str) -> Result<StoredDocument> {
+    let mut doc: StoredDocument = serde_json::from_str(data)?;
+    doc.key = Some(key);
+    doc.rev = Some(rev);
+    Ok(doc)
+}
+
+fn read_link(key: String, data: &str) -> Result<DocumentLink> {
+    let mut link: DocumentLink = serde_json::from_str(data)?;
+    link.key = Some(key);
+    Ok(link)
+}
+
+fn save_document(
+    trx: &Transaction,
+    doc: &StoredDocument,
+    options: SaveOptions,
+) -> Result<DocumentRef> {
+    let mut stored = doc.clone();
+    stored.key = None;
+    stored.rev = None;
+    stored.updated_at = Utc::now();
+    stored.content_hash = Some(content_hash(&doc.content));
+
+    let key = match &doc.key {
+        Some(key) => key.clone(),
+        None => next_key(trx, "documents")?,
+    };
+    let rev = new_rev();
+    let mut values: Vec<SqlValue> = vec![
+        key.clone().into(),
+        rev.clone().into(),
+        stored.title.clone().into(),
+        stored.format.clone().into(),
+        visibility_name(stored.visibility)?.into(),
+        stored.parent.clone().into(),
+        stored.created_at.timestamp_millis().into(),
+        stored.updated_at.timestamp_millis().into(),
+        serde_json::to_string(&stored)?.into(),
+    ];
+    match (&doc.key, &doc.rev) {
+        (Some(_), Some(expected)) if !options.force => {
+            values.push(expected.clone().into());
+            let updated = trx.execute(
+                "UPDATE documents SET rev = ?2, title = ?3, format = ?4, visibility = ?5, \
+                 parent = ?6, created_at = ?7, updated_at = ?8, data = ?9 \
+                 WHERE key = ?1 AND rev = ?10",
+                params_from_iter(values),
+            )?;
+            if updated == 0 {
+                let exists = trx
+                    .query_row("SELECT 1 FROM documents WHERE key = ?1", [&key], |_| Ok(()))
+                    .optional()?
+                    .is_some();
+                return Err(if exists {
+                    DbError::Conflict { key }
+                } else {
+                    not_found(&key)
+                });
+            }
+        }
+        _ => {
+            trx.execute(
+                "INSERT INTO documents \
+                 (key, rev, title, format, visibility, parent, created_at, updated_at, data) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) \
+                 ON CONFLICT (key) DO UPDATE SET rev = ?2, title = ?3, format = ?4, \
+                 visibility = ?5, parent = ?6, created_at = ?7, updated_at = ?8, data = ?9",
+                params_from_iter(values),
+            )?;
+        }
+    }
+
+    trx.execute("DELETE FROM document_tags WHERE key = ?1", [&key])?;
+    for tag in &stored.tags {
+        trx.execute(
+            "INSERT OR IGNORE INTO document_tags (key, tag) VALUES (?1, ?2)",
+            [&key, tag],
+        )?;
+    }
+    trx.execute("DELETE FROM documents_fts WHERE key = ?1", [&key])?;
+    trx.execute(
+        "INSERT INTO documents_fts (key, title, content) VALUES (?1, ?2, ?3)",
+        [&key, &stored.title, &stored.content],
+    )?;
+    Ok(DocumentRef { key, rev })
+}
+
+fn add_link(trx: &Transaction, link: &DocumentLink) -> Result<String> {
+    let key = next_key(trx, "links")?;
+    let mut stored = link.clone();
+    stored.key = None;
+    trx.execute(
+        "INSERT INTO links (key, source, target, data) VALUES (?1, ?2, ?3, ?4)",
+        [
+            &key,
+            link.from_key(),
+            link.to_key(),
+            &serde_json::to_string(&stored)?,
+        ],
+    )?;
+    Ok(key)
+}
+
+/// Replace the links from the document with `key` by `links`
+fn replace_links(trx: &Transaction, key: &str, links: Vec<DocumentLink>) -> Result<()> {
+    trx.execute("DELETE FROM links WHERE source = ?1", [key])?;
+    for link in links {
+        add_link(
+            trx,
+            &DocumentLink {
+                from: document_id(key),
+                ..link
+            },
+        )?;
+    }
+    Ok(())
+}
+
+fn get_links(conn: &Connection, column: &str, key: &str) -> Result<Vec<DocumentLink>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT key, data FROM links WHERE {column} = ?1 ORDER BY rowid"
+    ))?;
+    let rows = statement.query_map([key], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?;
+    rows.map(|row| {
+        let (key, data) = row?;
+        read_link(key, &data)
+    })
+    .collect()
+}
+
+/// FTS5 query for a parsed search; `None` if it matches everything
+fn fts_query(search: &SearchQuery) -> Option<String> {
+    if search.is_empty() {
+        return None;
+    }
+    let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+    let mut clauses = Vec::new();
+    if !search.terms.is_empty() {
+        let terms: Vec<String> = search.terms.iter().map(|term| quote(term)).collect();
+        clauses.push(format!("({})", terms.join(" OR ")));
+    }
+    clauses.extend(search.phrases.iter().map(|phrase| quote(phrase)));
+    let mut expression = clauses.join(" AND ");
+    if !search.excluded.is_empty() {
+        let excluded: Vec<String> = search.excluded.iter().map(|word| quote(word)).collect();
+        expression = format!("({}) NOT ({})", expression, excluded.join(" OR "));
+    }
+    Some(expression)
+}
+
+/// `FROM` and `WHERE` clauses over `d` for `query`, with their parameters,
+/// and the `ORDER BY` expression
+fn to_sql(query: &DocumentQuery) -> (String, Vec<SqlValue>, String) {
+    let fts = fts_query(&SearchQuery::parse(
+        query.text.as_deref().unwrap_or_default(),
+    ));
+    let mut sql = String::from("FROM documents d");
+    let mut values = Vec::new();
+    let mut conditions = Vec::new();
+    if let Some(fts) = &fts {
+        sql.push_str(" JOIN documents_fts ON documents_fts.key = d.key");
+        conditions.push("documents_fts MATCH ?".to_string());
+        values.push(SqlValue::Text(fts.clone()));
+    }
+    for tag in &query.all_tags {
+        conditions.push(
+            "EXISTS (SELECT 1 FROM document_tags t WHERE t.key = d.key AND t.tag = ?)".to_string(),
+        );
+        values.push(SqlValue::Text(tag.clone()));
+    }
+    if !query.any_tags.is_empty() {
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM document_tags t WHERE t.key = d.key AND t.tag IN ({}))",
+            vec!["?"; query.any_tags.len()].join(", ")
+        ));
+        values.extend(query.any_tags.iter().cloned().map(SqlValue::Text));
+    }
+    let visibility = query.visibility.and_then(|v| visibility_name(v).ok());
+    for (column, value) in [
+        ("format", &query.format),
+        ("visibility", &visibility),
+        ("parent", &query.parent),
+    ] {
+        if let Some(value) = value {
+            conditions.push(format!("d.{column} = ?"));
+            values.push(SqlValue::Text(value.clone()));
+        }
+    }
+    for (column, after, before) in [
+        ("created_at", query.created_after, query.created_before),
+        ("updated_at", query.updated_after, query.updated_before),
+    ] {
+        if let Some(after) = after {
+            conditions.push(format!("d.{column} >= ?"));
+            values.push(SqlValue::Integer(after.timestamp_millis()));
+        }
+        if let Some(before) = before {
+            conditions.push(format!("d.{column} < ?"));
+            values.push(SqlValue::Integer(before.timestamp_millis()));
+        }
+    }
+    for (flag, value) in [("pinned", query.pinned), ("favorite", query.favorite)] {
+        if let Some(value) = value {
+            conditions.push(format!("IFNULL(json_extract(d.data, '$.{flag}'), 0) = ?"));
+            values.push(SqlValue::Integer(value.into()));
+        }
+    }
+    if !query.include_archived {
+        conditions.push("IFNULL(json_extract(d.data, '$.archived'), 0) = 0".to_string());
+    }
+    // No share grants here: readers see their own, unowned and public
+    // documents
+    if let Some(reader) = &query.reader {
+        conditions.push(
+            "(json_extract(d.data, '$.owner') IS NULL \
+             OR json_extract(d.data, '$.owner') = ? OR d.visibility = 'public')"
+                .to_string(),
+        );
+        values.push(SqlValue::Text(reader.clone()));
+    }
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+    }
+
+    let order = match query.sort {
+        DocumentSort::Relevance if fts.is_some() => "score, d.key",
+        DocumentSort::Relevance | DocumentSort::RecentlyUpdated => "d.updated_at DESC, d.key",
+        DocumentSort::Title => "d.title, d.key",
+        DocumentSort::NewestFirst => "d.created_at DESC, d.key",
+        DocumentSort::OldestFirst => "d.created_at, d.key",
+        DocumentSort::LeastRecentlyUpdated => "d.updated_at, d.key",
+    };
+    let order = if query.pinned_first {
+        format!("IFNULL(json_extract(d.data, '$.pinned'), 0) DESC, {order}")
+    } else {
+        order.to_string()
+    };
+    (sql, values, order)
+}
+
+/// One page of the documents matching `query`, with their BM25 scores
+fn find_documents(
+    conn: &Connection,
+    query: &DocumentQuery,
+    page: PageRequest,
+) -> Result<Page<(StoredDocument, f64)>> {
+    let (source, mut values, order) = to_sql(query);
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) {source}"),
+        params_from_iter(&values),
+        |row| row.get(0),
+    )?;
+
+    let text = source.contains("documents_fts");
+    let score = if text { RANK } else { "0.0" };
+    values.push(SqlValue::Integer(page.limit as i64));
+    values.push(SqlValue::Integer(page.offset as i64));
+    let mut statement = conn.prepare(&format!(
+        "SELECT d.key, d.rev, d.data, {score} AS score {source} \
+         ORDER BY {order} LIMIT ? OFFSET ?"
+    ))?;
+    let rows = statement.query_map(params_from_iter(&values), |row| {
+        Ok((
+            row.get(0)?,
+            row.get(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, f64>(3)?,
+        ))
+    })?;
+    let items = rows
+        .map(|row| {
+            let (key, rev, data, score) = row?;
+            Ok((read_document(key, rev, &data)?, -score))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Page::new(items, total as usize, page))
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for SqliteStore {
+    async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let doc = doc.clone();
+        self.run(move |conn| {
+            let trx = conn.transaction()?;
+            let saved = save_document(&trx, &doc, options)?;
+            trx.commit()?;
+            Ok(saved)
+        })
+        .await
+    }
+
+    async fn get_document(&self, key: &str) -> Result<StoredDocument> {
+        let key = key.to_string();
+        self.run(move |conn| {
+            let row: Option<(String, String)> = conn
+                .query_row(
+                    "SELECT rev, data FROM documents WHERE key = ?1",
+                    [&key],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            let (rev, data) = row.ok_or_else(|| not_found(&key))?;
+            read_document(key, rev, &data)
+        })
+        .await
+    }
+
+    async fn delete_document(&self, key: &str) -> Result<()> {
+        let key = key.to_string();
+        self.run(move |conn| {
+            let trx = conn.transaction()?;
+            if trx.execute("DELETE FROM documents WHERE key = ?1", [&key])? == 0 {
+                return Err(not_found(&key));
+            }
+            trx.execute("DELETE FROM document_tags WHERE key = ?1", [&key])?;
+            trx.execute("DELETE FROM documents_fts WHERE key = ?1", [&key])?;
+            trx.execute("DELETE FROM links WHERE source = ?1 OR target = ?1", [&key])?;
+            trx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn find_documents(
+        &self,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let query = query.clone();
+        self.run(move |conn| {
+            let found = find_documents(conn, &query, page)?;
+            let items = found.items.into_iter().map(|(doc, _)| doc).collect();
+            Ok(Page::new(items, found.total, page))
+        })
+        .await
+    }
+
+    async fn search_fulltext(&self, query: &str, page: PageRequest) -> Result<Page<SearchResult>> {
+        let parsed = SearchQuery::parse(query);
+        if parsed.is_empty() {
+            return Ok(Page::new(Vec::new(), 0, page));
+        }
+        let query = DocumentQuery::new().with_text(query);
+        self.run(move |conn| {
+            let found = find_documents(conn, &query, page)?;
+            let items = found
+                .items
+                .into_iter()
+                .map(|(doc, score)| search_result(doc, score, &parsed))
+                .collect();
+            Ok(Page::new(items, found.total, page))
+        })
+        .await
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        self.run(|conn| {
+            let mut statement = conn.prepare(
+                "SELECT tag, COUNT(*) AS count FROM document_tags \
+                 GROUP BY tag ORDER BY count DESC, tag",
+            )?;
+            let rows = statement.query_map([], |row| {
+                Ok(TagInfo {
+                    name: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as u64,
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        })
+        .await
+    }
+
+    async fn add_link(&self, link: &DocumentLink) -> Result<String> {
+        let link = link.clone();
+        self.run(move |conn| {
+            let trx = conn.transaction()?;
+            let key = add_link(&trx, &link)?;
+            trx.commit()?;
+            Ok(key)
+        })
+        .await
+    }
+
+    async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>> {
+        let links = links.to_vec();
+        self.run(move |conn| {
+            let trx = conn.transaction()?;
+            let keys = links
+                .iter()
+                .map(|link| add_link(&trx, link))
+                .collect::<Result<_>>()?;
+            trx.commit()?;
+            Ok(keys)
+        })
+        .await
+    }
+
+    async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+        let key = key.to_string();
+        let links = links.to_vec();
+        self.run(move |conn| {
+            let trx = conn.transaction()?;
+            replace_links(&trx, &key, links)?;
+            trx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        let key = key.to_string();
+        self.run(move |conn| get_links(conn, "source", &key)).await
+    }
+
+    async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        let key = key.to_string();
+        self.run(move |conn| get_links(conn, "target", &key)).await
+    }
+
+    async fn save_with_links(
+        &self,
+        doc: &StoredDocument,
+        links: &[DocumentLink],
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let doc = doc.clone();
+        let links = links.to_vec();
+        self.run(move |conn| {
+            let trx = conn.transaction()?;
+            let saved = save_document(&trx, &doc, options)?;
+            replace_links(&trx, &saved.key, links)?;
+            trx.commit()?;
+            Ok(saved)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LinkType;
+
+    #[test]
+    fn test_fts_query() {
+        let query = |text: &str| fts_query(&SearchQuery::parse(text));
+        assert_eq!(query(""), None);
+        assert_eq!(query("-draft"), None);
+        assert_eq!(
+            query("rust \"borrow checker\" -go"),
+            Some("((\"rust\") AND \"borrow checker\") NOT (\"go\")".to_string())
+        );
+        assert_eq!(query("a b"), Some("(\"a\" OR \"b\")".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_documents() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let doc = StoredDocument::new("Ownership", "The borrow checker in Rust", "md")
+            .with_tags(["rust", "notes"]);
+        let saved = store.save_document(&doc).await.unwrap();
+        store
+            .save_document(&StoredDocument::new("Goroutines", "Channels in Go", "md"))
+            .await
+            .unwrap();
+
+        let mut read = store.get_document(&saved.key).await.unwrap();
+        assert_eq!(read.content, doc.content);
+        assert_eq!(read.rev.as_deref(), Some(saved.rev.as_str()));
+
+        read.title = "Ownership rules".to_string();
+        let resaved = store.save_document(&read).await.unwrap();
+        assert!(matches!(
+            store.save_document(&read).await,
+            Err(DbError::Conflict { .. })
+        ));
+        assert_ne!(resaved.rev, saved.rev);
+
+        let found = store
+            .find_documents(
+                &DocumentQuery::new().with_all_tags(["rust"]),
+                PageRequest::first(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.total, 1);
+        let pinned = store
+            .find_documents(
+                &DocumentQuery::new().with_pinned(false).pinned_first(),
+                PageRequest::first(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(pinned.total, 2);
+        let private = StoredDocument::new("Plan", "", "md").with_owner("ada");
+        store.save_document(&private).await.unwrap();
+        for (reader, total) in [("ada", 3), ("bob", 2)] {
+            let query = DocumentQuery::new().with_reader(reader);
+            let found = store
+                .find_documents(&query, PageRequest::first(10))
+                .await
+                .unwrap();
+            assert_eq!(found.total, total);
+        }
+        let hits = store
+            .search_fulltext("borrowing -go", PageRequest::first(10))
+            .await
+            .unwrap();
+        assert_eq!(hits.items[0].title, "Ownership rules");
+        assert!(hits.items[0].score > 0.0);
+        assert_eq!(store.list_tags().await.unwrap().len(), 2);
+
+        store.delete_document(&saved.key).await.unwrap();
+        assert!(matches!(
+            store.get_document(&saved.key).await,
+            Err(DbError::NotFound { .. })
+        ));
+        assert!(store.list_tags().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_links() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let a = store
+            .save_document(&StoredDocument::new("A", "", "md"))
+            .await
+            .unwrap();
+        let b = store
+            .save_document(&StoredDocument::new("B", "", "md"))
+            .await
+            .unwrap();
+        let mut doc = store.get_document(&a.key).await.unwrap();
+        doc.content = "See B".to_string();
+        let links = [DocumentLink::new("", &b.key, LinkType::Reference)];
+        store
+            .save_with_links(&doc, &links, SaveOptions::default())
+            .await
+            .unwrap();
+
+        let from_a = store.get_links(&a.key).await.unwrap();
+        assert_eq!(from_a.len(), 1);
+        assert_eq!(from_a[0].from_key(), a.key);
+        assert_eq!(store.get_backlinks(&b.key).await.unwrap(), from_a);
+
+        let keys = store
+            .add_links(&[
+                DocumentLink::new(&b.key, &a.key, LinkType::Reference),
+                DocumentLink::new(&b.key, &a.key, LinkType::Related),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(store.get_backlinks(&a.key).await.unwrap().len(), 2);
+        store.replace_links(&b.key, &[]).await.unwrap();
+        assert!(store.get_backlinks(&a.key).await.unwrap().is_empty());
+
+        store.delete_document(&b.key).await.unwrap();
+        assert!(store.get_links(&a.key).await.unwrap().is_empty());
+    }
+}
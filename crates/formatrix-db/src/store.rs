@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Storage backend abstraction
+//!
+//! [`DocumentStore`] is the set of document and link operations the rest
+//! of Formatrix needs from a library, so callers can take any backend,
+//! boxed as `dyn DocumentStore` if need be. [`FormatrixDb`] is the ArangoDB
+//! implementation; the other features of this crate, such as sharing,
+//! notebooks and analytics, stay specific to it.
+
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::{
+    DocumentLink, DocumentRef, SaveOptions, SearchResult, StoredDocument, TagInfo,
+};
+use crate::page::{Page, PageRequest};
+use crate::query::DocumentQuery;
+
+/// Document and link storage
+///
+/// Backends follow [`FormatrixDb`]'s contract: a save with a stale `rev`
+/// fails with [`crate::DbError::Conflict`] unless
+/// [`SaveOptions::force`] is set, a missing document is
+/// [`crate::DbError::NotFound`], and deleting a document deletes its
+/// links.
+#[async_trait::async_trait]
+pub trait DocumentStore: Send + Sync {
+    /// Insert or update a document
+    async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef>;
+
+    /// Insert or update a document, failing on concurrent changes
+    async fn save_document(&self, doc: &StoredDocument) -> Result<DocumentRef> {
+        self.save_document_with(doc, SaveOptions::default()).await
+    }
+
+    async fn get_document(&self, key: &str) -> Result<StoredDocument>;
+
+    async fn delete_document(&self, key: &str) -> Result<()>;
+
+    /// One page of the documents matching `query`
+    async fn find_documents(
+        &self,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>>;
+
+    /// Documents matching a [`crate::SearchQuery`], best match first
+    async fn search_fulltext(&self, query: &str, page: PageRequest) -> Result<Page<SearchResult>>;
+
+    /// All tags with their document counts, most used first
+    async fn list_tags(&self) -> Result<Vec<TagInfo>>;
+
+    /// Store a link, returning its key
+    async fn add_link(&self, link: &DocumentLink) -> Result<String>;
+
+    /// Store many links, all or nothing, returning their keys in order
+    async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>>;
+
+    /// Replace the links from the document with `key`, all or nothing
+    async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()>;
+
+    /// Links from the document with `key`
+    async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>>;
+
+    /// Links to the document with `key`
+    async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>>;
+
+    /// Save a document and replace its outgoing links, all or nothing
+    async fn save_with_links(
+        &self,
+        doc: &StoredDocument,
+        links: &[DocumentLink],
+        options: SaveOptions,
+    ) -> Result<DocumentRef>;
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for FormatrixDb {
+    async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        FormatrixDb::save_document_with(self, doc, options).await
+    }
+
+    async fn get_document(&self, key: &str) -> Result<StoredDocument> {
+        FormatrixDb::get_document(self, key).await
+    }
+
+    async fn delete_document(&self, key: &str) -> Result<()> {
+        FormatrixDb::delete_document(self, key).await
+    }
+
+    async fn find_documents(
+        &self,
+        query: &DocumentQuery,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        FormatrixDb::find_documents(self, query, page).await
+    }
+
+    async fn search_fulltext(&self, query: &str, page: PageRequest) -> Result<Page<SearchResult>> {
+        FormatrixDb::search_fulltext(self, query, page).await
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        FormatrixDb::list_tags(self).await
+    }
+
+    async fn add_link(&self, link: &DocumentLink) -> Result<String> {
+        FormatrixDb::add_link(self, link).await
+    }
+
+    async fn add_links(&self, links: &[DocumentLink]) -> Result<Vec<String>> {
+        FormatrixDb::add_links(self, links).await
+    }
+
+    async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+        FormatrixDb::replace_links(self, key, links).await
+    }
+
+    async fn get_links(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        FormatrixDb::get_links(self, key).await
+    }
+
+    async fn get_backlinks(&self, key: &str) -> Result<Vec<DocumentLink>> {
+        FormatrixDb::get_backlinks(self, key).await
+    }
+
+    async fn save_with_links(
+        &self,
+        doc: &StoredDocument,
+        links: &[DocumentLink],
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        FormatrixDb::save_with_links(self, doc, links, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatrix_db_is_a_store() {
+        fn as_store(db: &FormatrixDb) -> &dyn DocumentStore {
+            db
+        }
+        let _ = as_store;
+    }
+}
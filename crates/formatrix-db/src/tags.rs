@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Tag usage counts and namespaces
+//!
+//! The `tags` collection holds one `{ name, count }` entry per tag in use.
+//! Saves adjust it by the difference between the stored and the new tags,
+//! deletes by the removed document's tags, and a tag whose count drops to
+//! zero is removed. [`FormatrixDb::recount_tags`] rebuilds the collection
+//! from the documents if the counts have drifted anyway.
+//!
+//! Tags can be namespaced with [`TAG_SEPARATOR`], as in
+//! `project/formatrix/db`. A prefix query for `project/formatrix` matches
+//! that tag and everything below it, and [`FormatrixDb::tag_tree`] rolls
+//! the counts of nested tags up to their parents.
+
+use crate::acl::READABLE_FILTER;
+use crate::client::FormatrixDb;
+use crate::error::Result;
+use crate::models::{StoredDocument, TagInfo, DOCUMENTS, GRANTS, TAGS};
+use crate::page::{Page, PageRequest};
+use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Separates the levels of a namespaced tag
+pub const TAG_SEPARATOR: char = '/';
+
+/// A tag with its descendants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagNode {
+    /// Last segment, e.g. `db`
+    pub name: String,
+    /// Full tag, e.g. `project/formatrix/db`
+    pub path: String,
+    /// Documents tagged with exactly this tag
+    pub count: u64,
+    /// `count` plus the counts of every descendant; a document with several
+    /// tags in the subtree is counted once per tag
+    pub total: u64,
+    pub children: Vec<TagNode>,
+}
+
+/// `tag` without empty segments, e.g. `/a//b/` becomes `a/b`
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split(TAG_SEPARATOR)
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join(&TAG_SEPARATOR.to_string())
+}
+
+/// Arrange flat tag counts into a tree, sorted by name at every level
+///
+/// Parents that are never used on their own appear with a `count` of 0.
+pub fn build_tag_tree(tags: &[TagInfo]) -> Vec<TagNode> {
+    #[derive(Default)]
+    struct Builder {
+        count: u64,
+        children: BTreeMap<String, Builder>,
+    }
+
+    fn finish(name: String, path: String, builder: Builder) -> TagNode {
+        let children: Vec<TagNode> = builder
+            .children
+            .into_iter()
+            .map(|(name, child)| {
+                let path = format!("{}{}{}", path, TAG_SEPARATOR, name);
+                finish(name, path, child)
+            })
+            .collect();
+        TagNode {
+            total: builder.count + children.iter().map(|child| child.total).sum::<u64>(),
+            name,
+            path,
+            count: builder.count,
+            children,
+        }
+    }
+
+    let mut root = Builder::default();
+    for tag in tags {
+        let path = normalize_tag(&tag.name);
+        if path.is_empty() {
+            continue;
+        }
+        let node = path.split(TAG_SEPARATOR).fold(&mut root, |node, segment| {
+            node.children.entry(segment.to_string()).or_default()
+        });
+        node.count += tag.count;
+    }
+    root.children
+        .into_iter()
+        .map(|(name, child)| finish(name.clone(), name, child))
+        .collect()
+}
+
+/// Tags gained and lost going from `old` to `new`, each listed once
+pub fn tag_delta(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let old: BTreeSet<&String> = old.iter().collect();
+    let new: BTreeSet<&String> = new.iter().collect();
+    let added = new.difference(&old).map(|tag| tag.to_string()).collect();
+    let removed = old.difference(&new).map(|tag| tag.to_string()).collect();
+    (added, removed)
+}
+
+impl Transaction {
+    /// Count one more use of each `added` tag and one fewer of each
+    /// `removed` tag
+    pub(crate) async fn adjust_tags(&self, added: &[String], removed: &[String]) -> Result<()> {
+        if !added.is_empty() {
+            self.query::<Value>(
+                "FOR tag IN @tags \
+                 UPSERT { name: tag } INSERT { name: tag, count: 1 } \
+                 UPDATE { count: OLD.count + 1 } IN @@tags",
+                HashMap::from([("tags", json!(added)), ("@tags", json!(TAGS))]),
+            )
+            .await?;
+        }
+        if !removed.is_empty() {
+            self.query::<Value>(
+                "FOR t IN @@tags FILTER t.name IN @tags AND t.count <= 1 REMOVE t IN @@tags",
+                HashMap::from([("tags", json!(removed)), ("@tags", json!(TAGS))]),
+            )
+            .await?;
+            self.query::<Value>(
+                "FOR t IN @@tags FILTER t.name IN @tags \
+                 UPDATE t WITH { count: t.count - 1 } IN @@tags",
+                HashMap::from([("tags", json!(removed)), ("@tags", json!(TAGS))]),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+impl FormatrixDb {
+    /// Unarchived documents tagged with `prefix` or any tag below it, most
+    /// recent first
+    pub async fn search_by_tag_prefix(
+        &self,
+        prefix: &str,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        self.tag_prefix_page(prefix, None, page).await
+    }
+
+    /// [`FormatrixDb::search_by_tag_prefix`], limited to what `reader` may
+    /// read if given
+    pub(crate) async fn tag_prefix_page(
+        &self,
+        prefix: &str,
+        reader: Option<&str>,
+        page: PageRequest,
+    ) -> Result<Page<StoredDocument>> {
+        let tag = normalize_tag(prefix);
+        let mut filter = "FILTER LENGTH(FOR t IN d.tags || [] \
+             FILTER t == @tag OR STARTS_WITH(t, @below) LIMIT 1 RETURN t) > 0 \
+             FILTER d.archived != true"
+            .to_string();
+        let mut vars = HashMap::from([
+            ("below", json!(format!("{}{}", tag, TAG_SEPARATOR))),
+            ("tag", json!(tag)),
+        ]);
+        if let Some(reader) = reader {
+            filter.push(' ');
+            filter.push_str(READABLE_FILTER);
+            vars.insert("user", json!(reader));
+            vars.insert("@grants", json!(GRANTS));
+        }
+        self.page_documents(&filter, "d.updated_at DESC", vars, page)
+            .await
+    }
+
+    /// Every tag in use, arranged by namespace with rolled-up counts
+    pub async fn tag_tree(&self) -> Result<Vec<TagNode>> {
+        Ok(build_tag_tree(&self.list_tags().await?))
+    }
+
+    /// Rebuild the tag counts from the documents, returning the number of
+    /// distinct tags
+    pub async fn recount_tags(&self) -> Result<usize> {
+        let trx = self.begin_transaction(&[TAGS]).await?;
+        let result = async {
+            trx.query::<Value>(
+                "FOR t IN @@tags REMOVE t IN @@tags",
+                HashMap::from([("@tags", json!(TAGS))]),
+            )
+            .await?;
+            let names: Vec<String> = trx
+                .query(
+                    "FOR d IN @@documents FOR tag IN UNIQUE(d.tags || []) \
+                     COLLECT name = tag WITH COUNT INTO count \
+                     INSERT { name, count } INTO @@tags RETURN name",
+                    HashMap::from([("@documents", json!(DOCUMENTS)), ("@tags", json!(TAGS))]),
+                )
+                .await?;
+            Ok(names.len())
+        }
+        .await;
+        trx.finish(result).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let info = |name: &str, count| TagInfo {
+            name: name.to_string(),
+            count,
+        };
+        let tree = build_tag_tree(&[
+            info("project/formatrix/db", 3),
+            info("project/formatrix", 1),
+            info("project/other/", 2),
+            info("notes", 4),
+        ]);
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!((tree[0].name.as_str(), tree[0].total), ("notes", 4));
+        let project = &tree[1];
+        assert_eq!((project.count, project.total), (0, 6));
+        let formatrix = &project.children[0];
+        assert_eq!(formatrix.path, "project/formatrix");
+        assert_eq!((formatrix.count, formatrix.total), (1, 4));
+        assert_eq!(formatrix.children[0].path, "project/formatrix/db");
+        assert_eq!(project.children[1].path, "project/other");
+        assert_eq!(normalize_tag(" /a//b / "), "a/b");
+    }
+
+    #[test]
+    fn test_tag_delta() {
+        let (added, removed) = tag_delta(&tags(&["a", "b", "b"]), &tags(&["b", "c", "c"]));
+        assert_eq!(added, tags(&["c"]));
+        assert_eq!(removed, tags(&["a"]));
+
+        let (added, removed) = tag_delta(&[], &tags(&["x", "x"]));
+        assert_eq!(added, tags(&["x"]));
+        assert!(removed.is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Multi-operation transactions
+//!
+//! A [`Transaction`] is an ArangoDB stream transaction: queries run through
+//! it see each other's writes, and nothing becomes visible to other clients
+//! until [`Transaction::commit`]. Saving and deleting documents always run
+//! in one, so a failure half-way never leaves tags, links or versions out of
+//! step with the documents.
+
+use crate::client::FormatrixDb;
+use crate::dedup::content_hash;
+use crate::error::{self, DbError, Result};
+use crate::models::{
+    document_id, DocumentLink, DocumentRef, SaveOptions, StoredDocument, DOCUMENTS,
+    DOCUMENT_VERSIONS, LINKS, TAGS,
+};
+use crate::tags::tag_delta;
+use crate::versions::SNAPSHOT_AQL;
+use arangors::client::reqwest::ReqwestClient;
+use arangors::transaction::{TransactionCollections, TransactionSettings};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// How long a transaction waits for locks on its collections, in seconds
+pub const LOCK_TIMEOUT: usize = 30;
+
+/// A running stream transaction
+///
+/// Dropping a transaction without committing leaves it to expire on the
+/// server; call [`Transaction::abort`] to release its locks straight away.
+pub struct Transaction {
+    trx: arangors::transaction::Transaction<ReqwestClient>,
+}
+
+impl FormatrixDb {
+    /// Start a transaction that may write to the `write` collections
+    pub async fn begin_transaction(&self, write: &[&str]) -> Result<Transaction> {
+        self.start_transaction(|| {
+            TransactionCollections::builder()
+                .write(write.iter().map(|name| name.to_string()).collect())
+                .build()
+        })
+        .await
+    }
+
+    /// Start a transaction that reads one consistent snapshot of the
+    /// `read` collections
+    pub async fn begin_read_transaction(&self, read: &[&str]) -> Result<Transaction> {
+        self.start_transaction(|| {
+            TransactionCollections::builder()
+                .read(read.iter().map(|name| name.to_string()).collect())
+                .build()
+        })
+        .await
+    }
+
+    /// Begin a transaction on `collections`, retrying per
+    /// [`crate::DbConfig::retry`]
+    async fn start_transaction(
+        &self,
+        collections: impl Fn() -> TransactionCollections,
+    ) -> Result<Transaction> {
+        let trx = self
+            .config()
+            .retry
+            .run(|| async {
+                let settings = TransactionSettings::builder()
+                    .collections(collections())
+                    .lock_timeout(LOCK_TIMEOUT)
+                    .build();
+                Ok(self.db().begin_transaction(settings).await?)
+            })
+            .await?;
+        Ok(Transaction { trx })
+    }
+
+    /// Save a document and replace its outgoing links in one transaction
+    ///
+    /// The links' `from` is set to the saved document, so they can be built
+    /// before a new document has a key.
+    pub async fn save_with_links(
+        &self,
+        doc: &StoredDocument,
+        links: &[DocumentLink],
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let trx = self
+            .begin_transaction(&[DOCUMENTS, TAGS, DOCUMENT_VERSIONS, LINKS])
+            .await?;
+        let result = async {
+            let saved = trx.save_document_with(doc, options).await?;
+            trx.replace_links(&saved.key, links).await?;
+            Ok(saved)
+        }
+        .await;
+        let saved = trx.finish(result).await?;
+        #[cfg(feature = "git-history")]
+        self.record_git_save(&saved.key).await;
+        Ok(saved)
+    }
+}
+
+impl Transaction {
+    /// Server-side transaction id
+    pub fn id(&self) -> &str {
+        self.trx.id()
+    }
+
+    /// Run `aql` with bind variables inside the transaction
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        aql: &str,
+        vars: HashMap<&str, Value>,
+    ) -> Result<Vec<T>> {
+        Ok(self.trx.aql_bind_vars(aql, vars).await?)
+    }
+
+    /// Make the transaction's writes visible
+    pub async fn commit(self) -> Result<()> {
+        self.trx.commit().await?;
+        Ok(())
+    }
+
+    /// Discard the transaction's writes
+    pub async fn abort(self) -> Result<()> {
+        self.trx.abort().await?;
+        Ok(())
+    }
+
+    /// Commit if `result` is `Ok`, abort otherwise, passing `result` through
+    pub async fn finish<T>(self, result: Result<T>) -> Result<T> {
+        match result {
+            Ok(value) => {
+                self.commit().await?;
+                Ok(value)
+            }
+            Err(err) => {
+                if let Err(abort) = self.abort().await {
+                    tracing::warn!("failed to abort transaction: {}", abort);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// [`FormatrixDb::save_document_with`] inside the transaction
+    pub async fn save_document_with(
+        &self,
+        doc: &StoredDocument,
+        options: SaveOptions,
+    ) -> Result<DocumentRef> {
+        let mut stored = doc.clone();
+        stored.rev = None;
+        stored.updated_at = Utc::now();
+        stored.content_hash = Some(content_hash(&doc.content));
+        let stored = serde_json::to_value(&stored)?;
+
+        let (aql, mut vars) = match (&doc.key, &doc.rev) {
+            (None, _) => (
+                "INSERT @doc INTO @@documents \
+                 RETURN { key: NEW._key, rev: NEW._rev, old_tags: [] }",
+                HashMap::new(),
+            ),
+            (Some(key), Some(rev)) if !options.force => (
+                "UPDATE { _key: @key, _rev: @rev } WITH @doc IN @@documents \
+                 OPTIONS { ignoreRevs: false } \
+                 RETURN { key: NEW._key, rev: NEW._rev, old_tags: OLD.tags }",
+                HashMap::from([("key", json!(key)), ("rev", json!(rev))]),
+            ),
+            (Some(key), _) => (
+                "UPSERT { _key: @key } INSERT @doc UPDATE @doc IN @@documents \
+                 RETURN { key: NEW._key, rev: NEW._rev, old_tags: OLD.tags }",
+                HashMap::from([("key", json!(key))]),
+            ),
+        };
+        vars.insert("doc", stored);
+        vars.insert("@documents", json!(DOCUMENTS));
+
+        #[derive(Deserialize)]
+        struct Saved {
+            key: String,
+            rev: String,
+            /// Null when the document is new
+            old_tags: Option<Vec<String>>,
+        }
+
+        let saved: Saved = self
+            .trx
+            .aql_bind_vars(aql, vars)
+            .await
+            .map_err(|err| error::write_error(err, doc.key.as_deref().unwrap_or_default()))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::Query("save returned no key".to_string()))?;
+
+        let (added, removed) = tag_delta(&saved.old_tags.unwrap_or_default(), &doc.tags);
+        self.adjust_tags(&added, &removed).await?;
+        self.query::<u32>(
+            SNAPSHOT_AQL,
+            HashMap::from([
+                ("id", json!(document_id(&saved.key))),
+                ("@versions", json!(DOCUMENT_VERSIONS)),
+            ]),
+        )
+        .await?;
+        Ok(DocumentRef {
+            key: saved.key,
+            rev: saved.rev,
+        })
+    }
+
+    /// Replace the links from the document with `key` by `links`
+    ///
+    /// Auto backlinks from the document are kept: they mirror other
+    /// documents' references (see [`crate::autolink`]).
+    pub async fn replace_links(&self, key: &str, links: &[DocumentLink]) -> Result<()> {
+        let id = document_id(key);
+        let links = links
+            .iter()
+            .map(|link| {
+                let mut link = link.clone();
+                link.key = None;
+                link.from = id.clone();
+                serde_json::to_value(link)
+            })
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        self.query::<Value>(
+            "FOR l IN @@links FILTER l._from == @id \
+             FILTER l.link_type != 'backlink' OR l.auto != true REMOVE l IN @@links",
+            HashMap::from([("id", json!(id)), ("@links", json!(LINKS))]),
+        )
+        .await?;
+        self.query::<Value>(
+            "FOR l IN @links INSERT l INTO @@links",
+            HashMap::from([("links", json!(links)), ("@links", json!(LINKS))]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// [`FormatrixDb::delete_document`] inside the transaction
+    pub async fn delete_document(&self, key: &str) -> Result<()> {
+        self.query::<Value>(
+            "FOR l IN @@links FILTER l._from == @id OR l._to == @id REMOVE l IN @@links",
+            HashMap::from([("id", json!(document_id(key))), ("@links", json!(LINKS))]),
+        )
+        .await?;
+        self.query::<Value>(
+            "FOR v IN @@versions FILTER v.document == @key REMOVE v IN @@versions",
+            HashMap::from([("key", json!(key)), ("@versions", json!(DOCUMENT_VERSIONS))]),
+        )
+        .await?;
+        self.remove_from_notebooks(key).await?;
+        self.remove_grants(key).await?;
+        self.remove_share_tokens(key).await?;
+        self.remove_attachments(key).await?;
+        self.remove_embedding(key).await?;
+        let removed: Vec<Option<Vec<String>>> = self
+            .query(
+                "REMOVE { _key: @key } IN @@documents OPTIONS { ignoreErrors: true } \
+                 RETURN OLD.tags",
+                HashMap::from([("key", json!(key)), ("@documents", json!(DOCUMENTS))]),
+            )
+            .await?;
+        let tags = removed
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::NotFound {
+                key: key.to_string(),
+            })?
+            .unwrap_or_default();
+        let (_, removed) = tag_delta(&tags, &[]);
+        self.adjust_tags(&[], &removed).await
+    }
+}
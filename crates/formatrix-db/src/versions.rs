@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Document version history
+//!
+//! Every save snapshots the stored document into `document_versions`,
+//! numbered from 1 per document, so edits can be rolled back. Restoring
+//! a version saves it as the current content, which is itself snapshotted,
+//! so a restore can be undone the same way.
+
+use crate::client::FormatrixDb;
+use crate::error::{DbError, Result};
+use crate::models::{document_id, StoredDocument, DOCUMENT_VERSIONS};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// A snapshot of a document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentVersion {
+    /// Key of the versioned document
+    pub document: String,
+    /// 1 for the first save
+    pub version: u32,
+    pub title: String,
+    pub content: String,
+    pub format: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub saved_at: DateTime<Utc>,
+}
+
+impl DocumentVersion {
+    /// Copy this snapshot's fields over `doc`, keeping its identity
+    pub fn apply_to(&self, doc: &mut StoredDocument) {
+        doc.title = self.title.clone();
+        doc.content = self.content.clone();
+        doc.format = self.format.clone();
+        doc.tags = self.tags.clone();
+    }
+}
+
+/// A version without its content, for listings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: u32,
+    pub title: String,
+    pub saved_at: DateTime<Utc>,
+    /// Content length in characters
+    pub length: usize,
+}
+
+/// Snapshot the document `@id` into `@@versions`, returning the new
+/// version number
+pub(crate) const SNAPSHOT_AQL: &str = "LET d = DOCUMENT(@id) FILTER d != null \
+     LET last = FIRST(FOR v IN @@versions FILTER v.document == d._key \
+         SORT v.version DESC LIMIT 1 RETURN v.version) \
+     INSERT { document: d._key, version: (last || 0) + 1, title: d.title, \
+              content: d.content, format: d.format, tags: d.tags, \
+              saved_at: d.updated_at } INTO @@versions \
+     RETURN NEW.version";
+
+impl FormatrixDb {
+    /// Snapshot the stored document with `key`, returning the new version
+    /// number
+    pub async fn save_revision(&self, key: &str) -> Result<u32> {
+        self.insert_query(
+            SNAPSHOT_AQL,
+            HashMap::from([
+                ("id", json!(document_id(key))),
+                ("@versions", json!(DOCUMENT_VERSIONS)),
+            ]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DbError::NotFound {
+            key: key.to_string(),
+        })
+    }
+
+    /// Versions of a document, newest first
+    pub async fn list_versions(&self, key: &str) -> Result<Vec<VersionInfo>> {
+        self.query(
+            "FOR v IN @@versions FILTER v.document == @key SORT v.version DESC \
+             RETURN { version: v.version, title: v.title, saved_at: v.saved_at, \
+                      length: CHAR_LENGTH(v.content) }",
+            HashMap::from([("key", json!(key)), ("@versions", json!(DOCUMENT_VERSIONS))]),
+        )
+        .await
+    }
+
+    pub async fn get_version(&self, key: &str, version: u32) -> Result<DocumentVersion> {
+        self.query(
+            "FOR v IN @@versions FILTER v.document == @key AND v.version == @version \
+             LIMIT 1 RETURN UNSET(v, '_key', '_id', '_rev')",
+            HashMap::from([
+                ("key", json!(key)),
+                ("version", json!(version)),
+                ("@versions", json!(DOCUMENT_VERSIONS)),
+            ]),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DbError::NotFound {
+            key: format!("{}@{}", key, version),
+        })
+    }
+
+    /// Make `version` the current content of the document, returning the
+    /// restored document
+    pub async fn restore_version(&self, key: &str, version: u32) -> Result<StoredDocument> {
+        let snapshot = self.get_version(key, version).await?;
+        let mut doc = self.get_document(key).await?;
+        snapshot.apply_to(&mut doc);
+        self.save_document(&doc).await?;
+        self.get_document(key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_version() {
+        let mut doc = StoredDocument::new("Draft", "new text", "md").with_tags(["wip"]);
+        doc.key = Some("42".to_string());
+        let version = DocumentVersion {
+            document: "42".to_string(),
+            version: 1,
+            title: "First".to_string(),
+            content: "* old text".to_string(),
+            format: "org".to_string(),
+            tags: Vec::new(),
+            saved_at: Utc::now(),
+        };
+        version.apply_to(&mut doc);
+        assert_eq!(doc.key.as_deref(), Some("42"));
+        assert_eq!((doc.title.as_str(), doc.format.as_str()), ("First", "org"));
+        assert!(doc.tags.is_empty());
+    }
+}
@@ -5,7 +5,7 @@
 //! All handlers are synchronous — uses std::fs instead of tokio::fs.
 //! Gossamer runs each command invocation on its own thread.
 
-use formatrix_core::{ParseConfig, RenderConfig};
+use formatrix_core::{FormatHandler, FormatRegistry, ParseConfig, Parser, RenderConfig, Renderer};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 
@@ -185,20 +185,8 @@ pub fn load_document(path: String) -> Result<DocumentData, String> {
         .map_err(|e| format!("Failed to read file: {}", e))?;
 
     // Detect format from extension
-    let format = std::path::Path::new(&path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|ext| match ext {
-            "txt" => "txt",
-            "md" | "markdown" => "md",
-            "adoc" | "asciidoc" => "adoc",
-            "dj" | "djot" => "djot",
-            "org" => "org",
-            "rst" => "rst",
-            "typ" => "typ",
-            _ => "txt",
-        })
-        .unwrap_or("txt")
+    let format = formatrix_core::format_from_extension(std::path::Path::new(&path))
+        .map_or("txt", |f| f.extension())
         .to_string();
 
     let word_count = content.split_whitespace().count();
@@ -243,12 +231,6 @@ pub fn convert_to_format(
     from_format: String,
     to_format: String,
 ) -> Result<ConversionResult, String> {
-    use formatrix_core::formats::{
-        AsciidocHandler, DjotHandler, MarkdownHandler, OrgModeHandler, PlainTextHandler,
-        RstHandler, TypstHandler,
-    };
-    use formatrix_core::traits::{Parser, Renderer};
-
     // Return content as-is if converting to same format
     if from_format == to_format {
         return Ok(ConversionResult {
@@ -257,71 +239,31 @@ pub fn convert_to_format(
         });
     }
 
+    let source = handler(&from_format)?;
+    let target = handler(&to_format)?;
+
     // Parse source format
-    let parse_config = ParseConfig::default();
-    let render_config = RenderConfig::default();
-
-    let doc = match from_format.as_str() {
-        "txt" => PlainTextHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "md" => MarkdownHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "adoc" => AsciidocHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "djot" => DjotHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "org" => OrgModeHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "rst" => RstHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "typ" => TypstHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        _ => {
-            return Err(format!("Unsupported source format: {}", from_format));
-        }
-    };
+    let doc = source
+        .parse(&content, &ParseConfig::default())
+        .map_err(|e| e.to_string())?;
 
     // Render to target format
-    let output = match to_format.as_str() {
-        "txt" => PlainTextHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "md" => MarkdownHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "adoc" => AsciidocHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "djot" => DjotHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "org" => OrgModeHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "rst" => RstHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "typ" => TypstHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        _ => {
-            return Err(format!("Unsupported target format: {}", to_format));
-        }
-    };
+    let output = target
+        .render(&doc, &RenderConfig::default())
+        .map_err(|e| e.to_string())?;
 
     // Emit conversion event
     emit_event(DocumentEvent::converted(&content, &output, &from_format, &to_format));
 
+    // Warn about constructs the target format cannot represent
+    let warnings = formatrix_core::compatibility(Parser::format(source), Renderer::format(target))
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
     Ok(ConversionResult {
         content: output,
-        warnings: Vec::new(),
+        warnings,
     })
 }
 
@@ -335,40 +277,7 @@ pub struct ParsedDocument {
 
 /// Parse a document and return metadata
 pub fn parse_document(content: String, format: String) -> Result<ParsedDocument, String> {
-    use formatrix_core::formats::{
-        AsciidocHandler, DjotHandler, MarkdownHandler, OrgModeHandler, PlainTextHandler,
-        RstHandler, TypstHandler,
-    };
-    use formatrix_core::traits::Parser;
-
-    let parse_config = ParseConfig::default();
-
-    let doc = match format.as_str() {
-        "txt" => PlainTextHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "md" => MarkdownHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "adoc" => AsciidocHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "djot" => DjotHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "org" => OrgModeHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "rst" => RstHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        "typ" => TypstHandler::new()
-            .parse(&content, &parse_config)
-            .map_err(|e| e.to_string())?,
-        _ => {
-            return Err(format!("Unsupported format: {}", format));
-        }
-    };
+    let doc = parse_as(&content, &format)?;
 
     Ok(ParsedDocument {
         title: doc.meta.title,
@@ -377,50 +286,46 @@ pub fn parse_document(content: String, format: String) -> Result<ParsedDocument,
     })
 }
 
-/// Render a document from content (parses as markdown, renders to target format)
-pub fn render_document(content: String, to_format: String) -> Result<String, String> {
-    use formatrix_core::formats::{
-        AsciidocHandler, DjotHandler, MarkdownHandler, OrgModeHandler, PlainTextHandler,
-        RstHandler, TypstHandler,
-    };
-    use formatrix_core::traits::{Parser, Renderer};
+/// Writing statistics and readability for the status bar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAnalysis {
+    pub stats: formatrix_core::DocumentStats,
+    /// `None` when the document has no prose
+    pub readability: Option<formatrix_core::Readability>,
+}
 
-    // Parse as markdown by default for rendering
-    let parse_config = ParseConfig::default();
-    let render_config = RenderConfig::default();
+/// Compute statistics and readability scores for a document
+pub fn analyze_document(content: String, format: String) -> Result<DocumentAnalysis, String> {
+    let doc = parse_as(&content, &format)?;
 
-    let doc = MarkdownHandler::new()
-        .parse(&content, &parse_config)
-        .map_err(|e| e.to_string())?;
+    Ok(DocumentAnalysis {
+        stats: doc.stats(),
+        readability: doc.readability(),
+    })
+}
 
-    let output = match to_format.as_str() {
-        "txt" => PlainTextHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "md" => MarkdownHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "adoc" => AsciidocHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "djot" => DjotHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "org" => OrgModeHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "rst" => RstHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        "typ" => TypstHandler::new()
-            .render(&doc, &render_config)
-            .map_err(|e| e.to_string())?,
-        _ => {
-            return Err(format!("Unsupported target format: {}", to_format));
-        }
-    };
+/// Parse content in the format with the given frontend id ("md", "org", ...)
+fn parse_as(content: &str, format: &str) -> Result<formatrix_core::Document, String> {
+    handler(format)?
+        .parse(content, &ParseConfig::default())
+        .map_err(|e| e.to_string())
+}
+
+/// Built-in handler for a frontend format id ("md", "org", ...)
+fn handler(format: &str) -> Result<&'static dyn FormatHandler, String> {
+    FormatRegistry::global()
+        .find(format)
+        .ok_or_else(|| format!("Unsupported format: {}", format))
+}
+
+/// Render a document from content (parses as markdown, renders to target format)
+pub fn render_document(content: String, to_format: String) -> Result<String, String> {
+    // Parse as markdown by default for rendering
+    let doc = parse_as(&content, "md")?;
 
-    Ok(output)
+    handler(&to_format)?
+        .render(&doc, &RenderConfig::default())
+        .map_err(|e| e.to_string())
 }
 
 /// Detect format from content using heuristics
@@ -479,3 +384,105 @@ pub fn get_supported_formats() -> Vec<FormatInfo> {
         },
     ]
 }
+
+// =============================================================================
+// Editing buffers
+// =============================================================================
+
+/// Open buffers by id; each keeps its text in a rope alongside the AST
+static BUFFERS: std::sync::LazyLock<
+    std::sync::Mutex<std::collections::HashMap<String, formatrix_core::buffer::DocumentBuffer>>,
+> = std::sync::LazyLock::new(|| std::sync::Mutex::new(std::collections::HashMap::new()));
+
+/// Buffer summary returned after opening or editing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferState {
+    pub id: String,
+    pub title: Option<String>,
+    pub block_count: usize,
+    /// Blocks re-parsed by the latest edit, for patching the preview
+    pub changed_blocks: std::ops::Range<usize>,
+}
+
+fn with_buffer<T>(
+    id: &str,
+    f: impl FnOnce(&mut formatrix_core::buffer::DocumentBuffer) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut buffers = BUFFERS.lock().map_err(|e| e.to_string())?;
+    let buffer = buffers
+        .get_mut(id)
+        .ok_or_else(|| format!("Unknown buffer: {}", id))?;
+    f(buffer)
+}
+
+fn buffer_state(
+    id: String,
+    buffer: &mut formatrix_core::buffer::DocumentBuffer,
+) -> Result<BufferState, String> {
+    let doc = buffer.document().map_err(|e| e.to_string())?;
+    Ok(BufferState {
+        id,
+        title: doc.meta.title.clone(),
+        block_count: doc.content.len(),
+        changed_blocks: buffer.changed_blocks(),
+    })
+}
+
+/// Open an editing buffer for content in the given format
+pub fn open_buffer(content: String, format: String) -> Result<BufferState, String> {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+    let format = Parser::format(handler(&format)?);
+    let mut buffer = formatrix_core::buffer::DocumentBuffer::new(&content, format);
+    let id = format!("buf-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let state = buffer_state(id.clone(), &mut buffer)?;
+
+    BUFFERS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(id, buffer);
+    Ok(state)
+}
+
+/// Replace the bytes `start..end` of a buffer with `text`
+pub fn edit_buffer(
+    id: String,
+    start: usize,
+    end: usize,
+    text: String,
+) -> Result<BufferState, String> {
+    with_buffer(&id, |buffer| {
+        buffer.replace(start..end, &text).map_err(|e| e.to_string())?;
+        buffer_state(id.clone(), buffer)
+    })
+}
+
+/// Line and column of a byte offset in a buffer
+pub fn buffer_position(
+    id: String,
+    offset: usize,
+) -> Result<formatrix_core::buffer::Position, String> {
+    with_buffer(&id, |buffer| buffer.position(offset).map_err(|e| e.to_string()))
+}
+
+/// Byte offset of a line and column in a buffer
+pub fn buffer_offset(id: String, line: usize, column: usize) -> Result<usize, String> {
+    with_buffer(&id, |buffer| {
+        buffer
+            .offset(formatrix_core::buffer::Position { line, column })
+            .ok_or_else(|| format!("Line {} is past the end of the buffer", line))
+    })
+}
+
+/// Current text of a buffer
+pub fn buffer_text(id: String) -> Result<String, String> {
+    with_buffer(&id, |buffer| Ok(buffer.text()))
+}
+
+/// Discard a buffer
+pub fn close_buffer(id: String) {
+    if let Ok(mut buffers) = BUFFERS.lock() {
+        buffers.remove(&id);
+    }
+}
@@ -29,8 +29,15 @@ fn main() {
         .command("get_document_events", commands::get_document_events)
         .command("clear_document_events", commands::clear_document_events)
         .command("parse_document", commands::parse_document)
+        .command("analyze_document", commands::analyze_document)
         .command("render_document", commands::render_document)
         .command("detect_format", commands::detect_format)
         .command("get_supported_formats", commands::get_supported_formats)
+        .command("open_buffer", commands::open_buffer)
+        .command("edit_buffer", commands::edit_buffer)
+        .command("buffer_position", commands::buffer_position)
+        .command("buffer_offset", commands::buffer_offset)
+        .command("buffer_text", commands::buffer_text)
+        .command("close_buffer", commands::close_buffer)
         .run();
 }
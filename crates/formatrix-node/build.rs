@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+
+fn main() {
+    napi_build::setup();
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Formatrix Node - formatrix-core as a native Node.js addon
+//!
+//! Parsing, rendering and conversion for JavaScript tooling (static site
+//! generators, editor extensions) without shelling out to a binary. Build
+//! with `npm run build` from this directory, which runs `napi build` and
+//! generates `index.js` and `index.d.ts`.
+//!
+//! The exports mirror `formatrix-wasm`: formats are named by their ids
+//! ("md", "org", "adoc", ...) and ASTs are plain objects in the shape of
+//! `docs/schema/document.schema.json`. Function names are camelCased on
+//! the JavaScript side.
+
+use formatrix_core::{Document, FormatHandler, FormatRegistry, ParseConfig, RenderConfig};
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+use serde_json::Value;
+
+/// Built-in handler for a format id
+fn handler(format: &str) -> Result<&'static dyn FormatHandler> {
+    FormatRegistry::global().find(format).ok_or_else(|| {
+        Error::new(
+            Status::InvalidArg,
+            format!("Unsupported format: {}", format),
+        )
+    })
+}
+
+fn error(err: impl std::fmt::Display) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+fn parse_as(content: &str, format: &str) -> Result<Document> {
+    handler(format)?
+        .parse(content, &ParseConfig::default())
+        .map_err(error)
+}
+
+fn to_js(value: &impl serde::Serialize) -> Result<Value> {
+    serde_json::to_value(value).map_err(error)
+}
+
+/// Rendering options; unset fields keep the renderer defaults
+#[napi(object)]
+#[derive(Debug, Default)]
+pub struct RenderOptions {
+    pub line_width: Option<u32>,
+    pub indent: Option<String>,
+    pub hard_breaks: Option<bool>,
+}
+
+impl From<RenderOptions> for RenderConfig {
+    fn from(options: RenderOptions) -> Self {
+        let mut config = RenderConfig::default();
+        if let Some(width) = options.line_width {
+            config.line_width = width as usize;
+        }
+        if let Some(indent) = options.indent {
+            config.indent = indent;
+        }
+        if let Some(hard_breaks) = options.hard_breaks {
+            config.hard_breaks = hard_breaks;
+        }
+        config
+    }
+}
+
+/// Parse `content` and return its AST
+#[napi]
+pub fn parse(content: String, format: String) -> Result<Value> {
+    to_js(&parse_as(&content, &format)?)
+}
+
+/// Render an AST produced by `parse` (possibly edited) to `format`
+#[napi]
+pub fn render(ast: Value, format: String, options: Option<RenderOptions>) -> Result<String> {
+    let doc: Document = serde_json::from_value(ast)
+        .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid AST: {}", e)))?;
+    handler(&format)?
+        .render(&doc, &options.unwrap_or_default().into())
+        .map_err(error)
+}
+
+/// Convert `content` from one format to another
+#[napi]
+pub fn convert(
+    content: String,
+    from: String,
+    to: String,
+    options: Option<RenderOptions>,
+) -> Result<String> {
+    let doc = parse_as(&content, &from)?;
+    handler(&to)?
+        .render(&doc, &options.unwrap_or_default().into())
+        .map_err(error)
+}
+
+/// Guess the format id of `content` ("txt" when unsure)
+#[napi]
+pub fn detect_format(content: String) -> String {
+    formatrix_core::format_from_content(&content)
+        .extension()
+        .to_string()
+}
+
+/// Headings of `content` as `{ level, text, id }` objects
+#[napi]
+pub fn outline(content: String, format: String) -> Result<Value> {
+    to_js(&parse_as(&content, &format)?.outline())
+}
+
+/// Word, sentence, heading and code counts of `content`
+#[napi]
+pub fn stats(content: String, format: String) -> Result<Value> {
+    to_js(&parse_as(&content, &format)?.stats())
+}
+
+/// Ids of all built-in formats
+#[napi]
+pub fn formats() -> Vec<String> {
+    FormatRegistry::global()
+        .formats()
+        .iter()
+        .map(|format| format.extension().to_string())
+        .collect()
+}
+
+/// Feature names supported by `format`, e.g. "tables" or "footnotes"
+#[napi]
+pub fn supported_features(format: String) -> Result<Vec<String>> {
+    let features = handler(&format)?.supported_features();
+    Ok(features.iter().map(|f| f.to_string()).collect())
+}
+
+/// Version of formatrix-core compiled into this addon
+#[napi]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Formatrix Python bindings
+//!
+//! A `formatrix` extension module for batch conversion and analysis from
+//! scripts and notebooks. Build and install it into the active virtualenv
+//! with `maturin develop` from this directory.
+//!
+//! Formats are named by their ids ("md", "org", "adoc", ...). Structured
+//! results (ASTs, statistics, outlines) are returned as plain dicts and
+//! lists via JSON, so they match `docs/schema/document.schema.json`.
+
+#![forbid(unsafe_code)]
+use formatrix_core::keywords::KeywordOptions;
+use formatrix_core::{
+    Document, FormatHandler, FormatRegistry, ParseConfig, Parser, RenderConfig, Renderer,
+};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::path::PathBuf;
+
+create_exception!(
+    formatrix,
+    FormatrixError,
+    PyException,
+    "A document could not be parsed, rendered, read or written"
+);
+
+fn error(err: impl std::fmt::Display) -> PyErr {
+    FormatrixError::new_err(err.to_string())
+}
+
+/// Built-in handler for a format id
+fn find_handler(id: &str) -> PyResult<&'static dyn FormatHandler> {
+    FormatRegistry::global()
+        .find(id)
+        .ok_or_else(|| error(format!("Unsupported format: {}", id)))
+}
+
+/// Convert a serialisable value to Python dicts and lists
+fn to_python(py: Python<'_>, value: &impl Serialize) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(error)?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/// A parsed document
+#[pyclass(module = "formatrix", name = "Document")]
+#[derive(Clone)]
+pub struct PyDocument {
+    inner: Document,
+}
+
+#[pymethods]
+impl PyDocument {
+    /// Parse `content` written in `format`
+    #[staticmethod]
+    fn parse(content: &str, format: &str) -> PyResult<Self> {
+        PyFormatHandler::new(format)?.parse(content)
+    }
+
+    /// Open a file, detecting its format from the extension or content
+    #[staticmethod]
+    fn open(path: PathBuf) -> PyResult<Self> {
+        let opened = formatrix_core::open_file(path).map_err(error)?;
+        Ok(Self {
+            inner: opened.document,
+        })
+    }
+
+    /// Build a document from the JSON produced by `to_json`
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let inner = serde_json::from_str(json).map_err(error)?;
+        Ok(Self { inner })
+    }
+
+    fn render(&self, format: &str) -> PyResult<String> {
+        PyFormatHandler::new(format)?.render(self)
+    }
+
+    /// Save to `path`, in `format` or else the format implied by the
+    /// extension
+    #[pyo3(signature = (path, format = None))]
+    fn save(&self, path: PathBuf, format: Option<&str>) -> PyResult<()> {
+        match format {
+            Some(id) => {
+                let format = Parser::format(find_handler(id)?);
+                formatrix_core::save_file_as(&self.inner, path, format, &RenderConfig::default())
+            }
+            None => formatrix_core::save_file(&self.inner, path),
+        }
+        .map_err(error)
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.inner).map_err(error)
+    }
+
+    /// The AST as nested dicts and lists
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &self.inner)
+    }
+
+    /// Id of the format the document was parsed from
+    #[getter]
+    fn source_format(&self) -> &'static str {
+        self.inner.source_format.extension()
+    }
+
+    #[getter]
+    fn title(&self) -> Option<String> {
+        self.inner.meta.title.clone()
+    }
+
+    #[getter]
+    fn authors(&self) -> Vec<String> {
+        self.inner.meta.authors.clone()
+    }
+
+    #[getter]
+    fn date(&self) -> Option<String> {
+        self.inner.meta.date.clone()
+    }
+
+    /// Word, sentence, heading and code counts
+    fn stats(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &self.inner.stats())
+    }
+
+    /// Readability scores, or None for a document without prose
+    fn readability(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &self.inner.readability())
+    }
+
+    /// Headings as `{"level", "text", "id"}` dicts
+    fn outline(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &self.inner.outline())
+    }
+
+    /// The most frequent non-stopword terms with their counts
+    #[pyo3(signature = (limit = 10))]
+    fn keywords(&self, limit: usize) -> Vec<(String, usize)> {
+        self.inner
+            .keywords(&KeywordOptions::new().with_limit(limit))
+            .words
+            .into_iter()
+            .map(|k| (k.term, k.count))
+            .collect()
+    }
+
+    /// Number of top-level blocks
+    fn __len__(&self) -> usize {
+        self.inner.content.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<formatrix.Document format={:?} title={:?} blocks={}>",
+            self.source_format(),
+            self.inner.meta.title,
+            self.inner.content.len()
+        )
+    }
+}
+
+/// Parser and renderer for one format
+#[pyclass(module = "formatrix", name = "FormatHandler", frozen)]
+pub struct PyFormatHandler {
+    handler: &'static dyn FormatHandler,
+}
+
+#[pymethods]
+impl PyFormatHandler {
+    #[new]
+    fn new(id: &str) -> PyResult<Self> {
+        Ok(Self {
+            handler: find_handler(id)?,
+        })
+    }
+
+    #[getter]
+    fn id(&self) -> &'static str {
+        Parser::format(self.handler).extension()
+    }
+
+    fn parse(&self, content: &str) -> PyResult<PyDocument> {
+        let inner = self
+            .handler
+            .parse(content, &ParseConfig::default())
+            .map_err(error)?;
+        Ok(PyDocument { inner })
+    }
+
+    fn render(&self, document: &PyDocument) -> PyResult<String> {
+        Renderer::render(self.handler, &document.inner, &RenderConfig::default()).map_err(error)
+    }
+
+    fn supports_feature(&self, feature: &str) -> bool {
+        self.handler.supports_feature(feature)
+    }
+
+    fn supported_features(&self) -> Vec<&'static str> {
+        self.handler.supported_features().to_vec()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<formatrix.FormatHandler {:?}>", self.id())
+    }
+}
+
+/// The built-in format handlers
+#[pyclass(module = "formatrix", name = "Registry", frozen)]
+pub struct PyRegistry;
+
+#[pymethods]
+impl PyRegistry {
+    #[new]
+    fn new() -> Self {
+        Self
+    }
+
+    /// Ids of all registered formats
+    fn formats(&self) -> Vec<&'static str> {
+        formats()
+    }
+
+    /// The handler for `id`, or None
+    fn get(&self, id: &str) -> Option<PyFormatHandler> {
+        PyFormatHandler::new(id).ok()
+    }
+
+    fn convert(&self, content: &str, from_format: &str, to_format: &str) -> PyResult<String> {
+        convert(content, from_format, to_format)
+    }
+
+    fn __contains__(&self, id: &str) -> bool {
+        FormatRegistry::global().find(id).is_some()
+    }
+}
+
+/// Parse `content` written in `format`
+#[pyfunction]
+fn parse(content: &str, format: &str) -> PyResult<PyDocument> {
+    PyDocument::parse(content, format)
+}
+
+/// Open and parse a file
+#[pyfunction]
+fn open_file(path: PathBuf) -> PyResult<PyDocument> {
+    PyDocument::open(path)
+}
+
+/// Convert `content` from one format to another
+#[pyfunction]
+fn convert(content: &str, from_format: &str, to_format: &str) -> PyResult<String> {
+    let doc = PyFormatHandler::new(from_format)?.parse(content)?;
+    PyFormatHandler::new(to_format)?.render(&doc)
+}
+
+/// Guess the format id of `content` ("txt" when unsure)
+#[pyfunction]
+fn detect_format(content: &str) -> &'static str {
+    formatrix_core::format_from_content(content).extension()
+}
+
+/// Ids of all built-in formats
+#[pyfunction]
+fn formats() -> Vec<&'static str> {
+    FormatRegistry::global()
+        .formats()
+        .iter()
+        .map(|format| format.extension())
+        .collect()
+}
+
+#[pymodule]
+fn formatrix(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add("FormatrixError", m.py().get_type::<FormatrixError>())?;
+    m.add_class::<PyDocument>()?;
+    m.add_class::<PyFormatHandler>()?;
+    m.add_class::<PyRegistry>()?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(open_file, m)?)?;
+    m.add_function(wrap_pyfunction!(convert, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_format, m)?)?;
+    m.add_function(wrap_pyfunction!(formats, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (c) Jonathan D.A. Jewell <j.d.a.jewell@open.ac.uk>
+//! Formatrix WASM - formatrix-core for the browser
+//!
+//! Parsing, conversion, format detection and outlines compiled to
+//! WebAssembly, so the GUI frontend (or any web page) can preview a
+//! conversion without a round trip to the backend. Build with
+//! `wasm-pack build crates/formatrix-wasm --target web`.
+//!
+//! Formats are named by their ids ("md", "org", "adoc", ...) as in the GUI
+//! commands. ASTs cross into JavaScript as plain objects in the shape of
+//! `docs/schema/document.schema.json`.
+
+#![forbid(unsafe_code)]
+use formatrix_core::{Document, FormatHandler, FormatRegistry, ParseConfig, RenderConfig};
+use wasm_bindgen::prelude::*;
+
+/// Built-in handler for a format id
+fn handler(format: &str) -> Result<&'static dyn FormatHandler, JsError> {
+    FormatRegistry::global()
+        .find(format)
+        .ok_or_else(|| JsError::new(&format!("Unsupported format: {}", format)))
+}
+
+fn parse_as(content: &str, format: &str) -> Result<Document, JsError> {
+    Ok(handler(format)?.parse(content, &ParseConfig::default())?)
+}
+
+/// Parse `content` and return its AST
+#[wasm_bindgen]
+pub fn parse(content: &str, format: &str) -> Result<JsValue, JsError> {
+    let doc = parse_as(content, format)?;
+    Ok(serde_wasm_bindgen::to_value(&doc)?)
+}
+
+/// Render an AST produced by [`parse`] (possibly edited) to `format`
+#[wasm_bindgen]
+pub fn render(ast: JsValue, format: &str) -> Result<String, JsError> {
+    let doc: Document = serde_wasm_bindgen::from_value(ast)?;
+    Ok(handler(format)?.render(&doc, &RenderConfig::default())?)
+}
+
+/// Convert `content` from one format to another
+#[wasm_bindgen]
+pub fn convert(content: &str, from: &str, to: &str) -> Result<String, JsError> {
+    let doc = parse_as(content, from)?;
+    Ok(handler(to)?.render(&doc, &RenderConfig::default())?)
+}
+
+/// Guess the format id of `content` ("txt" when unsure)
+#[wasm_bindgen(js_name = detectFormat)]
+pub fn detect_format(content: &str) -> String {
+    formatrix_core::format_from_content(content)
+        .extension()
+        .to_string()
+}
+
+/// Headings of `content` as `{ level, text, id }` objects, for a
+/// navigation pane
+#[wasm_bindgen]
+pub fn outline(content: &str, format: &str) -> Result<JsValue, JsError> {
+    let outline = parse_as(content, format)?.outline();
+    Ok(serde_wasm_bindgen::to_value(&outline)?)
+}
+
+/// Version of formatrix-core compiled into this module
+#[wasm_bindgen]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}